@@ -0,0 +1,137 @@
+//! Benchmarks for the hot paths: formatting large inputs, resolving
+//! EditorConfig rules in deep trees, and end-to-end runs over a synthetic
+//! tree of files.
+//!
+//! `cargo bench` runs every benchmark at full size under Criterion, which
+//! reports each one's time and the change since the previous run. Under
+//! `cargo test --benches` they run once at a small size, as a smoke test that
+//! they still work. Set `BASEFMT_BENCH_FILES` to change
+//! the number of files in the end-to-end tree (100,000 by default).
+
+use basefmt::editorconfig::{EditorConfigCache, FormatRules};
+use basefmt::filesystem::MemoryFileSystem;
+use basefmt::format::format_str;
+use basefmt::runner::Runner;
+use criterion::{Criterion, criterion_group, criterion_main};
+use indoc::{formatdoc, indoc};
+use std::env;
+use std::fs;
+use std::hint::black_box;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+criterion_group!(
+    benches,
+    bench_format_str,
+    bench_editorconfig_resolution,
+    bench_end_to_end
+);
+criterion_main!(benches);
+
+/// Whether to build inputs at full size.
+///
+/// `cargo bench` passes `--bench`; `cargo test` doesn't, and Criterion then
+/// runs each benchmark only once.
+fn full_size() -> bool {
+    env::args().any(|arg| arg == "--bench")
+}
+
+fn bench_format_str(c: &mut Criterion) {
+    let lines = if full_size() { 200_000 } else { 100 };
+    let clean: String = (0..lines)
+        .map(|i| format!("line {i} of a large file\n"))
+        .collect();
+    let dirty: String = (0..lines)
+        // ast-grep-ignore: prefer-indoc
+        .map(|i| format!("line {i} of a large file  \r\n\n"))
+        .collect();
+    let rules = FormatRules {
+        max_blank_lines: Some(1),
+        ..FormatRules::default()
+    };
+
+    let mut group = c.benchmark_group("format_str");
+    group.bench_function("clean", |b| {
+        b.iter(|| format_str(black_box(&clean), &rules));
+    });
+    group.bench_function("dirty", |b| {
+        b.iter(|| format_str(black_box(&dirty), &rules));
+    });
+    group.finish();
+}
+
+fn bench_editorconfig_resolution(c: &mut Criterion) {
+    let (depth, files_per_dir) = if full_size() { (30, 100) } else { (3, 2) };
+    let mut fs = MemoryFileSystem::new();
+    let mut dir = PathBuf::from("/project");
+    let mut files = Vec::new();
+    for level in 0..depth {
+        fs = fs.with_file(
+            dir.join(".editorconfig"),
+            formatdoc! {"
+                [*.txt]
+                insert_final_newline = true
+                [level{level}/**]
+                indent_size = 2
+            "},
+        );
+        files.extend((0..files_per_dir).map(|i| dir.join(format!("file{i}.txt"))));
+        dir = dir.join(format!("level{level}"));
+    }
+    let fs = Arc::new(fs);
+
+    c.bench_function("editorconfig/deep_tree", |b| {
+        b.iter(|| {
+            let cache = EditorConfigCache::with_file_system(fs.clone());
+            for file in &files {
+                black_box(cache.rules_for(file));
+            }
+        });
+    });
+}
+
+fn bench_end_to_end(c: &mut Criterion) {
+    let file_count = if full_size() {
+        env::var("BASEFMT_BENCH_FILES")
+            .ok()
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(100_000)
+    } else {
+        20
+    };
+    let Ok(temp_dir) = tempfile::TempDir::new() else {
+        eprintln!("skipping end-to-end benchmark: cannot create a temporary directory");
+        return;
+    };
+    let created = fs::write(
+        temp_dir.path().join(".editorconfig"),
+        indoc! {"
+            root = true
+            [*]
+            insert_final_newline = true
+        "},
+    )
+    .and_then(|()| {
+        (0..file_count).try_for_each(|i| {
+            let dir = temp_dir.path().join(format!("dir{}", i / 1000));
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join(format!("file{i}.txt")), "content\n")
+        })
+    });
+    if let Err(err) = created {
+        eprintln!("skipping end-to-end benchmark: {err}");
+        return;
+    }
+
+    // The files are already formatted, so every iteration does the same work
+    // and reports nothing
+    let runner = Runner::new().paths([temp_dir.path()]).check(true);
+    let mut group = c.benchmark_group("end_to_end");
+    // A single run over the full tree takes seconds, so keep to Criterion's
+    // minimum number of samples
+    group.sample_size(10);
+    group.bench_function(format!("check_{file_count}_files"), |b| {
+        b.iter(|| runner.run().ok());
+    });
+    group.finish();
+}
@@ -0,0 +1,53 @@
+//! Feeds arbitrary bytes and rule combinations into `format_bytes`, checking
+//! that it doesn't panic and that formatting its output changes nothing.
+
+#![no_main]
+
+use basefmt::editorconfig::{Charset, FormatRules, LineEnding};
+use basefmt::format::format_bytes;
+use libfuzzer_sys::fuzz_target;
+
+/// Picks a value for each rule from two bits of `selector`: unset, off, or
+/// on, and for `max_blank_lines` unset or a limit of 0 to 2; whether to
+/// normalize the encoding takes one bit.
+fn rules_from(selector: u16) -> FormatRules {
+    let flag = |shift: u16| match (selector >> shift) & 0b11 {
+        0 => None,
+        1 => Some(false),
+        _ => Some(true),
+    };
+    FormatRules {
+        ensure_final_newline: flag(0),
+        remove_trailing_spaces: flag(2),
+        remove_leading_newlines: flag(4),
+        preserve_markdown_hard_breaks: flag(6),
+        max_blank_lines: match (selector >> 8) & 0b11 {
+            0 => None,
+            limit => Some(usize::from(limit - 1)),
+        },
+        skip_minified: None,
+        generated_marker_lines: None,
+        end_of_line: match (selector >> 10) & 0b11 {
+            1 => Some(LineEnding::Lf),
+            2 => Some(LineEnding::Crlf),
+            3 => Some(LineEnding::Cr),
+            _ => None,
+        },
+        preserve_yaml_block_scalars: flag(12),
+        normalize_encoding: ((selector >> 14) & 1 == 1).then_some(Charset::Utf8),
+    }
+}
+
+fuzz_target!(|input: (u16, &[u8])| {
+    let (selector, content) = input;
+    let rules = rules_from(selector);
+    let Some(once) = format_bytes(content, &rules) else {
+        return;
+    };
+    let twice = format_bytes(&once.content, &rules);
+    assert_eq!(
+        twice.map(|formatted| formatted.changed),
+        Some(false),
+        "rules: {rules:?}"
+    );
+});
@@ -0,0 +1,57 @@
+/*
+ * C interface of basefmt, built with the `capi` feature (see the C API
+ * section of README.md). Every function is documented in src/capi.rs.
+ */
+#ifndef BASEFMT_H
+#define BASEFMT_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define BASEFMT_OK 0
+#define BASEFMT_CHANGED 1
+#define BASEFMT_INVALID_ARGUMENT (-1)
+#define BASEFMT_NOT_UTF8 (-2)
+
+#define BASEFMT_END_OF_LINE_KEEP 0
+#define BASEFMT_END_OF_LINE_LF 1
+#define BASEFMT_END_OF_LINE_CRLF 2
+#define BASEFMT_END_OF_LINE_CR 3
+
+typedef struct BasefmtRules {
+    bool ensure_final_newline;
+    bool remove_trailing_spaces;
+    bool remove_leading_newlines;
+    bool preserve_markdown_hard_breaks;
+    bool preserve_yaml_block_scalars;
+    /* One of the BASEFMT_END_OF_LINE_* constants */
+    uint8_t end_of_line;
+    /* Negative for unlimited */
+    int64_t max_blank_lines;
+} BasefmtRules;
+
+/* Formatted UTF-8 text, not NUL-terminated; release with basefmt_buffer_free */
+typedef struct BasefmtBuffer {
+    uint8_t *data;
+    size_t len;
+} BasefmtBuffer;
+
+BasefmtRules basefmt_default_rules(void);
+
+int32_t basefmt_format_buffer(const uint8_t *input, size_t len, const BasefmtRules *rules,
+                              BasefmtBuffer *output);
+
+int32_t basefmt_check_buffer(const uint8_t *input, size_t len, const BasefmtRules *rules);
+
+void basefmt_buffer_free(BasefmtBuffer buffer);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* BASEFMT_H */
@@ -0,0 +1,104 @@
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Returns the canonical, absolute form of `path`, like
+/// [`Path::canonicalize`], but without the `\\?\` extended-length prefix it
+/// adds on Windows wherever the path can be written without it.
+///
+/// Canonical paths are matched against exclude globs and `.editorconfig`
+/// sections, and the prefix would make them differ from the paths users
+/// write, e.g. `\\?\C:\work\vendor\a.js` instead of `C:\work\vendor\a.js`.
+/// This includes paths longer than 260 characters, since std adds the prefix
+/// back itself whenever such a path is opened.
+pub(crate) fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    let canonical = path.canonicalize()?;
+    #[cfg(windows)]
+    if let Some(simplified) = canonical.to_str().and_then(strip_verbatim_prefix) {
+        return Ok(PathBuf::from(simplified));
+    }
+    Ok(canonical)
+}
+
+/// Removes the `.` and `..` components of `path` without consulting the
+/// file system, so that a file reached by different paths compares equal.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Rewrites `\\?\C:\dir` as `C:\dir` and `\\?\UNC\server\share` as
+/// `\\server\share`, or returns `None` if `path` has no such prefix or the
+/// result would mean something else.
+#[cfg_attr(
+    not(any(windows, test)),
+    expect(dead_code, reason = "only used on Windows")
+)]
+fn strip_verbatim_prefix(path: &str) -> Option<String> {
+    let rest = path.strip_prefix(r"\\?\")?;
+    let simplified = if let Some(unc) = rest.strip_prefix(r"UNC\") {
+        format!(r"\\{unc}")
+    } else {
+        let bytes = rest.as_bytes();
+        if bytes.len() < 3 || !bytes[0].is_ascii_alphabetic() || &bytes[1..3] != br":\" {
+            return None;
+        }
+        rest.to_string()
+    };
+
+    // Without the prefix, Win32 normalizes these names, e.g. trimming
+    // trailing dots and spaces, so they would name a different file
+    let unambiguous = simplified
+        .split('\\')
+        .skip(if simplified.starts_with(r"\\") { 4 } else { 1 })
+        .all(|name| {
+            !name.ends_with(['.', ' '])
+                && !name
+                    .chars()
+                    .any(|c| matches!(c, '<' | '>' | ':' | '"' | '/' | '|' | '?' | '*'))
+                && !is_reserved_name(name)
+        });
+    unambiguous.then_some(simplified)
+}
+
+/// Returns whether `name` is a device name such as `CON` or `com1.txt`,
+/// which Win32 resolves to the device regardless of the directory.
+fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    let upper = stem.to_ascii_uppercase();
+    matches!(upper.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((upper.starts_with("COM") || upper.starts_with("LPT"))
+            && upper.len() == 4
+            && upper[3..].chars().all(|c| matches!(c, '1'..='9')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::disk(r"\\?\C:\work\a.txt", Some(r"C:\work\a.txt"))]
+    #[case::unc(r"\\?\UNC\server\share\a.txt", Some(r"\\server\share\a.txt"))]
+    #[case::no_prefix(r"C:\work\a.txt", None)]
+    #[case::volume_guid(r"\\?\Volume{1234}\a.txt", None)]
+    #[case::trailing_dot(r"\\?\C:\work\a.", None)]
+    #[case::reserved_name(r"\\?\C:\work\con.txt", None)]
+    #[case::reserved_name_lookalike(r"\\?\C:\work\console.txt", Some(r"C:\work\console.txt"))]
+    fn test_strip_verbatim_prefix(#[case] path: &str, #[case] expected: Option<&str>) {
+        assert_eq!(strip_verbatim_prefix(path).as_deref(), expected);
+    }
+}
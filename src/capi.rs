@@ -0,0 +1,312 @@
+// C ABI module
+// This module exposes in-memory formatting to C and other languages that can
+// call C functions, so that editors can link basefmt instead of spawning it.
+
+use crate::editorconfig::{FormatRules, LineEnding};
+use crate::format::{Formatted, format_str};
+use std::{ptr, slice};
+
+/// The buffer was already formatted
+pub const BASEFMT_OK: i32 = 0;
+/// The buffer needs formatting (or, from [`basefmt_format_buffer`], was
+/// formatted)
+pub const BASEFMT_CHANGED: i32 = 1;
+/// A pointer was null or a field of [`BasefmtRules`] was out of range
+pub const BASEFMT_INVALID_ARGUMENT: i32 = -1;
+/// The buffer is not valid UTF-8
+pub const BASEFMT_NOT_UTF8: i32 = -2;
+
+/// Lines keep the terminator most common in the buffer
+pub const BASEFMT_END_OF_LINE_KEEP: u8 = 0;
+/// Lines end with `\n`
+pub const BASEFMT_END_OF_LINE_LF: u8 = 1;
+/// Lines end with `\r\n`
+pub const BASEFMT_END_OF_LINE_CRLF: u8 = 2;
+/// Lines end with a lone `\r`
+pub const BASEFMT_END_OF_LINE_CR: u8 = 3;
+
+/// The formatting rules of a call, with every rule set.
+///
+/// Get the defaults from [`basefmt_default_rules`] and change the rules that
+/// differ, so that code written against this struct keeps working.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BasefmtRules {
+    /// Whether to ensure the buffer ends with a newline
+    pub ensure_final_newline: bool,
+    /// Whether to remove trailing spaces from each line
+    pub remove_trailing_spaces: bool,
+    /// Whether to remove leading newlines from the buffer
+    pub remove_leading_newlines: bool,
+    /// Whether to keep exactly two trailing spaces (a Markdown hard line
+    /// break) when removing trailing spaces
+    pub preserve_markdown_hard_breaks: bool,
+    /// Whether to leave the bodies of YAML block scalars alone
+    pub preserve_yaml_block_scalars: bool,
+    /// Which terminator to end lines with, one of the
+    /// `BASEFMT_END_OF_LINE_*` constants
+    pub end_of_line: u8,
+    /// How many consecutive blank lines to keep; negative for unlimited
+    pub max_blank_lines: i64,
+}
+
+/// Formatted text allocated by basefmt, which must be released with
+/// [`basefmt_buffer_free`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct BasefmtBuffer {
+    /// The UTF-8 text, which isn't NUL-terminated
+    pub data: *mut u8,
+    /// Length of `data` in bytes
+    pub len: usize,
+}
+
+impl BasefmtRules {
+    /// Converts the rules to [`FormatRules`], or returns `None` if
+    /// `end_of_line` is not one of the `BASEFMT_END_OF_LINE_*` constants.
+    fn to_format_rules(self) -> Option<FormatRules> {
+        let end_of_line = match self.end_of_line {
+            BASEFMT_END_OF_LINE_KEEP => None,
+            BASEFMT_END_OF_LINE_LF => Some(LineEnding::Lf),
+            BASEFMT_END_OF_LINE_CRLF => Some(LineEnding::Crlf),
+            BASEFMT_END_OF_LINE_CR => Some(LineEnding::Cr),
+            _ => return None,
+        };
+        Some(FormatRules {
+            ensure_final_newline: Some(self.ensure_final_newline),
+            remove_trailing_spaces: Some(self.remove_trailing_spaces),
+            remove_leading_newlines: Some(self.remove_leading_newlines),
+            preserve_markdown_hard_breaks: Some(self.preserve_markdown_hard_breaks),
+            preserve_yaml_block_scalars: Some(self.preserve_yaml_block_scalars),
+            end_of_line,
+            max_blank_lines: usize::try_from(self.max_blank_lines).ok(),
+            ..FormatRules::default()
+        })
+    }
+}
+
+/// Returns the rules a file gets when nothing configures it, as with
+/// [`FormatRules::default`].
+#[unsafe(no_mangle)]
+pub extern "C" fn basefmt_default_rules() -> BasefmtRules {
+    let rules = FormatRules::default();
+    BasefmtRules {
+        ensure_final_newline: rules.ensure_final_newline(),
+        remove_trailing_spaces: rules.remove_trailing_spaces(),
+        remove_leading_newlines: rules.remove_leading_newlines(),
+        preserve_markdown_hard_breaks: rules.preserve_markdown_hard_breaks(),
+        preserve_yaml_block_scalars: rules.preserve_yaml_block_scalars(),
+        end_of_line: BASEFMT_END_OF_LINE_KEEP,
+        max_blank_lines: -1,
+    }
+}
+
+/// Formats the `len` bytes of UTF-8 text at `input` with `rules`, like
+/// [`format_str`], and stores the formatted text in `output`.
+///
+/// Returns [`BASEFMT_CHANGED`] if formatting changed the text and
+/// [`BASEFMT_OK`] otherwise. On any other return value, `output` is left
+/// untouched and must not be freed.
+///
+/// # Safety
+///
+/// `input` must point to `len` readable bytes (it may be null if `len` is
+/// 0), and `rules` and `output` must be null or valid pointers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn basefmt_format_buffer(
+    input: *const u8,
+    len: usize,
+    rules: *const BasefmtRules,
+    output: *mut BasefmtBuffer,
+) -> i32 {
+    if output.is_null() {
+        return BASEFMT_INVALID_ARGUMENT;
+    }
+    // SAFETY: the caller guarantees the pointers are valid
+    let formatted = match unsafe { format_input(input, len, rules) } {
+        Ok(formatted) => formatted,
+        Err(code) => return code,
+    };
+    let data = Box::into_raw(formatted.content.into_bytes().into_boxed_slice());
+    // SAFETY: `output` is not null, and valid as the caller guarantees
+    unsafe {
+        output.write(BasefmtBuffer {
+            data: data.cast(),
+            len: data.len(),
+        });
+    }
+    if formatted.changed {
+        BASEFMT_CHANGED
+    } else {
+        BASEFMT_OK
+    }
+}
+
+/// Checks whether the `len` bytes of UTF-8 text at `input` are formatted
+/// according to `rules`.
+///
+/// Returns [`BASEFMT_OK`] if they are and [`BASEFMT_CHANGED`] if formatting
+/// would change them.
+///
+/// # Safety
+///
+/// `input` must point to `len` readable bytes (it may be null if `len` is
+/// 0), and `rules` must be null or a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn basefmt_check_buffer(
+    input: *const u8,
+    len: usize,
+    rules: *const BasefmtRules,
+) -> i32 {
+    // SAFETY: the caller guarantees the pointers are valid
+    match unsafe { format_input(input, len, rules) } {
+        Ok(formatted) if formatted.changed => BASEFMT_CHANGED,
+        Ok(_) => BASEFMT_OK,
+        Err(code) => code,
+    }
+}
+
+/// Releases a buffer returned by [`basefmt_format_buffer`]. Does nothing if
+/// `buffer.data` is null.
+///
+/// # Safety
+///
+/// `buffer` must have been returned by [`basefmt_format_buffer`] and not
+/// freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn basefmt_buffer_free(buffer: BasefmtBuffer) {
+    if buffer.data.is_null() {
+        return;
+    }
+    // SAFETY: the buffer was allocated as a boxed slice of `len` bytes
+    drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(buffer.data, buffer.len)) });
+}
+
+/// Formats the input of a call, or returns the error code to return.
+///
+/// # Safety
+///
+/// Same as [`basefmt_check_buffer`].
+unsafe fn format_input(
+    input: *const u8,
+    len: usize,
+    rules: *const BasefmtRules,
+) -> Result<Formatted<String>, i32> {
+    if (input.is_null() && len > 0) || rules.is_null() {
+        return Err(BASEFMT_INVALID_ARGUMENT);
+    }
+    let content = if len == 0 {
+        &[]
+    } else {
+        // SAFETY: the caller guarantees `input` points to `len` bytes
+        unsafe { slice::from_raw_parts(input, len) }
+    };
+    // SAFETY: `rules` is not null, and valid as the caller guarantees
+    let rules = unsafe { *rules }
+        .to_format_rules()
+        .ok_or(BASEFMT_INVALID_ARGUMENT)?;
+    let content = str::from_utf8(content).map_err(|_| BASEFMT_NOT_UTF8)?;
+    Ok(format_str(content, &rules))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn format(input: &[u8], rules: &BasefmtRules) -> (i32, Option<Vec<u8>>) {
+        let mut output = BasefmtBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+        };
+        // SAFETY: every pointer is valid
+        let code =
+            unsafe { basefmt_format_buffer(input.as_ptr(), input.len(), rules, &mut output) };
+        if output.data.is_null() {
+            return (code, None);
+        }
+        // SAFETY: `output` was just filled in by `basefmt_format_buffer`
+        let formatted = unsafe { slice::from_raw_parts(output.data, output.len) }.to_vec();
+        // SAFETY: `output` was returned by `basefmt_format_buffer`
+        unsafe { basefmt_buffer_free(output) };
+        (code, Some(formatted))
+    }
+
+    #[rstest]
+    #[case::formatted(b"a\n", basefmt_default_rules(), (BASEFMT_OK, Some(b"a\n".to_vec())))]
+    #[case::changed(b"a  ", basefmt_default_rules(), (BASEFMT_CHANGED, Some(b"a\n".to_vec())))]
+    #[case::empty(b"", basefmt_default_rules(), (BASEFMT_OK, Some(Vec::new())))]
+    #[case::rules(
+        b"a  \r\n",
+        BasefmtRules {
+            remove_trailing_spaces: false,
+            end_of_line: BASEFMT_END_OF_LINE_LF,
+            ..basefmt_default_rules()
+        },
+        (BASEFMT_CHANGED, Some(b"a  \n".to_vec()))
+    )]
+    #[case::max_blank_lines(
+        b"a\n\n\n\nb\n", // ast-grep-ignore: prefer-indoc
+        BasefmtRules {
+            max_blank_lines: 1,
+            ..basefmt_default_rules()
+        },
+        (BASEFMT_CHANGED, Some(b"a\n\nb\n".to_vec())) // ast-grep-ignore: prefer-indoc
+    )]
+    #[case::not_utf8(b"\xC3\x28", basefmt_default_rules(), (BASEFMT_NOT_UTF8, None))]
+    #[case::invalid_end_of_line(
+        b"a\n",
+        BasefmtRules {
+            end_of_line: 4,
+            ..basefmt_default_rules()
+        },
+        (BASEFMT_INVALID_ARGUMENT, None)
+    )]
+    fn test_format_buffer(
+        #[case] input: &[u8],
+        #[case] rules: BasefmtRules,
+        #[case] expected: (i32, Option<Vec<u8>>),
+    ) {
+        assert_eq!(format(input, &rules), expected);
+    }
+
+    #[rstest]
+    #[case::formatted(b"a\n", BASEFMT_OK)]
+    #[case::needs_formatting(b"\na", BASEFMT_CHANGED)]
+    #[case::not_utf8(b"\xFF", BASEFMT_NOT_UTF8)]
+    fn test_check_buffer(#[case] input: &[u8], #[case] expected: i32) {
+        let rules = basefmt_default_rules();
+        // SAFETY: every pointer is valid
+        let code = unsafe { basefmt_check_buffer(input.as_ptr(), input.len(), &rules) };
+        assert_eq!(code, expected);
+    }
+
+    #[test]
+    fn test_null_pointers() {
+        let rules = basefmt_default_rules();
+        let mut output = BasefmtBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+        };
+        // SAFETY: null pointers are rejected before being read
+        let codes = unsafe {
+            (
+                basefmt_check_buffer(ptr::null(), 1, &rules),
+                basefmt_check_buffer(b"a".as_ptr(), 1, ptr::null()),
+                basefmt_format_buffer(b"a".as_ptr(), 1, &rules, ptr::null_mut()),
+                basefmt_format_buffer(ptr::null(), 0, &rules, &mut output),
+            )
+        };
+        // SAFETY: `output` was returned by `basefmt_format_buffer`
+        unsafe { basefmt_buffer_free(output) };
+        assert_eq!(
+            codes,
+            (
+                BASEFMT_INVALID_ARGUMENT,
+                BASEFMT_INVALID_ARGUMENT,
+                BASEFMT_INVALID_ARGUMENT,
+                BASEFMT_OK
+            )
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use crate::error::BasefmtError;
+use crate::format::temp;
+use ignore::WalkBuilder;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How old a temporary file must be before it is considered orphaned.
+///
+/// A running basefmt keeps each temporary file only for the time it takes to
+/// write one file, so anything older than this was left behind by a run that
+/// crashed or was killed.
+pub const DEFAULT_MIN_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// Finds temporary files left behind by interrupted basefmt runs.
+///
+/// Unlike [`find_files`](crate::find::find_files), this also searches hidden
+/// and ignored files, since the temporary files are hidden and often match
+/// ignore patterns such as `*.tmp`. Only files last modified at least
+/// `min_age` ago are returned, sorted by path.
+///
+/// # Examples
+///
+/// ```no_run
+/// use basefmt::clean::{DEFAULT_MIN_AGE, find_stale_temp_files};
+/// use std::path::Path;
+///
+/// for path in find_stale_temp_files(&[Path::new(".")], DEFAULT_MIN_AGE).unwrap() {
+///     println!("{}", path.display());
+/// }
+/// ```
+pub fn find_stale_temp_files(
+    paths: &[impl AsRef<Path>],
+    min_age: Duration,
+) -> Result<Vec<PathBuf>, BasefmtError> {
+    let now = SystemTime::now();
+    let mut files = Vec::new();
+    let mut failures = Vec::new();
+
+    for path in paths {
+        let path = path.as_ref();
+        if let Err(err) = path.metadata() {
+            eprintln!("{}: {}", path.display(), err);
+            failures.push((path.to_path_buf(), err));
+            continue;
+        }
+
+        let walk = WalkBuilder::new(path)
+            .standard_filters(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build();
+        for result in walk {
+            let entry = match result {
+                Ok(entry) => entry,
+                Err(err) => {
+                    eprintln!("{}: {}", path.display(), err);
+                    continue;
+                }
+            };
+            if !entry.file_type().is_some_and(|ft| ft.is_file())
+                || !temp::is_temp_file_name(entry.file_name())
+            {
+                continue;
+            }
+            let age = entry
+                .metadata()
+                .ok()
+                .and_then(|metadata| metadata.modified().ok())
+                .and_then(|modified| now.duration_since(modified).ok());
+            if age.is_some_and(|age| age >= min_age) {
+                files.push(entry.into_path());
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        return Err(BasefmtError::Traversal { failures });
+    }
+
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Removes the files returned by [`find_stale_temp_files`].
+///
+/// Returns the removed files; files that cannot be removed are reported on
+/// stderr and counted as errors.
+pub fn remove_stale_temp_files(
+    paths: &[impl AsRef<Path>],
+    min_age: Duration,
+) -> Result<CleanResult, BasefmtError> {
+    let mut result = CleanResult::default();
+    for path in find_stale_temp_files(paths, min_age)? {
+        match fs::remove_file(&path) {
+            Ok(()) => result.removed.push(path),
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                result.error_count += 1;
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Result of [`remove_stale_temp_files`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CleanResult {
+    /// Files that were removed
+    pub removed: Vec<PathBuf>,
+    /// Number of files that could not be removed
+    pub error_count: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Creates a basefmt temporary file in `dir` that was last modified
+    /// `age` ago.
+    fn create_temp_file(dir: &Path, age: Duration) -> PathBuf {
+        let (file, path) = temp::create_in(dir).unwrap().keep().unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_find_stale_temp_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let nested = temp_dir.path().join("nested");
+        fs::create_dir(&nested).unwrap();
+        fs::write(temp_dir.path().join(".gitignore"), "*.tmp\n").unwrap();
+        fs::write(temp_dir.path().join("regular.tmp"), "").unwrap();
+        let stale = create_temp_file(&nested, Duration::from_secs(3600));
+        create_temp_file(temp_dir.path(), Duration::ZERO);
+
+        let files = find_stale_temp_files(&[temp_dir.path()], DEFAULT_MIN_AGE).unwrap();
+
+        assert_eq!(files, vec![stale]);
+    }
+
+    #[test]
+    fn test_remove_stale_temp_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let stale = create_temp_file(temp_dir.path(), Duration::from_secs(3600));
+
+        let result = remove_stale_temp_files(&[temp_dir.path()], DEFAULT_MIN_AGE).unwrap();
+
+        assert_eq!(
+            (result, stale.exists()),
+            (
+                CleanResult {
+                    removed: vec![stale],
+                    error_count: 0,
+                },
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn test_find_stale_temp_files_nonexistent_path() {
+        let result = find_stale_temp_files(&["/nonexistent/path"], DEFAULT_MIN_AGE);
+        assert!(result.is_err());
+    }
+}
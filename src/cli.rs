@@ -0,0 +1,167 @@
+use basefmt::editorconfig::FormatRules;
+use basefmt::format::SymlinkMode;
+use basefmt::rules::RuleProfile;
+use basefmt::runner::{CacheStrategy, ErrorFormat, OutputFormat};
+use clap::{Subcommand, ValueEnum};
+
+mod cache;
+mod check_config;
+mod clean;
+mod doctor;
+mod explain;
+mod format;
+mod interrupt;
+mod list_files;
+mod summary;
+mod why;
+
+pub(crate) use cache::{CacheCommand, clear_cache};
+pub(crate) use check_config::{CheckConfigArgs, check_config};
+pub(crate) use clean::{CleanArgs, clean};
+pub(crate) use doctor::{DoctorArgs, doctor};
+pub(crate) use explain::{ExplainArgs, explain};
+pub(crate) use format::{Args, format};
+pub(crate) use list_files::{ListFilesArgs, list_files};
+pub(crate) use why::{WhyArgs, why};
+
+#[derive(Subcommand)]
+pub(crate) enum Command {
+    /// Remove temporary files left behind by crashed or killed runs
+    Clean(CleanArgs),
+    /// Manage the cache of formatted files
+    #[command(subcommand)]
+    Cache(CacheCommand),
+    /// Validate .basefmt.toml and warn about misspelled .editorconfig properties
+    CheckConfig(CheckConfigArgs),
+    /// Check a directory's configuration for problems and show the rules it comes down to
+    Doctor(DoctorArgs),
+    /// Show every rule a file is formatted with and where it comes from
+    Explain(ExplainArgs),
+    /// Print the files a run would process, without processing them
+    ListFiles(ListFilesArgs),
+    /// Tell whether a run would process a file and, if not, what leaves it out
+    Why(WhyArgs),
+}
+
+/// Combines every `--rule`, later ones winning, and turns off every
+/// `--skip-rule` on top.
+fn cli_rules(rule: &[FormatRules], skip_rule: &[FormatRules]) -> FormatRules {
+    rule.iter()
+        .chain(skip_rule)
+        .fold(FormatRules::default(), |rules, rule| {
+            rules.with_overrides(rule)
+        })
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum SymlinkArg {
+    /// Rewrite the file the link points to and keep the link
+    Follow,
+    /// Replace the link with a regular file
+    Replace,
+}
+
+impl From<SymlinkArg> for SymlinkMode {
+    fn from(arg: SymlinkArg) -> Self {
+        match arg {
+            SymlinkArg::Follow => SymlinkMode::Follow,
+            SymlinkArg::Replace => SymlinkMode::Replace,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum ProfileArg {
+    /// Remove leading blank lines and trailing whitespace, ensure a final newline
+    Standard,
+    /// The standard rules, and shorten runs of blank lines to one
+    Strict,
+    /// The standard rules, but keep Markdown hard line breaks
+    MarkdownSafe,
+    /// Remove trailing whitespace and ensure a final newline
+    Minimal,
+    /// Change nothing that isn't enabled explicitly
+    None,
+}
+
+impl From<ProfileArg> for RuleProfile {
+    fn from(arg: ProfileArg) -> Self {
+        match arg {
+            ProfileArg::Standard => RuleProfile::Standard,
+            ProfileArg::Strict => RuleProfile::Strict,
+            ProfileArg::MarkdownSafe => RuleProfile::MarkdownSafe,
+            ProfileArg::Minimal => RuleProfile::Minimal,
+            ProfileArg::None => RuleProfile::None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum OutputArg {
+    /// `github` inside GitHub Actions, `color` in a terminal, `text`
+    /// everywhere else
+    Auto,
+    /// Diagnostics for files with problems on stderr
+    Text,
+    /// Diagnostics on stderr like `text`, with their locations in bold
+    Color,
+    /// Diagnostics on stderr grouped by directory, with counts
+    Grouped,
+    /// One JSON object per processed file on stdout, as soon as it is done
+    Jsonl,
+    /// GitHub Actions annotations on stdout
+    Github,
+    /// A shields.io endpoint badge JSON on stdout, once every file is done
+    Badge,
+}
+
+impl From<OutputArg> for OutputFormat {
+    fn from(arg: OutputArg) -> Self {
+        match arg {
+            OutputArg::Auto => OutputFormat::detect(),
+            OutputArg::Text => OutputFormat::Text,
+            OutputArg::Color => OutputFormat::Color,
+            OutputArg::Grouped => OutputFormat::Grouped,
+            OutputArg::Jsonl => OutputFormat::Jsonl,
+            OutputArg::Github => OutputFormat::GithubActions,
+            OutputArg::Badge => OutputFormat::Badge,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum ErrorFormatArg {
+    /// A report grouped by error message and directory
+    Human,
+    /// One `path:kind` line per file, e.g. `a.txt:permission_denied`
+    Short,
+    /// One JSON object per file with its path, kind, and error message
+    Json,
+}
+
+impl From<ErrorFormatArg> for ErrorFormat {
+    fn from(arg: ErrorFormatArg) -> Self {
+        match arg {
+            ErrorFormatArg::Human => ErrorFormat::Human,
+            ErrorFormatArg::Short => ErrorFormat::Short,
+            ErrorFormatArg::Json => ErrorFormat::Json,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum CacheStrategyArg {
+    /// By size and modification time
+    Metadata,
+    /// By size and a hash of the content, reading every file
+    Content,
+}
+
+impl From<CacheStrategyArg> for CacheStrategy {
+    fn from(arg: CacheStrategyArg) -> Self {
+        match arg {
+            CacheStrategyArg::Metadata => CacheStrategy::Metadata,
+            CacheStrategyArg::Content => CacheStrategy::Content,
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use basefmt::runner::{self, RunOptions};
+use clap::Subcommand;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Subcommand)]
+pub(crate) enum CacheCommand {
+    /// Remove the cache, so that the next run processes every file
+    Clear(CacheClearArgs),
+}
+
+#[derive(clap::Args)]
+pub(crate) struct CacheClearArgs {
+    #[clap(
+        default_value = ".",
+        help = "List of files/directories whose cache to remove"
+    )]
+    paths: Vec<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "DIR",
+        env = "BASEFMT_CACHE_DIR",
+        help = "Remove DIR instead of the configured cache directory"
+    )]
+    cache_dir: Option<PathBuf>,
+}
+
+pub(crate) fn clear_cache(args: &CacheClearArgs) -> ExitCode {
+    let options = RunOptions {
+        cache_dir: args.cache_dir.clone(),
+        ..RunOptions::default()
+    };
+    match runner::clear_cache(&args.paths, &options) {
+        Ok(removed) => {
+            if let Some(dir) = removed {
+                println!("{}", dir.display());
+            }
+            ExitCode::from(0)
+        }
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use basefmt::runner::{self, RunOptions};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(clap::Args)]
+pub(crate) struct CheckConfigArgs {
+    #[clap(
+        default_value = ".",
+        help = "List of files/directories whose configuration to check"
+    )]
+    paths: Vec<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "NAME",
+        env = "BASEFMT_EDITORCONFIG_FILE",
+        help = "Read EditorConfig settings from NAME in each directory instead of .editorconfig"
+    )]
+    editorconfig_file: Option<String>,
+
+    #[clap(
+        long,
+        value_name = "DIR",
+        help = "Don't read EditorConfig files above DIR"
+    )]
+    root_dir: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "Ignore keys of .basefmt.toml files that basefmt doesn't know instead of failing"
+    )]
+    allow_unknown_config_keys: bool,
+}
+
+pub(crate) fn check_config(args: &CheckConfigArgs) -> ExitCode {
+    let options = RunOptions {
+        editorconfig_file: args.editorconfig_file.clone(),
+        root_dir: args.root_dir.clone(),
+        allow_unknown_config_keys: args.allow_unknown_config_keys,
+        ..RunOptions::default()
+    };
+    match runner::check_config_with_options(&args.paths, &options) {
+        Ok(warnings) => {
+            for warning in &warnings {
+                eprintln!("{warning}");
+            }
+            ExitCode::from(if warnings.is_empty() { 0 } else { 1 })
+        }
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
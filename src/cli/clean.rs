@@ -0,0 +1,47 @@
+use basefmt::clean::{DEFAULT_MIN_AGE, find_stale_temp_files, remove_stale_temp_files};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+#[derive(clap::Args)]
+pub(crate) struct CleanArgs {
+    #[clap(default_value = ".", help = "List of files/directories to clean")]
+    paths: Vec<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        default_value_t = DEFAULT_MIN_AGE.as_secs(),
+        help = "Only remove temporary files last modified at least this long ago"
+    )]
+    min_age: u64,
+
+    #[clap(
+        long,
+        help = "List the files that would be removed without removing them"
+    )]
+    dry_run: bool,
+}
+
+pub(crate) fn clean(args: &CleanArgs) -> ExitCode {
+    let min_age = Duration::from_secs(args.min_age);
+    let result = if args.dry_run {
+        find_stale_temp_files(&args.paths, min_age).map(|files| (files, 0))
+    } else {
+        remove_stale_temp_files(&args.paths, min_age)
+            .map(|result| (result.removed, result.error_count))
+    };
+
+    match result {
+        Ok((files, error_count)) => {
+            for file in files {
+                println!("{}", file.display());
+            }
+            ExitCode::from(if error_count > 0 { 2 } else { 0 })
+        }
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
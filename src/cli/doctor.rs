@@ -0,0 +1,99 @@
+use basefmt::runner::{self, Diagnosis, RunOptions, display_path};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(clap::Args)]
+pub(crate) struct DoctorArgs {
+    #[clap(default_value = ".", help = "Directory whose setup to check")]
+    dir: PathBuf,
+
+    #[clap(
+        long,
+        value_name = "NAME",
+        env = "BASEFMT_EDITORCONFIG_FILE",
+        help = "Read EditorConfig settings from NAME in each directory instead of .editorconfig"
+    )]
+    editorconfig_file: Option<String>,
+
+    #[clap(
+        long,
+        value_name = "DIR",
+        help = "Don't read EditorConfig files above DIR"
+    )]
+    root_dir: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "Ignore keys of .basefmt.toml files that basefmt doesn't know instead of failing"
+    )]
+    allow_unknown_config_keys: bool,
+}
+
+/// Prints the configuration files in use, the problems with them, and the
+/// rules they come down to, exiting with 1 if there are problems.
+pub(crate) fn doctor(args: &DoctorArgs) -> ExitCode {
+    let options = RunOptions {
+        editorconfig_file: args.editorconfig_file.clone(),
+        root_dir: args.root_dir.clone(),
+        allow_unknown_config_keys: args.allow_unknown_config_keys,
+        ..RunOptions::default()
+    };
+    match runner::diagnose(&args.dir, &options) {
+        Ok(diagnosis) => {
+            print_diagnosis(&diagnosis);
+            ExitCode::from(if diagnosis.has_problems() { 1 } else { 0 })
+        }
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn print_diagnosis(diagnosis: &Diagnosis) {
+    println!("Configuration files:");
+    print_paths(&diagnosis.config_files);
+    println!();
+    println!("EditorConfig files:");
+    print_paths(&diagnosis.editorconfig_files);
+    println!();
+
+    let problems: Vec<String> = diagnosis
+        .config_errors
+        .iter()
+        .map(ToString::to_string)
+        .chain(diagnosis.warnings.iter().map(ToString::to_string))
+        .chain(diagnosis.conflicts.iter().map(ToString::to_string))
+        .collect();
+    println!("Problems:");
+    if problems.is_empty() {
+        println!("  none");
+    }
+    for problem in &problems {
+        println!("  {problem}");
+    }
+    println!();
+
+    println!("Effective defaults:");
+    let settings: Vec<String> = diagnosis
+        .rules
+        .iter()
+        .map(|rule| {
+            let value = rule.value.as_deref().unwrap_or("unset");
+            format!("{} = {value}", rule.name)
+        })
+        .collect();
+    let width = settings.iter().map(String::len).max().unwrap_or(0);
+    for (setting, rule) in settings.iter().zip(&diagnosis.rules) {
+        println!("  {setting:width$}  # {}", rule.source);
+    }
+}
+
+fn print_paths(paths: &[PathBuf]) {
+    if paths.is_empty() {
+        println!("  none");
+    }
+    for path in paths {
+        println!("  {}", display_path(path));
+    }
+}
@@ -0,0 +1,88 @@
+use super::{ProfileArg, cli_rules};
+use basefmt::editorconfig::FormatRules;
+use basefmt::rules::RuleProfile;
+use basefmt::runner::{self, RunOptions};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[derive(clap::Args)]
+pub(crate) struct ExplainArgs {
+    #[clap(help = "File whose rules to show")]
+    path: PathBuf,
+
+    #[clap(
+        long,
+        value_name = "KEY=VALUE",
+        help = "Set a rule as for formatting (repeatable)"
+    )]
+    rule: Vec<FormatRules>,
+
+    #[clap(
+        long,
+        value_name = "RULE",
+        value_parser = FormatRules::disabling,
+        help = "Turn off a rule as for formatting (repeatable)"
+    )]
+    skip_rule: Vec<FormatRules>,
+
+    #[clap(
+        long,
+        visible_alias = "preset",
+        value_enum,
+        help = "Rules for files .editorconfig says nothing about [default: standard]"
+    )]
+    profile: Option<ProfileArg>,
+
+    #[clap(
+        long,
+        value_name = "NAME",
+        env = "BASEFMT_EDITORCONFIG_FILE",
+        help = "Read EditorConfig settings from NAME in each directory instead of .editorconfig"
+    )]
+    editorconfig_file: Option<String>,
+
+    #[clap(
+        long,
+        value_name = "DIR",
+        help = "Don't read EditorConfig files above DIR"
+    )]
+    root_dir: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "Ignore keys of .basefmt.toml files that basefmt doesn't know instead of failing"
+    )]
+    allow_unknown_config_keys: bool,
+}
+
+/// Prints each rule as `name = value`, followed by where it comes from.
+pub(crate) fn explain(args: &ExplainArgs) -> ExitCode {
+    let options = RunOptions {
+        editorconfig_file: args.editorconfig_file.clone(),
+        root_dir: args.root_dir.clone(),
+        rules: cli_rules(&args.rule, &args.skip_rule),
+        profile: args.profile.map(RuleProfile::from),
+        allow_unknown_config_keys: args.allow_unknown_config_keys,
+        ..RunOptions::default()
+    };
+    match runner::explain_rules(Path::new("."), &args.path, &options) {
+        Ok(rules) => {
+            let settings: Vec<String> = rules
+                .iter()
+                .map(|rule| {
+                    let value = rule.value.as_deref().unwrap_or("unset");
+                    format!("{} = {value}", rule.name)
+                })
+                .collect();
+            let width = settings.iter().map(String::len).max().unwrap_or(0);
+            for (setting, rule) in settings.iter().zip(&rules) {
+                println!("{setting:width$}  # {}", rule.source);
+            }
+            ExitCode::from(0)
+        }
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
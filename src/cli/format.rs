@@ -0,0 +1,506 @@
+use super::interrupt::install_interrupt_handler;
+use super::summary::summary;
+use super::{
+    CacheStrategyArg, Command, ErrorFormatArg, OutputArg, ProfileArg, SymlinkArg, cli_rules,
+};
+use basefmt::editorconfig::FormatRules;
+use basefmt::format::{Backup, LineRange, ReadOnlyPolicy, WriteOptions};
+use basefmt::rules::RuleProfile;
+use basefmt::runner::{self, CacheStrategy, Interrupt, RunOptions, Runner};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+pub(crate) struct Args {
+    #[command(subcommand)]
+    pub(crate) command: Option<Command>,
+
+    #[clap(
+        default_value = ".",
+        help = "List of files/directories to format, or - to format stdin to stdout"
+    )]
+    paths: Vec<PathBuf>,
+
+    #[clap(short, long, help = "Check mode (don't write changes)")]
+    check: bool,
+
+    #[clap(
+        long,
+        conflicts_with_all = ["check", "paths"],
+        help = "Serve newline-delimited JSON-RPC formatting requests on stdin for editor plugins"
+    )]
+    ipc: bool,
+
+    #[clap(
+        long,
+        requires = "check",
+        conflicts_with = "write_patch",
+        help = "Stop checking at the first file that needs formatting"
+    )]
+    fail_fast: bool,
+
+    #[clap(
+        long,
+        requires = "check",
+        conflicts_with_all = ["write_patch", "stats"],
+        help = "Stop checking each file at its first violation, reporting only that one"
+    )]
+    first_violation: bool,
+
+    #[clap(
+        long,
+        help = "Show file paths in reports as absolute paths instead of relative to the current directory"
+    )]
+    absolute_paths: bool,
+
+    #[clap(
+        long,
+        value_name = "N",
+        help = "Abort with exit code 4 once N files could not be processed"
+    )]
+    max_errors: Option<NonZeroUsize>,
+
+    #[clap(
+        long,
+        help = "Save a copy of each changed file (suffixed with --backup-suffix) before rewriting it"
+    )]
+    backup: bool,
+
+    #[clap(
+        long,
+        value_name = "SUFFIX",
+        default_value = ".orig",
+        requires = "backup",
+        help = "Suffix appended to backup file names"
+    )]
+    backup_suffix: String,
+
+    #[clap(
+        long,
+        value_name = "DIR",
+        conflicts_with = "backup_suffix",
+        help = "Save backups into DIR, mirroring each file's path (implies --backup)"
+    )]
+    backup_dir: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = SymlinkArg::Follow,
+        help = "How to write files reached through a symbolic link"
+    )]
+    symlinks: SymlinkArg,
+
+    #[clap(
+        long,
+        help = "Format read-only files by temporarily making them writable (they are skipped otherwise)"
+    )]
+    force: bool,
+
+    #[clap(
+        long,
+        help = "Fsync each file's directory after rewriting it, so changes survive a power loss"
+    )]
+    durable: bool,
+
+    #[clap(
+        long,
+        value_name = "DIR",
+        help = "Refuse to modify files outside DIR once symbolic links are resolved"
+    )]
+    restrict_to: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["check", "backup", "backup_dir"],
+        help = "Write formatted copies into DIR, mirroring each file's path, instead of modifying files in place"
+    )]
+    output_dir: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["backup", "backup_dir", "output_dir", "lines"],
+        help = "Write the changes formatting would make to FILE as a patch for git apply, instead of modifying files (implies --check)"
+    )]
+    write_patch: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "START:END",
+        conflicts_with = "check",
+        help = "Only edit lines START to END of a single file (repeatable)"
+    )]
+    lines: Vec<LineRange>,
+
+    #[clap(
+        long,
+        value_name = "BYTES",
+        conflicts_with_all = ["check", "output_dir", "lines"],
+        help = "Format files larger than BYTES line by line instead of reading them into memory whole"
+    )]
+    stream_above: Option<u64>,
+
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = OutputArg::Auto,
+        help = "How to report the outcome of each file"
+    )]
+    output: OutputArg,
+
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = ErrorFormatArg::Human,
+        help = "How to report files that could not be processed in text output"
+    )]
+    error_format: ErrorFormatArg,
+
+    #[clap(
+        long,
+        value_name = "KEY=VALUE",
+        help = "Set a rule for every file, overriding .basefmt.toml and .editorconfig (repeatable), e.g. insert_final_newline=false"
+    )]
+    rule: Vec<FormatRules>,
+
+    #[clap(
+        long,
+        value_name = "RULE",
+        value_parser = FormatRules::disabling,
+        help = "Turn off a rule for every file, overriding --rule, .basefmt.toml and .editorconfig (repeatable), e.g. trim_trailing_whitespace"
+    )]
+    skip_rule: Vec<FormatRules>,
+
+    #[clap(
+        long,
+        visible_alias = "preset",
+        value_enum,
+        help = "Rules for files .editorconfig says nothing about [default: standard]"
+    )]
+    profile: Option<ProfileArg>,
+
+    #[clap(
+        long,
+        help = "Fail with exit code 3, listing the files, if no rule is set for some files"
+    )]
+    require_rules: bool,
+
+    #[clap(
+        long,
+        help = "Fail with exit code 3, listing the files, if no .editorconfig applies to some files"
+    )]
+    require_config: bool,
+
+    #[clap(
+        long,
+        help = "Report how many files and lines violate each rule once every file is done"
+    )]
+    stats: bool,
+
+    #[clap(
+        long,
+        value_name = "FILE",
+        requires = "check",
+        help = "Append a timestamped JSON line of violations per rule and per top-level directory to FILE"
+    )]
+    append_history: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "PATTERN",
+        help = "Leave files matching the glob PATTERN alone, in addition to the exclude patterns of .basefmt.toml (repeatable)"
+    )]
+    exclude: Vec<String>,
+
+    #[clap(
+        long,
+        help = "Also format files in git submodules and other nested repositories"
+    )]
+    include_submodules: bool,
+
+    #[clap(
+        long,
+        help = "Apply .gitignore files even outside a git repository, e.g. in an exported tarball"
+    )]
+    no_require_git: bool,
+
+    #[clap(
+        long,
+        help = "Also search the directories symbolic links point to, each once"
+    )]
+    follow_links: bool,
+
+    #[clap(
+        long,
+        help = "Ignore keys of .basefmt.toml files that basefmt doesn't know instead of failing"
+    )]
+    allow_unknown_config_keys: bool,
+
+    #[clap(
+        long,
+        help = "Fail if a file named on the command line is skipped, e.g. because it is binary"
+    )]
+    error_on_skipped: bool,
+
+    #[clap(
+        long,
+        value_name = "BYTES",
+        help = "Skip files larger than BYTES without reading them"
+    )]
+    max_file_size: Option<u64>,
+
+    #[clap(
+        short,
+        long,
+        value_name = "N",
+        help = "Number of threads to process files with [default: one per CPU core]"
+    )]
+    jobs: Option<NonZeroUsize>,
+
+    #[clap(
+        long,
+        conflicts_with = "jobs",
+        help = "Process files one at a time in discovery order, for reproducible logs"
+    )]
+    sequential: bool,
+
+    #[clap(
+        short,
+        long,
+        help = "List each rewritten file along with what was changed"
+    )]
+    verbose: bool,
+
+    #[clap(
+        long,
+        value_name = "NAME",
+        env = "BASEFMT_EDITORCONFIG_FILE",
+        help = "Read EditorConfig settings from NAME in each directory instead of .editorconfig"
+    )]
+    editorconfig_file: Option<String>,
+
+    #[clap(
+        long,
+        value_name = "DIR",
+        help = "Don't read EditorConfig files above DIR"
+    )]
+    root_dir: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "DIR",
+        help = "Treat DIR as the project root: read .basefmt.toml from it, and match exclude patterns and show paths relative to it"
+    )]
+    base_dir: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "PATH",
+        help = "Format stdin (-) with the rules for PATH, which is shown in reports instead of <stdin>"
+    )]
+    stdin_path: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "Process every file, instead of skipping files a previous run found formatted"
+    )]
+    no_cache: bool,
+
+    #[clap(
+        long,
+        value_name = "DIR",
+        env = "BASEFMT_CACHE_DIR",
+        help = "Keep the cache of formatted files in DIR [default: .basefmt-cache in the project root]"
+    )]
+    cache_dir: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_enum,
+        help = "How to tell whether a cached file has changed [default: metadata]"
+    )]
+    cache_strategy: Option<CacheStrategyArg>,
+
+    #[clap(
+        long,
+        help = "Don't wait for other runs formatting the same project, nor make them wait, and don't lock files while replacing them"
+    )]
+    no_lock: bool,
+
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 10,
+        help = "How long to wait for another process that holds the lock of a file to be replaced before failing it"
+    )]
+    file_lock_timeout: u64,
+}
+
+impl Args {
+    fn rules(&self) -> FormatRules {
+        cli_rules(&self.rule, &self.skip_rule)
+    }
+
+    fn backup(&self) -> Option<Backup> {
+        if let Some(dir) = &self.backup_dir {
+            Some(Backup::Directory(dir.clone()))
+        } else if self.backup {
+            Some(Backup::Suffix(self.backup_suffix.clone()))
+        } else {
+            None
+        }
+    }
+}
+
+/// Formats, or with `--check` checks, the files given on the command line.
+pub(crate) fn format(args: &Args) -> ExitCode {
+    let stdin = args.paths.iter().any(|path| path == Path::new("-"));
+    if stdin && args.paths.len() > 1 {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "- can't be combined with other paths",
+            )
+            .exit();
+    }
+    if stdin && (args.write_patch.is_some() || args.output_dir.is_some()) {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "- can't be combined with --write-patch or --output-dir",
+            )
+            .exit();
+    }
+    if !args.lines.is_empty() && !matches!(args.paths.as_slice(), [path] if path.is_file()) {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--lines requires exactly one file path",
+            )
+            .exit();
+    }
+
+    let options = RunOptions {
+        write: WriteOptions {
+            backup: args.backup(),
+            symlinks: args.symlinks.into(),
+            read_only: if args.force {
+                ReadOnlyPolicy::Force
+            } else {
+                ReadOnlyPolicy::Skip
+            },
+            durable: args.durable,
+            output_dir: args.output_dir.clone(),
+            lines: args.lines.clone(),
+            stream_above: args.stream_above,
+            lock_timeout: (!args.no_lock).then(|| Duration::from_secs(args.file_lock_timeout)),
+            restrict_to: args.restrict_to.clone(),
+        },
+        output: args.output.into(),
+        error_format: args.error_format.into(),
+        // A run blocked on stdin can only be stopped by exiting
+        interrupt: if args.ipc || stdin {
+            Interrupt::new()
+        } else {
+            install_interrupt_handler()
+        },
+        exclude: args.exclude.clone(),
+        include_submodules: args.include_submodules,
+        no_require_git: args.no_require_git,
+        follow_links: args.follow_links,
+        allow_unknown_config_keys: args.allow_unknown_config_keys,
+        jobs: args.jobs,
+        fail_fast: args.fail_fast,
+        first_violation: args.first_violation,
+        absolute_paths: args.absolute_paths,
+        max_errors: args.max_errors,
+        error_on_skipped: args.error_on_skipped,
+        max_file_size: args.max_file_size,
+        sequential: args.sequential,
+        verbose: args.verbose,
+        editorconfig_file: args.editorconfig_file.clone(),
+        root_dir: args.root_dir.clone(),
+        base_dir: args.base_dir.clone(),
+        rules: args.rules(),
+        profile: args.profile.map(RuleProfile::from),
+        require_rules: args.require_rules,
+        require_config: args.require_config,
+        stats: args.stats,
+        append_history: args.append_history.clone(),
+        write_patch: args.write_patch.clone(),
+        cache: !args.no_cache,
+        cache_dir: args.cache_dir.clone(),
+        cache_strategy: args.cache_strategy.map(CacheStrategy::from),
+        lock: !args.no_lock,
+    };
+    if args.ipc {
+        return match runner::serve_ipc(
+            options.base_dir.as_deref().unwrap_or(Path::new(".")),
+            io::stdin().lock(),
+            io::stdout().lock(),
+            &options,
+        ) {
+            Ok(()) => ExitCode::from(0),
+            Err(err) => {
+                eprintln!("Error: {err}");
+                ExitCode::from(2)
+            }
+        };
+    }
+    let check = args.check || args.write_patch.is_some();
+    let result = if stdin {
+        runner::run_stdin(
+            options.base_dir.as_deref().unwrap_or(Path::new(".")),
+            args.stdin_path.as_deref().unwrap_or(Path::new("<stdin>")),
+            io::stdin().lock(),
+            io::stdout().lock(),
+            check,
+            &options,
+        )
+    } else {
+        Runner::new()
+            .paths(&args.paths)
+            .check(check)
+            .options(options)
+            .run()
+    };
+
+    match result {
+        Ok(result) => {
+            if result.interrupted_count > 0 {
+                eprintln!(
+                    "Interrupted: processed {} of {} files",
+                    result.total_files - result.interrupted_count,
+                    result.total_files
+                );
+            }
+            if result.aborted {
+                let errors = if result.error_count == 1 {
+                    "error"
+                } else {
+                    "errors"
+                };
+                eprintln!(
+                    "Aborted after {} {errors}, leaving {} of {} files unprocessed",
+                    result.error_count, result.cancelled_count, result.total_files
+                );
+            }
+            if args.verbose {
+                eprintln!("{}", summary(&result, check));
+            }
+            ExitCode::from(result.exit_code())
+        }
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use basefmt::runner::Interrupt;
+
+/// Lets the first Ctrl-C finish the files already in progress, so no
+/// temporary files are left behind; a second one exits immediately.
+pub(super) fn install_interrupt_handler() -> Interrupt {
+    let interrupt = Interrupt::new();
+    let handler = interrupt.clone();
+    let installed = ctrlc::set_handler(move || {
+        if handler.is_triggered() {
+            std::process::exit(130);
+        }
+        handler.trigger();
+    });
+    if let Err(err) = installed {
+        eprintln!("Warning: failed to install Ctrl-C handler: {err}");
+    }
+    interrupt
+}
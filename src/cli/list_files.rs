@@ -0,0 +1,79 @@
+use basefmt::runner::{self, RunOptions, display_path};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(clap::Args)]
+pub(crate) struct ListFilesArgs {
+    #[clap(default_value = ".", help = "List of files/directories to search")]
+    paths: Vec<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "PATTERN",
+        help = "Leave files matching the glob PATTERN out, in addition to the exclude patterns of .basefmt.toml (repeatable)"
+    )]
+    exclude: Vec<String>,
+
+    #[clap(
+        long,
+        help = "Also list files in git submodules and other nested repositories"
+    )]
+    include_submodules: bool,
+
+    #[clap(
+        long,
+        help = "Apply .gitignore files even outside a git repository, e.g. in an exported tarball"
+    )]
+    no_require_git: bool,
+
+    #[clap(
+        long,
+        help = "Also search the directories symbolic links point to, each once"
+    )]
+    follow_links: bool,
+
+    #[clap(
+        long,
+        help = "Ignore keys of .basefmt.toml files that basefmt doesn't know instead of failing"
+    )]
+    allow_unknown_config_keys: bool,
+
+    #[clap(
+        short = '0',
+        long,
+        help = "End each path with a NUL byte instead of a newline, writing it exactly as it is"
+    )]
+    null: bool,
+}
+
+/// Prints the files a run would process, one per line or NUL-terminated.
+pub(crate) fn list_files(args: &ListFilesArgs) -> ExitCode {
+    let options = RunOptions {
+        exclude: args.exclude.clone(),
+        include_submodules: args.include_submodules,
+        no_require_git: args.no_require_git,
+        follow_links: args.follow_links,
+        allow_unknown_config_keys: args.allow_unknown_config_keys,
+        ..RunOptions::default()
+    };
+    let result = runner::list_files(&args.paths, &options).and_then(|files| {
+        let mut stdout = io::stdout().lock();
+        for file in files {
+            if args.null {
+                stdout.write_all(file.as_os_str().as_encoded_bytes())?;
+                stdout.write_all(b"\0")?;
+            } else {
+                writeln!(stdout, "{}", display_path(&file))?;
+            }
+        }
+        stdout.flush()
+    });
+    match result {
+        Ok(()) => ExitCode::from(0),
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
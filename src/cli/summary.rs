@@ -0,0 +1,45 @@
+use basefmt::runner::RunnerResult;
+
+/// Describes what happened to the files of a run, e.g.
+/// `3 files: 1 changed, 1 unchanged, 1 skipped (1 binary)`.
+pub(super) fn summary(result: &RunnerResult, check: bool) -> String {
+    let mut counts = if check {
+        vec![format!("{} need formatting", result.unformatted_count)]
+    } else {
+        vec![format!("{} changed", result.changed_count)]
+    };
+    counts.push(format!("{} unchanged", result.unchanged_count));
+    let reasons: Vec<String> = [
+        (result.binary_count, "binary"),
+        (result.minified_count, "minified"),
+        (result.generated_count, "generated"),
+        (result.excluded_count, "excluded"),
+        (result.no_rules_count, "without rules"),
+        (result.too_large_count, "too large"),
+    ]
+    .into_iter()
+    .filter(|&(count, _)| count > 0)
+    .map(|(count, reason)| format!("{count} {reason}"))
+    .collect();
+    if reasons.is_empty() {
+        counts.push(format!("{} skipped", result.skipped_count));
+    } else {
+        counts.push(format!(
+            "{} skipped ({})",
+            result.skipped_count,
+            reasons.join(", ")
+        ));
+    }
+    if result.error_count > 0 {
+        counts.push(format!("{} failed", result.error_count));
+    }
+    if result.cancelled_count > 0 {
+        counts.push(format!("{} not checked", result.cancelled_count));
+    }
+    let files = if result.total_files == 1 {
+        "file"
+    } else {
+        "files"
+    };
+    format!("{} {files}: {}", result.total_files, counts.join(", "))
+}
@@ -0,0 +1,61 @@
+use basefmt::runner::{self, RunOptions, display_path};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[derive(clap::Args)]
+pub(crate) struct WhyArgs {
+    #[clap(help = "File to explain")]
+    path: PathBuf,
+
+    #[clap(
+        long,
+        value_name = "PATTERN",
+        help = "Leave files matching the glob PATTERN out, in addition to the exclude patterns of .basefmt.toml (repeatable)"
+    )]
+    exclude: Vec<String>,
+
+    #[clap(
+        long,
+        help = "Also search git submodules and other nested repositories"
+    )]
+    include_submodules: bool,
+
+    #[clap(
+        long,
+        help = "Apply .gitignore files even outside a git repository, e.g. in an exported tarball"
+    )]
+    no_require_git: bool,
+
+    #[clap(
+        long,
+        help = "Ignore keys of .basefmt.toml files that basefmt doesn't know instead of failing"
+    )]
+    allow_unknown_config_keys: bool,
+}
+
+/// Prints whether a run over the current directory would process the file
+/// and, if not, why, exiting with 1 if it wouldn't.
+pub(crate) fn why(args: &WhyArgs) -> ExitCode {
+    let options = RunOptions {
+        exclude: args.exclude.clone(),
+        include_submodules: args.include_submodules,
+        no_require_git: args.no_require_git,
+        allow_unknown_config_keys: args.allow_unknown_config_keys,
+        ..RunOptions::default()
+    };
+    let path = display_path(&args.path);
+    match runner::explain_exclusion(Path::new("."), &args.path, &options) {
+        Ok(None) => {
+            println!("{path}: processed");
+            ExitCode::from(0)
+        }
+        Ok(Some(exclusion)) => {
+            println!("{path}: not processed: {exclusion}");
+            ExitCode::from(1)
+        }
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
@@ -1,15 +1,75 @@
+use crate::editorconfig::FormatRules;
+use crate::error::BasefmtError;
+use crate::filesystem::{FileSystem, RealFileSystem};
+use crate::rules::{RuleProfile, RuleTable};
+use crate::runner::CacheStrategy;
 use globset::{Glob, GlobSet, GlobSetBuilder};
 use serde::Deserialize;
-use std::fs;
-use std::io;
-use std::path::Path;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+mod extends;
+mod format;
+mod keys;
+mod nested;
+
+use format::ConfigText;
+pub(crate) use format::file_names;
+pub use nested::NestedConfigs;
 
 /// Configuration for basefmt, typically loaded from .basefmt.toml
 #[derive(Debug)]
 pub struct Config {
+    /// Path to the file the configuration was loaded from, if any
+    pub path: Option<PathBuf>,
+
+    /// Paths of the files it extends, directly or not, nearest first
+    pub extends: Vec<PathBuf>,
+
     /// List of glob patterns to exclude from formatting
     pub exclude: Vec<String>,
 
+    /// Number of threads to process files with; one per CPU core if unset
+    pub jobs: Option<NonZeroUsize>,
+
+    /// Name of the EditorConfig file to look up in each directory instead of
+    /// `.editorconfig`, e.g. `.editorconfig-ci`
+    pub editorconfig_file: Option<String>,
+
+    /// Whether the directory of `.basefmt.toml` is the project root, above
+    /// which no EditorConfig file is read
+    pub root: bool,
+
+    /// Rules from the `[rules]` table, which override EditorConfig
+    pub rules: FormatRules,
+
+    /// Rules from the `[defaults]` table, for files EditorConfig says
+    /// nothing about; they take precedence over the built-in rules for the
+    /// file's extension and the profile
+    pub defaults: FormatRules,
+
+    /// Rules for files EditorConfig says nothing about
+    pub profile: Option<RuleProfile>,
+
+    /// Whether files get built-in rules for their extension where
+    /// EditorConfig says nothing, e.g. keeping trailing whitespace in
+    /// Markdown (see [`RuleResolver::with_language_defaults`])
+    ///
+    /// [`RuleResolver::with_language_defaults`]: crate::rules::RuleResolver::with_language_defaults
+    pub language_defaults: bool,
+
+    /// Whether `.gitignore` files only apply inside a git repository, as in
+    /// git; unset, they also apply to trees without a `.git` directory, such
+    /// as an exported tarball
+    pub require_git: bool,
+
+    /// Directory to keep the cache of formatted files in, relative to the
+    /// directory of `.basefmt.toml`; `.basefmt-cache` there if unset
+    pub cache_dir: Option<PathBuf>,
+
+    /// How the cache tells whether a file has changed
+    pub cache_strategy: Option<CacheStrategy>,
+
     /// Pre-built GlobSet for efficient matching
     matcher: GlobSet,
 }
@@ -26,57 +86,142 @@ impl Config {
     /// # Returns
     ///
     /// Returns `Ok(Config)` with the loaded or default configuration, or an error
-    /// if the file exists but cannot be read or parsed.
-    pub fn load(dir: &Path) -> io::Result<Self> {
-        let config_path = dir.join(".basefmt.toml");
+    /// if the file exists but cannot be read or parsed, including if it has a
+    /// key basefmt doesn't know.
+    pub fn load(dir: &Path) -> Result<Self, BasefmtError> {
+        Self::load_in(&RealFileSystem, dir)
+    }
 
-        if !config_path.exists() {
-            return Ok(Config::default());
-        }
+    /// Loads configuration from .basefmt.toml in the specified directory of
+    /// `fs`, as [`Config::load`] does from disk.
+    pub fn load_in(fs: &dyn FileSystem, dir: &Path) -> Result<Self, BasefmtError> {
+        Ok(Self::load_existing_in(fs, dir, false)?.unwrap_or_default())
+    }
 
-        let content = fs::read_to_string(&config_path)?;
+    /// Loads configuration like [`Config::load`], but ignores the keys
+    /// basefmt doesn't know instead of failing on them, e.g. for a file
+    /// written for a newer version.
+    pub fn load_ignoring_unknown_keys(dir: &Path) -> Result<Self, BasefmtError> {
+        Ok(Self::load_existing_in(&RealFileSystem, dir, true)?.unwrap_or_default())
+    }
+
+    /// Loads configuration from .basefmt.toml in the specified directory of
+    /// `fs` like [`Config::load_in`], or returns `None` if there is none.
+    /// Unknown keys are ignored if `ignore_unknown_keys` is set.
+    pub(crate) fn load_existing_in(
+        fs: &dyn FileSystem,
+        dir: &Path,
+        ignore_unknown_keys: bool,
+    ) -> Result<Option<Self>, BasefmtError> {
+        let Some(text) = format::read_config_file(fs, dir)? else {
+            return Ok(None);
+        };
 
         #[derive(Deserialize)]
         struct ConfigFile {
             #[serde(default)]
             exclude: Vec<String>,
+            jobs: Option<NonZeroUsize>,
+            editorconfig_file: Option<String>,
+            #[serde(default)]
+            root: bool,
+            #[serde(default)]
+            rules: RuleTable,
+            #[serde(default)]
+            defaults: RuleTable,
+            #[serde(alias = "preset")]
+            profile: Option<RuleProfile>,
+            language_defaults: Option<bool>,
+            require_git: Option<bool>,
+            cache_dir: Option<PathBuf>,
+            cache_strategy: Option<CacheStrategy>,
         }
 
-        let config_file: ConfigFile = toml::from_str(&content).map_err(|err| {
-            io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("failed to parse .basefmt.toml: {err}"),
-            )
-        })?;
+        let resolved = extends::resolve(fs, &text, ignore_unknown_keys)?;
+        let ConfigText {
+            path,
+            format,
+            content,
+        } = text;
+        let config_file: ConfigFile = if resolved.changed {
+            format.parse(&path, &format.render(&path, &resolved.table)?)?
+        } else {
+            // Parsing the text again keeps the location of invalid values in
+            // the error
+            format.parse(&path, &content)?
+        };
 
         let matcher = Self::build_matcher(&config_file.exclude)?;
 
-        Ok(Config {
+        Ok(Some(Config {
+            path: Some(path),
+            extends: resolved.bases,
             exclude: config_file.exclude,
+            jobs: config_file.jobs,
+            editorconfig_file: config_file.editorconfig_file,
+            root: config_file.root,
+            rules: config_file.rules.into(),
+            defaults: config_file.defaults.into(),
+            profile: config_file.profile,
+            language_defaults: config_file.language_defaults.unwrap_or(true),
+            require_git: config_file.require_git.unwrap_or(true),
+            cache_dir: config_file.cache_dir,
+            cache_strategy: config_file.cache_strategy,
+            matcher,
+        }))
+    }
+
+    /// Creates a configuration that excludes the given glob patterns, without
+    /// reading any `.basefmt.toml`.
+    ///
+    /// Returns an error if any pattern is not a valid glob.
+    pub fn with_exclude(patterns: Vec<String>) -> Result<Self, BasefmtError> {
+        let matcher = Self::build_matcher(&patterns)?;
+        Ok(Config {
+            path: None,
+            extends: Vec::new(),
+            exclude: patterns,
+            jobs: None,
+            editorconfig_file: None,
+            root: false,
+            rules: FormatRules::default(),
+            defaults: FormatRules::default(),
+            profile: None,
+            language_defaults: true,
+            require_git: true,
+            cache_dir: None,
+            cache_strategy: None,
             matcher,
         })
     }
 
+    /// Adds `patterns` to the exclude patterns of this configuration.
+    ///
+    /// Returns an error if any pattern is not a valid glob.
+    pub fn with_additional_exclude(mut self, patterns: &[String]) -> Result<Self, BasefmtError> {
+        if patterns.is_empty() {
+            return Ok(self);
+        }
+        self.exclude.extend_from_slice(patterns);
+        self.matcher = Self::build_matcher(&self.exclude)?;
+        Ok(self)
+    }
+
     /// Builds a GlobSet from the exclude patterns for efficient matching.
-    fn build_matcher(patterns: &[String]) -> io::Result<GlobSet> {
+    fn build_matcher(patterns: &[String]) -> Result<GlobSet, BasefmtError> {
         let mut builder = GlobSetBuilder::new();
 
         for pattern in patterns {
-            let glob = Glob::new(pattern).map_err(|err| {
-                io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    format!("invalid glob pattern '{pattern}': {err}"),
-                )
+            let glob = Glob::new(pattern).map_err(|source| BasefmtError::InvalidGlob {
+                pattern: pattern.clone(),
+                source,
             })?;
             builder.add(glob);
         }
 
-        builder.build().map_err(|err| {
-            io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!("failed to build glob set: {err}"),
-            )
-        })
+        builder
+            .build()
+            .map_err(|source| BasefmtError::GlobSet { source })
     }
 
     /// Checks if a file should be excluded based on the exclude patterns.
@@ -93,33 +238,46 @@ impl Config {
     pub fn is_excluded(&self, path: &Path) -> bool {
         self.matcher.is_match(path)
     }
+
+    /// Returns the first exclude pattern that matches `path`, if any.
+    pub fn matching_exclude(&self, path: &Path) -> Option<&str> {
+        self.matcher
+            .matches(path)
+            .first()
+            .and_then(|&index| self.exclude.get(index))
+            .map(String::as_str)
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
+            path: None,
+            extends: Vec::new(),
             exclude: Vec::new(),
+            jobs: None,
+            editorconfig_file: None,
+            root: false,
+            rules: FormatRules::default(),
+            defaults: FormatRules::default(),
+            profile: None,
+            language_defaults: true,
+            require_git: true,
+            cache_dir: None,
+            cache_strategy: None,
             matcher: GlobSet::empty(),
         }
     }
 }
 
-#[cfg(test)]
-impl Config {
-    fn with_exclude(patterns: Vec<String>) -> io::Result<Self> {
-        let matcher = Self::build_matcher(&patterns)?;
-        Ok(Config {
-            exclude: patterns,
-            matcher,
-        })
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::filesystem::MemoryFileSystem;
     use indoc::indoc;
+    use rstest::rstest;
     use std::fs;
+    use std::io;
     use tempfile::TempDir;
 
     #[test]
@@ -176,6 +334,300 @@ mod tests {
         assert_eq!(config.exclude, vec!["*.min.*", "test/**", "vendor/**"]);
     }
 
+    #[test]
+    fn test_config_load_jobs() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(".basefmt.toml");
+        fs::write(&config_path, "jobs = 4\n").unwrap();
+
+        let config = Config::load(temp_dir.path()).unwrap();
+
+        assert_eq!(config.jobs, NonZeroUsize::new(4));
+    }
+
+    #[test]
+    fn test_config_load_editorconfig_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(".basefmt.toml");
+        fs::write(&config_path, "editorconfig_file = \".editorconfig-ci\"\n").unwrap();
+
+        let config = Config::load(temp_dir.path()).unwrap();
+
+        assert_eq!(
+            config.editorconfig_file.as_deref(),
+            Some(".editorconfig-ci")
+        );
+    }
+
+    #[test]
+    fn test_config_load_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(".basefmt.toml");
+        fs::write(
+            &config_path,
+            indoc! {r#"
+                cache_dir = "target/basefmt"
+                cache_strategy = "content"
+            "#},
+        )
+        .unwrap();
+
+        let config = Config::load(temp_dir.path()).unwrap();
+
+        assert_eq!(
+            (config.cache_dir, config.cache_strategy),
+            (
+                Some(PathBuf::from("target/basefmt")),
+                Some(CacheStrategy::Content)
+            )
+        );
+    }
+
+    #[test]
+    fn test_config_load_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(".basefmt.toml");
+        fs::write(&config_path, "root = true\n").unwrap();
+
+        let config = Config::load(temp_dir.path()).unwrap();
+
+        assert!(config.root);
+    }
+
+    #[test]
+    fn test_config_load_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(".basefmt.toml");
+        fs::write(
+            &config_path,
+            indoc! {"
+                [rules]
+                trim_trailing_whitespace = true
+                max_blank_lines = 1
+            "},
+        )
+        .unwrap();
+
+        let config = Config::load(temp_dir.path()).unwrap();
+
+        assert_eq!(
+            config.rules,
+            FormatRules {
+                remove_trailing_spaces: Some(true),
+                max_blank_lines: Some(1),
+                ..FormatRules::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_config_load_defaults() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join(".basefmt.toml"),
+            indoc! {"
+                [defaults]
+                insert_final_newline = true
+                trim_leading_newlines = false
+            "},
+        )
+        .unwrap();
+
+        let config = Config::load(temp_dir.path()).unwrap();
+
+        assert_eq!(
+            (config.rules, config.defaults),
+            (
+                FormatRules::default(),
+                FormatRules {
+                    ensure_final_newline: Some(true),
+                    remove_leading_newlines: Some(false),
+                    ..FormatRules::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_config_load_profile() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(".basefmt.toml");
+        fs::write(&config_path, "profile = \"minimal\"\n").unwrap();
+
+        let config = Config::load(temp_dir.path()).unwrap();
+
+        assert_eq!(config.profile, Some(RuleProfile::Minimal));
+    }
+
+    #[rstest]
+    #[case::enabled_by_default("", true)]
+    #[case::disabled("language_defaults = false\n", false)]
+    fn test_config_load_language_defaults(#[case] content: &str, #[case] expected: bool) {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join(".basefmt.toml"), content).unwrap();
+
+        let config = Config::load(temp_dir.path()).unwrap();
+
+        assert_eq!(config.language_defaults, expected);
+    }
+
+    #[rstest]
+    #[case::required_by_default("", true)]
+    #[case::not_required("require_git = false\n", false)]
+    fn test_config_load_require_git(#[case] content: &str, #[case] expected: bool) {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join(".basefmt.toml"), content).unwrap();
+
+        let config = Config::load(temp_dir.path()).unwrap();
+
+        assert_eq!(config.require_git, expected);
+    }
+
+    #[test]
+    fn test_config_load_unknown_rule() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(".basefmt.toml");
+        fs::write(
+            &config_path,
+            indoc! {"
+                [rules]
+                trim_trailing_whitespaces = true
+            "},
+        )
+        .unwrap();
+
+        let err = Config::load(temp_dir.path()).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[rstest]
+    #[case::misspelled(
+        "exlcude = [\"*.md\"]\n",
+        "unknown key 'exlcude' (did you mean 'exclude'?)"
+    )]
+    #[case::unrelated("colour = true\n", "unknown key 'colour'")]
+    #[case::rule(
+        "[rules]\nmax_blank_line = 1\n", // ast-grep-ignore: prefer-indoc
+        "unknown key 'rules.max_blank_line' (did you mean 'rules.max_blank_lines'?)"
+    )]
+    fn test_config_load_unknown_key(#[case] content: &str, #[case] expected: &str) {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(".basefmt.toml");
+        fs::write(&config_path, content).unwrap();
+
+        let err = Config::load(temp_dir.path()).unwrap_err();
+
+        assert_eq!(
+            (err.kind(), err.to_string()),
+            (
+                io::ErrorKind::InvalidData,
+                format!("{}: {expected}", config_path.display())
+            )
+        );
+    }
+
+    #[test]
+    fn test_config_load_ignoring_unknown_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join(".basefmt.toml"),
+            indoc! {r#"
+                exclude = ["*.md"]
+                colour = true
+
+                [rules]
+                max_blank_lines = 1
+                max_line_length = 80
+            "#},
+        )
+        .unwrap();
+
+        let config = Config::load_ignoring_unknown_keys(temp_dir.path()).unwrap();
+
+        assert_eq!(
+            (config.exclude, config.rules),
+            (
+                vec!["*.md".to_string()],
+                FormatRules {
+                    max_blank_lines: Some(1),
+                    ..FormatRules::default()
+                }
+            )
+        );
+    }
+
+    #[rstest]
+    #[case::yaml(
+        ".basefmt.yaml",
+        indoc! {"
+            exclude:
+              - '*.md'
+            rules:
+              max_blank_lines: 1
+        "}
+    )]
+    #[case::json(
+        ".basefmt.json",
+        r#"{"exclude": ["*.md"], "rules": {"max_blank_lines": 1}}"#
+    )]
+    fn test_config_load_other_formats(#[case] name: &str, #[case] content: &str) {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(name);
+        fs::write(&config_path, content).unwrap();
+
+        let config = Config::load(temp_dir.path()).unwrap();
+
+        assert_eq!(
+            (config.path, config.exclude, config.rules),
+            (
+                Some(config_path),
+                vec!["*.md".to_string()],
+                FormatRules {
+                    max_blank_lines: Some(1),
+                    ..FormatRules::default()
+                }
+            )
+        );
+    }
+
+    #[rstest]
+    #[case::empty_yaml(".basefmt.yaml", "", Ok(Vec::new()))]
+    #[case::unknown_key_in_json(
+        ".basefmt.json",
+        r#"{"exlcude": []}"#,
+        Err("unknown key 'exlcude' (did you mean 'exclude'?)")
+    )]
+    fn test_config_load_other_formats_edge_cases(
+        #[case] name: &str,
+        #[case] content: &str,
+        #[case] expected: Result<Vec<String>, &str>,
+    ) {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(name);
+        fs::write(&config_path, content).unwrap();
+
+        let result = Config::load(temp_dir.path());
+
+        assert_eq!(
+            result
+                .map(|config| config.exclude)
+                .map_err(|err| err.to_string()),
+            expected.map_err(|message| format!("{}: {message}", config_path.display()))
+        );
+    }
+
+    #[test]
+    fn test_config_load_zero_jobs() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(".basefmt.toml");
+        fs::write(&config_path, "jobs = 0\n").unwrap();
+
+        let err = Config::load(temp_dir.path()).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
     #[test]
     fn test_config_load_invalid_toml() {
         let temp_dir = TempDir::new().unwrap();
@@ -190,6 +642,7 @@ mod tests {
             err.to_string(),
             format!("failed to parse .basefmt.toml: {toml_err}")
         );
+        assert!(matches!(err, BasefmtError::ConfigSyntax { path, .. } if path == config_path));
     }
 
     #[test]
@@ -240,6 +693,21 @@ mod tests {
         assert!(!config.is_excluded(Path::new("src/main.js")));
     }
 
+    #[test]
+    fn test_matching_exclude() {
+        let config =
+            Config::with_exclude(vec!["test/**".to_string(), "*.min.*".to_string()]).unwrap();
+
+        assert_eq!(
+            [
+                config.matching_exclude(Path::new("test/app.min.js")),
+                config.matching_exclude(Path::new("app.min.js")),
+                config.matching_exclude(Path::new("src/main.js")),
+            ],
+            [Some("test/**"), Some("*.min.*"), None]
+        );
+    }
+
     #[test]
     fn test_is_excluded_no_patterns() {
         let config = Config::with_exclude(Vec::new()).unwrap();
@@ -281,6 +749,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_with_additional_exclude() {
+        let config = Config::with_exclude(vec!["vendor/**".to_string()])
+            .unwrap()
+            .with_additional_exclude(&["*.min.js".to_string()])
+            .unwrap();
+
+        assert_eq!(
+            (
+                config.exclude.clone(),
+                config.is_excluded(Path::new("vendor/lib.js")),
+                config.is_excluded(Path::new("app.min.js")),
+                config.is_excluded(Path::new("app.js"))
+            ),
+            (
+                vec!["vendor/**".to_string(), "*.min.js".to_string()],
+                true,
+                true,
+                false
+            )
+        );
+    }
+
     #[test]
     fn test_is_excluded_specific_file() {
         let config = Config::with_exclude(vec!["specific/file.txt".to_string()]).unwrap();
@@ -298,4 +789,17 @@ mod tests {
         assert!(config.is_excluded(Path::new("file.TXT")));
         assert!(!config.is_excluded(Path::new("file.txt")));
     }
+
+    #[test]
+    fn test_config_load_in_memory() {
+        let fs =
+            MemoryFileSystem::new().with_file("/p/.basefmt.toml", "exclude = [\"*.min.js\"]\n");
+
+        let config = Config::load_in(&fs, Path::new("/p")).unwrap();
+
+        assert_eq!(
+            (config.is_excluded(Path::new("app.min.js")), config.exclude),
+            (true, vec!["*.min.js".to_string()])
+        );
+    }
 }
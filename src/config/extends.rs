@@ -0,0 +1,195 @@
+use super::format::{ConfigText, Format, read_config_text};
+use super::keys;
+use crate::canonical::normalize;
+use crate::error::BasefmtError;
+use crate::filesystem::FileSystem;
+use std::path::{Path, PathBuf};
+
+/// A configuration file parsed along with the files it extends.
+pub(super) struct Resolved {
+    /// The keys of the file layered over those of the files it extends
+    pub(super) table: toml::Table,
+    /// Whether `table` differs from what the file says on its own, having
+    /// keys merged in or removed
+    pub(super) changed: bool,
+    /// The files it extends, directly or not, nearest first
+    pub(super) bases: Vec<PathBuf>,
+}
+
+/// Parses the configuration file `text` and, if it has an `extends` key, the
+/// file that names, which is resolved relative to the directory of `text`
+/// and may extend another in turn.
+///
+/// Tables such as `[rules]` are merged key by key, so that a file only needs
+/// to set the rules it changes; any other key a file sets replaces what the
+/// file it extends says. The keys basefmt doesn't know are removed, or
+/// rejected unless `ignore_unknown_keys` is set.
+pub(super) fn resolve(
+    fs: &dyn FileSystem,
+    text: &ConfigText,
+    ignore_unknown_keys: bool,
+) -> Result<Resolved, BasefmtError> {
+    let mut chain = Vec::new();
+    resolve_chain(fs, text, ignore_unknown_keys, &mut chain)
+}
+
+fn resolve_chain(
+    fs: &dyn FileSystem,
+    text: &ConfigText,
+    ignore_unknown_keys: bool,
+    chain: &mut Vec<PathBuf>,
+) -> Result<Resolved, BasefmtError> {
+    let mut table: toml::Table = text.format.parse(&text.path, &text.content)?;
+    let unknown_keys = keys::remove_unknown_keys(&mut table);
+    if let Some(unknown) = unknown_keys.first()
+        && !ignore_unknown_keys
+    {
+        return Err(BasefmtError::UnknownConfigKey {
+            path: text.path.clone(),
+            key: unknown.key.clone(),
+            suggestion: unknown.suggestion.clone(),
+        });
+    }
+    let changed = !unknown_keys.is_empty();
+
+    let Some(extends) = table.remove("extends") else {
+        return Ok(Resolved {
+            table,
+            changed,
+            bases: Vec::new(),
+        });
+    };
+    let toml::Value::String(base) = extends else {
+        return Err(BasefmtError::InvalidExtends {
+            path: text.path.clone(),
+        });
+    };
+    let dir = text.path.parent().unwrap_or(Path::new(""));
+    let base_path = normalize(&dir.join(base));
+    chain.push(normalize(&text.path));
+    if chain.contains(&base_path) {
+        return Err(BasefmtError::ExtendsCycle {
+            path: text.path.clone(),
+            base: base_path,
+        });
+    }
+
+    let format = Format::for_path(&base_path);
+    let base_text = read_config_text(fs, base_path.clone(), format)?;
+    let mut resolved = resolve_chain(fs, &base_text, ignore_unknown_keys, chain)?;
+    // `preset` is an alias of `profile`, which either file may use
+    if let Some(profile) = table.remove("preset") {
+        table.entry("profile").or_insert(profile);
+    }
+    if let Some(profile) = resolved.table.remove("preset") {
+        resolved.table.entry("profile").or_insert(profile);
+    }
+    merge(&mut resolved.table, table);
+    resolved.bases.insert(0, base_path);
+    resolved.changed = true;
+    Ok(resolved)
+}
+
+/// Layers the keys of `table` over those of `base`, merging tables key by
+/// key.
+fn merge(base: &mut toml::Table, table: toml::Table) {
+    for (key, value) in table {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(table)) => merge(base, table),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Config;
+    use super::*;
+    use crate::editorconfig::FormatRules;
+    use crate::filesystem::MemoryFileSystem;
+    use crate::rules::RuleProfile;
+    use indoc::indoc;
+    use rstest::rstest;
+
+    #[test]
+    fn test_extends_layers_over_base() {
+        let fs = MemoryFileSystem::new()
+            .with_file(
+                "/org/base.yaml",
+                indoc! {"
+                    preset: minimal
+                    exclude: ['vendor/**']
+                    rules:
+                      trim_trailing_whitespace: true
+                      max_blank_lines: 1
+                "},
+            )
+            .with_file(
+                "/org/policy.toml",
+                indoc! {r#"
+                    extends = "base.yaml"
+                    jobs = 2
+                "#},
+            )
+            .with_file(
+                "/p/.basefmt.toml",
+                indoc! {r#"
+                    extends = "../org/./policy.toml"
+                    exclude = ["dist/**"]
+
+                    [rules]
+                    max_blank_lines = 2
+                "#},
+            );
+
+        let config = Config::load_in(&fs, Path::new("/p")).unwrap();
+
+        assert_eq!(
+            (
+                config.extends,
+                config.exclude,
+                config.jobs.map(usize::from),
+                config.profile,
+                config.rules
+            ),
+            (
+                vec![
+                    PathBuf::from("/org/policy.toml"),
+                    PathBuf::from("/org/base.yaml")
+                ],
+                vec!["dist/**".to_string()],
+                Some(2),
+                Some(RuleProfile::Minimal),
+                FormatRules {
+                    remove_trailing_spaces: Some(true),
+                    max_blank_lines: Some(2),
+                    ..FormatRules::default()
+                }
+            )
+        );
+    }
+
+    #[rstest]
+    #[case::cycle(
+        "extends = \"b.toml\"\n",
+        "extends = \"../p/.basefmt.toml\"\n",
+        "/p/b.toml: 'extends' forms a cycle through /p/.basefmt.toml"
+    )]
+    #[case::not_a_path("extends = 1\n", "", "/p/.basefmt.toml: 'extends' must be a path")]
+    #[case::unknown_key_in_base(
+        "extends = \"b.toml\"\n",
+        "exlcude = []\n",
+        "/p/b.toml: unknown key 'exlcude' (did you mean 'exclude'?)"
+    )]
+    fn test_extends_errors(#[case] config: &str, #[case] base: &str, #[case] expected: &str) {
+        let fs = MemoryFileSystem::new()
+            .with_file("/p/.basefmt.toml", config)
+            .with_file("/p/b.toml", base);
+
+        let err = Config::load_in(&fs, Path::new("/p")).unwrap_err();
+
+        assert_eq!(err.to_string(), expected);
+    }
+}
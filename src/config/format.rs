@@ -0,0 +1,179 @@
+use crate::error::{BasefmtError, Operation};
+use crate::filesystem::FileSystem;
+use serde::de::DeserializeOwned;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A format the configuration file can be written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Format {
+    Toml,
+    Yaml,
+    Json,
+}
+
+/// The names of the configuration files looked up in a directory, in order
+/// of precedence: only the first one that exists is read.
+const CONFIG_FILES: [(&str, Format); 4] = [
+    (".basefmt.toml", Format::Toml),
+    (".basefmt.yaml", Format::Yaml),
+    (".basefmt.yml", Format::Yaml),
+    (".basefmt.json", Format::Json),
+];
+
+/// Returns the names of the configuration files looked up in a directory,
+/// in order of precedence.
+pub(crate) fn file_names() -> impl Iterator<Item = &'static str> {
+    CONFIG_FILES.iter().map(|(name, _)| *name)
+}
+
+/// The text of a configuration file, with its path and format.
+pub(super) struct ConfigText {
+    pub(super) path: PathBuf,
+    pub(super) format: Format,
+    pub(super) content: String,
+}
+
+/// Reads the configuration file in `dir` of `fs` with the highest
+/// precedence, or returns `None` if there is none.
+pub(super) fn read_config_file(
+    fs: &dyn FileSystem,
+    dir: &Path,
+) -> Result<Option<ConfigText>, BasefmtError> {
+    for (name, format) in CONFIG_FILES {
+        match read_config_text(fs, dir.join(name), format) {
+            Ok(text) => return Ok(Some(text)),
+            Err(BasefmtError::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(None)
+}
+
+/// Reads the configuration file at `path` of `fs`, written in `format`.
+pub(super) fn read_config_text(
+    fs: &dyn FileSystem,
+    path: PathBuf,
+    format: Format,
+) -> Result<ConfigText, BasefmtError> {
+    let content = match fs.read(&path) {
+        Ok(content) => content,
+        Err(source) => {
+            return Err(BasefmtError::Io {
+                path,
+                operation: Operation::ReadConfig,
+                source,
+            });
+        }
+    };
+    let content = String::from_utf8(content).map_err(|source| BasefmtError::ConfigEncoding {
+        path: path.clone(),
+        source,
+    })?;
+    Ok(ConfigText {
+        path,
+        format,
+        content,
+    })
+}
+
+impl Format {
+    /// Returns the format of the file at `path` by its extension: TOML
+    /// unless it is `.yaml`, `.yml`, or `.json`.
+    pub(super) fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml" | "yml") => Format::Yaml,
+            Some("json") => Format::Json,
+            _ => Format::Toml,
+        }
+    }
+
+    /// Parses `content`, the text of the configuration file at `path`.
+    pub(super) fn parse<T: DeserializeOwned>(
+        self,
+        path: &Path,
+        content: &str,
+    ) -> Result<T, BasefmtError> {
+        let path = path.to_path_buf();
+        match self {
+            Format::Toml => toml::from_str(content).map_err(|source| BasefmtError::ConfigSyntax {
+                path,
+                source: Box::new(source),
+            }),
+            // An empty YAML document is null rather than an empty mapping
+            Format::Yaml if content.trim().is_empty() => serde_yaml::from_str("{}")
+                .map_err(|source| BasefmtError::ConfigYaml { path, source }),
+            Format::Yaml => serde_yaml::from_str(content)
+                .map_err(|source| BasefmtError::ConfigYaml { path, source }),
+            Format::Json => serde_json::from_str(content)
+                .map_err(|source| BasefmtError::ConfigJson { path, source }),
+        }
+    }
+
+    /// Writes `table` as the text of the configuration file at `path`, to be
+    /// parsed with [`Format::parse`].
+    pub(super) fn render(self, path: &Path, table: &toml::Table) -> Result<String, BasefmtError> {
+        let path = path.to_path_buf();
+        match self {
+            Format::Toml => Ok(table.to_string()),
+            Format::Yaml => serde_yaml::to_string(table)
+                .map_err(|source| BasefmtError::ConfigYaml { path, source }),
+            Format::Json => serde_json::to_string(table)
+                .map_err(|source| BasefmtError::ConfigJson { path, source }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::MemoryFileSystem;
+    use indoc::indoc;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::toml_first(&[".basefmt.json", ".basefmt.yaml", ".basefmt.toml"], Some(".basefmt.toml"))]
+    #[case::yaml_over_json(&[".basefmt.json", ".basefmt.yml"], Some(".basefmt.yml"))]
+    #[case::json(&[".basefmt.json"], Some(".basefmt.json"))]
+    #[case::none(&["basefmt.toml"], None)]
+    fn test_read_config_file(#[case] files: &[&str], #[case] expected: Option<&str>) {
+        let fs = files.iter().fold(MemoryFileSystem::new(), |fs, file| {
+            fs.with_file(Path::new("/p").join(file), "")
+        });
+
+        let text = read_config_file(&fs, Path::new("/p")).unwrap();
+
+        assert_eq!(
+            text.map(|text| text.path),
+            expected.map(|name| Path::new("/p").join(name))
+        );
+    }
+
+    #[rstest]
+    #[case::toml(Format::Toml, "jobs = 2\n[rules]\nmax_blank_lines = 1\n")] // ast-grep-ignore: prefer-indoc
+    #[case::yaml(Format::Yaml, "jobs: 2\nrules:\n  max_blank_lines: 1\n")]
+    #[case::json(Format::Json, r#"{"jobs": 2, "rules": {"max_blank_lines": 1}}"#)]
+    fn test_parse(#[case] format: Format, #[case] content: &str) {
+        let table: toml::Table = format.parse(Path::new("/p/config"), content).unwrap();
+        let rendered = format.render(Path::new("/p/config"), &table).unwrap();
+
+        assert_eq!(
+            (
+                table.to_string(),
+                format
+                    .parse::<toml::Table>(Path::new("/p/config"), &rendered)
+                    .unwrap()
+            ),
+            (
+                indoc! {"
+                    jobs = 2
+
+                    [rules]
+                    max_blank_lines = 1
+                "}
+                .to_string(),
+                table
+            )
+        );
+    }
+}
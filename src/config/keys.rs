@@ -0,0 +1,121 @@
+use crate::editorconfig::closest_match;
+
+/// The keys `.basefmt.toml` reads at the top level.
+const TOP_LEVEL_KEYS: &[&str] = &[
+    "exclude",
+    "jobs",
+    "editorconfig_file",
+    "root",
+    "rules",
+    "defaults",
+    "extends",
+    "profile",
+    "preset",
+    "language_defaults",
+    "require_git",
+    "cache_dir",
+    "cache_strategy",
+];
+
+/// The keys of the `[rules]` and `[defaults]` tables.
+const RULE_KEYS: &[&str] = &[
+    "insert_final_newline",
+    "trim_trailing_whitespace",
+    "trim_leading_newlines",
+    "preserve_markdown_hard_breaks",
+    "max_blank_lines",
+    "skip_minified",
+    "generated_marker_lines",
+    "end_of_line",
+    "preserve_yaml_block_scalars",
+    "normalize_encoding",
+];
+
+/// A key of `.basefmt.toml` that basefmt doesn't read, e.g. a misspelled one.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct UnknownKey {
+    /// The key, as `rules.KEY` for one in the `[rules]` table and likewise
+    /// for `[defaults]`
+    pub(super) key: String,
+    /// The known key it is most likely a misspelling of, written the same way
+    pub(super) suggestion: Option<String>,
+}
+
+/// Removes the keys basefmt doesn't read from the parsed `.basefmt.toml`,
+/// returning them sorted by key, those at the top level first.
+pub(super) fn remove_unknown_keys(table: &mut toml::Table) -> Vec<UnknownKey> {
+    let mut unknown = remove_from(table, TOP_LEVEL_KEYS, "");
+    for name in ["defaults", "rules"] {
+        if let Some(toml::Value::Table(rules)) = table.get_mut(name) {
+            unknown.extend(remove_from(rules, RULE_KEYS, &format!("{name}.")));
+        }
+    }
+    unknown
+}
+
+fn remove_from(table: &mut toml::Table, known: &[&'static str], prefix: &str) -> Vec<UnknownKey> {
+    let keys: Vec<String> = table
+        .keys()
+        .filter(|key| !known.contains(&key.as_str()))
+        .cloned()
+        .collect();
+    keys.into_iter()
+        .map(|key| {
+            table.remove(&key);
+            UnknownKey {
+                suggestion: closest_match(&key, known).map(|known| format!("{prefix}{known}")),
+                key: format!("{prefix}{key}"),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn test_remove_unknown_keys() {
+        let mut table: toml::Table = indoc! {"
+            exlcude = []
+            jobs = 2
+            colour = true
+
+            [rules]
+            max_blank_lines = 1
+            trim_trailing_whitespaces = true
+        "}
+        .parse()
+        .unwrap();
+
+        let unknown = remove_unknown_keys(&mut table);
+
+        assert_eq!(
+            (unknown, table.to_string()),
+            (
+                vec![
+                    UnknownKey {
+                        key: "colour".to_string(),
+                        suggestion: None,
+                    },
+                    UnknownKey {
+                        key: "exlcude".to_string(),
+                        suggestion: Some("exclude".to_string()),
+                    },
+                    UnknownKey {
+                        key: "rules.trim_trailing_whitespaces".to_string(),
+                        suggestion: Some("rules.trim_trailing_whitespace".to_string()),
+                    },
+                ],
+                indoc! {"
+                    jobs = 2
+
+                    [rules]
+                    max_blank_lines = 1
+                "}
+                .to_string()
+            )
+        );
+    }
+}
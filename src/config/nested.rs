@@ -0,0 +1,279 @@
+use super::Config;
+use crate::editorconfig::FormatRules;
+use crate::error::BasefmtError;
+use crate::filesystem::{FileSystem, RealFileSystem};
+use crate::rules::RuleProfile;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+/// The `.basefmt.toml` files that apply to a directory, outermost first,
+/// each with the directory it is in.
+type ConfigStack = Arc<Vec<(PathBuf, Arc<Config>)>>;
+
+/// Looks up and caches the `.basefmt.toml` files in the subdirectories of a
+/// project, such as the packages of a monorepo.
+///
+/// As with `.editorconfig` files, each applies to the files below its
+/// directory, and nearer ones take precedence over those further up: their
+/// `[rules]` are layered over the outer ones, the nearest `profile` is used,
+/// and every `exclude` pattern applies, matched against paths relative to the
+/// directory of its file. The project's own `.basefmt.toml` in the root
+/// directory is not read here, and neither are files outside it.
+///
+/// Only these keys are read from nested files; the others, such as
+/// `[defaults]`, `jobs`, and `cache_dir`, are taken from the project's file.
+pub struct NestedConfigs {
+    fs: Arc<dyn FileSystem>,
+    root_dir: PathBuf,
+    ignore_unknown_keys: bool,
+    dir_stacks: RwLock<HashMap<PathBuf, ConfigStack>>,
+    errors: Mutex<Vec<BasefmtError>>,
+}
+
+impl NestedConfigs {
+    /// Creates an empty cache for the project in the canonical `root_dir`,
+    /// reading `.basefmt.toml` files from disk.
+    pub fn new(root_dir: impl Into<PathBuf>) -> Self {
+        Self::with_file_system(Arc::new(RealFileSystem), root_dir)
+    }
+
+    /// Creates an empty cache for the project in `root_dir` that reads
+    /// `.basefmt.toml` files from `fs`.
+    pub fn with_file_system(fs: Arc<dyn FileSystem>, root_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            fs,
+            root_dir: root_dir.into(),
+            ignore_unknown_keys: false,
+            dir_stacks: RwLock::new(HashMap::new()),
+            errors: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Ignores the keys basefmt doesn't know in the nested files instead of
+    /// treating the files as invalid (see
+    /// [`Config::load_ignoring_unknown_keys`]).
+    pub fn ignoring_unknown_keys(mut self) -> Self {
+        self.ignore_unknown_keys = true;
+        self
+    }
+
+    /// Returns the rules the `[rules]` tables of the nested files set for the
+    /// file at the given canonical path.
+    pub fn rules_for(&self, canonical_path: &Path) -> FormatRules {
+        self.stack_for(canonical_path)
+            .iter()
+            .fold(FormatRules::default(), |rules, (_, config)| {
+                rules.with_overrides(&config.rules)
+            })
+    }
+
+    /// Returns the path of each nested file that applies to the file at the
+    /// given canonical path, outermost first, with the rules of its
+    /// `[rules]` table.
+    pub fn rules_by_file_for(&self, canonical_path: &Path) -> Vec<(PathBuf, FormatRules)> {
+        self.stack_for(canonical_path)
+            .iter()
+            .map(|(dir, config)| {
+                let path = config
+                    .path
+                    .clone()
+                    .unwrap_or_else(|| dir.join(".basefmt.toml"));
+                (path, config.rules.clone())
+            })
+            .collect()
+    }
+
+    /// Returns the profile of the nearest nested file that sets one for the
+    /// file at the given canonical path.
+    pub fn profile_for(&self, canonical_path: &Path) -> Option<RuleProfile> {
+        self.stack_for(canonical_path)
+            .iter()
+            .rev()
+            .find_map(|(_, config)| config.profile)
+    }
+
+    /// Returns whether a nested file excludes the file at the given canonical
+    /// path.
+    pub fn is_excluded(&self, canonical_path: &Path) -> bool {
+        self.exclusion_for(canonical_path).is_some()
+    }
+
+    /// Returns the path of the outermost nested file with an exclude pattern
+    /// that matches the file at the given canonical path, and the pattern.
+    pub fn exclusion_for(&self, canonical_path: &Path) -> Option<(PathBuf, String)> {
+        self.stack_for(canonical_path)
+            .iter()
+            .find_map(|(dir, config)| {
+                let relative = canonical_path.strip_prefix(dir).ok()?;
+                let pattern = config.matching_exclude(relative)?;
+                let path = config
+                    .path
+                    .clone()
+                    .unwrap_or_else(|| dir.join(".basefmt.toml"));
+                Some((path, pattern.to_string()))
+            })
+    }
+
+    /// Returns the errors from reading the invalid nested files found so far,
+    /// which are ignored otherwise, leaving none behind.
+    pub fn take_errors(&self) -> Vec<BasefmtError> {
+        std::mem::take(&mut self.errors.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    fn stack_for(&self, canonical_path: &Path) -> ConfigStack {
+        match canonical_path.parent() {
+            Some(dir) => self.stack_for_dir(dir),
+            None => ConfigStack::default(),
+        }
+    }
+
+    fn stack_for_dir(&self, dir: &Path) -> ConfigStack {
+        if dir == self.root_dir || !dir.starts_with(&self.root_dir) {
+            return ConfigStack::default();
+        }
+        if let Some(stack) = self
+            .dir_stacks
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(dir)
+        {
+            return Arc::clone(stack);
+        }
+
+        let mut combined = match dir.parent() {
+            Some(parent) => self.stack_for_dir(parent).as_ref().clone(),
+            None => Vec::new(),
+        };
+        let loaded = Config::load_existing_in(self.fs.as_ref(), dir, self.ignore_unknown_keys);
+
+        let mut dir_stacks = self
+            .dir_stacks
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(stack) = dir_stacks.get(dir) {
+            return Arc::clone(stack);
+        }
+        match loaded {
+            Ok(Some(config)) => combined.push((dir.to_path_buf(), Arc::new(config))),
+            Ok(None) => {}
+            Err(err) => self
+                .errors
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(err),
+        }
+        Arc::clone(
+            dir_stacks
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| Arc::new(combined)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::MemoryFileSystem;
+    use indoc::indoc;
+
+    fn configs() -> NestedConfigs {
+        let fs = MemoryFileSystem::new()
+            .with_file("/p/.basefmt.toml", "exclude = [\"*.txt\"]\n")
+            .with_file(
+                "/p/packages/app/.basefmt.toml",
+                indoc! {r#"
+                    profile = "minimal"
+                    exclude = ["dist/**"]
+
+                    [rules]
+                    trim_trailing_whitespace = false
+                    max_blank_lines = 1
+                "#},
+            )
+            .with_file(
+                "/p/packages/app/docs/.basefmt.toml",
+                indoc! {"
+                    [rules]
+                    max_blank_lines = 2
+                "},
+            )
+            .with_file("/p/packages/broken/.basefmt.toml", "exclude = 1\n");
+        NestedConfigs::with_file_system(Arc::new(fs), "/p")
+    }
+
+    #[test]
+    fn test_nearest_config_takes_precedence() {
+        let configs = configs();
+        let lookup = |path: &str| {
+            let path = Path::new(path);
+            (
+                configs.rules_for(path),
+                configs.profile_for(path),
+                configs.is_excluded(path),
+            )
+        };
+
+        assert_eq!(
+            [
+                lookup("/p/a.txt"),
+                lookup("/p/packages/app/dist/a.js"),
+                lookup("/p/packages/app/docs/a.md"),
+                lookup("/outside/a.md"),
+            ],
+            [
+                (FormatRules::default(), None, false),
+                (
+                    FormatRules {
+                        remove_trailing_spaces: Some(false),
+                        max_blank_lines: Some(1),
+                        ..FormatRules::default()
+                    },
+                    Some(RuleProfile::Minimal),
+                    true
+                ),
+                (
+                    FormatRules {
+                        remove_trailing_spaces: Some(false),
+                        max_blank_lines: Some(2),
+                        ..FormatRules::default()
+                    },
+                    Some(RuleProfile::Minimal),
+                    false
+                ),
+                (FormatRules::default(), None, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_config_is_reported_once() {
+        let configs = configs();
+
+        let rules = configs.rules_for(Path::new("/p/packages/broken/a.txt"));
+        configs.rules_for(Path::new("/p/packages/broken/b.txt"));
+
+        assert_eq!(
+            (
+                rules,
+                configs
+                    .take_errors()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+            ),
+            (
+                FormatRules::default(),
+                vec![
+                    Config::load_existing_in(
+                        configs.fs.as_ref(),
+                        Path::new("/p/packages/broken"),
+                        false
+                    )
+                    .unwrap_err()
+                    .to_string()
+                ]
+            )
+        );
+    }
+}
@@ -0,0 +1,244 @@
+// dprint plugin module
+// This module maps dprint's configuration onto basefmt's rules, so that
+// teams already running dprint can load basefmt as one of its Wasm plugins
+// instead of running another process.
+
+use crate::editorconfig::{FormatRules, LineEnding};
+use crate::format::format_str;
+use crate::rules::RuleTable;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+#[cfg(target_arch = "wasm32")]
+mod wasm;
+
+/// The key of basefmt's section in `dprint.json`.
+pub const CONFIG_KEY: &str = "basefmt";
+
+/// Keys of a plugin's section that dprint itself reads.
+const DPRINT_KEYS: [&str; 2] = ["associations", "locked"];
+
+/// The configuration dprint registers for a plugin: the plugin's own
+/// section of `dprint.json` and the options shared by every plugin.
+#[derive(Debug, Default, Deserialize)]
+pub struct RawConfig {
+    #[serde(default)]
+    pub plugin: Map<String, Value>,
+    #[serde(default)]
+    pub global: Map<String, Value>,
+}
+
+/// A problem with a key of the configuration, reported by dprint along with
+/// the name of the plugin.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigDiagnostic {
+    pub property_name: String,
+    pub message: String,
+}
+
+/// Resolves the rules that `config` sets, along with a diagnostic for each
+/// key that is unknown or has an invalid value.
+///
+/// The keys of basefmt's section are those of the `[rules]` table of
+/// `.basefmt.toml` in camel case, e.g. `trimTrailingWhitespace`, plus
+/// dprint's `newLineKind`, which like the global option of the same name
+/// sets `end_of_line`: `lf` or `crlf`, or `auto` or `system` to keep the
+/// line ending most common in each file. The other global options, such as
+/// `lineWidth`, don't concern basefmt and are ignored. Rules left unset get
+/// their defaults, as with EditorConfig.
+///
+/// # Examples
+///
+/// ```
+/// use basefmt::dprint::{RawConfig, resolve_config};
+/// use basefmt::editorconfig::{FormatRules, LineEnding};
+///
+/// let config: RawConfig = serde_json::from_str(
+///     r#"{"plugin":{"maxBlankLines":1},"global":{"newLineKind":"lf"}}"#,
+/// )
+/// .unwrap();
+/// assert_eq!(
+///     resolve_config(&config),
+///     (
+///         FormatRules {
+///             max_blank_lines: Some(1),
+///             end_of_line: Some(LineEnding::Lf),
+///             ..FormatRules::default()
+///         },
+///         Vec::new()
+///     )
+/// );
+/// ```
+pub fn resolve_config(config: &RawConfig) -> (FormatRules, Vec<ConfigDiagnostic>) {
+    let mut rules = FormatRules::default();
+    let mut diagnostics = Vec::new();
+    let mut diagnose = |property_name: &str, message: String| {
+        diagnostics.push(ConfigDiagnostic {
+            property_name: property_name.to_string(),
+            message,
+        });
+    };
+    if let Some(kind) = config.global.get("newLineKind") {
+        match new_line_kind(kind) {
+            Ok(end_of_line) => rules.end_of_line = end_of_line,
+            Err(message) => diagnose("newLineKind", message),
+        }
+    }
+    for (key, value) in &config.plugin {
+        if DPRINT_KEYS.contains(&key.as_str()) {
+            continue;
+        }
+        if key == "newLineKind" {
+            match new_line_kind(value) {
+                Ok(end_of_line) => rules.end_of_line = end_of_line,
+                Err(message) => diagnose(key, message),
+            }
+            continue;
+        }
+        let table = Map::from_iter([(snake_case(key), value.clone())]);
+        match RuleTable::deserialize(Value::Object(table)) {
+            Ok(table) => rules = rules.with_overrides(&table.into()),
+            Err(err) => diagnose(key, err.to_string()),
+        }
+    }
+    (rules, diagnostics)
+}
+
+/// Returns the value of every rule that applies with `rules`, unset ones at
+/// their defaults, keyed as in basefmt's section of `dprint.json`, for
+/// `dprint output-resolved-config`. `maxBlankLines` is `null` when blank
+/// lines are unlimited.
+pub fn resolved_config(rules: &FormatRules) -> Value {
+    serde_json::json!({
+        "insertFinalNewline": rules.ensure_final_newline(),
+        "trimTrailingWhitespace": rules.remove_trailing_spaces(),
+        "trimLeadingNewlines": rules.remove_leading_newlines(),
+        "preserveMarkdownHardBreaks": rules.preserve_markdown_hard_breaks(),
+        "maxBlankLines": rules.max_blank_lines,
+        "skipMinified": rules.skip_minified(),
+        "generatedMarkerLines": rules.generated_marker_lines(),
+        "newLineKind": rules.end_of_line.map_or("auto", LineEnding::name),
+        "preserveYamlBlockScalars": rules.preserve_yaml_block_scalars(),
+    })
+}
+
+/// Formats `text` with `rules`, returning the formatted text if it changed.
+pub fn format_text(text: &str, rules: &FormatRules) -> Option<String> {
+    let formatted = format_str(text, rules);
+    formatted.changed.then_some(formatted.content)
+}
+
+/// Returns the line ending a `newLineKind` value sets, if any.
+fn new_line_kind(value: &Value) -> Result<Option<LineEnding>, String> {
+    match value.as_str() {
+        Some("lf") => Ok(Some(LineEnding::Lf)),
+        Some("crlf") => Ok(Some(LineEnding::Crlf)),
+        Some("auto" | "system") => Ok(None),
+        _ => Err(format!(
+            "expected \"auto\", \"lf\", \"crlf\", or \"system\", found {value}"
+        )),
+    }
+}
+
+/// Converts a camel case key such as `trimTrailingWhitespace` to the snake
+/// case of `.basefmt.toml`.
+fn snake_case(key: &str) -> String {
+    let mut snake = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            snake.push('_');
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::empty(r#"{}"#, (FormatRules::default(), Vec::new()))]
+    #[case::rules(
+        r#"{"plugin":{"insertFinalNewline":false,"trimTrailingWhitespace":true,"locked":true}}"#,
+        (
+            FormatRules {
+                ensure_final_newline: Some(false),
+                remove_trailing_spaces: Some(true),
+                ..FormatRules::default()
+            },
+            Vec::new()
+        )
+    )]
+    #[case::plugin_new_line_kind_wins(
+        r#"{"plugin":{"newLineKind":"crlf"},"global":{"newLineKind":"lf","lineWidth":80}}"#,
+        (
+            FormatRules {
+                end_of_line: Some(LineEnding::Crlf),
+                ..FormatRules::default()
+            },
+            Vec::new()
+        )
+    )]
+    #[case::invalid(
+        r#"{"plugin":{"maxBlankLines":"two","indentWidth":2},"global":{"newLineKind":"cr"}}"#,
+        (
+            FormatRules::default(),
+            vec![
+                ConfigDiagnostic {
+                    property_name: "newLineKind".to_string(),
+                    message: "expected \"auto\", \"lf\", \"crlf\", or \"system\", found \"cr\"".to_string(),
+                },
+                ConfigDiagnostic {
+                    property_name: "indentWidth".to_string(),
+                    message: "unknown field `indent_width`, expected one of `insert_final_newline`, `trim_trailing_whitespace`, `trim_leading_newlines`, `preserve_markdown_hard_breaks`, `max_blank_lines`, `skip_minified`, `generated_marker_lines`, `end_of_line`, `preserve_yaml_block_scalars`, `normalize_encoding`".to_string(),
+                },
+                ConfigDiagnostic {
+                    property_name: "maxBlankLines".to_string(),
+                    message: "invalid type: string \"two\", expected usize".to_string(),
+                },
+            ]
+        )
+    )]
+    fn test_resolve_config(
+        #[case] config: &str,
+        #[case] expected: (FormatRules, Vec<ConfigDiagnostic>),
+    ) {
+        let config: RawConfig = serde_json::from_str(config).unwrap();
+        assert_eq!(resolve_config(&config), expected);
+    }
+
+    #[test]
+    fn test_resolved_config() {
+        let rules = FormatRules {
+            max_blank_lines: Some(2),
+            end_of_line: Some(LineEnding::Crlf),
+            ..FormatRules::default()
+        };
+        assert_eq!(
+            resolved_config(&rules),
+            serde_json::json!({
+                "insertFinalNewline": true,
+                "trimTrailingWhitespace": true,
+                "trimLeadingNewlines": true,
+                "preserveMarkdownHardBreaks": false,
+                "maxBlankLines": 2,
+                "skipMinified": true,
+                "generatedMarkerLines": 0,
+                "newLineKind": "crlf",
+                "preserveYamlBlockScalars": false,
+            })
+        );
+    }
+
+    #[rstest]
+    #[case::changed("a  ", Some("a\n".to_string()))]
+    #[case::unchanged("a\n", None)]
+    fn test_format_text(#[case] text: &str, #[case] expected: Option<String>) {
+        assert_eq!(format_text(text, &FormatRules::default()), expected);
+    }
+}
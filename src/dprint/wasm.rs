@@ -0,0 +1,245 @@
+// The exports of a dprint Wasm plugin, schema version 4. dprint passes
+// every input (configuration, file path, file text) by writing it into a
+// buffer of the plugin that it allocates with `clear_shared_bytes`, and
+// reads every output from the same buffer after a call returns its size.
+
+use super::{
+    CONFIG_KEY, ConfigDiagnostic, RawConfig, format_text, resolve_config, resolved_config,
+};
+use crate::editorconfig::FormatRules;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// What `format` returns when the text was already formatted
+const NO_CHANGE: u32 = 0;
+/// What `format` returns when the text was formatted
+const CHANGE: u32 = 1;
+/// What `format` returns when formatting failed
+const ERROR: u32 = 2;
+
+#[derive(Default)]
+struct State {
+    /// The buffer dprint writes inputs to and reads outputs from
+    shared_bytes: Vec<u8>,
+    /// The resolved configurations dprint has registered, by ID
+    configs: HashMap<u32, (FormatRules, Vec<ConfigDiagnostic>)>,
+    /// The overrides of the next `format` call
+    override_config: Option<RawConfig>,
+    formatted_text: String,
+    error_text: String,
+}
+
+static STATE: Mutex<Option<State>> = Mutex::new(None);
+
+fn with_state<T>(f: impl FnOnce(&mut State) -> T) -> T {
+    // Wasm plugins are single-threaded, so the lock is never contended, and
+    // the state is never left half-updated
+    let mut guard: MutexGuard<'_, Option<State>> =
+        STATE.lock().unwrap_or_else(PoisonError::into_inner);
+    f(guard.get_or_insert_with(State::default))
+}
+
+/// Moves `bytes` into the shared buffer, returning their length.
+fn share(state: &mut State, bytes: Vec<u8>) -> u32 {
+    state.shared_bytes = bytes;
+    state.shared_bytes.len() as u32
+}
+
+fn share_json(state: &mut State, value: &impl Serialize) -> u32 {
+    share(state, serde_json::to_vec(value).unwrap_or_default())
+}
+
+fn take_shared_string(state: &mut State) -> String {
+    String::from_utf8_lossy(&std::mem::take(&mut state.shared_bytes)).into_owned()
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PluginInfo {
+    name: &'static str,
+    version: &'static str,
+    config_key: &'static str,
+    help_url: &'static str,
+    config_schema_url: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FileMatchingInfo {
+    file_extensions: Vec<String>,
+    file_names: Vec<String>,
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn dprint_plugin_version_4() -> u32 {
+    4
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn get_shared_bytes_ptr() -> *const u8 {
+    with_state(|state| state.shared_bytes.as_ptr())
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn clear_shared_bytes(size: u32) -> *const u8 {
+    with_state(|state| {
+        state.shared_bytes = vec![0; size as usize];
+        state.shared_bytes.as_ptr()
+    })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn get_plugin_info() -> u32 {
+    with_state(|state| {
+        share_json(
+            state,
+            &PluginInfo {
+                name: env!("CARGO_PKG_NAME"),
+                version: env!("CARGO_PKG_VERSION"),
+                config_key: CONFIG_KEY,
+                help_url: env!("CARGO_PKG_REPOSITORY"),
+                config_schema_url: "",
+            },
+        )
+    })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn get_license_text() -> u32 {
+    with_state(|state| share(state, include_bytes!("../../LICENSE").to_vec()))
+}
+
+/// Reads the configuration to register from the shared buffer.
+#[unsafe(no_mangle)]
+pub extern "C" fn register_config(config_id: u32) {
+    with_state(|state| {
+        let text = take_shared_string(state);
+        let resolved = match serde_json::from_str(&text) {
+            Ok(config) => resolve_config(&config),
+            Err(err) => (
+                FormatRules::default(),
+                vec![ConfigDiagnostic {
+                    property_name: CONFIG_KEY.to_string(),
+                    message: err.to_string(),
+                }],
+            ),
+        };
+        state.configs.insert(config_id, resolved);
+    });
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn release_config(config_id: u32) {
+    with_state(|state| {
+        state.configs.remove(&config_id);
+    });
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn get_config_diagnostics(config_id: u32) -> u32 {
+    with_state(|state| {
+        let diagnostics = state
+            .configs
+            .get(&config_id)
+            .map(|(_, diagnostics)| diagnostics.clone())
+            .unwrap_or_default();
+        share_json(state, &diagnostics)
+    })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn get_resolved_config(config_id: u32) -> u32 {
+    with_state(|state| {
+        let rules = state
+            .configs
+            .get(&config_id)
+            .map(|(rules, _)| resolved_config(rules))
+            .unwrap_or_default();
+        share_json(state, &rules)
+    })
+}
+
+/// basefmt applies to any text file, so it matches none by default: name
+/// the files in the `associations` of its section instead.
+#[unsafe(no_mangle)]
+pub extern "C" fn get_config_file_matching(_config_id: u32) -> u32 {
+    with_state(|state| {
+        share_json(
+            state,
+            &FileMatchingInfo {
+                file_extensions: Vec::new(),
+                file_names: Vec::new(),
+            },
+        )
+    })
+}
+
+/// Reads the path of the file to format next from the shared buffer. The
+/// rules don't depend on it, since dprint resolves the configuration.
+#[unsafe(no_mangle)]
+pub extern "C" fn set_file_path() {
+    with_state(|state| {
+        take_shared_string(state);
+    });
+}
+
+/// Reads the overrides of the next `format` call from the shared buffer.
+#[unsafe(no_mangle)]
+pub extern "C" fn set_override_config() {
+    with_state(|state| {
+        let text = take_shared_string(state);
+        state.override_config = serde_json::from_str::<serde_json::Map<_, _>>(&text)
+            .ok()
+            .map(|plugin| RawConfig {
+                plugin,
+                ..RawConfig::default()
+            });
+    });
+}
+
+/// Formats the text in the shared buffer with the registered configuration
+/// `config_id`, returning whether it changed or an error occurred.
+#[unsafe(no_mangle)]
+pub extern "C" fn format(config_id: u32) -> u32 {
+    with_state(|state| {
+        let text = match String::from_utf8(std::mem::take(&mut state.shared_bytes)) {
+            Ok(text) => text,
+            Err(err) => {
+                state.error_text = format!("file is not valid UTF-8: {err}");
+                return ERROR;
+            }
+        };
+        let Some((rules, _)) = state.configs.get(&config_id) else {
+            state.error_text = format!("configuration {config_id} was not registered");
+            return ERROR;
+        };
+        let rules = match state.override_config.take() {
+            Some(overrides) => rules.with_overrides(&resolve_config(&overrides).0),
+            None => rules.clone(),
+        };
+        match format_text(&text, &rules) {
+            Some(formatted) => {
+                state.formatted_text = formatted;
+                CHANGE
+            }
+            None => NO_CHANGE,
+        }
+    })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn get_formatted_text() -> u32 {
+    with_state(|state| {
+        let text = std::mem::take(&mut state.formatted_text);
+        share(state, text.into_bytes())
+    })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn get_error_text() -> u32 {
+    with_state(|state| {
+        let text = std::mem::take(&mut state.error_text);
+        share(state, text.into_bytes())
+    })
+}
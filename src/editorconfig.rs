@@ -2,187 +2,297 @@
 // This module is responsible for reading EditorConfig files and mapping properties
 // to basefmt's formatting rules.
 
-use ec4rs::property::{FinalNewline, TrimTrailingWs};
-use ec4rs::{ConfigFile, Properties, PropertiesSource, Section};
-use std::collections::HashMap;
+use crate::canonical;
+use ec4rs::property::{EndOfLine, FinalNewline, TrimTrailingWs};
+use ec4rs::{ConfigParser, Properties};
 use std::io;
-use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::path::Path;
+
+mod cache;
+mod lint;
+mod sections;
+
+pub use cache::EditorConfigCache;
+use cache::parse_config;
+pub(crate) use lint::closest_match;
+pub use lint::{PropertyWarning, lint_editorconfig};
+pub use sections::SectionRules;
 
 /// Configuration rules for formatting a file
-#[derive(Clone, Debug, PartialEq, Eq)]
+///
+/// Each rule is `None` when nothing sets it, so that layered sources (e.g.
+/// EditorConfig and options given in code) can tell an unset rule from a
+/// disabled one. Unset rules fall back to their defaults when formatting;
+/// see the accessor methods of the same names.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct FormatRules {
     /// Whether to ensure the file ends with a newline
-    pub ensure_final_newline: bool,
+    pub ensure_final_newline: Option<bool>,
     /// Whether to remove trailing spaces from each line
-    pub remove_trailing_spaces: bool,
+    pub remove_trailing_spaces: Option<bool>,
     /// Whether to remove leading newlines from the file
-    pub remove_leading_newlines: bool,
+    pub remove_leading_newlines: Option<bool>,
+    /// Whether to keep exactly two trailing spaces (a Markdown hard line break)
+    /// when removing trailing spaces
+    pub preserve_markdown_hard_breaks: Option<bool>,
+    /// How many consecutive blank lines to keep between other lines; runs
+    /// longer than this are shortened. Unlimited if unset.
+    pub max_blank_lines: Option<usize>,
+    /// Whether to leave minified files, such as bundled JavaScript, alone
+    pub skip_minified: Option<bool>,
+    /// How many lines at the top of a file to search for a generated-code
+    /// marker such as `@generated` or `DO NOT EDIT`; files with one are left
+    /// alone. No file is searched if unset.
+    pub generated_marker_lines: Option<usize>,
+    /// Which terminator to end lines with; lines keep the one most common in
+    /// the file if unset
+    pub end_of_line: Option<LineEnding>,
+    /// Whether to leave the bodies of YAML block scalars (`|` and `>`)
+    /// alone, whose trailing whitespace and blank lines are part of the value
+    pub preserve_yaml_block_scalars: Option<bool>,
+    /// Which encoding to convert text files in another encoding to; files
+    /// are left in their encoding if unset
+    pub normalize_encoding: Option<Charset>,
 }
 
-impl Default for FormatRules {
-    fn default() -> Self {
-        Self {
-            ensure_final_newline: true,
-            remove_trailing_spaces: true,
-            remove_leading_newlines: true,
-        }
-    }
+/// A line terminator basefmt can normalize line endings to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    /// `\n`, as on Unix
+    Lf,
+    /// `\r\n`, as on Windows
+    Crlf,
+    /// A lone `\r`, as on classic Mac OS
+    Cr,
 }
 
-/// Get formatting rules for a file from EditorConfig
-///
-/// This function reads the EditorConfig file for the given path and returns
-/// the corresponding formatting rules.
-///
-/// # EditorConfig Property Mapping
-///
-/// - `insert_final_newline` → `ensure_final_newline`
-/// - `trim_trailing_whitespace` → `remove_trailing_spaces`
-/// - `trim_leading_newlines` (custom) → `remove_leading_newlines`
-///
-/// # Property Value Interpretation
-///
-/// - `true` → rule enabled
-/// - `false` → rule disabled
-/// - `unset` → rule disabled
-/// - not specified → rule enabled (default)
-pub fn get_format_rules(path: &Path) -> FormatRules {
-    match path.canonicalize() {
-        Ok(resolved) => {
-            let mut cache = EditorConfigCache::new();
-            cache.rules_for(&resolved)
-        }
-        Err(_) => FormatRules::default(),
-    }
+/// A character encoding basefmt can convert files to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Deserialize)]
+pub enum Charset {
+    /// UTF-8 without a byte order mark
+    #[serde(rename = "utf-8")]
+    Utf8,
 }
 
-/// Caches parsed EditorConfig files to avoid redundant IO on large projects.
-#[derive(Default)]
-pub struct EditorConfigCache {
-    dir_stacks: HashMap<PathBuf, Arc<Vec<Arc<ParsedConfig>>>>,
-    config_files: HashMap<PathBuf, Option<Arc<ParsedConfig>>>,
-    rules_cache: HashMap<PathBuf, FormatRules>,
-}
+impl LineEnding {
+    /// Returns the terminator itself.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
 
-impl EditorConfigCache {
-    /// Creates an empty cache.
-    pub fn new() -> Self {
-        Self::default()
+    /// Returns the value of `end_of_line` for this terminator, e.g. `crlf`.
+    pub fn name(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "lf",
+            LineEnding::Crlf => "crlf",
+            LineEnding::Cr => "cr",
+        }
     }
+}
 
-    /// Returns formatting rules for the given canonical path, caching repeated lookups.
+impl FormatRules {
+    /// Returns these rules with every rule that is set in `overrides`
+    /// replaced by its value there.
     ///
-    /// # Arguments
+    /// # Examples
     ///
-    /// * `canonical_path` - A canonicalized (absolute) path to the file.
-    ///   Must be obtained via `Path::canonicalize()`.
-    pub fn rules_for(&mut self, canonical_path: &Path) -> FormatRules {
-        if let Some(rules) = self.rules_cache.get(canonical_path) {
-            return rules.clone();
-        }
-
-        let mut properties = Properties::new();
-        if let Some(parent) = canonical_path.parent() {
-            for config in self.stack_for_dir(parent).iter() {
-                config.apply_to(&mut properties, canonical_path);
-            }
+    /// ```
+    /// use basefmt::editorconfig::FormatRules;
+    ///
+    /// let base = FormatRules {
+    ///     ensure_final_newline: Some(false),
+    ///     remove_trailing_spaces: Some(false),
+    ///     ..FormatRules::default()
+    /// };
+    /// let overrides = FormatRules {
+    ///     remove_trailing_spaces: Some(true),
+    ///     ..FormatRules::default()
+    /// };
+    /// assert_eq!(
+    ///     base.with_overrides(&overrides),
+    ///     FormatRules {
+    ///         ensure_final_newline: Some(false),
+    ///         remove_trailing_spaces: Some(true),
+    ///         ..FormatRules::default()
+    ///     }
+    /// );
+    /// ```
+    pub fn with_overrides(&self, overrides: &FormatRules) -> FormatRules {
+        FormatRules {
+            ensure_final_newline: overrides.ensure_final_newline.or(self.ensure_final_newline),
+            remove_trailing_spaces: overrides
+                .remove_trailing_spaces
+                .or(self.remove_trailing_spaces),
+            remove_leading_newlines: overrides
+                .remove_leading_newlines
+                .or(self.remove_leading_newlines),
+            preserve_markdown_hard_breaks: overrides
+                .preserve_markdown_hard_breaks
+                .or(self.preserve_markdown_hard_breaks),
+            max_blank_lines: overrides.max_blank_lines.or(self.max_blank_lines),
+            skip_minified: overrides.skip_minified.or(self.skip_minified),
+            generated_marker_lines: overrides
+                .generated_marker_lines
+                .or(self.generated_marker_lines),
+            end_of_line: overrides.end_of_line.or(self.end_of_line),
+            preserve_yaml_block_scalars: overrides
+                .preserve_yaml_block_scalars
+                .or(self.preserve_yaml_block_scalars),
+            normalize_encoding: overrides.normalize_encoding.or(self.normalize_encoding),
         }
-        let rules = rules_from_properties(&properties);
-        self.rules_cache
-            .insert(canonical_path.to_path_buf(), rules.clone());
-        rules
     }
 
-    fn stack_for_dir(&mut self, dir: &Path) -> Arc<Vec<Arc<ParsedConfig>>> {
-        if let Some(stack) = self.dir_stacks.get(dir) {
-            return Arc::clone(stack);
-        }
+    /// Whether to ensure the file ends with a newline; enabled if unset.
+    pub fn ensure_final_newline(&self) -> bool {
+        self.ensure_final_newline.unwrap_or(true)
+    }
 
-        let mut combined = if let Some(parent) = dir.parent() {
-            self.stack_for_dir(parent).as_ref().clone()
-        } else {
-            Vec::new()
-        };
+    /// Whether to remove trailing spaces from each line; enabled if unset.
+    pub fn remove_trailing_spaces(&self) -> bool {
+        self.remove_trailing_spaces.unwrap_or(true)
+    }
 
-        if let Some(config) = self.load_config_for_dir(dir) {
-            if config.is_root {
-                combined.clear();
-            }
-            combined.push(config);
-        }
+    /// Whether to remove leading newlines from the file; enabled if unset.
+    pub fn remove_leading_newlines(&self) -> bool {
+        self.remove_leading_newlines.unwrap_or(true)
+    }
 
-        let stack = Arc::new(combined);
-        self.dir_stacks
-            .insert(dir.to_path_buf(), Arc::clone(&stack));
-        stack
+    /// The most consecutive blank lines to keep; no limit if unset or turned
+    /// off with [`FormatRules::disabling`], which stores `usize::MAX` so that
+    /// it overrides a limit set elsewhere.
+    pub fn max_blank_lines(&self) -> Option<usize> {
+        self.max_blank_lines.filter(|&max| max != usize::MAX)
     }
 
-    fn load_config_for_dir(&mut self, dir: &Path) -> Option<Arc<ParsedConfig>> {
-        if let Some(entry) = self.config_files.get(dir) {
-            return entry.clone();
-        }
-
-        let config_path = dir.join(".editorconfig");
-        let parsed = match ConfigFile::open(&config_path) {
-            Ok(file) => self.parse_config_file(dir, file),
-            Err(ec4rs::ParseError::Io(err)) if err.kind() == io::ErrorKind::NotFound => None,
-            Err(err) => {
-                eprintln!(
-                    "{}: failed to read .editorconfig: {}",
-                    config_path.display(),
-                    err
-                );
-                None
-            }
-        };
+    /// Whether to keep Markdown hard line breaks; disabled if unset.
+    pub fn preserve_markdown_hard_breaks(&self) -> bool {
+        self.preserve_markdown_hard_breaks.unwrap_or(false)
+    }
 
-        self.config_files.insert(dir.to_path_buf(), parsed.clone());
-        parsed
+    /// Whether to leave minified files alone; enabled if unset.
+    pub fn skip_minified(&self) -> bool {
+        self.skip_minified.unwrap_or(true)
     }
 
-    fn parse_config_file(&self, dir: &Path, file: ConfigFile) -> Option<Arc<ParsedConfig>> {
-        let ConfigFile { path, mut reader } = file;
-        let mut sections = Vec::new();
-        while let Some(section_result) = reader.next() {
-            match section_result {
-                Ok(section) => sections.push(section),
-                Err(err) => {
-                    eprintln!(
-                        "{}:{}: failed to parse .editorconfig: {}",
-                        path.display(),
-                        reader.line_no(),
-                        err
-                    );
-                    return None;
-                }
-            }
-        }
+    /// How many lines to search for a generated-code marker; none if unset.
+    pub fn generated_marker_lines(&self) -> usize {
+        self.generated_marker_lines.unwrap_or(0)
+    }
 
-        Some(Arc::new(ParsedConfig {
-            dir: dir.to_path_buf(),
-            is_root: reader.is_root,
-            sections: Arc::new(sections),
-        }))
+    /// Whether to leave YAML block scalar bodies alone; disabled if unset.
+    pub fn preserve_yaml_block_scalars(&self) -> bool {
+        self.preserve_yaml_block_scalars.unwrap_or(false)
     }
-}
 
-#[derive(Clone)]
-struct ParsedConfig {
-    dir: PathBuf,
-    is_root: bool,
-    sections: Arc<Vec<Section>>,
+    /// Whether every rule that can change a file is disabled, so that
+    /// formatting leaves any file as it is.
+    pub fn changes_nothing(&self) -> bool {
+        !self.ensure_final_newline()
+            && !self.remove_trailing_spaces()
+            && !self.remove_leading_newlines()
+            && self.max_blank_lines().is_none()
+            && self.end_of_line.is_none()
+            && self.normalize_encoding.is_none()
+    }
 }
 
-impl ParsedConfig {
-    fn apply_to(&self, props: &mut Properties, file_path: &Path) {
-        let rel_path = file_path.strip_prefix(&self.dir).unwrap_or(file_path);
-        for section in self.sections.as_ref() {
-            let _ = section.apply_to(props, rel_path);
+/// Get formatting rules for a file from EditorConfig
+///
+/// This function reads the EditorConfig file for the given path and returns
+/// the corresponding formatting rules.
+///
+/// # EditorConfig Property Mapping
+///
+/// - `insert_final_newline` → `ensure_final_newline`
+/// - `trim_trailing_whitespace` → `remove_trailing_spaces`
+/// - `end_of_line` → `end_of_line`
+/// - `trim_leading_newlines` (custom) → `remove_leading_newlines`
+/// - `preserve_markdown_hard_breaks` (custom) → `preserve_markdown_hard_breaks`
+/// - `basefmt_max_blank_lines` (custom) → `max_blank_lines`
+/// - `basefmt_skip_minified` (custom) → `skip_minified`
+/// - `basefmt_generated_marker_lines` (custom) → `generated_marker_lines`
+/// - `basefmt_preserve_yaml_block_scalars` (custom) → `preserve_yaml_block_scalars`
+/// - `basefmt_normalize_encoding` (custom, `utf-8`) → `normalize_encoding`
+///
+/// The custom boolean properties can also be spelled with a `basefmt_`
+/// prefix (e.g. `basefmt_trim_leading_newlines`), which takes precedence
+/// over the unprefixed name.
+///
+/// # Property Value Interpretation
+///
+/// - `true` → rule enabled (`Some(true)`)
+/// - `false` → rule disabled (`Some(false)`)
+/// - `unset` → rule disabled (`Some(false)`)
+/// - not specified → `None`, so the rule's default applies: enabled, except
+///   for `preserve_markdown_hard_breaks`, which is opt-in
+///
+/// `basefmt_max_blank_lines` takes a non-negative integer; any other value
+/// leaves the number of blank lines unlimited. So does
+/// `basefmt_generated_marker_lines`, where any other value searches no line.
+/// `end_of_line` takes `lf`, `crlf`, or `cr`; any other value leaves line
+/// endings as they are.
+pub fn get_format_rules(path: &Path) -> FormatRules {
+    match canonical::canonicalize(path) {
+        Ok(resolved) => {
+            let cache = EditorConfigCache::new();
+            cache.rules_for(&resolved)
         }
+        Err(_) => FormatRules::default(),
     }
 }
 
+/// Get formatting rules for a file from the text of a single `.editorconfig`
+/// file, without touching the file system.
+///
+/// `path` is the file's path relative to the directory the `.editorconfig`
+/// belongs to, e.g. `docs/README.md`. Properties are interpreted as in
+/// [`get_format_rules`].
+///
+/// Returns an [`io::ErrorKind::InvalidData`] error if `editorconfig` cannot be
+/// parsed.
+///
+/// # Examples
+///
+/// ```
+/// use basefmt::editorconfig::rules_from_editorconfig;
+/// use std::path::Path;
+///
+/// let rules = rules_from_editorconfig("[*.md]\ntrim_trailing_whitespace = false\n", Path::new("README.md")).unwrap();
+/// assert!(!rules.remove_trailing_spaces());
+/// ```
+pub fn rules_from_editorconfig(editorconfig: &str, path: &Path) -> io::Result<FormatRules> {
+    let parser = ConfigParser::new_buffered(editorconfig.as_bytes()).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("failed to parse .editorconfig: {err}"),
+        )
+    })?;
+    let config = parse_config(
+        Path::new(""),
+        Path::new(""),
+        editorconfig.as_bytes(),
+        parser,
+    )
+    .map_err(|(line, err)| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("line {line}: failed to parse .editorconfig: {err}"),
+        )
+    })?;
+
+    let mut properties = Properties::new();
+    config.apply_to(&mut properties, path);
+    Ok(rules_from_properties(&properties))
+}
+
+/// Prefix of the EditorConfig properties specific to basefmt.
+const NAMESPACE: &str = "basefmt_";
+
 fn rules_from_properties(properties: &Properties) -> FormatRules {
     let parse_bool_value = |prop: &str| -> bool {
         match prop.to_lowercase().as_str() {
@@ -193,25 +303,71 @@ fn rules_from_properties(properties: &Properties) -> FormatRules {
     };
 
     let ensure_final_newline = match properties.get::<FinalNewline>() {
-        Ok(prop) => matches!(prop, FinalNewline::Value(true)),
-        Err(raw) => raw.into_option().is_none(),
+        Ok(prop) => Some(matches!(prop, FinalNewline::Value(true))),
+        Err(raw) => raw.into_option().map(|_| false),
     };
 
     let remove_trailing_spaces = match properties.get::<TrimTrailingWs>() {
-        Ok(prop) => matches!(prop, TrimTrailingWs::Value(true)),
-        Err(raw) => raw.into_option().is_none(),
+        Ok(prop) => Some(matches!(prop, TrimTrailingWs::Value(true))),
+        Err(raw) => raw.into_option().map(|_| false),
+    };
+
+    let end_of_line = match properties.get::<EndOfLine>() {
+        Ok(EndOfLine::Lf) => Some(LineEnding::Lf),
+        Ok(EndOfLine::CrLf) => Some(LineEnding::Crlf),
+        Ok(EndOfLine::Cr) => Some(LineEnding::Cr),
+        Err(_) => None,
     };
 
-    let remove_leading_newlines = properties
-        .get_raw_for_key("trim_leading_newlines")
+    // `basefmt_`-prefixed spellings win over the original custom names
+    let custom_raw = |key: &str| {
+        properties
+            .get_raw_for_key(format!("{NAMESPACE}{key}"))
+            .into_option()
+            .or_else(|| properties.get_raw_for_key(key).into_option())
+    };
+
+    let remove_leading_newlines = custom_raw("trim_leading_newlines").map(parse_bool_value);
+
+    let preserve_markdown_hard_breaks =
+        custom_raw("preserve_markdown_hard_breaks").map(parse_bool_value);
+
+    let max_blank_lines = properties
+        .get_raw_for_key(format!("{NAMESPACE}max_blank_lines"))
+        .into_option()
+        .and_then(|value| value.parse().ok());
+
+    let skip_minified = properties
+        .get_raw_for_key(format!("{NAMESPACE}skip_minified"))
+        .into_option()
+        .map(parse_bool_value);
+
+    let generated_marker_lines = properties
+        .get_raw_for_key(format!("{NAMESPACE}generated_marker_lines"))
+        .into_option()
+        .and_then(|value| value.parse().ok());
+
+    let preserve_yaml_block_scalars = properties
+        .get_raw_for_key(format!("{NAMESPACE}preserve_yaml_block_scalars"))
+        .into_option()
+        .map(parse_bool_value);
+
+    let normalize_encoding = properties
+        .get_raw_for_key(format!("{NAMESPACE}normalize_encoding"))
         .into_option()
-        .map(parse_bool_value)
-        .unwrap_or(true);
+        .and_then(|value| (value.eq_ignore_ascii_case("utf-8")).then_some(Charset::Utf8));
 
     FormatRules {
         ensure_final_newline,
         remove_trailing_spaces,
         remove_leading_newlines,
+        preserve_markdown_hard_breaks,
+        max_blank_lines,
+        skip_minified,
+        generated_marker_lines,
+        end_of_line,
+        preserve_yaml_block_scalars,
+        normalize_encoding,
     }
 }
 
@@ -271,9 +427,16 @@ mod tests {
             trim_leading_newlines = true
         "},
         FormatRules {
-            ensure_final_newline: true,
-            remove_trailing_spaces: true,
-            remove_leading_newlines: true,
+            ensure_final_newline: Some(true),
+            remove_trailing_spaces: Some(true),
+            remove_leading_newlines: Some(true),
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
         }
     )]
     #[case::all_false(
@@ -286,9 +449,16 @@ mod tests {
             trim_leading_newlines = false
         "},
         FormatRules {
-            ensure_final_newline: false,
-            remove_trailing_spaces: false,
-            remove_leading_newlines: false,
+            ensure_final_newline: Some(false),
+            remove_trailing_spaces: Some(false),
+            remove_leading_newlines: Some(false),
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
         }
     )]
     #[case::unset(
@@ -301,9 +471,16 @@ mod tests {
             trim_leading_newlines = unset
         "},
         FormatRules {
-            ensure_final_newline: false,
-            remove_trailing_spaces: false,
-            remove_leading_newlines: false,
+            ensure_final_newline: Some(false),
+            remove_trailing_spaces: Some(false),
+            remove_leading_newlines: Some(false),
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
         }
     )]
     #[case::not_present(
@@ -315,9 +492,16 @@ mod tests {
             indent_style = space
         "},
         FormatRules {
-            ensure_final_newline: true,
-            remove_trailing_spaces: true,
-            remove_leading_newlines: true,
+            ensure_final_newline: None,
+            remove_trailing_spaces: None,
+            remove_leading_newlines: None,
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
         }
     )]
     #[case::mixed(
@@ -330,11 +514,138 @@ mod tests {
             trim_leading_newlines = true
         "},
         FormatRules {
-            ensure_final_newline: true,
-            remove_trailing_spaces: false,
-            remove_leading_newlines: true,
+            ensure_final_newline: Some(true),
+            remove_trailing_spaces: Some(false),
+            remove_leading_newlines: Some(true),
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
+        }
+    )]
+    #[case::namespaced(
+        indoc! {"
+            root = true
+
+            [*]
+            basefmt_trim_leading_newlines = false
+            basefmt_preserve_markdown_hard_breaks = true
+            basefmt_max_blank_lines = 2
+            basefmt_skip_minified = false
+            basefmt_generated_marker_lines = 5
+        "},
+        FormatRules {
+            ensure_final_newline: None,
+            remove_trailing_spaces: None,
+            remove_leading_newlines: Some(false),
+            preserve_markdown_hard_breaks: Some(true),
+            max_blank_lines: Some(2),
+            skip_minified: Some(false),
+            generated_marker_lines: Some(5),
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
+        }
+    )]
+    #[case::namespaced_takes_precedence(
+        indoc! {"
+            root = true
+
+            [*]
+            basefmt_trim_leading_newlines = false
+            trim_leading_newlines = true
+        "},
+        FormatRules {
+            ensure_final_newline: None,
+            remove_trailing_spaces: None,
+            remove_leading_newlines: Some(false),
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
         }
     )]
+    #[case::end_of_line(
+        indoc! {"
+            root = true
+
+            [*]
+            end_of_line = crlf
+        "},
+        FormatRules {
+            end_of_line: Some(LineEnding::Crlf),
+            ..FormatRules::default()
+        }
+    )]
+    #[case::end_of_line_cr(
+        indoc! {"
+            root = true
+
+            [*]
+            end_of_line = cr
+        "},
+        FormatRules {
+            end_of_line: Some(LineEnding::Cr),
+            ..FormatRules::default()
+        }
+    )]
+    #[case::unsupported_end_of_line(
+        indoc! {"
+            root = true
+
+            [*]
+            end_of_line = native
+        "},
+        FormatRules::default()
+    )]
+    #[case::preserve_yaml_block_scalars(
+        indoc! {"
+            root = true
+
+            [*]
+            basefmt_preserve_yaml_block_scalars = true
+        "},
+        FormatRules {
+            preserve_yaml_block_scalars: Some(true),
+            ..FormatRules::default()
+        }
+    )]
+    #[case::normalize_encoding(
+        indoc! {"
+            root = true
+
+            [*]
+            basefmt_normalize_encoding = UTF-8
+        "},
+        FormatRules {
+            normalize_encoding: Some(Charset::Utf8),
+            ..FormatRules::default()
+        }
+    )]
+    #[case::unsupported_normalize_encoding(
+        indoc! {"
+            root = true
+
+            [*]
+            basefmt_normalize_encoding = latin1
+        "},
+        FormatRules::default()
+    )]
+    #[case::invalid_max_blank_lines(
+        indoc! {"
+            root = true
+
+            [*]
+            basefmt_max_blank_lines = -1
+        "},
+        FormatRules::default()
+    )]
     fn test_property_matrix(#[case] config: &str, #[case] expected: FormatRules) {
         let workspace = TestWorkspace::new();
         workspace.write_editorconfig(".", config);
@@ -359,9 +670,16 @@ mod tests {
         "},
         "test.md",
         FormatRules {
-            ensure_final_newline: true,
-            remove_trailing_spaces: false,
-            remove_leading_newlines: true,
+            ensure_final_newline: Some(true),
+            remove_trailing_spaces: Some(false),
+            remove_leading_newlines: Some(true),
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
         }
     )]
     #[case::section_txt(
@@ -378,9 +696,40 @@ mod tests {
         "},
         "test.txt",
         FormatRules {
-            ensure_final_newline: true,
-            remove_trailing_spaces: true,
-            remove_leading_newlines: true,
+            ensure_final_newline: Some(true),
+            remove_trailing_spaces: Some(true),
+            remove_leading_newlines: Some(true),
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
+        }
+    )]
+    #[case::section_markdown_hard_breaks(
+        indoc! {"
+            root = true
+
+            [*]
+            trim_trailing_whitespace = true
+
+            [*.md]
+            preserve_markdown_hard_breaks = true
+        "},
+        "test.md",
+        FormatRules {
+            ensure_final_newline: None,
+            remove_trailing_spaces: Some(true),
+            remove_leading_newlines: None,
+            preserve_markdown_hard_breaks: Some(true),
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
         }
     )]
     #[case::dir_match(
@@ -396,9 +745,16 @@ mod tests {
         "},
         "test/example.txt",
         FormatRules {
-            ensure_final_newline: true,
-            remove_trailing_spaces: false,
-            remove_leading_newlines: true,
+            ensure_final_newline: Some(true),
+            remove_trailing_spaces: Some(false),
+            remove_leading_newlines: None,
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
         }
     )]
     #[case::dir_outside(
@@ -414,9 +770,16 @@ mod tests {
         "},
         "root.txt",
         FormatRules {
-            ensure_final_newline: true,
-            remove_trailing_spaces: true,
-            remove_leading_newlines: true,
+            ensure_final_newline: Some(true),
+            remove_trailing_spaces: Some(true),
+            remove_leading_newlines: None,
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
         }
     )]
     #[case::extension_md(
@@ -435,9 +798,16 @@ mod tests {
         "},
         "README.md",
         FormatRules {
-            ensure_final_newline: false,
-            remove_trailing_spaces: false,
-            remove_leading_newlines: true,
+            ensure_final_newline: Some(false),
+            remove_trailing_spaces: Some(false),
+            remove_leading_newlines: None,
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
         }
     )]
     #[case::extension_txt(
@@ -456,9 +826,16 @@ mod tests {
         "},
         "test.txt",
         FormatRules {
-            ensure_final_newline: true,
-            remove_trailing_spaces: true,
-            remove_leading_newlines: true,
+            ensure_final_newline: Some(true),
+            remove_trailing_spaces: Some(true),
+            remove_leading_newlines: None,
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
         }
     )]
     fn test_pattern_matching(
@@ -482,9 +859,16 @@ mod tests {
         assert_eq!(
             rules,
             FormatRules {
-                ensure_final_newline: true,
-                remove_trailing_spaces: true,
-                remove_leading_newlines: true,
+                ensure_final_newline: None,
+                remove_trailing_spaces: None,
+                remove_leading_newlines: None,
+                preserve_markdown_hard_breaks: None,
+                max_blank_lines: None,
+                skip_minified: None,
+                generated_marker_lines: None,
+                end_of_line: None,
+                preserve_yaml_block_scalars: None,
+                normalize_encoding: None,
             }
         );
     }
@@ -505,9 +889,16 @@ mod tests {
         ],
         "subdir/test.txt",
         FormatRules {
-            ensure_final_newline: true,
-            remove_trailing_spaces: true,
-            remove_leading_newlines: true,
+            ensure_final_newline: Some(true),
+            remove_trailing_spaces: Some(true),
+            remove_leading_newlines: None,
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
         }
     )]
     #[case::child_overrides(
@@ -533,9 +924,16 @@ mod tests {
         ],
         "subdir/test.txt",
         FormatRules {
-            ensure_final_newline: true,
-            remove_trailing_spaces: false,
-            remove_leading_newlines: true,
+            ensure_final_newline: Some(true),
+            remove_trailing_spaces: Some(false),
+            remove_leading_newlines: Some(true),
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
         }
     )]
     #[case::root_stops_search(
@@ -562,9 +960,16 @@ mod tests {
         ],
         "subdir/test.txt",
         FormatRules {
-            ensure_final_newline: true,
-            remove_trailing_spaces: false,
-            remove_leading_newlines: true,
+            ensure_final_newline: None,
+            remove_trailing_spaces: Some(false),
+            remove_leading_newlines: None,
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
         }
     )]
     #[case::root_false_propagates(
@@ -589,9 +994,16 @@ mod tests {
         ],
         "child/test.txt",
         FormatRules {
-            ensure_final_newline: true,
-            remove_trailing_spaces: true,
-            remove_leading_newlines: true,
+            ensure_final_newline: Some(true),
+            remove_trailing_spaces: Some(true),
+            remove_leading_newlines: None,
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
         }
     )]
     #[case::missing_root_merges(
@@ -615,9 +1027,16 @@ mod tests {
         ],
         "mid/leaf/test.txt",
         FormatRules {
-            ensure_final_newline: true,
-            remove_trailing_spaces: true,
-            remove_leading_newlines: true,
+            ensure_final_newline: Some(true),
+            remove_trailing_spaces: Some(true),
+            remove_leading_newlines: None,
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
         }
     )]
     fn test_hierarchy(
@@ -658,7 +1077,7 @@ mod tests {
         workspace.write_file(file_path, "test");
 
         let rules = workspace.rules(file_path);
-        assert_eq!(rules.ensure_final_newline, !should_match);
+        assert_eq!(rules.ensure_final_newline, should_match.then_some(false));
     }
 
     #[test]
@@ -679,9 +1098,16 @@ mod tests {
         assert_eq!(
             rules,
             FormatRules {
-                ensure_final_newline: false,
-                remove_trailing_spaces: true,
-                remove_leading_newlines: true,
+                ensure_final_newline: Some(false),
+                remove_trailing_spaces: None,
+                remove_leading_newlines: None,
+                preserve_markdown_hard_breaks: None,
+                max_blank_lines: None,
+                skip_minified: None,
+                generated_marker_lines: None,
+                end_of_line: None,
+                preserve_yaml_block_scalars: None,
+                normalize_encoding: None,
             },
             "Invalid boolean values should be treated as false/disabled"
         );
@@ -698,9 +1124,16 @@ mod tests {
         assert_eq!(
             rules,
             FormatRules {
-                ensure_final_newline: true,
-                remove_trailing_spaces: true,
-                remove_leading_newlines: true,
+                ensure_final_newline: None,
+                remove_trailing_spaces: None,
+                remove_leading_newlines: None,
+                preserve_markdown_hard_breaks: None,
+                max_blank_lines: None,
+                skip_minified: None,
+                generated_marker_lines: None,
+                end_of_line: None,
+                preserve_yaml_block_scalars: None,
+                normalize_encoding: None,
             },
             "Malformed .editorconfig should be handled gracefully"
         );
@@ -727,9 +1160,69 @@ mod tests {
         unix_fs::symlink(&real_file, &link_file).unwrap();
 
         let rules = get_format_rules(&link_file);
-        assert!(
+        assert_eq!(
             rules.ensure_final_newline,
+            Some(true),
             "Symlinks should be resolved to find .editorconfig"
         );
     }
+
+    #[rstest]
+    #[case::matching_section(
+        "docs/README.md",
+        FormatRules {
+            ensure_final_newline: Some(true),
+            remove_trailing_spaces: Some(false),
+            remove_leading_newlines: None,
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
+        }
+    )]
+    #[case::other_section(
+        "src/main.rs",
+        FormatRules {
+            ensure_final_newline: Some(false),
+            remove_trailing_spaces: None,
+            remove_leading_newlines: None,
+            preserve_markdown_hard_breaks: None,
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
+        }
+    )]
+    fn test_rules_from_editorconfig(#[case] path: &str, #[case] expected: FormatRules) {
+        let config = indoc! {"
+            root = true
+
+            [*]
+            insert_final_newline = false
+
+            [*.md]
+            insert_final_newline = true
+            trim_trailing_whitespace = false
+        "};
+
+        assert_eq!(
+            rules_from_editorconfig(config, Path::new(path)).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_rules_from_malformed_editorconfig() {
+        let config = indoc! {"
+            [*
+            insert_final_newline = false
+        "};
+        let err = rules_from_editorconfig(config, Path::new("a.txt")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
 }
@@ -0,0 +1,473 @@
+use super::sections::section_headers;
+use super::{FormatRules, PropertyWarning, lint_editorconfig, rules_from_properties};
+use crate::filesystem::{FileSystem, RealFileSystem};
+use ec4rs::{ConfigParser, Properties, PropertiesSource, Section};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Caches parsed EditorConfig files to avoid redundant IO on large projects.
+///
+/// The cache is `Sync`, so one instance can be shared by every worker thread
+/// of a run (or by every request of a long-lived process) instead of each
+/// keeping its own. Lookups only take a read lock on the cache they hit, and
+/// `.editorconfig` files are read and parsed without holding any lock, so
+/// threads resolving rules for different files don't wait on each other's
+/// IO. Two threads missing on the same directory at once may both read its
+/// `.editorconfig`, but only the first result is kept.
+pub struct EditorConfigCache {
+    fs: Arc<dyn FileSystem>,
+    file_name: String,
+    root_dir: Option<PathBuf>,
+    lint: bool,
+    warnings: Mutex<Vec<PropertyWarning>>,
+    dir_stacks: RwLock<HashMap<PathBuf, ConfigStack>>,
+    config_files: RwLock<HashMap<PathBuf, Option<Arc<ParsedConfig>>>>,
+    rules_cache: RwLock<HashMap<PathBuf, FormatRules>>,
+}
+
+/// The parsed `.editorconfig` files that apply to a directory, outermost
+/// first.
+pub(super) type ConfigStack = Arc<Vec<Arc<ParsedConfig>>>;
+
+impl Default for EditorConfigCache {
+    fn default() -> Self {
+        Self::with_file_system(Arc::new(RealFileSystem))
+    }
+}
+
+impl EditorConfigCache {
+    /// Creates an empty cache that reads `.editorconfig` files from disk.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty cache that reads `.editorconfig` files through `fs`.
+    ///
+    /// Paths passed to [`EditorConfigCache::rules_for`] are then looked up in
+    /// `fs` as given, so they only need to be absolute within it.
+    pub fn with_file_system(fs: Arc<dyn FileSystem>) -> Self {
+        Self {
+            fs,
+            file_name: ".editorconfig".to_string(),
+            root_dir: None,
+            lint: false,
+            warnings: Mutex::new(Vec::new()),
+            dir_stacks: RwLock::new(HashMap::new()),
+            config_files: RwLock::new(HashMap::new()),
+            rules_cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Looks up `file_name` in each directory instead of `.editorconfig`, so
+    /// that e.g. CI can use a `.editorconfig-ci` next to the canonical one.
+    pub fn with_file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = file_name.into();
+        self
+    }
+
+    /// Stops looking for `.editorconfig` files in parent directories at
+    /// `root_dir`, as if it held one with `root = true`, so that files above
+    /// it (e.g. a stray `~/.editorconfig`) never apply.
+    ///
+    /// `root_dir` must be canonical like the paths passed to
+    /// [`EditorConfigCache::rules_for`]; paths outside it are unaffected.
+    pub fn with_root_dir(mut self, root_dir: PathBuf) -> Self {
+        self.root_dir = Some(root_dir);
+        self
+    }
+
+    /// Also lints every `.editorconfig` the cache reads with
+    /// [`lint_editorconfig`], collecting the results in
+    /// [`EditorConfigCache::warnings`].
+    pub fn with_lint(mut self) -> Self {
+        self.lint = true;
+        self
+    }
+
+    /// Returns the lint warnings for the `.editorconfig` files read so far,
+    /// in the order they were read. Always empty unless
+    /// [`EditorConfigCache::with_lint`] was used.
+    pub fn warnings(&self) -> Vec<PropertyWarning> {
+        lock(&self.warnings).clone()
+    }
+
+    /// Returns the paths an EditorConfig file was looked up at so far,
+    /// whether or not one was found there, so that callers holding on to the
+    /// cache can tell when it is stale.
+    pub fn lookups(&self) -> Vec<PathBuf> {
+        read(&self.config_files)
+            .keys()
+            .map(|dir| dir.join(&self.file_name))
+            .collect()
+    }
+
+    /// Returns formatting rules for the given canonical path, caching repeated lookups.
+    ///
+    /// # Arguments
+    ///
+    /// * `canonical_path` - A canonicalized (absolute) path to the file.
+    ///   Must be obtained via `Path::canonicalize()`.
+    pub fn rules_for(&self, canonical_path: &Path) -> FormatRules {
+        if let Some(rules) = read(&self.rules_cache).get(canonical_path) {
+            return rules.clone();
+        }
+
+        let mut properties = Properties::new();
+        if let Some(parent) = canonical_path.parent() {
+            for config in self.stack_for_dir(parent).iter() {
+                config.apply_to(&mut properties, canonical_path);
+            }
+        }
+        let rules = rules_from_properties(&properties);
+        write(&self.rules_cache).insert(canonical_path.to_path_buf(), rules.clone());
+        rules
+    }
+
+    /// Returns whether any EditorConfig file applies to the given canonical
+    /// path, whether or not one of its sections matches the file.
+    pub fn has_config_for(&self, canonical_path: &Path) -> bool {
+        canonical_path
+            .parent()
+            .is_some_and(|parent| !self.stack_for_dir(parent).is_empty())
+    }
+
+    pub(super) fn stack_for_dir(&self, dir: &Path) -> ConfigStack {
+        if let Some(stack) = read(&self.dir_stacks).get(dir) {
+            return Arc::clone(stack);
+        }
+
+        let parent = dir
+            .parent()
+            .filter(|_| self.root_dir.as_deref() != Some(dir));
+        let mut combined = if let Some(parent) = parent {
+            self.stack_for_dir(parent).as_ref().clone()
+        } else {
+            Vec::new()
+        };
+
+        if let Some(config) = self.load_config_for_dir(dir) {
+            if config.is_root {
+                combined.clear();
+            }
+            combined.push(config);
+        }
+
+        Arc::clone(
+            write(&self.dir_stacks)
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| Arc::new(combined)),
+        )
+    }
+
+    fn load_config_for_dir(&self, dir: &Path) -> Option<Arc<ParsedConfig>> {
+        if let Some(entry) = read(&self.config_files).get(dir) {
+            return entry.clone();
+        }
+
+        let config_path = dir.join(&self.file_name);
+        let mut warnings = Vec::new();
+        let parsed = match self.fs.read(&config_path) {
+            Ok(content) => {
+                if self.lint {
+                    warnings = lint_editorconfig(&config_path, &String::from_utf8_lossy(&content));
+                }
+                parse_config_file(dir, &config_path, &content)
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => {
+                eprintln!(
+                    "{}: failed to read .editorconfig: {}",
+                    config_path.display(),
+                    err
+                );
+                None
+            }
+        };
+
+        match write(&self.config_files).entry(dir.to_path_buf()) {
+            // Another thread read this directory's `.editorconfig` meanwhile
+            Entry::Occupied(entry) => entry.get().clone(),
+            Entry::Vacant(entry) => {
+                lock(&self.warnings).extend(warnings);
+                entry.insert(parsed).clone()
+            }
+        }
+    }
+}
+
+// A poisoned lock only means another thread panicked while holding it; the
+// maps are always left consistent, so keep using them.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn parse_config_file(dir: &Path, path: &Path, content: &[u8]) -> Option<Arc<ParsedConfig>> {
+    let parser = match ConfigParser::new_buffered_with_path(content, Some(path)) {
+        Ok(parser) => parser,
+        Err(err) => {
+            eprintln!("{}: failed to read .editorconfig: {}", path.display(), err);
+            return None;
+        }
+    };
+    match parse_config(dir, path, content, parser) {
+        Ok(config) => Some(Arc::new(config)),
+        Err((line, err)) => {
+            eprintln!(
+                "{}:{}: failed to parse .editorconfig: {}",
+                path.display(),
+                line,
+                err
+            );
+            None
+        }
+    }
+}
+
+/// Parses every section of the EditorConfig file at `path` with `content`,
+/// returning the line number along with the error if one cannot be parsed.
+pub(super) fn parse_config<R: io::BufRead>(
+    dir: &Path,
+    path: &Path,
+    content: &[u8],
+    mut parser: ConfigParser<R>,
+) -> Result<ParsedConfig, (usize, ec4rs::ParseError)> {
+    let mut sections = Vec::new();
+    while let Some(section_result) = parser.next() {
+        match section_result {
+            Ok(section) => sections.push(section),
+            Err(err) => return Err((parser.line_no(), err)),
+        }
+    }
+
+    Ok(ParsedConfig {
+        dir: dir.to_path_buf(),
+        path: path.to_path_buf(),
+        is_root: parser.is_root,
+        sections: Arc::new(sections),
+        headers: Arc::new(section_headers(content)),
+    })
+}
+
+#[derive(Clone)]
+pub(super) struct ParsedConfig {
+    pub(super) dir: PathBuf,
+    pub(super) path: PathBuf,
+    is_root: bool,
+    pub(super) sections: Arc<Vec<Section>>,
+    /// The line and pattern of the header of each section
+    pub(super) headers: Arc<Vec<(usize, String)>>,
+}
+
+impl ParsedConfig {
+    pub(super) fn apply_to(&self, props: &mut Properties, file_path: &Path) {
+        let rel_path = file_path.strip_prefix(&self.dir).unwrap_or(file_path);
+        for section in self.sections.as_ref() {
+            let _ = section.apply_to(props, rel_path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::MemoryFileSystem;
+    use indoc::indoc;
+    use rstest::rstest;
+
+    #[test]
+    fn test_cache_with_file_system() {
+        let fs = MemoryFileSystem::new().with_file(
+            "/virtual/.editorconfig",
+            indoc! {"
+                [*.txt]
+                trim_trailing_whitespace = false
+            "},
+        );
+        let cache = EditorConfigCache::with_file_system(Arc::new(fs));
+
+        assert_eq!(
+            cache.rules_for(Path::new("/virtual/docs/a.txt")),
+            FormatRules {
+                ensure_final_newline: None,
+                remove_trailing_spaces: Some(false),
+                remove_leading_newlines: None,
+                preserve_markdown_hard_breaks: None,
+                max_blank_lines: None,
+                skip_minified: None,
+                generated_marker_lines: None,
+                end_of_line: None,
+                preserve_yaml_block_scalars: None,
+                normalize_encoding: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_cache_with_file_name() {
+        let fs = MemoryFileSystem::new()
+            .with_file(
+                "/p/.editorconfig",
+                indoc! {"
+                    [*]
+                    insert_final_newline = false
+                "},
+            )
+            .with_file(
+                "/p/.editorconfig-ci",
+                indoc! {"
+                    [*]
+                    trim_trailing_whitespace = false
+                "},
+            );
+        let cache =
+            EditorConfigCache::with_file_system(Arc::new(fs)).with_file_name(".editorconfig-ci");
+
+        assert_eq!(
+            cache.rules_for(Path::new("/p/a.txt")),
+            FormatRules {
+                remove_trailing_spaces: Some(false),
+                ..FormatRules::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_cache_with_root_dir() {
+        let fs = MemoryFileSystem::new()
+            .with_file(
+                "/home/.editorconfig",
+                indoc! {"
+                    [*]
+                    insert_final_newline = false
+                "},
+            )
+            .with_file(
+                "/home/checkout/.editorconfig",
+                indoc! {"
+                    [*]
+                    trim_trailing_whitespace = false
+                "},
+            );
+        let cache = EditorConfigCache::with_file_system(Arc::new(fs))
+            .with_root_dir(PathBuf::from("/home/checkout"));
+
+        assert_eq!(
+            (
+                cache.rules_for(Path::new("/home/checkout/src/a.txt")),
+                cache.rules_for(Path::new("/home/b.txt"))
+            ),
+            (
+                FormatRules {
+                    remove_trailing_spaces: Some(false),
+                    ..FormatRules::default()
+                },
+                FormatRules {
+                    ensure_final_newline: Some(false),
+                    ..FormatRules::default()
+                }
+            )
+        );
+    }
+
+    #[rstest]
+    #[case::section_matches("/project/a.txt", true)]
+    #[case::no_section_matches("/project/a.md", true)]
+    #[case::outside_project("/other/a.txt", false)]
+    fn test_cache_has_config_for(#[case] path: &str, #[case] expected: bool) {
+        let fs = MemoryFileSystem::new().with_file("/project/.editorconfig", "[*.txt]\n");
+        let cache = EditorConfigCache::with_file_system(Arc::new(fs));
+
+        assert_eq!(cache.has_config_for(Path::new(path)), expected);
+    }
+
+    #[test]
+    fn test_cache_with_lint() {
+        let fs = MemoryFileSystem::new()
+            .with_file(
+                "/p/.editorconfig",
+                indoc! {"
+                    [*]
+                    trim_trailing_whitespaces = true
+                "},
+            )
+            .with_file(
+                "/p/sub/.editorconfig",
+                indoc! {"
+                    [*]
+                    insert_final_newline = true
+                "},
+            );
+        let cache = EditorConfigCache::with_file_system(Arc::new(fs)).with_lint();
+
+        cache.rules_for(Path::new("/p/sub/a.txt"));
+        cache.rules_for(Path::new("/p/b.txt"));
+
+        assert_eq!(
+            cache.warnings(),
+            [PropertyWarning {
+                path: PathBuf::from("/p/.editorconfig"),
+                line: 2,
+                key: "trim_trailing_whitespaces".to_string(),
+                suggestion: "trim_trailing_whitespace",
+            }]
+        );
+    }
+
+    #[test]
+    fn test_cache_shared_across_threads() {
+        let fs = MemoryFileSystem::new().with_file(
+            "/p/.editorconfig",
+            indoc! {"
+                [*.md]
+                trim_trailing_whitespaces = false
+                trim_leading_newlines = false
+            "},
+        );
+        let cache = EditorConfigCache::with_file_system(Arc::new(fs)).with_lint();
+
+        let results: Vec<(FormatRules, FormatRules)> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        (
+                            cache.rules_for(Path::new("/p/docs/a.md")),
+                            cache.rules_for(Path::new("/p/b.txt")),
+                        )
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        let md = FormatRules {
+            remove_leading_newlines: Some(false),
+            ..FormatRules::default()
+        };
+        assert_eq!(
+            (results, cache.warnings()),
+            (
+                vec![(md, FormatRules::default()); 8],
+                vec![PropertyWarning {
+                    path: PathBuf::from("/p/.editorconfig"),
+                    line: 2,
+                    key: "trim_trailing_whitespaces".to_string(),
+                    suggestion: "trim_trailing_whitespace",
+                }]
+            )
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The properties basefmt reads, which misspellings are compared against.
+const KNOWN_PROPERTIES: &[&str] = &[
+    "insert_final_newline",
+    "trim_trailing_whitespace",
+    "end_of_line",
+    "trim_leading_newlines",
+    "preserve_markdown_hard_breaks",
+    "basefmt_trim_leading_newlines",
+    "basefmt_preserve_markdown_hard_breaks",
+    "basefmt_max_blank_lines",
+    "basefmt_skip_minified",
+    "basefmt_generated_marker_lines",
+    "basefmt_preserve_yaml_block_scalars",
+    "basefmt_normalize_encoding",
+];
+
+/// Standard EditorConfig properties that basefmt ignores, so they are never
+/// mistaken for misspellings of the ones it reads.
+const OTHER_PROPERTIES: &[&str] = &[
+    "root",
+    "indent_style",
+    "indent_size",
+    "tab_width",
+    "charset",
+    "max_line_length",
+    "spelling_language",
+];
+
+/// How many single-character edits away from a known property a key may be
+/// to count as a misspelling of it.
+const MAX_DISTANCE: usize = 2;
+
+/// A property in an `.editorconfig` that looks like a misspelling of one
+/// basefmt reads, and therefore silently has no effect.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PropertyWarning {
+    /// Path to the `.editorconfig` file
+    pub path: PathBuf,
+    /// 1-based line of the property
+    pub line: usize,
+    /// The property as written
+    pub key: String,
+    /// The property basefmt reads that `key` most likely meant
+    pub suggestion: &'static str,
+}
+
+impl fmt::Display for PropertyWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: unknown property '{}' (did you mean '{}'?)",
+            self.path.display(),
+            self.line,
+            self.key,
+            self.suggestion
+        )
+    }
+}
+
+/// Flags properties in the text of the `.editorconfig` at `path` that are
+/// near-misses of properties basefmt reads, such as
+/// `trim_trailing_whitespaces`.
+///
+/// Other unknown properties are left alone, since they are often meant for
+/// other tools.
+pub fn lint_editorconfig(path: &Path, content: &str) -> Vec<PropertyWarning> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let line = line.trim();
+            if line.starts_with(['#', ';', '[']) {
+                return None;
+            }
+            let (key, _) = line.split_once('=')?;
+            let key = key.trim();
+            let suggestion = suggest(&key.to_lowercase())?;
+            Some(PropertyWarning {
+                path: path.to_path_buf(),
+                line: index + 1,
+                key: key.to_string(),
+                suggestion,
+            })
+        })
+        .collect()
+}
+
+fn suggest(key: &str) -> Option<&'static str> {
+    if KNOWN_PROPERTIES.contains(&key) || OTHER_PROPERTIES.contains(&key) {
+        return None;
+    }
+    closest_match(key, KNOWN_PROPERTIES)
+}
+
+/// Returns the one of `candidates` that `key` is most likely a misspelling
+/// of, if any is close enough.
+pub(crate) fn closest_match(key: &str, candidates: &[&'static str]) -> Option<&'static str> {
+    candidates
+        .iter()
+        .map(|known| (edit_distance(key, known), *known))
+        .filter(|(distance, _)| *distance <= MAX_DISTANCE)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, known)| known)
+}
+
+/// Returns the Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use rstest::rstest;
+
+    #[test]
+    fn test_lint_editorconfig() {
+        let content = indoc! {"
+            root = true
+            # trim_trailing_whitespaces = true
+
+            [*]
+            trim_trailing_whitespaces = true
+            Insert_Final_Newlines = false
+            trim-leading-newlines = false
+            insert_final_newline = true
+            indent_size = 2
+            quote_type = single
+            basefmt_max_blank_line = 1
+        "};
+
+        assert_eq!(
+            lint_editorconfig(Path::new(".editorconfig"), content),
+            vec![
+                PropertyWarning {
+                    path: PathBuf::from(".editorconfig"),
+                    line: 5,
+                    key: "trim_trailing_whitespaces".to_string(),
+                    suggestion: "trim_trailing_whitespace",
+                },
+                PropertyWarning {
+                    path: PathBuf::from(".editorconfig"),
+                    line: 6,
+                    key: "Insert_Final_Newlines".to_string(),
+                    suggestion: "insert_final_newline",
+                },
+                PropertyWarning {
+                    path: PathBuf::from(".editorconfig"),
+                    line: 7,
+                    key: "trim-leading-newlines".to_string(),
+                    suggestion: "trim_leading_newlines",
+                },
+                PropertyWarning {
+                    path: PathBuf::from(".editorconfig"),
+                    line: 11,
+                    key: "basefmt_max_blank_line".to_string(),
+                    suggestion: "basefmt_max_blank_lines",
+                },
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::identical("abc", "abc", 0)]
+    #[case::insertion("abc", "abcd", 1)]
+    #[case::substitution("abc", "abd", 1)]
+    #[case::empty("", "abc", 3)]
+    fn test_edit_distance(#[case] a: &str, #[case] b: &str, #[case] expected: usize) {
+        assert_eq!(edit_distance(a, b), expected);
+    }
+
+    #[test]
+    fn test_display() {
+        let warning = PropertyWarning {
+            path: PathBuf::from("docs/.editorconfig"),
+            line: 3,
+            key: "insert_final_newlines".to_string(),
+            suggestion: "insert_final_newline",
+        };
+        assert_eq!(
+            warning.to_string(),
+            "docs/.editorconfig:3: unknown property 'insert_final_newlines' (did you mean 'insert_final_newline'?)"
+        );
+    }
+}
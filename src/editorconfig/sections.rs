@@ -0,0 +1,160 @@
+use super::{EditorConfigCache, FormatRules, rules_from_properties};
+use std::path::{Path, PathBuf};
+
+/// A section of an `.editorconfig` file that applies to a file, with the
+/// rules it sets (see [`EditorConfigCache::sections_for`]).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SectionRules {
+    /// Path to the `.editorconfig` file
+    pub path: PathBuf,
+    /// 1-based line of the section header
+    pub line: usize,
+    /// The pattern of the section header, e.g. `*.md`
+    pub pattern: String,
+    /// The rules the section sets on its own
+    pub rules: FormatRules,
+}
+
+impl EditorConfigCache {
+    /// Returns the paths of the EditorConfig files that apply to the files in
+    /// the given canonical directory, outermost first.
+    pub fn files_for(&self, canonical_dir: &Path) -> Vec<PathBuf> {
+        self.stack_for_dir(canonical_dir)
+            .iter()
+            .map(|config| config.path.clone())
+            .collect()
+    }
+
+    /// Returns the sections of the EditorConfig files that apply to the given
+    /// canonical path, in the order they are applied, so that later ones take
+    /// precedence, along with the rules each sets.
+    ///
+    /// Sections that set none of the properties basefmt reads are left out.
+    pub fn sections_for(&self, canonical_path: &Path) -> Vec<SectionRules> {
+        let Some(parent) = canonical_path.parent() else {
+            return Vec::new();
+        };
+        let mut sections = Vec::new();
+        for config in self.stack_for_dir(parent).iter() {
+            let rel_path = canonical_path
+                .strip_prefix(&config.dir)
+                .unwrap_or(canonical_path);
+            for (section, (line, pattern)) in config.sections.iter().zip(config.headers.iter()) {
+                if !section.applies_to(rel_path) {
+                    continue;
+                }
+                let rules = rules_from_properties(section.props());
+                if rules != FormatRules::default() {
+                    sections.push(SectionRules {
+                        path: config.path.clone(),
+                        line: *line,
+                        pattern: pattern.clone(),
+                        rules,
+                    });
+                }
+            }
+        }
+        sections
+    }
+}
+
+/// Returns the 1-based line and the pattern of each section header in the
+/// text of an `.editorconfig`, read the way ec4rs reads them.
+pub(super) fn section_headers(content: &[u8]) -> Vec<(usize, String)> {
+    String::from_utf8_lossy(content)
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let line = line.trim_start();
+            if line.starts_with(['#', ';']) {
+                return None;
+            }
+            // A comment may follow the closing bracket
+            let line = match (line.rfind(']'), line.rfind(['#', ';'])) {
+                (Some(bracket), Some(comment)) if comment > bracket => &line[..comment],
+                _ => line,
+            };
+            let pattern = line.trim_end().strip_prefix('[')?.strip_suffix(']')?;
+            Some((index + 1, pattern.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::MemoryFileSystem;
+    use indoc::indoc;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_sections_for() {
+        let fs = MemoryFileSystem::new()
+            .with_file(
+                "/p/.editorconfig",
+                indoc! {"
+                    root = true
+
+                    [*]
+                    insert_final_newline = true
+                    trim_trailing_whitespace = true
+
+                    [*.txt] # plain text
+                    indent_size = 2
+
+                    [*.md]
+                    trim_trailing_whitespace = false
+                "},
+            )
+            .with_file(
+                "/p/docs/.editorconfig",
+                indoc! {"
+                    [{*.md,*.rst}]
+                    basefmt_max_blank_lines = 1
+                "},
+            );
+        let cache = EditorConfigCache::with_file_system(Arc::new(fs));
+
+        assert_eq!(
+            cache.sections_for(Path::new("/p/docs/guide.md")),
+            vec![
+                SectionRules {
+                    path: PathBuf::from("/p/.editorconfig"),
+                    line: 3,
+                    pattern: "*".to_string(),
+                    rules: FormatRules {
+                        ensure_final_newline: Some(true),
+                        remove_trailing_spaces: Some(true),
+                        ..FormatRules::default()
+                    },
+                },
+                SectionRules {
+                    path: PathBuf::from("/p/.editorconfig"),
+                    line: 10,
+                    pattern: "*.md".to_string(),
+                    rules: FormatRules {
+                        remove_trailing_spaces: Some(false),
+                        ..FormatRules::default()
+                    },
+                },
+                SectionRules {
+                    path: PathBuf::from("/p/docs/.editorconfig"),
+                    line: 1,
+                    pattern: "{*.md,*.rst}".to_string(),
+                    rules: FormatRules {
+                        max_blank_lines: Some(1),
+                        ..FormatRules::default()
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_section_headers() {
+        assert_eq!(
+            section_headers(b"root = true\n[*]\n; [not]\n [*.md] # docs\n"),
+            vec![(2, "*".to_string()), (4, "*.md".to_string())]
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::string::FromUtf8Error;
+use thiserror::Error;
+
+/// An error from loading the configuration or discovering files, carrying
+/// the path involved, what was being done with it, and the underlying cause,
+/// so that library users can tell failures apart without parsing messages.
+///
+/// Converts into an [`io::Error`] of the matching [`kind`](Self::kind) that
+/// wraps it, for callers that deal in `io::Result`.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum BasefmtError {
+    /// A file or directory could not be accessed
+    #[error("{}: failed to {operation}: {source}", path.display())]
+    Io {
+        /// The path being accessed
+        path: PathBuf,
+        /// What was being done with `path`
+        operation: Operation,
+        /// The underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// Some of the paths to search could not be traversed; each problem has
+    /// already been reported on stderr
+    #[error("errors occurred while processing files: {}", display_paths(failures))]
+    Traversal {
+        /// The paths whose traversal failed, each with its error
+        failures: Vec<(PathBuf, io::Error)>,
+    },
+    /// `.basefmt.toml` is not valid UTF-8
+    #[error("{}: invalid UTF-8: {source}", path.display())]
+    ConfigEncoding {
+        /// Path to `.basefmt.toml`
+        path: PathBuf,
+        /// The underlying error
+        #[source]
+        source: FromUtf8Error,
+    },
+    /// `.basefmt.toml` is not valid TOML or has invalid values
+    #[error("failed to parse .basefmt.toml: {source}")]
+    ConfigSyntax {
+        /// Path to `.basefmt.toml`
+        path: PathBuf,
+        /// The underlying error, boxed since it is several times the size of
+        /// the other variants
+        #[source]
+        source: Box<toml::de::Error>,
+    },
+    /// `.basefmt.yaml` or `.basefmt.yml` is not valid YAML or has invalid
+    /// values
+    #[error("failed to parse {}: {source}", path.display())]
+    ConfigYaml {
+        /// Path to the file
+        path: PathBuf,
+        /// The underlying error
+        #[source]
+        source: serde_yaml::Error,
+    },
+    /// `.basefmt.json` is not valid JSON or has invalid values
+    #[error("failed to parse {}: {source}", path.display())]
+    ConfigJson {
+        /// Path to `.basefmt.json`
+        path: PathBuf,
+        /// The underlying error
+        #[source]
+        source: serde_json::Error,
+    },
+    /// The `extends` key of `.basefmt.toml` is not a path
+    #[error("{}: 'extends' must be a path", path.display())]
+    InvalidExtends {
+        /// Path to `.basefmt.toml`
+        path: PathBuf,
+    },
+    /// `.basefmt.toml` extends a file that extends it in turn, directly or
+    /// through others
+    #[error("{}: 'extends' forms a cycle through {}", path.display(), base.display())]
+    ExtendsCycle {
+        /// Path to the file whose `extends` closes the cycle
+        path: PathBuf,
+        /// The file it extends, which already extends it
+        base: PathBuf,
+    },
+    /// `.basefmt.toml` has a key basefmt doesn't know, e.g. a misspelled one
+    #[error("{}: unknown key '{key}'{}", path.display(), display_suggestion(suggestion.as_deref()))]
+    UnknownConfigKey {
+        /// Path to `.basefmt.toml`
+        path: PathBuf,
+        /// The key, as `rules.KEY` for one in the `[rules]` table
+        key: String,
+        /// The known key it is most likely a misspelling of
+        suggestion: Option<String>,
+    },
+    /// An exclude pattern is not a valid glob
+    #[error("invalid glob pattern '{pattern}': {source}")]
+    InvalidGlob {
+        /// The pattern as written
+        pattern: String,
+        /// The underlying error
+        #[source]
+        source: globset::Error,
+    },
+    /// The exclude patterns are valid but too large to match together
+    #[error("failed to build glob set: {source}")]
+    GlobSet {
+        /// The underlying error
+        #[source]
+        source: globset::Error,
+    },
+}
+
+/// What was being done when a [`BasefmtError::Io`] occurred.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Operation {
+    /// Reading `.basefmt.toml`
+    ReadConfig,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::ReadConfig => f.write_str("read configuration"),
+        }
+    }
+}
+
+impl BasefmtError {
+    /// Returns the [`io::ErrorKind`] that best describes this error.
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            BasefmtError::Io { source, .. } => source.kind(),
+            BasefmtError::Traversal { failures } => match failures.as_slice() {
+                [(_, err)] => err.kind(),
+                _ => io::ErrorKind::Other,
+            },
+            BasefmtError::ConfigEncoding { .. }
+            | BasefmtError::ConfigSyntax { .. }
+            | BasefmtError::ConfigYaml { .. }
+            | BasefmtError::ConfigJson { .. }
+            | BasefmtError::InvalidExtends { .. }
+            | BasefmtError::ExtendsCycle { .. }
+            | BasefmtError::UnknownConfigKey { .. } => io::ErrorKind::InvalidData,
+            BasefmtError::InvalidGlob { .. } | BasefmtError::GlobSet { .. } => {
+                io::ErrorKind::InvalidInput
+            }
+        }
+    }
+}
+
+impl From<BasefmtError> for io::Error {
+    fn from(err: BasefmtError) -> Self {
+        io::Error::new(err.kind(), err)
+    }
+}
+
+fn display_paths(failures: &[(PathBuf, io::Error)]) -> String {
+    failures
+        .iter()
+        .map(|(path, _)| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn display_suggestion(suggestion: Option<&str>) -> String {
+    suggestion
+        .map(|suggestion| format!(" (did you mean '{suggestion}'?)"))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn test_into_io_error() {
+        let err = io::Error::from(BasefmtError::Io {
+            path: PathBuf::from(".basefmt.toml"),
+            operation: Operation::ReadConfig,
+            source: io::Error::from(io::ErrorKind::PermissionDenied),
+        });
+
+        assert_eq!(
+            (
+                err.kind(),
+                err.to_string(),
+                err.get_ref()
+                    .and_then(|inner| inner.downcast_ref::<BasefmtError>())
+                    .and_then(|inner| inner.source())
+                    .map(ToString::to_string)
+            ),
+            (
+                io::ErrorKind::PermissionDenied,
+                ".basefmt.toml: failed to read configuration: permission denied".to_string(),
+                Some("permission denied".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn test_traversal_kind() {
+        let single = BasefmtError::Traversal {
+            failures: vec![(PathBuf::from("a"), io::Error::from(io::ErrorKind::NotFound))],
+        };
+        let several = BasefmtError::Traversal {
+            failures: vec![
+                (PathBuf::from("a"), io::Error::from(io::ErrorKind::NotFound)),
+                (PathBuf::from("b"), io::Error::from(io::ErrorKind::NotFound)),
+            ],
+        };
+
+        assert_eq!(
+            (single.kind(), several.kind(), several.to_string()),
+            (
+                io::ErrorKind::NotFound,
+                io::ErrorKind::Other,
+                "errors occurred while processing files: a, b".to_string()
+            )
+        );
+    }
+}
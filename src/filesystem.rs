@@ -0,0 +1,173 @@
+use crate::format::temp;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+mod memory;
+mod walk;
+
+pub use memory::MemoryFileSystem;
+
+/// The file access basefmt needs to find, configure, check and format files.
+///
+/// Implement this to serve files from somewhere other than the local disk,
+/// e.g. from a host application's buffers when running as WASM, where
+/// `std::fs` is unavailable. A missing file must be reported with
+/// [`io::ErrorKind::NotFound`].
+pub trait FileSystem: Send + Sync {
+    /// Reads the whole contents of the file at `path`.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Replaces the contents of the file at `path`, creating it if needed.
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// Returns every file to format under `path`, or `path` itself if it is a
+    /// file.
+    ///
+    /// Problems that only affect part of the traversal are returned in place
+    /// of the entries they hide, so the rest can still be processed.
+    /// Returns a single error if `path` doesn't exist.
+    fn files_under(&self, path: &Path) -> Vec<io::Result<PathBuf>>;
+
+    /// Returns every file to format under `path` like
+    /// [`FileSystem::files_under`], but applies `.gitignore` files even where
+    /// no git repository contains them.
+    ///
+    /// Defaults to [`FileSystem::files_under`], for file systems that don't
+    /// read `.gitignore` files at all.
+    fn files_under_without_git(&self, path: &Path) -> Vec<io::Result<PathBuf>> {
+        self.files_under(path)
+    }
+
+    /// Returns every file to format under `path` like
+    /// [`FileSystem::files_under`], or like
+    /// [`FileSystem::files_under_without_git`] if `gitignore_without_git` is
+    /// set, and also under the directories symbolic links point to.
+    ///
+    /// A directory reached again through another path must be left out, with
+    /// an error wrapping [`AlreadyWalked`] in its place, so that a link to a
+    /// parent directory can't make the walk loop.
+    ///
+    /// Defaults to not following links, for file systems without them.
+    fn files_under_following_links(
+        &self,
+        path: &Path,
+        gitignore_without_git: bool,
+    ) -> Vec<io::Result<PathBuf>> {
+        if gitignore_without_git {
+            self.files_under_without_git(path)
+        } else {
+            self.files_under(path)
+        }
+    }
+}
+
+/// The error in place of a directory that a walk following symbolic links
+/// left out, since it was already walked through another path (see
+/// [`FileSystem::files_under_following_links`]).
+///
+/// Searching for files only warns about these rather than failing.
+#[derive(Debug, Error)]
+#[error("skipped directory that was already visited through another path")]
+pub struct AlreadyWalked(pub PathBuf);
+
+/// Whether `dir` has a `.git` entry, which makes it the root of a git
+/// repository: a directory in a regular checkout and a file in worktrees and
+/// submodules.
+pub(crate) fn has_git_entry(fs: &dyn FileSystem, dir: &Path) -> bool {
+    // Reading a directory fails, but not with `NotFound`
+    !matches!(
+        fs.read(&dir.join(".git")),
+        Err(err) if err.kind() == io::ErrorKind::NotFound
+    )
+}
+
+/// The local file system, accessed through `std::fs`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RealFileSystem;
+
+impl FileSystem for RealFileSystem {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    /// Writes to a temporary file next to `path` and renames it into place,
+    /// keeping the permissions of the file it replaces.
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let parent_dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let mut temp_file = temp::create_in(parent_dir)?;
+        temp_file.write_all(contents)?;
+        if let Ok(metadata) = fs::metadata(path) {
+            temp_file
+                .as_file()
+                .set_permissions(metadata.permissions())?;
+        }
+        temp_file.persist(path).map_err(io::Error::from)?;
+        Ok(())
+    }
+
+    /// Hidden files and files specified in `.gitignore` are skipped, and
+    /// symbolic links are not followed.
+    fn files_under(&self, path: &Path) -> Vec<io::Result<PathBuf>> {
+        if let Err(err) = path.metadata() {
+            return vec![Err(err)];
+        }
+        walk::files_under(path, true, false)
+    }
+
+    fn files_under_without_git(&self, path: &Path) -> Vec<io::Result<PathBuf>> {
+        if let Err(err) = path.metadata() {
+            return vec![Err(err)];
+        }
+        walk::files_under(path, false, false)
+    }
+
+    fn files_under_following_links(
+        &self,
+        path: &Path,
+        gitignore_without_git: bool,
+    ) -> Vec<io::Result<PathBuf>> {
+        if let Err(err) = path.metadata() {
+            return vec![Err(err)];
+        }
+        walk::files_under(path, !gitignore_without_git, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_real_write_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("script.sh");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        RealFileSystem.write(&path, b"new").unwrap();
+
+        assert_eq!(
+            (
+                fs::read_to_string(&path).unwrap(),
+                fs::metadata(&path).unwrap().permissions().mode() & 0o777
+            ),
+            ("new".to_string(), 0o755)
+        );
+    }
+
+    #[test]
+    fn test_real_files_under_nonexistent_path() {
+        let errors: Vec<io::ErrorKind> = RealFileSystem
+            .files_under(Path::new("/nonexistent/path"))
+            .into_iter()
+            .map(|result| result.unwrap_err().kind())
+            .collect();
+        assert_eq!(errors, vec![io::ErrorKind::NotFound]);
+    }
+}
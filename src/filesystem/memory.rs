@@ -0,0 +1,146 @@
+use super::FileSystem;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// A file system held entirely in memory, for embedding basefmt in hosts
+/// that keep their own buffers and for tests that shouldn't touch the disk.
+///
+/// Paths are compared exactly as given, so build the tree with the same kind
+/// of paths (e.g. absolute ones) that are later looked up. Directories exist
+/// implicitly as prefixes of the files they contain.
+///
+/// # Examples
+///
+/// ```
+/// use basefmt::filesystem::{FileSystem, MemoryFileSystem};
+/// use std::path::Path;
+///
+/// let fs = MemoryFileSystem::new().with_file("/project/a.txt", "hello  ");
+/// fs.write(Path::new("/project/a.txt"), b"hello\n").unwrap();
+/// assert_eq!(fs.read(Path::new("/project/a.txt")).unwrap(), b"hello\n");
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryFileSystem {
+    files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemoryFileSystem {
+    /// Creates an empty file system.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file, replacing any previous file at `path`.
+    pub fn with_file(self, path: impl Into<PathBuf>, contents: impl Into<Vec<u8>>) -> Self {
+        self.lock().insert(path.into(), contents.into());
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<PathBuf, Vec<u8>>> {
+        // The map is never left half-updated, so a panic elsewhere doesn't
+        // invalidate it
+        self.files.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.lock()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.lock().insert(path.to_path_buf(), contents.to_vec());
+        Ok(())
+    }
+
+    /// Hidden files (those with a path component below `path` starting with
+    /// `.`) are skipped, as on disk. Ignore files are not applied.
+    fn files_under(&self, path: &Path) -> Vec<io::Result<PathBuf>> {
+        let files = self.lock();
+        let mut under = files
+            .keys()
+            .filter(|file| file.starts_with(path))
+            .peekable();
+        if under.peek().is_none() {
+            return vec![Err(not_found(path))];
+        }
+        under
+            .filter(|file| {
+                file.strip_prefix(path)
+                    .is_ok_and(|relative| !relative.components().any(is_hidden))
+            })
+            .cloned()
+            .map(Ok)
+            .collect()
+    }
+}
+
+fn is_hidden(component: Component<'_>) -> bool {
+    matches!(component, Component::Normal(name) if name.to_string_lossy().starts_with('.'))
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{}: no such file in memory", path.display()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn tree() -> MemoryFileSystem {
+        MemoryFileSystem::new()
+            .with_file("/p/a.txt", "a")
+            .with_file("/p/.editorconfig", "")
+            .with_file("/p/.git/config", "")
+            .with_file("/p/src/b.rs", "b")
+            .with_file("/other/c.txt", "c")
+    }
+
+    #[rstest]
+    #[case::directory("/p", vec!["/p/a.txt", "/p/src/b.rs"])]
+    #[case::file("/p/src/b.rs", vec!["/p/src/b.rs"])]
+    #[case::hidden_root("/p/.git", vec!["/p/.git/config"])]
+    fn test_files_under(#[case] path: &str, #[case] expected: Vec<&str>) {
+        let files: Vec<PathBuf> = tree()
+            .files_under(Path::new(path))
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            files,
+            expected.into_iter().map(PathBuf::from).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_files_under_nonexistent_path() {
+        let errors: Vec<io::ErrorKind> = tree()
+            .files_under(Path::new("/missing"))
+            .into_iter()
+            .map(|result| result.unwrap_err().kind())
+            .collect();
+        assert_eq!(errors, vec![io::ErrorKind::NotFound]);
+    }
+
+    #[test]
+    fn test_write_then_read() {
+        let fs = tree();
+        fs.write(Path::new("/p/new.txt"), b"new").unwrap();
+        assert_eq!(
+            (
+                fs.read(Path::new("/p/new.txt")).unwrap(),
+                fs.read(Path::new("/p/missing.txt")).unwrap_err().kind()
+            ),
+            (b"new".to_vec(), io::ErrorKind::NotFound)
+        );
+    }
+}
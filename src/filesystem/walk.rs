@@ -0,0 +1,229 @@
+use super::AlreadyWalked;
+use ignore::{DirEntry, WalkBuilder};
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Identifies a directory however it is reached: by device and inode number
+/// on Unix, and by canonical path elsewhere.
+#[cfg(unix)]
+type DirId = (u64, u64);
+#[cfg(not(unix))]
+type DirId = PathBuf;
+
+/// The directories walked so far, and the ones reached again and left out.
+#[derive(Default)]
+struct Visited {
+    ids: HashSet<DirId>,
+    revisited: Vec<PathBuf>,
+}
+
+type SharedVisited = Arc<Mutex<Visited>>;
+
+/// Lists the files under `path`, skipping hidden and ignored ones. Unless
+/// `require_git` is set, `.gitignore` files apply outside git repositories
+/// too.
+///
+/// With `follow_links`, symbolic links to directories are followed too, and
+/// every directory is walked once, so that a link to a parent directory (or
+/// a cycle of links) can't make the walk loop or list files several times.
+/// Directories reached again are left out, with an [`AlreadyWalked`] error
+/// in their place.
+pub(super) fn files_under(
+    path: &Path,
+    require_git: bool,
+    follow_links: bool,
+) -> Vec<io::Result<PathBuf>> {
+    let mut files = Vec::new();
+    if !follow_links {
+        walk(path, require_git, None, &mut files);
+        return files;
+    }
+    let visited = SharedVisited::default();
+    walk(path, require_git, Some(&visited), &mut files);
+    let revisited = std::mem::take(
+        &mut visited
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .revisited,
+    );
+    files.extend(
+        revisited
+            .into_iter()
+            .map(|dir| Err(io::Error::other(AlreadyWalked(dir)))),
+    );
+    files
+}
+
+/// Walks `root`, following the symbolic links to directories if `visited`
+/// is given.
+fn walk(
+    root: &Path,
+    require_git: bool,
+    visited: Option<&SharedVisited>,
+    files: &mut Vec<io::Result<PathBuf>>,
+) {
+    let mut builder = WalkBuilder::new(root);
+    builder.require_git(require_git);
+    if let Some(visited) = visited {
+        if root.is_dir() && !first_visit(visited, root) {
+            return;
+        }
+        let filter_visited = Arc::clone(visited);
+        builder.filter_entry(move |entry| {
+            !is_dir(entry) || first_visit(&filter_visited, entry.path())
+        });
+    }
+    for result in builder.build() {
+        match result {
+            Ok(entry) if entry.file_type().is_some_and(|ft| ft.is_file()) => {
+                files.push(Ok(entry.into_path()));
+            }
+            Ok(entry)
+                if visited.is_some()
+                    && entry.depth() > 0
+                    && entry.path_is_symlink()
+                    && entry.path().is_dir() =>
+            {
+                walk(entry.path(), require_git, visited, files);
+            }
+            Ok(_) => {}
+            Err(err) => files.push(Err(io::Error::other(err))),
+        }
+    }
+}
+
+fn is_dir(entry: &DirEntry) -> bool {
+    entry.file_type().is_some_and(|ft| ft.is_dir())
+}
+
+/// Records that the directory at `path` is walked, or returns `false` and
+/// records that it was reached again if it already was.
+fn first_visit(visited: &SharedVisited, path: &Path) -> bool {
+    let Ok(id) = dir_id(path) else {
+        return true;
+    };
+    let mut visited = visited.lock().unwrap_or_else(PoisonError::into_inner);
+    let first = visited.ids.insert(id);
+    if !first {
+        visited.revisited.push(path.to_path_buf());
+    }
+    first
+}
+
+#[cfg(unix)]
+fn dir_id(path: &Path) -> io::Result<DirId> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = std::fs::metadata(path)?;
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_id(path: &Path) -> io::Result<DirId> {
+    crate::canonical::canonicalize(path)
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::fs;
+    use std::os::unix::fs as unix_fs;
+    use tempfile::TempDir;
+
+    /// The canonical paths of the files under `path` and of the directories
+    /// reached again, each sorted.
+    fn canonical_walk(path: &Path, follow_links: bool) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let (mut files, mut revisited) = (Vec::new(), Vec::new());
+        for result in files_under(path, true, follow_links) {
+            match result {
+                Ok(file) => files.push(file.canonicalize().unwrap()),
+                Err(err) => revisited.push(
+                    err.downcast::<AlreadyWalked>()
+                        .unwrap()
+                        .0
+                        .canonicalize()
+                        .unwrap(),
+                ),
+            }
+        }
+        files.sort();
+        revisited.sort();
+        (files, revisited)
+    }
+
+    #[rstest]
+    #[case::not_by_default(false, &["root/src/main.txt"])]
+    #[case::follow_links(true, &["root/src/main.txt", "shared/lib.txt"])]
+    fn test_links_to_directories(#[case] follow_links: bool, #[case] expected: &[&str]) {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir_all(base.join("root/src")).unwrap();
+        fs::create_dir(base.join("shared")).unwrap();
+        fs::write(base.join("root/src/main.txt"), "main").unwrap();
+        fs::write(base.join("shared/lib.txt"), "lib").unwrap();
+        unix_fs::symlink(base.join("shared"), base.join("root/src/shared")).unwrap();
+
+        assert_eq!(
+            canonical_walk(&base.join("root"), follow_links),
+            (
+                expected.iter().map(|path| base.join(path)).collect(),
+                vec![]
+            )
+        );
+    }
+
+    #[test]
+    fn test_walks_each_directory_once_in_link_cycle() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::create_dir_all(root.join("b")).unwrap();
+        fs::write(root.join("a/a.txt"), "a").unwrap();
+        fs::write(root.join("b/b.txt"), "b").unwrap();
+        unix_fs::symlink(root.join("b"), root.join("a/to_b")).unwrap();
+        unix_fs::symlink(root.join("a"), root.join("b/to_a")).unwrap();
+        unix_fs::symlink(&root, root.join("a/to_root")).unwrap();
+
+        assert_eq!(
+            canonical_walk(&root, true),
+            (
+                vec![root.join("a/a.txt"), root.join("b/b.txt")],
+                vec![root.clone(), root.join("a"), root.join("b")]
+            )
+        );
+    }
+
+    #[test]
+    fn test_applies_gitignore_outside_git_unless_required() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join(".gitignore"), "ignored.txt\n").unwrap();
+        fs::write(temp_dir.path().join("ignored.txt"), "ignored").unwrap();
+        fs::write(temp_dir.path().join("kept.txt"), "kept").unwrap();
+        let names = |require_git| {
+            let mut names: Vec<_> = files_under(temp_dir.path(), require_git, false)
+                .into_iter()
+                .map(|file| {
+                    file.unwrap()
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(
+            (names(true), names(false)),
+            (
+                vec!["ignored.txt".to_string(), "kept.txt".to_string()],
+                vec!["kept.txt".to_string()]
+            )
+        );
+    }
+}
@@ -1,13 +1,45 @@
-use ignore::Walk;
+use crate::error::BasefmtError;
+use crate::filesystem::{self, AlreadyWalked, FileSystem, RealFileSystem};
+use glob::PathGlob;
+use std::collections::HashMap;
 use std::io;
 use std::path::Path;
 use std::path::PathBuf;
 
+mod glob;
+mod omission;
+
+pub use omission::{Omission, omission_for};
+
+/// Options for finding files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FindOptions {
+    /// Also find files in nested git repositories, such as submodules,
+    /// below the given directories. These usually hold third-party code, so
+    /// they are skipped by default.
+    pub include_submodules: bool,
+    /// Apply `.gitignore` files even outside git repositories, e.g. in an
+    /// exported tarball or a template that hasn't been committed yet (see
+    /// [`FileSystem::files_under_without_git`]).
+    pub gitignore_without_git: bool,
+    /// Also search the directories symbolic links point to, each once (see
+    /// [`FileSystem::files_under_following_links`]). Links are left alone by
+    /// default, since they can point anywhere, such as outside the project.
+    pub follow_links: bool,
+}
+
 /// Finds all files in the specified paths, respecting .gitignore patterns.
 ///
 /// Recursively searches through directories and returns a list of all files found.
 /// Hidden files and files specified in .gitignore are automatically excluded
-/// by the `ignore` crate.
+/// by the `ignore` crate, and so are files in submodules (see
+/// [`FindOptions::include_submodules`]).
+///
+/// A path that doesn't exist but has glob metacharacters, such as
+/// `docs/**/*.md`, is expanded to the files that match it below the
+/// directory before its first pattern component, which is searched like
+/// any other directory; `*` and `?` don't match `/`, while `**` matches any
+/// number of directories.
 ///
 /// # Arguments
 ///
@@ -15,8 +47,9 @@ use std::path::PathBuf;
 ///
 /// # Returns
 ///
-/// Returns `Ok(Vec<PathBuf>)` containing all files found, or an error if:
-/// - Any path cannot be accessed or read
+/// Returns `Ok(Vec<PathBuf>)` containing all files found, or a
+/// [`BasefmtError::Traversal`] listing each path for which:
+/// - The path cannot be accessed or read
 /// - Errors occurred during directory traversal
 ///
 /// # Examples
@@ -28,49 +61,127 @@ use std::path::PathBuf;
 /// let files = find_files(&[Path::new("src")]).unwrap();
 /// println!("Found {} files", files.len());
 /// ```
-pub fn find_files(paths: &[impl AsRef<Path>]) -> io::Result<Vec<PathBuf>> {
+pub fn find_files(paths: &[impl AsRef<Path>]) -> Result<Vec<PathBuf>, BasefmtError> {
+    find_files_in(&RealFileSystem, paths)
+}
+
+/// Finds all files in the specified paths of `fs`, as
+/// [`FileSystem::files_under`] lists them.
+///
+/// Errors are reported as in [`find_files`].
+pub fn find_files_in(
+    fs: &dyn FileSystem,
+    paths: &[impl AsRef<Path>],
+) -> Result<Vec<PathBuf>, BasefmtError> {
+    find_files_with(fs, paths, &FindOptions::default())
+}
+
+/// Finds all files in the specified paths of `fs` like [`find_files_in`],
+/// with the given options.
+pub fn find_files_with(
+    fs: &dyn FileSystem,
+    paths: &[impl AsRef<Path>],
+    options: &FindOptions,
+) -> Result<Vec<PathBuf>, BasefmtError> {
+    find_files_and_revisits(fs, paths, options).map(|(files, _)| files)
+}
+
+/// Finds all files like [`find_files_with`], and also returns the
+/// directories left out since symbolic links led to them again, for the
+/// caller to warn about.
+pub(crate) fn find_files_and_revisits(
+    fs: &dyn FileSystem,
+    paths: &[impl AsRef<Path>],
+    options: &FindOptions,
+) -> Result<(Vec<PathBuf>, Vec<AlreadyWalked>), BasefmtError> {
     let mut files: Vec<PathBuf> = Vec::new();
-    let mut error_paths: Vec<String> = Vec::new();
+    let mut revisited = Vec::new();
+    let mut failures = Vec::new();
+    let mut repository_roots = HashMap::new();
+    let files_under = |path: &Path| {
+        if options.follow_links {
+            fs.files_under_following_links(path, options.gitignore_without_git)
+        } else if options.gitignore_without_git {
+            fs.files_under_without_git(path)
+        } else {
+            fs.files_under(path)
+        }
+    };
 
     for path in paths {
         let path = path.as_ref();
-
-        match path.metadata() {
-            Ok(_) => {
-                for result in Walk::new(path) {
-                    match result {
-                        Ok(entry) => {
-                            if entry.file_type().is_some_and(|ft| ft.is_file()) {
-                                files.push(entry.into_path());
-                            }
-                        }
-                        Err(err) => {
-                            eprintln!("{}: {}", path.display(), err);
-                            error_paths.push(path.display().to_string());
-                        }
+        let mut results = files_under(path);
+        let glob = match results.as_slice() {
+            [Err(err)] if err.kind() == io::ErrorKind::NotFound => PathGlob::parse(path),
+            _ => None,
+        };
+        let root = if let Some(glob) = &glob {
+            results = files_under(glob.root());
+            glob.root()
+        } else {
+            path
+        };
+        let (found, failed) = (files.len(), failures.len());
+
+        for result in results {
+            match result {
+                Ok(file) if glob.as_ref().is_some_and(|glob| !glob.matches(&file)) => {}
+                Ok(file)
+                    if !options.include_submodules
+                        && in_submodule(fs, root, &file, &mut repository_roots) => {}
+                Ok(file) => files.push(match &glob {
+                    Some(glob) => glob.expanded(file),
+                    None => file,
+                }),
+                Err(err) => match err.downcast::<AlreadyWalked>() {
+                    Ok(walked) => revisited.push(walked),
+                    Err(err) => {
+                        eprintln!("{}: {}", path.display(), err);
+                        failures.push((path.to_path_buf(), err));
                     }
-                }
-            }
-            Err(err) => {
-                eprintln!("{}: {}", path.display(), err);
-                error_paths.push(path.display().to_string());
+                },
             }
         }
+        if glob.is_some() && files.len() == found && failures.len() == failed {
+            let err = io::Error::new(io::ErrorKind::NotFound, "no files match the pattern");
+            eprintln!("{}: {}", path.display(), err);
+            failures.push((path.to_path_buf(), err));
+        }
     }
 
-    if !error_paths.is_empty() {
-        Err(io::Error::other(format!(
-            "errors occurred while processing files: {}",
-            error_paths.join(", ")
-        )))
+    if !failures.is_empty() {
+        Err(BasefmtError::Traversal { failures })
     } else {
-        Ok(files)
+        Ok((files, revisited))
     }
 }
 
+/// Whether `file`, found under `root`, is in a git repository nested below
+/// `root`, caching whether each directory is the root of one.
+///
+/// Only directories below `root` count, so that naming a submodule (or a
+/// directory in one) formats it.
+fn in_submodule(
+    fs: &dyn FileSystem,
+    root: &Path,
+    file: &Path,
+    repository_roots: &mut HashMap<PathBuf, bool>,
+) -> bool {
+    file.ancestors()
+        .skip(1)
+        .take_while(|dir| *dir != root && dir.starts_with(root))
+        .any(|dir| {
+            *repository_roots
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| filesystem::has_git_entry(fs, dir))
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::filesystem::MemoryFileSystem;
+    use rstest::rstest;
     use std::fs;
     use tempfile::TempDir;
 
@@ -272,4 +383,153 @@ mod tests {
         // Should find files inside the symlinked directory
         assert_eq!(result.len(), 1);
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_find_files_reports_revisited_directories_apart() {
+        use std::os::unix::fs as unix_fs;
+
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join("a")).unwrap();
+        fs::write(temp_dir.path().join("a/a.txt"), "a").unwrap();
+        unix_fs::symlink(temp_dir.path(), temp_dir.path().join("a/loop")).unwrap();
+
+        let (files, revisited) = find_files_and_revisits(
+            &RealFileSystem,
+            &[temp_dir.path()],
+            &FindOptions {
+                follow_links: true,
+                ..FindOptions::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            (
+                files,
+                revisited
+                    .into_iter()
+                    .map(|walked| walked.0)
+                    .collect::<Vec<_>>()
+            ),
+            (
+                vec![temp_dir.path().join("a/a.txt")],
+                vec![temp_dir.path().join("a/loop")]
+            )
+        );
+    }
+
+    #[test]
+    fn test_find_files_in_memory() {
+        let fs = MemoryFileSystem::new()
+            .with_file("/p/a.txt", "a")
+            .with_file("/p/src/b.rs", "b")
+            .with_file("/q/c.txt", "c");
+
+        let files = find_files_in(&fs, &["/p", "/q/c.txt"]).unwrap();
+
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from("/p/a.txt"),
+                PathBuf::from("/p/src/b.rs"),
+                PathBuf::from("/q/c.txt")
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::recursive("/p/**/*.md", Ok(vec!["/p/a.md", "/p/docs/b.md"]))]
+    #[case::one_directory("/p/docs/*.md", Ok(vec!["/p/docs/b.md"]))]
+    #[case::no_match(
+        "/p/**/*.rs",
+        Err("/p/**/*.rs: no files match the pattern".to_string())
+    )]
+    fn test_find_files_with_glob(
+        #[case] pattern: &str,
+        #[case] expected: Result<Vec<&str>, String>,
+    ) {
+        let fs = MemoryFileSystem::new()
+            .with_file("/p/a.md", "a")
+            .with_file("/p/a.txt", "a")
+            .with_file("/p/docs/b.md", "b")
+            .with_file("/p/.hidden/c.md", "c");
+
+        let files = find_files_in(&fs, &[pattern]);
+
+        let failures = |err| match err {
+            BasefmtError::Traversal { failures } => failures
+                .iter()
+                .map(|(path, err)| format!("{}: {err}", path.display()))
+                .collect(),
+            err => err.to_string(),
+        };
+        assert_eq!(
+            files.map_err(failures),
+            expected.map(|files| files.into_iter().map(PathBuf::from).collect())
+        );
+    }
+
+    #[rstest]
+    #[case::skips_submodules(&["/p"], false, vec!["/p/a.txt"])]
+    #[case::includes_submodules(
+        &["/p"],
+        true,
+        vec!["/p/a.txt", "/p/vendor/lib/b.txt", "/p/vendor/lib/src/c.txt"]
+    )]
+    #[case::named_submodule(
+        &["/p/vendor/lib"],
+        false,
+        vec!["/p/vendor/lib/b.txt", "/p/vendor/lib/src/c.txt"]
+    )]
+    #[case::named_directory_in_submodule(&["/p/vendor/lib/src"], false, vec!["/p/vendor/lib/src/c.txt"])]
+    fn test_find_files_with_submodules(
+        #[case] paths: &[&str],
+        #[case] include_submodules: bool,
+        #[case] expected: Vec<&str>,
+    ) {
+        let fs = MemoryFileSystem::new()
+            .with_file("/p/.git", "gitdir: /elsewhere")
+            .with_file("/p/a.txt", "a")
+            .with_file("/p/vendor/lib/.git", "gitdir: ../../.git/modules/lib")
+            .with_file("/p/vendor/lib/b.txt", "b")
+            .with_file("/p/vendor/lib/src/c.txt", "c");
+
+        let files = find_files_with(
+            &fs,
+            paths,
+            &FindOptions {
+                include_submodules,
+                ..FindOptions::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            files,
+            expected.into_iter().map(PathBuf::from).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_find_files_skips_nested_repository() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("a.txt");
+        fs::write(&file, "a").unwrap();
+        fs::create_dir_all(temp_dir.path().join("vendor/.git")).unwrap();
+        fs::write(temp_dir.path().join("vendor/b.txt"), "b").unwrap();
+
+        assert_eq!(find_files(&[temp_dir.path()]).unwrap(), vec![file]);
+    }
+
+    #[test]
+    fn test_find_files_in_memory_nonexistent_path() {
+        let fs = MemoryFileSystem::new().with_file("/p/a.txt", "a");
+        assert_eq!(
+            find_files_in(&fs, &["/p", "/missing"])
+                .unwrap_err()
+                .to_string(),
+            "errors occurred while processing files: /missing"
+        );
+    }
 }
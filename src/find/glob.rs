@@ -0,0 +1,138 @@
+use globset::{GlobBuilder, GlobMatcher};
+use std::path::{Path, PathBuf};
+
+/// A path given as a glob pattern, such as `docs/**/*.md`, for shells that
+/// don't expand patterns themselves (e.g. on Windows).
+///
+/// The pattern is split into the directory before its first component with
+/// a glob metacharacter, which is walked like any other directory, and the
+/// rest, which the files found there are matched against.
+pub(super) struct PathGlob {
+    /// The directory to walk
+    root: PathBuf,
+    /// Whether the pattern has no directory of its own, so that `root` is
+    /// `.` and is left out of the files found
+    implicit_root: bool,
+    matcher: GlobMatcher,
+}
+
+impl PathGlob {
+    /// Parses `path` as a glob pattern, or returns `None` if it has no glob
+    /// metacharacter or isn't a valid pattern.
+    ///
+    /// `*` and `?` don't match `/`, while `**` matches any number of
+    /// directories, as in `.gitignore`.
+    pub(super) fn parse(path: &Path) -> Option<Self> {
+        let mut root = PathBuf::new();
+        let mut pattern = Vec::new();
+        for component in path.components() {
+            let text = component.as_os_str().to_str()?;
+            if pattern.is_empty() && !text.contains(['*', '?', '[', '{']) {
+                root.push(component);
+            } else {
+                pattern.push(text);
+            }
+        }
+        if pattern.is_empty() {
+            return None;
+        }
+        let matcher = GlobBuilder::new(&pattern.join("/"))
+            .literal_separator(true)
+            .build()
+            .ok()?
+            .compile_matcher();
+        let implicit_root = root.as_os_str().is_empty();
+        if implicit_root {
+            root.push(".");
+        }
+        Some(Self {
+            root,
+            implicit_root,
+            matcher,
+        })
+    }
+
+    /// Returns the directory to walk.
+    pub(super) fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Whether `file`, found by walking [`PathGlob::root`], matches the
+    /// pattern.
+    pub(super) fn matches(&self, file: &Path) -> bool {
+        file.strip_prefix(&self.root)
+            .is_ok_and(|relative| self.matcher.is_match(relative))
+    }
+
+    /// Returns `file` as if the pattern had been expanded by a shell, i.e.
+    /// without a leading `./` the pattern didn't have.
+    pub(super) fn expanded(&self, file: PathBuf) -> PathBuf {
+        match file.strip_prefix(&self.root) {
+            Ok(relative) if self.implicit_root => relative.to_path_buf(),
+            _ => file,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::recursive(
+        "**/*.md",
+        Some("."),
+        &["./a.md", "./docs/a.md", "./a.txt"],
+        vec![true, true, false]
+    )]
+    #[case::single_directory(
+        "docs/*.md",
+        Some("docs"),
+        &["docs/a.md", "docs/sub/a.md"],
+        vec![true, false]
+    )]
+    #[case::pattern_in_directory(
+        "*/a.md",
+        Some("."),
+        &["./docs/a.md", "./a.md", "./x/y/a.md"],
+        vec![true, false, false]
+    )]
+    #[case::alternatives(
+        "src/*.{rs,toml}",
+        Some("src"),
+        &["src/a.rs", "src/b.toml", "src/c.md"],
+        vec![true, true, false]
+    )]
+    #[case::literal("docs/a.md", None, &["docs/a.md"], vec![false])]
+    #[case::invalid("docs/[a.md", None, &["docs/[a.md"], vec![false])]
+    fn test_path_glob(
+        #[case] pattern: &str,
+        #[case] expected_root: Option<&str>,
+        #[case] files: &[&str],
+        #[case] expected_matches: Vec<bool>,
+    ) {
+        let glob = PathGlob::parse(Path::new(pattern));
+        let matches: Vec<bool> = files
+            .iter()
+            .map(|file| {
+                glob.as_ref()
+                    .is_some_and(|glob| glob.matches(Path::new(file)))
+            })
+            .collect();
+
+        assert_eq!(
+            (glob.as_ref().map(|glob| glob.root().to_path_buf()), matches),
+            (expected_root.map(PathBuf::from), expected_matches)
+        );
+    }
+
+    #[rstest]
+    #[case::implicit_root("**/*.md", "./docs/a.md", "docs/a.md")]
+    #[case::explicit_root("./**/*.md", "./docs/a.md", "./docs/a.md")]
+    #[case::directory("docs/*.md", "docs/a.md", "docs/a.md")]
+    fn test_path_glob_expanded(#[case] pattern: &str, #[case] file: &str, #[case] expected: &str) {
+        let glob = PathGlob::parse(Path::new(pattern)).unwrap();
+        assert_eq!(glob.expanded(PathBuf::from(file)), PathBuf::from(expected));
+    }
+}
@@ -0,0 +1,322 @@
+use super::FindOptions;
+use crate::filesystem::{self, RealFileSystem};
+use ignore::Match;
+use ignore::gitignore::{self, Gitignore};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Why searching a directory leaves a file out (see [`omission_for`]).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Omission {
+    /// The file, or the directory at `path` it is in, has a name starting
+    /// with a dot
+    Hidden { path: PathBuf },
+    /// The `pattern` on `line` of the ignore file at `file`, such as a
+    /// `.gitignore`, matches the file or the directory at `path` it is in
+    Ignored {
+        path: PathBuf,
+        file: PathBuf,
+        line: Option<usize>,
+        pattern: String,
+    },
+    /// The file is a symbolic link to a file, which searches never follow
+    Symlink,
+    /// The file is in the nested git repository at `root`, such as a
+    /// submodule
+    Submodule { root: PathBuf },
+}
+
+impl fmt::Display for Omission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Omission::Hidden { path } => write!(f, "{} is hidden", path.display()),
+            Omission::Ignored {
+                path,
+                file,
+                line,
+                pattern,
+            } => {
+                write!(
+                    f,
+                    "{} is ignored by '{pattern}' in {}",
+                    path.display(),
+                    file.display()
+                )?;
+                match line {
+                    Some(line) => write!(f, ":{line}"),
+                    None => Ok(()),
+                }
+            }
+            Omission::Symlink => write!(f, "it is a symbolic link to a file"),
+            Omission::Submodule { root } => {
+                write!(f, "it is in the nested git repository {}", root.display())
+            }
+        }
+    }
+}
+
+impl Omission {
+    /// Returns the omission with each of its paths passed through `show`.
+    pub(crate) fn map_paths(self, show: impl Fn(&Path) -> PathBuf) -> Self {
+        match self {
+            Omission::Hidden { path } => Omission::Hidden { path: show(&path) },
+            Omission::Ignored {
+                path,
+                file,
+                line,
+                pattern,
+            } => Omission::Ignored {
+                path: show(&path),
+                file: show(&file),
+                line,
+                pattern,
+            },
+            Omission::Symlink => Omission::Symlink,
+            Omission::Submodule { root } => Omission::Submodule { root: show(&root) },
+        }
+    }
+}
+
+/// The ignore files of a directory.
+struct IgnoreFiles {
+    dir: PathBuf,
+    /// Whether the directory is the root of a repository, past which no
+    /// `.gitignore` applies
+    has_git: bool,
+    ignore: Gitignore,
+    gitignore: Gitignore,
+    exclude: Gitignore,
+}
+
+impl IgnoreFiles {
+    fn read(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            has_git: dir.join(".git").exists() || dir.join(".jj").exists(),
+            ignore: Gitignore::new(dir.join(".ignore")).0,
+            gitignore: Gitignore::new(dir.join(".gitignore")).0,
+            exclude: Gitignore::new(dir.join(".git/info/exclude")).0,
+        }
+    }
+}
+
+/// Returns why searching the directory `root` with `options` would leave
+/// out the file at `path` below it, as [`find_files_with`](super::find_files_with)
+/// does on the local file system, or `None` if it would be found.
+///
+/// Both paths must be absolute with their symbolic links resolved, except
+/// for the file itself. Like the search, this reads `.ignore` and
+/// `.gitignore` files, `.git/info/exclude`, and the global gitignore file
+/// of git, and an ignore file in a deeper directory takes precedence.
+pub fn omission_for(root: &Path, path: &Path, options: &FindOptions) -> Option<Omission> {
+    if !path.starts_with(root) || path == root {
+        return None;
+    }
+    // The ancestors of the file, nearest first
+    let ancestors: Vec<IgnoreFiles> = path.ancestors().skip(1).map(IgnoreFiles::read).collect();
+    let global = gitignore::gitconfig_excludes_path()
+        .map(|file| (Gitignore::global().0, file))
+        .filter(|(global, _)| !global.is_empty());
+    let entries: Vec<&Path> = path
+        .ancestors()
+        .take_while(|entry| *entry != root)
+        .collect();
+    for (depth, entry) in entries.iter().enumerate().rev() {
+        let parents = &ancestors[depth..];
+        match matched(entry, entry != &path, parents, global.as_ref(), options) {
+            Match::Ignore(omission) => return Some(omission),
+            Match::Whitelist(_) => {}
+            Match::None if is_hidden(entry) => {
+                return Some(Omission::Hidden {
+                    path: entry.to_path_buf(),
+                });
+            }
+            Match::None => {}
+        }
+    }
+
+    if path.is_symlink() {
+        return Some(Omission::Symlink);
+    }
+    if !options.include_submodules {
+        let submodule = entries
+            .iter()
+            .skip(1)
+            .rev()
+            .find(|dir| filesystem::has_git_entry(&RealFileSystem, dir));
+        if let Some(dir) = submodule {
+            return Some(Omission::Submodule {
+                root: dir.to_path_buf(),
+            });
+        }
+    }
+    None
+}
+
+/// Matches `entry` against the ignore files of `parents`, the directories
+/// it is in, nearest first, in the order of precedence of the search.
+fn matched(
+    entry: &Path,
+    is_dir: bool,
+    parents: &[IgnoreFiles],
+    global: Option<&(Gitignore, PathBuf)>,
+    options: &FindOptions,
+) -> Match<Omission> {
+    let require_git = !options.gitignore_without_git;
+    let check = |ignore: &Gitignore, file: PathBuf| {
+        ignore.matched(entry, is_dir).map(|glob| Omission::Ignored {
+            path: entry.to_path_buf(),
+            line: line_of(&file, glob.original()),
+            file,
+            pattern: glob.original().to_string(),
+        })
+    };
+    // `.gitignore` files only apply in a repository, up to its root
+    let repository = if !require_git {
+        parents
+    } else if let Some(root) = parents.iter().position(|parent| parent.has_git) {
+        &parents[..=root]
+    } else {
+        &[]
+    };
+    let global = global.filter(|_| !repository.is_empty());
+
+    parents
+        .iter()
+        .map(|parent| check(&parent.ignore, parent.dir.join(".ignore")))
+        .chain(
+            repository
+                .iter()
+                .map(|parent| check(&parent.gitignore, parent.dir.join(".gitignore"))),
+        )
+        .chain(
+            repository
+                .iter()
+                .map(|parent| check(&parent.exclude, parent.dir.join(".git/info/exclude"))),
+        )
+        .chain(global.map(|(global, file)| check(global, file.clone())))
+        .find(|found| !found.is_none())
+        .unwrap_or(Match::None)
+}
+
+/// Returns the number of the last line of the ignore file at `path` that
+/// is `pattern`, as the pattern that matches is the last one.
+fn line_of(path: &Path, pattern: &str) -> Option<usize> {
+    let content = fs::read_to_string(path).ok()?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| *line == pattern || line.trim_end() == pattern)
+        .last()
+        .map(|(index, _)| index + 1)
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.as_encoded_bytes().starts_with(b"."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use rstest::rstest;
+    use tempfile::TempDir;
+
+    #[rstest]
+    #[case::found("a.txt", FindOptions::default(), None)]
+    #[case::hidden(
+        ".env",
+        FindOptions::default(),
+        Some(Omission::Hidden { path: PathBuf::from(".env") })
+    )]
+    #[case::whitelisted_hidden_file(
+        ".keep",
+        FindOptions { gitignore_without_git: true, ..FindOptions::default() },
+        None
+    )]
+    #[case::gitignore_outside_repository("b.log", FindOptions::default(), None)]
+    #[case::gitignore_without_git(
+        "b.log",
+        FindOptions { gitignore_without_git: true, ..FindOptions::default() },
+        Some(Omission::Ignored {
+            path: PathBuf::from("b.log"),
+            file: PathBuf::from(".gitignore"),
+            line: Some(1),
+            pattern: "*.log".to_string(),
+        })
+    )]
+    #[case::ignore_file_over_gitignore(
+        "repo/c.log",
+        FindOptions::default(),
+        Some(Omission::Ignored {
+            path: PathBuf::from("repo/c.log"),
+            file: PathBuf::from("repo/.ignore"),
+            line: Some(1),
+            pattern: "c.*".to_string(),
+        })
+    )]
+    #[case::submodule(
+        "repo/sub/a.txt",
+        FindOptions::default(),
+        Some(Omission::Submodule { root: PathBuf::from("repo/sub") })
+    )]
+    #[case::include_submodules(
+        "repo/sub/a.txt",
+        FindOptions { include_submodules: true, ..FindOptions::default() },
+        None
+    )]
+    fn test_omission_for(
+        #[case] path: &str,
+        #[case] options: FindOptions,
+        #[case] expected: Option<Omission>,
+    ) {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::write(
+            root.join(".gitignore"),
+            indoc! {"
+                *.log
+                !.keep
+            "},
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("repo/.git")).unwrap();
+        fs::write(root.join("repo/.gitignore"), "!c.log\n").unwrap();
+        fs::write(root.join("repo/.ignore"), "c.*\n").unwrap();
+        fs::create_dir_all(root.join("repo/sub")).unwrap();
+        fs::write(root.join("repo/sub/.git"), "gitdir: ../.git/modules/sub\n").unwrap();
+        let search_root = if path.starts_with("repo/") {
+            root.join("repo")
+        } else {
+            root.clone()
+        };
+
+        let omission = omission_for(&search_root, &root.join(path), &options);
+
+        assert_eq!(
+            omission,
+            expected.map(|omission| match omission {
+                Omission::Ignored {
+                    path,
+                    file,
+                    line,
+                    pattern,
+                } => Omission::Ignored {
+                    path: root.join(path),
+                    file: root.join(file),
+                    line,
+                    pattern,
+                },
+                Omission::Hidden { path } => Omission::Hidden {
+                    path: root.join(path),
+                },
+                Omission::Submodule { root: dir } => Omission::Submodule {
+                    root: root.join(dir),
+                },
+                omission => omission,
+            })
+        );
+    }
+}
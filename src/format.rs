@@ -1,50 +1,132 @@
 use crate::editorconfig;
+use crate::filesystem::{FileSystem, RealFileSystem};
 use std::fs;
-use std::io::{self, Read, Write};
-use std::path::Path;
-use tempfile::NamedTempFile;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+mod backup;
+mod change;
+mod content;
+mod encoding;
+mod file_lock;
+mod generated;
+mod line_range;
+mod minified;
+mod mirror;
+mod output_dir;
+mod patch;
+mod quick;
+mod read;
+mod read_only;
+mod replace;
+mod stream;
+mod symlink;
+pub(crate) mod temp;
+mod violation;
+mod xattrs;
+mod yaml;
+
+pub use backup::Backup;
+pub use change::Change;
+pub use line_range::LineRange;
+pub use patch::unified_diff;
+pub use read_only::ReadOnlyPolicy;
+pub use symlink::SymlinkMode;
+pub use violation::Violation;
+
+use content::{FormattedContent, format_content};
+use read::{read_and_format_with_rules, text_to_format};
+use replace::write_formatted_output;
+
+/// Options controlling how formatted content is written back to disk.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Saves the pre-format content of each changed file before replacing it
+    pub backup: Option<Backup>,
+    /// How to write files whose path is a symbolic link
+    pub symlinks: SymlinkMode,
+    /// What to do with read-only files that need formatting
+    pub read_only: ReadOnlyPolicy,
+    /// Whether to fsync the parent directory after each rename, so the new
+    /// directory entry survives a power loss
+    pub durable: bool,
+    /// Write formatted copies into this directory, mirroring each file's
+    /// path, instead of modifying files in place
+    pub output_dir: Option<PathBuf>,
+    /// Only edit lines within these ranges; every line is edited if empty
+    pub lines: Vec<LineRange>,
+    /// Format files larger than this many bytes line by line, holding only
+    /// a few lines in memory instead of the whole file. Files in another
+    /// encoding than UTF-8, files with YAML block scalars preserved, and
+    /// ones being converted to UTF-8 are still read whole, as is every file
+    /// with `output_dir` or `lines` set
+    pub stream_above: Option<u64>,
+    /// Take an advisory lock on each file while replacing it, waiting up to
+    /// this long for another process that holds one; files are replaced
+    /// without locking if unset
+    pub lock_timeout: Option<Duration>,
+    /// Refuse to write any file that is outside this directory once
+    /// symbolic links are resolved, such as one a link in the tree points
+    /// to, reporting it as an error instead
+    pub restrict_to: Option<PathBuf>,
+}
 
 /// Result of a format operation.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum FormatResult {
-    /// File was modified
-    Changed,
+    /// File was modified; lists the problems that were fixed
+    Changed(Vec<Violation>),
     /// File was already properly formatted
     Unchanged,
-    /// File was skipped (e.g., binary file)
-    Skipped,
+    /// File was left alone for the given reason
+    Skipped(SkipReason),
+    /// File needs formatting but is read-only and was left untouched; lists
+    /// the problems that remain
+    ReadOnly(Vec<Violation>),
 }
 
 /// Result of a check operation.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum CheckResult {
     /// File is properly formatted
     Formatted,
-    /// File needs formatting
-    NeedsFormatting,
-    /// File was skipped (e.g., binary file)
-    Skipped,
+    /// File needs formatting; lists every problem that formatting would fix
+    NeedsFormatting(Vec<Violation>),
+    /// File was left alone for the given reason
+    Skipped(SkipReason),
 }
 
-fn read_and_format_with_rules(
-    path: &Path,
-    rules: &editorconfig::FormatRules,
-) -> io::Result<Option<(String, String, fs::Metadata)>> {
-    let file = fs::File::open(path)?;
-    let metadata = file.metadata()?;
-
-    let mut content = String::new();
-    let mut reader = io::BufReader::new(file);
-    match reader.read_to_string(&mut content) {
-        Ok(_) => {
-            let formatted = format_content(&content, rules);
-            Ok(Some((content, formatted, metadata)))
-        }
-        Err(err) if err.kind() == io::ErrorKind::InvalidData => {
-            // Skip binary files silently
-            Ok(None)
+/// Why a file was left alone without being formatted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SkipReason {
+    /// The file isn't text: neither UTF-8 nor UTF-16 with a byte order mark
+    Binary,
+    /// The file looks like a minified asset, such as bundled JavaScript
+    Minified,
+    /// A marker says the file is generated
+    Generated,
+    /// An exclude pattern matches the file
+    Excluded,
+    /// Every rule is off for the file, so formatting can't change it
+    NoRules,
+    /// The file is larger than the size limit of the run
+    TooLarge,
+}
+
+impl SkipReason {
+    /// Returns a stable `snake_case` identifier for the reason, suitable for
+    /// machine-readable output.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SkipReason::Binary => "binary",
+            SkipReason::Minified => "minified",
+            SkipReason::Generated => "generated",
+            SkipReason::Excluded => "excluded",
+            SkipReason::NoRules => "no_rules",
+            SkipReason::TooLarge => "too_large",
         }
-        Err(err) => Err(err),
     }
 }
 
@@ -56,10 +138,13 @@ fn read_and_format_with_rules(
 /// - Ensuring exactly one final newline
 ///
 /// Binary files (files containing invalid UTF-8) are silently skipped and
-/// treated as if they don't need formatting.
+/// treated as if they don't need formatting. UTF-16 files with a byte order
+/// mark are formatted and written back as UTF-16 in the same byte order.
 ///
 /// The file is only modified if formatting changes are needed. File permissions
-/// and other metadata are preserved through atomic write-and-rename operation.
+/// and extended attributes (including POSIX ACLs and macOS Finder metadata)
+/// are carried over to the replacement written by the atomic
+/// write-and-rename operation.
 ///
 /// # Arguments
 ///
@@ -68,9 +153,11 @@ fn read_and_format_with_rules(
 /// # Returns
 ///
 /// Returns:
-/// - `Ok(FormatResult::Changed)` if the file was modified
+/// - `Ok(FormatResult::Changed(_))` if the file was modified
 /// - `Ok(FormatResult::Unchanged)` if no changes were needed
-/// - `Ok(FormatResult::Skipped)` if the file is binary
+/// - `Ok(FormatResult::Skipped(_))` if the file is binary, minified, or has
+///   a generated-code marker
+/// - `Ok(FormatResult::ReadOnly(_))` if the file needs formatting but is read-only
 /// - `Err(...)` if the file cannot be read or written
 ///
 /// # Examples
@@ -80,9 +167,10 @@ fn read_and_format_with_rules(
 /// use std::path::Path;
 ///
 /// match format_file(Path::new("file.txt")).unwrap() {
-///     FormatResult::Changed => println!("File was formatted"),
+///     FormatResult::Changed(violations) => println!("Fixed {} problems", violations.len()),
 ///     FormatResult::Unchanged => println!("File was already formatted"),
-///     FormatResult::Skipped => println!("File was skipped"),
+///     FormatResult::Skipped(reason) => println!("File is {}", reason.as_str()),
+///     FormatResult::ReadOnly(_) => println!("File is read-only"),
 /// }
 /// ```
 pub fn format_file(path: &Path) -> io::Result<FormatResult> {
@@ -95,42 +183,65 @@ pub fn format_file_with_rules(
     path: &Path,
     rules: &editorconfig::FormatRules,
 ) -> io::Result<FormatResult> {
-    if let Some((content, formatted, metadata)) = read_and_format_with_rules(path, rules)? {
-        let changed = write_formatted_output(path, content, formatted, metadata)?;
-        if changed {
-            Ok(FormatResult::Changed)
-        } else {
-            Ok(FormatResult::Unchanged)
-        }
-    } else {
-        Ok(FormatResult::Skipped)
-    }
+    format_file_with_options(path, rules, &WriteOptions::default())
 }
 
-fn write_formatted_output(
+/// Formats a file in place using precomputed formatting rules and the given
+/// write options.
+///
+/// With [`WriteOptions::output_dir`] set, the source file is left untouched
+/// and its formatted content is written to the mirror location instead, even
+/// if it is already formatted, so the output tree holds every text file.
+/// Backups, symlink handling, and the read-only policy only concern in-place
+/// writes and are ignored.
+pub fn format_file_with_options(
     path: &Path,
-    original: String,
-    formatted: String,
-    metadata: fs::Metadata,
-) -> io::Result<bool> {
-    let changed = original != formatted;
-    if changed {
-        // Write to a temporary file first, then rename to preserve metadata
-        let parent_dir = path.parent().unwrap_or_else(|| Path::new("."));
-        let mut temp_file = NamedTempFile::new_in(parent_dir)?;
-        temp_file.write_all(formatted.as_bytes())?;
-        temp_file.as_file().sync_all()?;
-
-        // Set permissions before persisting
-        temp_file
-            .as_file()
-            .set_permissions(metadata.permissions())?;
-
-        // Atomically replace the original file
-        temp_file.persist(path)?;
+    rules: &editorconfig::FormatRules,
+    options: &WriteOptions,
+) -> io::Result<FormatResult> {
+    if let Some(limit) = options.stream_above
+        && options.output_dir.is_none()
+        && options.lines.is_empty()
+        && stream::can_stream(rules)
+        && fs::metadata(path)?.len() > limit
+        && let Some(result) = stream::format_file(path, rules, options)?
+    {
+        return Ok(result);
     }
+    let (text, formatted, metadata) = match read_and_format_with_rules(path, rules, &options.lines)?
+    {
+        Ok(read) => read,
+        Err(reason) => return Ok(FormatResult::Skipped(reason)),
+    };
+    let FormattedContent {
+        content: formatted,
+        violations,
+    } = formatted;
 
-    Ok(changed)
+    if let Some(dir) = &options.output_dir {
+        let unchanged = text.is_unchanged(&formatted);
+        output_dir::write(
+            dir,
+            path,
+            &text.target().encode(formatted),
+            metadata.permissions(),
+            options.durable,
+        )?;
+        return Ok(if unchanged {
+            FormatResult::Unchanged
+        } else {
+            FormatResult::Changed(violations)
+        });
+    }
+
+    if text.is_unchanged(&formatted) {
+        Ok(FormatResult::Unchanged)
+    } else if metadata.permissions().readonly() && options.read_only == ReadOnlyPolicy::Skip {
+        Ok(FormatResult::ReadOnly(violations))
+    } else {
+        write_formatted_output(path, &text.target().encode(formatted), metadata, options)?;
+        Ok(FormatResult::Changed(violations))
+    }
 }
 
 /// Checks if a file is properly formatted without modifying it.
@@ -145,8 +256,9 @@ fn write_formatted_output(
 ///
 /// Returns:
 /// - `Ok(CheckResult::Formatted)` if the file is properly formatted
-/// - `Ok(CheckResult::NeedsFormatting)` if formatting is needed
-/// - `Ok(CheckResult::Skipped)` if the file is binary
+/// - `Ok(CheckResult::NeedsFormatting(violations))` if formatting is needed
+/// - `Ok(CheckResult::Skipped(_))` if the file is binary, minified, or has a
+///   generated-code marker
 /// - `Err(...)` if the file cannot be read
 ///
 /// # Examples
@@ -157,8 +269,12 @@ fn write_formatted_output(
 ///
 /// match check_file(Path::new("file.txt")).unwrap() {
 ///     CheckResult::Formatted => println!("File is properly formatted"),
-///     CheckResult::NeedsFormatting => println!("File needs formatting"),
-///     CheckResult::Skipped => println!("File was skipped"),
+///     CheckResult::NeedsFormatting(violations) => {
+///         for violation in violations {
+///             println!("{violation}");
+///         }
+///     }
+///     CheckResult::Skipped(reason) => println!("File is {}", reason.as_str()),
 /// }
 /// ```
 pub fn check_file(path: &Path) -> io::Result<CheckResult> {
@@ -171,71 +287,194 @@ pub fn check_file_with_rules(
     path: &Path,
     rules: &editorconfig::FormatRules,
 ) -> io::Result<CheckResult> {
-    if let Some((content, formatted, _metadata)) = read_and_format_with_rules(path, rules)? {
-        if content == formatted {
-            Ok(CheckResult::Formatted)
-        } else {
-            Ok(CheckResult::NeedsFormatting)
-        }
-    } else {
-        Ok(CheckResult::Skipped)
-    }
+    check_file_in(&RealFileSystem, path, rules)
 }
 
-fn format_content(content: &str, rules: &editorconfig::FormatRules) -> String {
-    // If no rules are enabled, return content as-is
-    if !rules.remove_leading_newlines
-        && !rules.remove_trailing_spaces
-        && !rules.ensure_final_newline
-    {
-        return content.to_string();
+/// Checks a file like [`check_file_with_rules`], also returning the changes
+/// formatting would make as a patch (see [`unified_diff`]) if it needs
+/// formatting.
+pub fn check_file_with_patch(
+    path: &Path,
+    rules: &editorconfig::FormatRules,
+) -> io::Result<(CheckResult, Option<String>)> {
+    let (text, formatted, _) = match read_and_format_with_rules(path, rules, &[])? {
+        Ok(read) => read,
+        Err(reason) => return Ok((CheckResult::Skipped(reason), None)),
+    };
+    if text.is_unchanged(&formatted.content) {
+        return Ok((CheckResult::Formatted, None));
     }
+    // Converting the encoding alone changes no text to show
+    let patch = (text.content != formatted.content)
+        .then(|| unified_diff(path, &text.content, &formatted.content));
+    Ok((CheckResult::NeedsFormatting(formatted.violations), patch))
+}
 
-    // Collect lines, optionally skipping leading empty lines
-    let lines_iter = content.lines();
-    let mut lines: Vec<&str> = if rules.remove_leading_newlines {
-        lines_iter.skip_while(|line| line.is_empty()).collect()
-    } else {
-        lines_iter.collect()
+/// Formats a file read through `fs` in place using already resolved
+/// formatting rules.
+///
+/// The formatted content is handed to [`FileSystem::write`] as a whole, so
+/// the [`WriteOptions`] of [`format_file_with_options`], such as backups and
+/// symlink handling, don't apply. Binary files are reported as
+/// [`FormatResult::Skipped`], as are minified files unless `rules` say
+/// otherwise.
+pub fn format_file_in(
+    fs: &dyn FileSystem,
+    path: &Path,
+    rules: &editorconfig::FormatRules,
+) -> io::Result<FormatResult> {
+    let text = match text_to_format(fs.read(path)?, rules) {
+        Ok(text) => text,
+        Err(reason) => return Ok(FormatResult::Skipped(reason)),
     };
-
-    // Always remove trailing empty lines (to normalize file endings)
-    while lines.last().is_some_and(|line| line.is_empty()) {
-        lines.pop();
+    let formatted = text.format(rules, &[]);
+    if text.is_unchanged(&formatted.content) {
+        return Ok(FormatResult::Unchanged);
     }
+    fs.write(path, &text.target().encode(formatted.content))?;
+    Ok(FormatResult::Changed(formatted.violations))
+}
 
-    if lines.is_empty() {
-        return String::new();
-    }
+/// Checks a file read through `fs` using already resolved formatting rules.
+///
+/// Binary files (files containing invalid UTF-8) are reported as
+/// [`CheckResult::Skipped`], as in [`check_file`], and so are minified files
+/// unless `rules` say otherwise.
+pub fn check_file_in(
+    fs: &dyn FileSystem,
+    path: &Path,
+    rules: &editorconfig::FormatRules,
+) -> io::Result<CheckResult> {
+    Ok(check_content(fs.read(path)?, rules))
+}
 
-    // Build result with capacity hint to avoid reallocations
-    let mut result = String::with_capacity(content.len());
-    for (i, line) in lines.iter().enumerate() {
-        if i > 0 {
-            result.push('\n');
-        }
-        // Optionally trim trailing spaces
-        if rules.remove_trailing_spaces {
-            result.push_str(line.trim_end());
-        } else {
-            result.push_str(line);
-        }
+/// Checks a file like [`check_file_with_rules`], but stops checking it at
+/// the first violation that shows it needs formatting, which saves checking
+/// and keeping the rest of a large file in memory.
+///
+/// Only that violation is returned, so there may be more. Files are skipped
+/// as in [`check_file`]: the rest of the file is still read to tell whether
+/// it is binary.
+pub fn check_file_until_violation(
+    path: &Path,
+    rules: &editorconfig::FormatRules,
+) -> io::Result<CheckResult> {
+    quick::check_until_violation(fs::File::open(path)?, rules)
+}
+
+/// Returns why a file is left alone without being formatted, as in
+/// [`check_file`], if it is: because it is binary, or generated or minified
+/// as `rules` say.
+///
+/// The file isn't formatted, and no more of it is kept in memory than tells
+/// whether it is generated or minified, so this is cheaper than
+/// [`check_file_with_rules`] for files whose rules change nothing (see
+/// [`FormatRules::changes_nothing`](editorconfig::FormatRules::changes_nothing)).
+pub fn skip_reason(
+    path: &Path,
+    rules: &editorconfig::FormatRules,
+) -> io::Result<Option<SkipReason>> {
+    quick::skip_reason(fs::File::open(path)?, rules)
+}
+
+/// Checks `content`, the bytes of a file, using already resolved formatting
+/// rules.
+fn check_content(content: Vec<u8>, rules: &editorconfig::FormatRules) -> CheckResult {
+    let text = match text_to_format(content, rules) {
+        Ok(text) => text,
+        Err(reason) => return CheckResult::Skipped(reason),
+    };
+    let formatted = text.format(rules, &[]);
+    if text.is_unchanged(&formatted.content) {
+        CheckResult::Formatted
+    } else {
+        CheckResult::NeedsFormatting(formatted.violations)
     }
+}
 
-    // Optionally add final newline
-    if rules.ensure_final_newline {
-        result.push('\n');
+/// Content produced by formatting, together with whether it differs from the
+/// input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Formatted<T> {
+    /// The formatted content
+    pub content: T,
+    /// Whether formatting changed the content
+    pub changed: bool,
+}
+
+/// Formats an in-memory string with the given rules.
+///
+/// This applies exactly the same rules as [`format_file_with_rules`] without
+/// touching the filesystem, so it can be used by tools that hold the content
+/// themselves (e.g. editor buffers).
+///
+/// # Examples
+///
+/// ```
+/// use basefmt::editorconfig::FormatRules;
+/// use basefmt::format::{Formatted, format_str};
+///
+/// let formatted = format_str("hello  ", &FormatRules::default());
+/// assert_eq!(
+///     formatted,
+///     Formatted {
+///         content: "hello\n".to_string(),
+///         changed: true,
+///     }
+/// );
+/// ```
+pub fn format_str(content: &str, rules: &editorconfig::FormatRules) -> Formatted<String> {
+    let formatted = format_content(content, rules).content;
+    let changed = formatted != content;
+    Formatted {
+        content: formatted,
+        changed,
     }
+}
 
-    result
+/// Formats an in-memory byte slice with the given rules.
+///
+/// Returns `None` if the bytes are not valid UTF-8, mirroring how binary files
+/// are skipped by [`format_file`]. Like files, UTF-16 with a byte order mark
+/// is formatted and returned in the same encoding, unless `rules` say to
+/// convert text to UTF-8.
+///
+/// # Examples
+///
+/// ```
+/// use basefmt::editorconfig::FormatRules;
+/// use basefmt::format::{Formatted, format_bytes};
+///
+/// assert_eq!(
+///     format_bytes(b"hello\n", &FormatRules::default()),
+///     Some(Formatted {
+///         content: b"hello\n".to_vec(),
+///         changed: false,
+///     })
+/// );
+/// assert_eq!(format_bytes(&[0xC3, 0x28], &FormatRules::default()), None);
+/// ```
+pub fn format_bytes(
+    content: &[u8],
+    rules: &editorconfig::FormatRules,
+) -> Option<Formatted<Vec<u8>>> {
+    let (content, encoding) = encoding::to_utf8(content.to_vec())?;
+    let text = read::decode(content, encoding, rules)?;
+    let formatted = text.format(rules, &[]).content;
+    Some(Formatted {
+        changed: !text.is_unchanged(&formatted),
+        content: text.target().encode(formatted),
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::filesystem::MemoryFileSystem;
     use indoc::indoc;
     use rstest::rstest;
     use std::fs;
+    use std::path::PathBuf;
     use tempfile::TempDir;
 
     // Helper function to create a .editorconfig file with all rules enabled
@@ -255,40 +494,69 @@ mod tests {
         .unwrap();
     }
 
-    // The `input`/`expected` literals below are short enough that indoc! would
-    // add lines without improving readability, so the `prefer-indoc` lint is
-    // suppressed line-by-line below. The `removes_trailing_spaces` case
-    // additionally can't switch to indoc!: its trailing spaces are the point
-    // of the case, and indoc! would store them as literal trailing whitespace
-    // on real source lines, which basefmt's own trim-trailing-whitespace
-    // formatting (self-applied via lefthook) would strip on the next format
-    // pass.
+    /// Violations found in the `"\n\ntest content  \n\n"` fixture most tests
+    /// below write.
+    fn dirty_violations() -> Vec<Violation> {
+        vec![
+            Violation::LeadingBlankLines { count: 2 },
+            Violation::TrailingWhitespace {
+                line: 3,
+                column: 13,
+            },
+            Violation::TrailingBlankLines { line: 4, count: 1 },
+        ]
+    }
+
     #[rstest]
-    #[case::removes_leading_newlines(
-        "\n\nfirst line\nsecond line\n", // ast-grep-ignore: prefer-indoc
-        "first line\nsecond line\n" // ast-grep-ignore: prefer-indoc
+    #[case::changed(
+        "\n\nfirst line\n", // ast-grep-ignore: prefer-indoc
+        Formatted {
+            content: "first line\n".to_string(),
+            changed: true,
+        }
     )]
-    #[case::removes_trailing_spaces(
-        "line with trailing spaces   \nanother line with spaces  \n", // ast-grep-ignore: prefer-indoc
-        "line with trailing spaces\nanother line with spaces\n" // ast-grep-ignore: prefer-indoc
+    #[case::unchanged(
+        "first line\n",
+        Formatted {
+            content: "first line\n".to_string(),
+            changed: false,
+        }
     )]
-    #[case::adds_final_newline(
-        "first line\nsecond line",
-        "first line\nsecond line\n" // ast-grep-ignore: prefer-indoc
+    fn test_format_str(#[case] input: &str, #[case] expected: Formatted<String>) {
+        assert_eq!(
+            format_str(input, &editorconfig::FormatRules::default()),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::changed(
+        b"first line",
+        Some(Formatted {
+            content: b"first line\n".to_vec(),
+            changed: true,
+        })
     )]
-    #[case::removes_multiple_final_newlines(
-        "first line\nsecond line\n\n\n", // ast-grep-ignore: prefer-indoc
-        "first line\nsecond line\n" // ast-grep-ignore: prefer-indoc
+    #[case::unchanged(
+        b"first line\n",
+        Some(Formatted {
+            content: b"first line\n".to_vec(),
+            changed: false,
+        })
     )]
-    #[case::empty_file("", "")]
-    #[case::only_newlines("\n\n\n", "")] // ast-grep-ignore: prefer-indoc
-    fn test_format_content(#[case] input: &str, #[case] expected: &str) {
-        let rules = editorconfig::FormatRules {
-            ensure_final_newline: true,
-            remove_trailing_spaces: true,
-            remove_leading_newlines: true,
-        };
-        assert_eq!(format_content(input, &rules), expected);
+    #[case::utf16(
+        b"\xFE\xFF\0a\0 \0\n",
+        Some(Formatted {
+            content: b"\xFE\xFF\0a\0\n".to_vec(),
+            changed: true,
+        })
+    )]
+    #[case::invalid_utf8(&[0xFF, 0xFE, 0xFD], None)]
+    fn test_format_bytes(#[case] input: &[u8], #[case] expected: Option<Formatted<Vec<u8>>>) {
+        assert_eq!(
+            format_bytes(input, &editorconfig::FormatRules::default()),
+            expected
+        );
     }
 
     #[test]
@@ -297,17 +565,128 @@ mod tests {
         create_default_editorconfig(&temp_dir);
         let file_path = temp_dir.path().join("test.txt");
         // Trailing spaces are the point of this fixture; see the comment on
-        // the `test_format_content` cases above for why it isn't converted
+        // the `test_format_content` cases in `content.rs` for why it isn't converted
         // to indoc!.
         fs::write(&file_path, "\n\ntest content  \n\n").unwrap(); // ast-grep-ignore: prefer-indoc
 
         let result = format_file(&file_path).unwrap();
 
-        assert_eq!(result, FormatResult::Changed);
+        assert_eq!(result, FormatResult::Changed(dirty_violations()));
         let content = fs::read_to_string(&file_path).unwrap();
         assert_eq!(content, "test content\n");
     }
 
+    #[rstest]
+    #[case::suffix(Backup::Suffix(".orig".to_string()))]
+    #[case::directory(Backup::Directory(PathBuf::from("backups")))]
+    fn test_format_file_writes_backup(#[case] backup: Backup) {
+        let temp_dir = TempDir::new().unwrap();
+        create_default_editorconfig(&temp_dir);
+        let file_path = temp_dir.path().join("test.txt");
+        // Trailing spaces are the point of this fixture; see the comment on
+        // the `test_format_content` cases in `content.rs` for why it isn't converted
+        // to indoc!.
+        fs::write(&file_path, "\n\ntest content  \n\n").unwrap(); // ast-grep-ignore: prefer-indoc
+        let backup = match backup {
+            Backup::Directory(dir) => Backup::Directory(temp_dir.path().join(dir)),
+            backup => backup,
+        };
+        let backup_path = backup.path_for(&file_path);
+        let options = WriteOptions {
+            backup: Some(backup),
+            ..WriteOptions::default()
+        };
+
+        let rules = editorconfig::get_format_rules(&file_path);
+        let result = format_file_with_options(&file_path, &rules, &options).unwrap();
+
+        assert_eq!(
+            (
+                result,
+                fs::read_to_string(&file_path).unwrap(),
+                fs::read_to_string(&backup_path).unwrap(),
+            ),
+            (
+                FormatResult::Changed(dirty_violations()),
+                "test content\n".to_string(),
+                "\n\ntest content  \n\n".to_string(), // ast-grep-ignore: prefer-indoc
+            )
+        );
+    }
+
+    #[test]
+    fn test_format_file_durable() {
+        let temp_dir = TempDir::new().unwrap();
+        create_default_editorconfig(&temp_dir);
+        let file_path = temp_dir.path().join("test.txt");
+        // Trailing spaces are the point of this fixture; see the comment on
+        // the `test_format_content` cases in `content.rs` for why it isn't converted
+        // to indoc!.
+        fs::write(&file_path, "\n\ntest content  \n\n").unwrap(); // ast-grep-ignore: prefer-indoc
+        let options = WriteOptions {
+            durable: true,
+            ..WriteOptions::default()
+        };
+
+        let rules = editorconfig::get_format_rules(&file_path);
+        let result = format_file_with_options(&file_path, &rules, &options).unwrap();
+
+        assert_eq!(
+            (result, fs::read_to_string(&file_path).unwrap()),
+            (
+                FormatResult::Changed(dirty_violations()),
+                "test content\n".to_string()
+            )
+        );
+    }
+
+    #[rstest]
+    #[case::dirty("\n\ntest content  \n\n", FormatResult::Changed(dirty_violations()))] // ast-grep-ignore: prefer-indoc
+    #[case::clean("test content\n", FormatResult::Unchanged)]
+    fn test_format_file_output_dir(#[case] content: &str, #[case] expected: FormatResult) {
+        let temp_dir = TempDir::new().unwrap();
+        create_default_editorconfig(&temp_dir);
+        let file_path = temp_dir.path().join("test.txt");
+        fs::write(&file_path, content).unwrap();
+        let output_dir = temp_dir.path().join("out");
+        let options = WriteOptions {
+            output_dir: Some(output_dir.clone()),
+            ..WriteOptions::default()
+        };
+
+        let rules = editorconfig::get_format_rules(&file_path);
+        let result = format_file_with_options(&file_path, &rules, &options).unwrap();
+
+        assert_eq!(
+            (
+                result,
+                fs::read_to_string(&file_path).unwrap(),
+                fs::read_to_string(mirror::mirror_path(&output_dir, &file_path)).unwrap(),
+            ),
+            (expected, content.to_string(), "test content\n".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_file_skips_backup_when_unchanged() {
+        let temp_dir = TempDir::new().unwrap();
+        create_default_editorconfig(&temp_dir);
+        let file_path = temp_dir.path().join("test.txt");
+        fs::write(&file_path, "test content\n").unwrap();
+        let options = WriteOptions {
+            backup: Some(Backup::Suffix(".orig".to_string())),
+            ..WriteOptions::default()
+        };
+
+        let rules = editorconfig::get_format_rules(&file_path);
+        let result = format_file_with_options(&file_path, &rules, &options).unwrap();
+
+        assert_eq!(
+            (result, temp_dir.path().join("test.txt.orig").exists()),
+            (FormatResult::Unchanged, false)
+        );
+    }
+
     #[test]
     fn test_format_file_no_changes() {
         let temp_dir = TempDir::new().unwrap();
@@ -340,13 +719,13 @@ mod tests {
         create_default_editorconfig(&temp_dir);
         let file_path = temp_dir.path().join("test.txt");
         // Trailing spaces are the point of this fixture; see the comment on
-        // the `test_format_content` cases above for why it isn't converted
+        // the `test_format_content` cases in `content.rs` for why it isn't converted
         // to indoc!.
         fs::write(&file_path, "\n\ntest content  \n\n").unwrap(); // ast-grep-ignore: prefer-indoc
 
         let result = check_file(&file_path).unwrap();
 
-        assert_eq!(result, CheckResult::NeedsFormatting);
+        assert_eq!(result, CheckResult::NeedsFormatting(dirty_violations()));
     }
 
     #[test]
@@ -358,7 +737,7 @@ mod tests {
         create_default_editorconfig(&temp_dir);
         let file_path = temp_dir.path().join("test.txt");
         // Trailing spaces are the point of this fixture; see the comment on
-        // the `test_format_content` cases above for why it isn't converted
+        // the `test_format_content` cases in `content.rs` for why it isn't converted
         // to indoc!.
         fs::write(&file_path, "\n\ntest content  \n\n").unwrap(); // ast-grep-ignore: prefer-indoc
 
@@ -376,6 +755,110 @@ mod tests {
         assert_eq!(original_mode, new_mode);
     }
 
+    #[rstest]
+    #[case::follow(SymlinkMode::Follow, (true, "test content\n", "test content\n"))]
+    #[case::replace(
+        SymlinkMode::Replace,
+        (false, "test content\n", "\n\ntest content  \n\n") // ast-grep-ignore: prefer-indoc
+    )]
+    #[cfg(unix)]
+    fn test_format_file_symlink_mode(
+        #[case] symlinks: SymlinkMode,
+        #[case] expected: (bool, &str, &str),
+    ) {
+        use std::os::unix::fs as unix_fs;
+
+        let temp_dir = TempDir::new().unwrap();
+        create_default_editorconfig(&temp_dir);
+        let target_path = temp_dir.path().join("target.txt");
+        let link_path = temp_dir.path().join("link.txt");
+        // Trailing spaces are the point of this fixture; see the comment on
+        // the `test_format_content` cases in `content.rs` for why it isn't converted
+        // to indoc!.
+        fs::write(&target_path, "\n\ntest content  \n\n").unwrap(); // ast-grep-ignore: prefer-indoc
+        unix_fs::symlink(&target_path, &link_path).unwrap();
+        let options = WriteOptions {
+            symlinks,
+            ..WriteOptions::default()
+        };
+
+        let rules = editorconfig::get_format_rules(&link_path);
+        format_file_with_options(&link_path, &rules, &options).unwrap();
+
+        assert_eq!(
+            (
+                fs::symlink_metadata(&link_path)
+                    .unwrap()
+                    .file_type()
+                    .is_symlink(),
+                fs::read_to_string(&link_path).unwrap().as_str(),
+                fs::read_to_string(&target_path).unwrap().as_str(),
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_format_file_preserves_extended_attributes() {
+        let temp_dir = TempDir::new().unwrap();
+        create_default_editorconfig(&temp_dir);
+        let file_path = temp_dir.path().join("test.txt");
+        // Trailing spaces are the point of this fixture; see the comment on
+        // the `test_format_content` cases in `content.rs` for why it isn't converted
+        // to indoc!.
+        fs::write(&file_path, "\n\ntest content  \n\n").unwrap(); // ast-grep-ignore: prefer-indoc
+        if xattr::set(&file_path, "user.basefmt.test", b"value").is_err() {
+            // The temp directory's filesystem doesn't support user attributes
+            return;
+        }
+
+        let result = format_file(&file_path).unwrap();
+
+        assert_eq!(
+            (result, xattr::get(&file_path, "user.basefmt.test").unwrap()),
+            (
+                FormatResult::Changed(dirty_violations()),
+                Some(b"value".to_vec())
+            )
+        );
+    }
+
+    #[rstest]
+    #[case::skip(ReadOnlyPolicy::Skip, (FormatResult::ReadOnly(dirty_violations()), "\n\ntest content  \n\n", true))] // ast-grep-ignore: prefer-indoc
+    #[case::force(ReadOnlyPolicy::Force, (FormatResult::Changed(dirty_violations()), "test content\n", true))]
+    fn test_format_file_read_only_policy(
+        #[case] read_only: ReadOnlyPolicy,
+        #[case] expected: (FormatResult, &str, bool),
+    ) {
+        let temp_dir = TempDir::new().unwrap();
+        create_default_editorconfig(&temp_dir);
+        let file_path = temp_dir.path().join("test.txt");
+        // Trailing spaces are the point of this fixture; see the comment on
+        // the `test_format_content` cases in `content.rs` for why it isn't converted
+        // to indoc!.
+        fs::write(&file_path, "\n\ntest content  \n\n").unwrap(); // ast-grep-ignore: prefer-indoc
+        let mut permissions = fs::metadata(&file_path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&file_path, permissions).unwrap();
+        let options = WriteOptions {
+            read_only,
+            ..WriteOptions::default()
+        };
+
+        let rules = editorconfig::get_format_rules(&file_path);
+        let result = format_file_with_options(&file_path, &rules, &options).unwrap();
+
+        assert_eq!(
+            (
+                result,
+                fs::read_to_string(&file_path).unwrap().as_str(),
+                fs::metadata(&file_path).unwrap().permissions().readonly(),
+            ),
+            expected
+        );
+    }
+
     #[test]
     fn test_format_file_skips_binary() {
         let temp_dir = TempDir::new().unwrap();
@@ -386,7 +869,7 @@ mod tests {
         let result = format_file(&file_path).unwrap();
 
         // Binary files should be skipped silently
-        assert_eq!(result, FormatResult::Skipped);
+        assert_eq!(result, FormatResult::Skipped(SkipReason::Binary));
 
         // Verify file was not modified
         let content = fs::read(&file_path).unwrap();
@@ -403,6 +886,157 @@ mod tests {
         let result = check_file(&file_path).unwrap();
 
         // Binary files should be skipped silently
-        assert_eq!(result, CheckResult::Skipped);
+        assert_eq!(result, CheckResult::Skipped(SkipReason::Binary));
+    }
+
+    #[rstest]
+    #[case::skipped_by_default(None, CheckResult::Skipped(SkipReason::Minified))]
+    #[case::enabled(Some(true), CheckResult::Skipped(SkipReason::Minified))]
+    #[case::disabled(Some(false), CheckResult::NeedsFormatting(vec![Violation::MissingFinalNewline]))]
+    fn test_check_file_skips_minified(
+        #[case] skip_minified: Option<bool>,
+        #[case] expected: CheckResult,
+    ) {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("bundle.min.js");
+        let content = "var a=1;".repeat(minified::MIN_SIZE / 4);
+        fs::write(&file_path, &content).unwrap();
+        let rules = editorconfig::FormatRules {
+            skip_minified,
+            ..editorconfig::FormatRules::default()
+        };
+
+        let fs = MemoryFileSystem::new().with_file(&file_path, content);
+        assert_eq!(
+            (
+                check_file_with_rules(&file_path, &rules).unwrap(),
+                check_file_in(&fs, &file_path, &rules).unwrap(),
+            ),
+            (expected.clone(), expected)
+        );
+    }
+
+    #[rstest]
+    #[case::marked(
+        Some(2),
+        FormatResult::Skipped(SkipReason::Generated),
+        "#!/bin/sh\n# @generated\necho  " // ast-grep-ignore: prefer-indoc
+    )]
+    #[case::marker_too_far_down(Some(1), FormatResult::Changed(vec![Violation::MissingFinalNewline]), "#!/bin/sh\n# @generated\necho")] // ast-grep-ignore: prefer-indoc
+    #[case::not_searched(None, FormatResult::Unchanged, "#!/bin/sh\n# @generated\necho\n")] // ast-grep-ignore: prefer-indoc
+    fn test_format_file_skips_generated(
+        #[case] generated_marker_lines: Option<usize>,
+        #[case] expected: FormatResult,
+        #[case] content: &str,
+    ) {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("generated.sh");
+        fs::write(&file_path, content).unwrap();
+        let rules = editorconfig::FormatRules {
+            generated_marker_lines,
+            ..editorconfig::FormatRules::default()
+        };
+
+        let fs = MemoryFileSystem::new().with_file(&file_path, content);
+        assert_eq!(
+            (
+                format_file_with_rules(&file_path, &rules).unwrap(),
+                format_file_in(&fs, &file_path, &rules).unwrap(),
+            ),
+            (expected.clone(), expected)
+        );
+    }
+
+    #[rstest]
+    #[case::dirty(
+        b"\n\ntest content  \n\n".as_slice(), // ast-grep-ignore: prefer-indoc
+        FormatResult::Changed(dirty_violations()),
+        b"test content\n".as_slice()
+    )]
+    #[case::clean(b"test content\n".as_slice(), FormatResult::Unchanged, b"test content\n".as_slice())]
+    #[case::binary(
+        b"\xC3\x28".as_slice(),
+        FormatResult::Skipped(SkipReason::Binary),
+        b"\xC3\x28".as_slice()
+    )]
+    #[case::utf16(
+        b"\xFF\xFEa\0 \0".as_slice(),
+        FormatResult::Changed(vec![
+            Violation::TrailingWhitespace { line: 1, column: 3 },
+            Violation::MissingFinalNewline,
+        ]),
+        b"\xFF\xFEa\0\n\0".as_slice()
+    )]
+    fn test_format_file_in_memory(
+        #[case] content: &[u8],
+        #[case] expected: FormatResult,
+        #[case] expected_content: &[u8],
+    ) {
+        let path = Path::new("/virtual/test.txt");
+        let fs = MemoryFileSystem::new().with_file(path, content);
+
+        let result = format_file_in(&fs, path, &editorconfig::FormatRules::default()).unwrap();
+
+        assert_eq!(
+            (result, fs.read(path).unwrap()),
+            (expected, expected_content.to_vec())
+        );
+    }
+
+    #[rstest]
+    #[case::windows_1252(
+        b"caf\xE9  \n".as_slice(),
+        FormatResult::Changed(vec![
+            Violation::NotUtf8 {
+                encoding: "Windows-1252",
+            },
+            Violation::TrailingWhitespace { line: 1, column: 5 },
+        ]),
+        "café\n".as_bytes()
+    )]
+    #[case::utf16_bom_dropped(
+        b"\xFE\xFF\0a\0\n".as_slice(),
+        FormatResult::Changed(vec![Violation::NotUtf8 { encoding: "UTF-16BE" }]),
+        b"a\n".as_slice()
+    )]
+    #[case::utf8_bom_kept(
+        "\u{feff}a\n".as_bytes(),
+        FormatResult::Unchanged,
+        "\u{feff}a\n".as_bytes()
+    )]
+    #[case::binary(
+        b"a\0\xFF".as_slice(),
+        FormatResult::Skipped(SkipReason::Binary),
+        b"a\0\xFF".as_slice()
+    )]
+    fn test_format_file_in_memory_normalizes_encoding(
+        #[case] content: &[u8],
+        #[case] expected: FormatResult,
+        #[case] expected_content: &[u8],
+    ) {
+        let path = Path::new("/virtual/test.txt");
+        let fs = MemoryFileSystem::new().with_file(path, content);
+        let rules = editorconfig::FormatRules {
+            normalize_encoding: Some(editorconfig::Charset::Utf8),
+            ..editorconfig::FormatRules::default()
+        };
+
+        let result = format_file_in(&fs, path, &rules).unwrap();
+
+        assert_eq!(
+            (result, fs.read(path).unwrap()),
+            (expected, expected_content.to_vec())
+        );
+    }
+
+    #[test]
+    fn test_check_file_in_memory() {
+        let path = Path::new("/virtual/test.txt");
+        let fs = MemoryFileSystem::new().with_file(path, "\n\ntest content  \n\n"); // ast-grep-ignore: prefer-indoc
+
+        assert_eq!(
+            check_file_in(&fs, path, &editorconfig::FormatRules::default()).unwrap(),
+            CheckResult::NeedsFormatting(dirty_violations())
+        );
     }
 }
@@ -0,0 +1,70 @@
+use super::mirror;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where to save the pre-format content of a file before it is replaced.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Backup {
+    /// Next to the original file, with the given suffix appended to its name
+    /// (e.g. `notes.txt` → `notes.txt.orig`).
+    Suffix(String),
+    /// Inside the given directory, mirroring the path of the original file.
+    ///
+    /// Root, prefix, `.` and `..` components are dropped, so `./docs/a.md`
+    /// and `/work/docs/a.md` are saved as `<dir>/docs/a.md` and
+    /// `<dir>/work/docs/a.md` respectively.
+    Directory(PathBuf),
+}
+
+impl Backup {
+    /// Returns the path the backup of `path` is written to.
+    pub fn path_for(&self, path: &Path) -> PathBuf {
+        match self {
+            Backup::Suffix(suffix) => {
+                let mut backup = path.as_os_str().to_owned();
+                backup.push(suffix);
+                PathBuf::from(backup)
+            }
+            Backup::Directory(dir) => mirror::mirror_path(dir, path),
+        }
+    }
+
+    /// Copies `path` to its backup location, overwriting any previous backup.
+    pub(crate) fn write(&self, path: &Path) -> io::Result<()> {
+        let backup_path = self.path_for(path);
+        if let Some(parent) = backup_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(path, &backup_path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::suffix_relative(Backup::Suffix(".orig".to_string()), "docs/a.md", "docs/a.md.orig")]
+    #[case::suffix_absolute(Backup::Suffix(".bak".to_string()), "/work/a.md", "/work/a.md.bak")]
+    #[case::directory_relative(
+        Backup::Directory(PathBuf::from("backups")),
+        "./docs/a.md",
+        "backups/docs/a.md"
+    )]
+    #[case::directory_absolute(
+        Backup::Directory(PathBuf::from("/backups")),
+        "/work/docs/a.md",
+        "/backups/work/docs/a.md"
+    )]
+    #[case::directory_parent(
+        Backup::Directory(PathBuf::from("backups")),
+        "../docs/a.md",
+        "backups/docs/a.md"
+    )]
+    fn test_path_for(#[case] backup: Backup, #[case] path: &str, #[case] expected: &str) {
+        assert_eq!(backup.path_for(Path::new(path)), PathBuf::from(expected));
+    }
+}
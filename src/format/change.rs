@@ -0,0 +1,163 @@
+use super::violation::Violation;
+use std::fmt;
+
+/// A summary of one kind of edit formatting made to a file, e.g. "removed
+/// trailing whitespace on 14 lines".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// Blank lines were removed from the start of the file
+    RemovedLeadingBlankLines(usize),
+    /// Trailing whitespace was removed from this many lines
+    TrimmedTrailingWhitespace(usize),
+    /// This many line terminators were normalized to the dominant one
+    NormalizedLineEndings(usize),
+    /// Blank lines beyond the allowed number of consecutive ones were removed
+    RemovedExcessBlankLines(usize),
+    /// Blank lines were removed from the end of the file
+    RemovedTrailingBlankLines(usize),
+    /// A final newline was added
+    AddedFinalNewline,
+    /// The final newline was removed
+    RemovedFinalNewline,
+    /// The file was converted from this encoding to UTF-8
+    ConvertedToUtf8(&'static str),
+}
+
+impl Change {
+    /// Groups the violations fixed in a file into one change per kind, in the
+    /// order each kind first appears.
+    pub fn summarize(violations: &[Violation]) -> Vec<Change> {
+        let mut changes: Vec<Change> = Vec::new();
+        for violation in violations {
+            let change = Change::from(violation);
+            match changes
+                .iter_mut()
+                .find(|existing| existing.kind() == change.kind())
+            {
+                Some(existing) => existing.merge(change),
+                None => changes.push(change),
+            }
+        }
+        changes
+    }
+
+    /// Returns the [`Violation::kind`] of the violations this change fixes.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Change::RemovedLeadingBlankLines(_) => "leading_blank_lines",
+            Change::TrimmedTrailingWhitespace(_) => "trailing_whitespace",
+            Change::NormalizedLineEndings(_) => "inconsistent_line_ending",
+            Change::RemovedExcessBlankLines(_) => "excess_blank_lines",
+            Change::RemovedTrailingBlankLines(_) => "trailing_blank_lines",
+            Change::AddedFinalNewline => "missing_final_newline",
+            Change::RemovedFinalNewline => "unexpected_final_newline",
+            Change::ConvertedToUtf8(_) => "not_utf8",
+        }
+    }
+
+    fn merge(&mut self, other: Change) {
+        match (self, other) {
+            (Change::RemovedLeadingBlankLines(count), Change::RemovedLeadingBlankLines(more))
+            | (Change::TrimmedTrailingWhitespace(count), Change::TrimmedTrailingWhitespace(more))
+            | (Change::NormalizedLineEndings(count), Change::NormalizedLineEndings(more))
+            | (Change::RemovedExcessBlankLines(count), Change::RemovedExcessBlankLines(more))
+            | (Change::RemovedTrailingBlankLines(count), Change::RemovedTrailingBlankLines(more)) =>
+            {
+                *count += more;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl From<&Violation> for Change {
+    fn from(violation: &Violation) -> Self {
+        match violation {
+            Violation::LeadingBlankLines { count } => Change::RemovedLeadingBlankLines(*count),
+            Violation::TrailingWhitespace { .. } => Change::TrimmedTrailingWhitespace(1),
+            Violation::InconsistentLineEnding { .. } => Change::NormalizedLineEndings(1),
+            Violation::ExcessBlankLines { count, .. } => Change::RemovedExcessBlankLines(*count),
+            Violation::TrailingBlankLines { count, .. } => {
+                Change::RemovedTrailingBlankLines(*count)
+            }
+            Violation::MissingFinalNewline => Change::AddedFinalNewline,
+            Violation::UnexpectedFinalNewline => Change::RemovedFinalNewline,
+            Violation::NotUtf8 { encoding } => Change::ConvertedToUtf8(encoding),
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines = |count: usize| if count == 1 { "line" } else { "lines" };
+        match self {
+            Change::RemovedLeadingBlankLines(count) => {
+                write!(f, "removed {count} leading blank {}", lines(*count))
+            }
+            Change::TrimmedTrailingWhitespace(count) => {
+                write!(
+                    f,
+                    "removed trailing whitespace on {count} {}",
+                    lines(*count)
+                )
+            }
+            Change::NormalizedLineEndings(count) => {
+                write!(f, "normalized line endings on {count} {}", lines(*count))
+            }
+            Change::RemovedExcessBlankLines(count) => {
+                write!(f, "removed {count} excess blank {}", lines(*count))
+            }
+            Change::RemovedTrailingBlankLines(count) => {
+                write!(f, "removed {count} trailing blank {}", lines(*count))
+            }
+            Change::AddedFinalNewline => f.write_str("added final newline"),
+            Change::RemovedFinalNewline => f.write_str("removed final newline"),
+            Change::ConvertedToUtf8(encoding) => write!(f, "converted from {encoding} to UTF-8"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn test_summarize() {
+        let violations = [
+            Violation::LeadingBlankLines { count: 2 },
+            Violation::TrailingWhitespace { line: 3, column: 4 },
+            Violation::InconsistentLineEnding { line: 3 },
+            Violation::TrailingWhitespace { line: 5, column: 1 },
+            Violation::MissingFinalNewline,
+        ];
+
+        assert_eq!(
+            Change::summarize(&violations),
+            vec![
+                Change::RemovedLeadingBlankLines(2),
+                Change::TrimmedTrailingWhitespace(2),
+                Change::NormalizedLineEndings(1),
+                Change::AddedFinalNewline,
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::leading_single(Change::RemovedLeadingBlankLines(1), "removed 1 leading blank line")]
+    #[case::trailing_whitespace(
+        Change::TrimmedTrailingWhitespace(14),
+        "removed trailing whitespace on 14 lines"
+    )]
+    #[case::line_endings(Change::NormalizedLineEndings(1), "normalized line endings on 1 line")]
+    #[case::excess_blank_lines(Change::RemovedExcessBlankLines(4), "removed 4 excess blank lines")]
+    #[case::trailing_plural(Change::RemovedTrailingBlankLines(3), "removed 3 trailing blank lines")]
+    #[case::removed_final_newline(Change::RemovedFinalNewline, "removed final newline")]
+    #[case::converted_to_utf8(
+        Change::ConvertedToUtf8("UTF-16LE"),
+        "converted from UTF-16LE to UTF-8"
+    )]
+    fn test_display(#[case] change: Change, #[case] expected: &str) {
+        assert_eq!(change.to_string(), expected);
+    }
+}
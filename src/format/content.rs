@@ -0,0 +1,777 @@
+use super::line_range::LineRange;
+use super::violation::Violation;
+use super::yaml;
+use crate::editorconfig::{FormatRules, LineEnding};
+
+/// Content produced by [`format_content`] along with every violation that
+/// formatting fixed.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct FormattedContent {
+    pub(super) content: String,
+    pub(super) violations: Vec<Violation>,
+}
+
+/// A line of the input and the terminator that ended it (`""` for a final
+/// line without one).
+pub(super) struct Line<'a> {
+    pub(super) number: usize,
+    pub(super) text: &'a str,
+    pub(super) ending: &'a str,
+}
+
+/// Splits `content` into lines ended by `\n`, `\r\n`, or a lone `\r`,
+/// keeping terminators.
+pub(super) fn split_lines(content: &str) -> Vec<Line<'_>> {
+    let mut lines = Vec::new();
+    let mut rest = content;
+    while !rest.is_empty() {
+        let end = rest.find(['\n', '\r']).unwrap_or(rest.len());
+        let (text, tail) = rest.split_at(end);
+        let ending_len = if tail.starts_with("\r\n") {
+            2
+        } else {
+            tail.len().min(1)
+        };
+        let (ending, next) = tail.split_at(ending_len);
+        lines.push(Line {
+            number: lines.len() + 1,
+            text,
+            ending,
+        });
+        rest = next;
+    }
+    lines
+}
+
+pub(super) fn format_content(content: &str, rules: &FormatRules) -> FormattedContent {
+    format_content_in(content, rules, &[])
+}
+
+/// Formats `content` like [`format_content`], but only edits lines within
+/// `ranges` (every line if `ranges` is empty).
+///
+/// Each edit belongs to a single line: dropping a leading or trailing blank
+/// line or one beyond `max_blank_lines`, trimming its whitespace,
+/// normalizing its terminator, or, for the last line, adding or removing the
+/// final newline. Edits on lines outside the ranges are skipped.
+pub(super) fn format_content_in(
+    content: &str,
+    rules: &FormatRules,
+    ranges: &[LineRange],
+) -> FormattedContent {
+    let mut violations = Vec::new();
+    let editable =
+        |line: &Line| ranges.is_empty() || ranges.iter().any(|range| range.contains(line.number));
+
+    // If no rules are enabled, return content as-is, apart from the line
+    // endings if those are configured
+    if !rules.remove_leading_newlines()
+        && !rules.remove_trailing_spaces()
+        && !rules.ensure_final_newline()
+        && rules.max_blank_lines().is_none()
+    {
+        let Some(line_ending) = rules.end_of_line else {
+            return FormattedContent {
+                content: content.to_string(),
+                violations,
+            };
+        };
+        let mut result = String::with_capacity(content.len());
+        for line in split_lines(content) {
+            result.push_str(line.text);
+            if line.ending.is_empty() || !editable(&line) {
+                result.push_str(line.ending);
+                continue;
+            }
+            result.push_str(line_ending.as_str());
+            if line.ending != line_ending.as_str() {
+                violations.push(Violation::InconsistentLineEnding { line: line.number });
+            }
+        }
+        return FormattedContent {
+            content: result,
+            violations,
+        };
+    }
+
+    let lines = split_lines(content);
+    // Block scalar bodies are left alone as part of a YAML value
+    let block_scalar_lines = if rules.preserve_yaml_block_scalars() {
+        yaml::block_scalar_body_lines(lines.iter().map(|line| line.text))
+    } else {
+        Vec::new()
+    };
+    let in_block_scalar = |line: &Line| block_scalar_lines.get(line.number - 1) == Some(&true);
+    let trim =
+        |line: &Line| rules.remove_trailing_spaces() && editable(line) && !in_block_scalar(line);
+    // Whitespace-only lines count as blank if their whitespace is trimmed,
+    // so that a second pass finds nothing left to drop
+    let blank = |line: &&Line| line.text.is_empty() || (trim(line) && is_blank(line.text));
+
+    // Optionally skip leading empty lines
+    let leading = if rules.remove_leading_newlines() {
+        lines.iter().take_while(blank).count()
+    } else {
+        0
+    };
+    // Always remove trailing empty lines (to normalize file endings)
+    let trailing_start = lines.len() - lines[leading..].iter().rev().take_while(blank).count();
+
+    let mut kept = Vec::with_capacity(lines.len());
+    let mut dropped_leading = 0;
+    let mut dropped_trailing: Option<(usize, usize)> = None;
+    // Interior runs of blank lines longer than `max_blank_lines`, reported
+    // before the first kept line after them. Whitespace-only lines after the
+    // last line with content are not between other lines, so they are left
+    // alone unless dropped as trailing blank lines.
+    let last_content = lines
+        .iter()
+        .rposition(|line| !is_blank(line.text))
+        .unwrap_or(0);
+    let mut excess: Vec<(usize, Violation)> = Vec::new();
+    let mut blank_run = 0;
+    for (index, line) in lines.iter().enumerate() {
+        if (index < leading || index >= trailing_start) && editable(line) {
+            if index < leading {
+                dropped_leading += 1;
+            } else {
+                let (_, count) = dropped_trailing.get_or_insert((line.number, 0));
+                *count += 1;
+            }
+            continue;
+        }
+        if !is_blank(line.text) || in_block_scalar(line) {
+            blank_run = 0;
+        } else if let Some(max) = rules.max_blank_lines().filter(|_| index < last_content) {
+            blank_run += 1;
+            if blank_run > max && editable(line) {
+                match excess.last_mut() {
+                    Some((at, Violation::ExcessBlankLines { count, .. })) if *at == kept.len() => {
+                        *count += 1;
+                    }
+                    _ => excess.push((
+                        kept.len(),
+                        Violation::ExcessBlankLines {
+                            line: line.number,
+                            count: 1,
+                        },
+                    )),
+                }
+                continue;
+            }
+        }
+        kept.push(line);
+    }
+    if dropped_leading > 0 {
+        violations.push(Violation::LeadingBlankLines {
+            count: dropped_leading,
+        });
+    }
+    let trailing_blank_lines =
+        dropped_trailing.map(|(line, count)| Violation::TrailingBlankLines { line, count });
+
+    let mut excess = excess.into_iter().peekable();
+    let Some((last, init)) = kept.split_last() else {
+        violations.extend(excess.map(|(_, violation)| violation));
+        violations.extend(trailing_blank_lines);
+        return FormattedContent {
+            content: String::new(),
+            violations,
+        };
+    };
+
+    // Re-join with the configured terminator, or else the file's own so that
+    // a CRLF file doesn't become a mixed-EOL one
+    let line_ending = rules
+        .end_of_line
+        .map_or_else(|| dominant_line_ending(content), LineEnding::as_str);
+
+    // Build result with capacity hint to avoid reallocations
+    let mut result = String::with_capacity(content.len());
+    for (index, line) in init.iter().enumerate() {
+        violations.extend(excess.next_if(|(at, _)| *at == index).map(|(_, v)| v));
+        push_text(&mut result, &mut violations, line, trim(line), rules);
+        if !editable(line) {
+            result.push_str(line.ending);
+            continue;
+        }
+        result.push_str(line_ending);
+        if line.ending != line_ending {
+            violations.push(Violation::InconsistentLineEnding { line: line.number });
+        }
+    }
+    violations.extend(excess.next_if(|(at, _)| *at == init.len()).map(|(_, v)| v));
+    push_text(&mut result, &mut violations, last, trim(last), rules);
+
+    violations.extend(excess.map(|(_, violation)| violation));
+    violations.extend(trailing_blank_lines);
+
+    if !editable(last) {
+        result.push_str(last.ending);
+    } else if rules.ensure_final_newline() {
+        // Optionally add final newline
+        result.push_str(line_ending);
+        if last.ending.is_empty() {
+            violations.push(Violation::MissingFinalNewline);
+        } else if last.ending != line_ending {
+            violations.push(Violation::InconsistentLineEnding { line: last.number });
+        }
+    } else if !last.ending.is_empty() {
+        violations.push(Violation::UnexpectedFinalNewline);
+    }
+
+    FormattedContent {
+        content: result,
+        violations,
+    }
+}
+
+/// Appends the text of `line` to `result`, trimming its trailing whitespace
+/// if `trim` is set.
+fn push_text(
+    result: &mut String,
+    violations: &mut Vec<Violation>,
+    line: &Line,
+    trim: bool,
+    rules: &FormatRules,
+) {
+    let text = if trim {
+        trim_line_end(line.text, rules.preserve_markdown_hard_breaks())
+    } else {
+        line.text
+    };
+    if text.len() < line.text.len() {
+        violations.push(Violation::TrailingWhitespace {
+            line: line.number,
+            column: text.chars().count() + 1,
+        });
+    }
+    result.push_str(text);
+}
+
+/// Returns the line terminator used by most lines in `content`.
+///
+/// Falls back to `\n` for content without any line terminator and when it
+/// is as common as another one, and prefers CRLF over a lone CR.
+fn dominant_line_ending(content: &str) -> &'static str {
+    let crlf = content.matches("\r\n").count();
+    let lf = content.matches('\n').count() - crlf;
+    let cr = content.matches('\r').count() - crlf;
+    most_common_line_ending(crlf, lf, cr)
+}
+
+/// Returns the line terminator [`dominant_line_ending`] picks for content
+/// with `crlf` lines ended by CRLF, `lf` by LF, and `cr` by a lone CR.
+pub(super) fn most_common_line_ending(crlf: usize, lf: usize, cr: usize) -> &'static str {
+    if crlf > lf && crlf >= cr {
+        "\r\n"
+    } else if cr > lf && cr > crlf {
+        "\r"
+    } else {
+        "\n"
+    }
+}
+
+/// Whether trimming removes `c`: any Unicode white space except U+2028 LINE
+/// SEPARATOR and U+2029 PARAGRAPH SEPARATOR, which JSON and JavaScript
+/// strings may contain verbatim.
+fn is_trimmed_whitespace(c: char) -> bool {
+    c.is_whitespace() && !matches!(c, '\u{2028}' | '\u{2029}')
+}
+
+/// Whether `text` is blank: empty or nothing but whitespace that trimming
+/// removes.
+pub(super) fn is_blank(text: &str) -> bool {
+    text.chars().all(is_trimmed_whitespace)
+}
+
+/// Trims trailing whitespace from a line.
+///
+/// In Markdown, exactly two trailing spaces after content mark a hard line
+/// break, so that suffix is kept when `preserve_hard_break` is set.
+pub(super) fn trim_line_end(line: &str, preserve_hard_break: bool) -> &str {
+    let trimmed = line.trim_end_matches(is_trimmed_whitespace);
+    if preserve_hard_break && !trimmed.is_empty() && &line[trimmed.len()..] == "  " {
+        line
+    } else {
+        trimmed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    // The `input`/`expected` literals below are short enough that indoc! would
+    // add lines without improving readability, so the `prefer-indoc` lint is
+    // suppressed line-by-line below. The `removes_trailing_spaces` case
+    // additionally can't switch to indoc!: its trailing spaces are the point
+    // of the case, and indoc! would store them as literal trailing whitespace
+    // on real source lines, which basefmt's own trim-trailing-whitespace
+    // formatting (self-applied via lefthook) would strip on the next format
+    // pass.
+    #[rstest]
+    #[case::removes_leading_newlines(
+        "\n\nfirst line\nsecond line\n", // ast-grep-ignore: prefer-indoc
+        "first line\nsecond line\n" // ast-grep-ignore: prefer-indoc
+    )]
+    #[case::removes_trailing_spaces(
+        "line with trailing spaces   \nanother line with spaces  \n", // ast-grep-ignore: prefer-indoc
+        "line with trailing spaces\nanother line with spaces\n" // ast-grep-ignore: prefer-indoc
+    )]
+    #[case::adds_final_newline(
+        "first line\nsecond line",
+        "first line\nsecond line\n" // ast-grep-ignore: prefer-indoc
+    )]
+    #[case::removes_multiple_final_newlines(
+        "first line\nsecond line\n\n\n", // ast-grep-ignore: prefer-indoc
+        "first line\nsecond line\n" // ast-grep-ignore: prefer-indoc
+    )]
+    #[case::removes_whitespace_only_leading_lines("  \n\t\nfirst line\n", "first line\n")]
+    #[case::removes_whitespace_only_trailing_lines("last line\n \n\t", "last line\n")]
+    #[case::empty_file("", "")]
+    #[case::only_newlines("\n\n\n", "")] // ast-grep-ignore: prefer-indoc
+    fn test_format_content(#[case] input: &str, #[case] expected: &str) {
+        let rules = FormatRules {
+            ensure_final_newline: Some(true),
+            remove_trailing_spaces: Some(true),
+            remove_leading_newlines: Some(true),
+            preserve_markdown_hard_breaks: Some(false),
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
+        };
+        assert_eq!(format_content(input, &rules).content, expected);
+    }
+
+    // Trailing spaces are the point of these cases; see the comment on the
+    // `test_format_content` cases above for why they aren't converted to
+    // indoc!.
+    #[rstest]
+    #[case::keeps_two_space_hard_break(
+        "first line  \nsecond line\n", // ast-grep-ignore: prefer-indoc
+        "first line  \nsecond line\n" // ast-grep-ignore: prefer-indoc
+    )]
+    #[case::trims_single_space("first line \nsecond line\n", "first line\nsecond line\n")] // ast-grep-ignore: prefer-indoc
+    #[case::trims_three_spaces(
+        "first line   \nsecond line\n", // ast-grep-ignore: prefer-indoc
+        "first line\nsecond line\n" // ast-grep-ignore: prefer-indoc
+    )]
+    #[case::trims_tab_suffix("first line\t\t\nsecond line\n", "first line\nsecond line\n")] // ast-grep-ignore: prefer-indoc
+    #[case::trims_whitespace_only_line(
+        "first line\n  \nsecond line\n",
+        "first line\n\nsecond line\n" // ast-grep-ignore: prefer-indoc
+    )]
+    fn test_format_content_preserves_markdown_hard_breaks(
+        #[case] input: &str,
+        #[case] expected: &str,
+    ) {
+        let rules = FormatRules {
+            ensure_final_newline: Some(true),
+            remove_trailing_spaces: Some(true),
+            remove_leading_newlines: Some(true),
+            preserve_markdown_hard_breaks: Some(true),
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
+        };
+        assert_eq!(format_content(input, &rules).content, expected);
+    }
+
+    #[rstest]
+    #[case::crlf_adds_crlf_final_newline(
+        "first line\r\nsecond line",
+        "first line\r\nsecond line\r\n" // ast-grep-ignore: prefer-indoc
+    )]
+    #[case::crlf_removes_multiple_final_newlines(
+        "first line\r\nsecond line\r\n\r\n\r\n", // ast-grep-ignore: prefer-indoc
+        "first line\r\nsecond line\r\n" // ast-grep-ignore: prefer-indoc
+    )]
+    #[case::mostly_crlf_uses_crlf(
+        "first line\r\nsecond line\r\nthird line\nfourth line", // ast-grep-ignore: prefer-indoc
+        "first line\r\nsecond line\r\nthird line\r\nfourth line\r\n" // ast-grep-ignore: prefer-indoc
+    )]
+    #[case::mostly_lf_uses_lf(
+        "first line\nsecond line\nthird line\r\nfourth line", // ast-grep-ignore: prefer-indoc
+        "first line\nsecond line\nthird line\nfourth line\n" // ast-grep-ignore: prefer-indoc
+    )]
+    #[case::tie_uses_lf(
+        "first line\r\nsecond line\nthird line", // ast-grep-ignore: prefer-indoc
+        "first line\nsecond line\nthird line\n" // ast-grep-ignore: prefer-indoc
+    )]
+    #[case::single_line_uses_lf("only line", "only line\n")]
+    #[case::cr_trims_each_line("first line  \rsecond line\t\r\r\r", "first line\rsecond line\r")]
+    #[case::mostly_cr_uses_cr(
+        "first line\rsecond line\rthird line\nfourth line",
+        "first line\rsecond line\rthird line\rfourth line\r"
+    )]
+    #[case::cr_crlf_tie_uses_crlf(
+        "first line\rsecond line\r\nthird line",
+        "first line\r\nsecond line\r\nthird line\r\n" // ast-grep-ignore: prefer-indoc
+    )]
+    fn test_format_content_matches_dominant_line_ending(
+        #[case] input: &str,
+        #[case] expected: &str,
+    ) {
+        let rules = FormatRules {
+            ensure_final_newline: Some(true),
+            remove_trailing_spaces: Some(true),
+            remove_leading_newlines: Some(true),
+            preserve_markdown_hard_breaks: Some(false),
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
+        };
+        assert_eq!(format_content(input, &rules).content, expected);
+    }
+
+    #[rstest]
+    #[case::crlf_to_lf(
+        "first line\r\nsecond line\r\n", // ast-grep-ignore: prefer-indoc
+        LineEnding::Lf,
+        true,
+        "first line\nsecond line\n", // ast-grep-ignore: prefer-indoc
+        vec![
+            Violation::InconsistentLineEnding { line: 1 },
+            Violation::InconsistentLineEnding { line: 2 },
+        ]
+    )]
+    #[case::lf_to_crlf(
+        "first line\nsecond line",
+        LineEnding::Crlf,
+        true,
+        "first line\r\nsecond line\r\n", // ast-grep-ignore: prefer-indoc
+        vec![
+            Violation::InconsistentLineEnding { line: 1 },
+            Violation::MissingFinalNewline,
+        ]
+    )]
+    #[case::only_line_endings(
+        "first line  \r\n\r\n", // ast-grep-ignore: prefer-indoc
+        LineEnding::Lf,
+        false,
+        "first line  \n\n", // ast-grep-ignore: prefer-indoc
+        vec![
+            Violation::InconsistentLineEnding { line: 1 },
+            Violation::InconsistentLineEnding { line: 2 },
+        ]
+    )]
+    #[case::cr_to_lf(
+        "first line\rsecond line\r",
+        LineEnding::Lf,
+        true,
+        "first line\nsecond line\n", // ast-grep-ignore: prefer-indoc
+        vec![
+            Violation::InconsistentLineEnding { line: 1 },
+            Violation::InconsistentLineEnding { line: 2 },
+        ]
+    )]
+    #[case::lf_to_cr(
+        "first line\nsecond line\n", // ast-grep-ignore: prefer-indoc
+        LineEnding::Cr,
+        false,
+        "first line\rsecond line\r",
+        vec![
+            Violation::InconsistentLineEnding { line: 1 },
+            Violation::InconsistentLineEnding { line: 2 },
+        ]
+    )]
+    #[case::already_crlf(
+        "first line\r\nsecond line\r\n", // ast-grep-ignore: prefer-indoc
+        LineEnding::Crlf,
+        true,
+        "first line\r\nsecond line\r\n", // ast-grep-ignore: prefer-indoc
+        vec![]
+    )]
+    fn test_format_content_uses_configured_line_ending(
+        #[case] input: &str,
+        #[case] end_of_line: LineEnding,
+        #[case] other_rules: bool,
+        #[case] expected: &str,
+        #[case] expected_violations: Vec<Violation>,
+    ) {
+        let rules = FormatRules {
+            ensure_final_newline: Some(other_rules),
+            remove_trailing_spaces: Some(other_rules),
+            remove_leading_newlines: Some(other_rules),
+            end_of_line: Some(end_of_line),
+            ..FormatRules::default()
+        };
+        let formatted = format_content(input, &rules);
+        assert_eq!(
+            (formatted.content.as_str(), formatted.violations),
+            (expected, expected_violations)
+        );
+    }
+
+    // Trailing spaces are the point of several of these cases; see the
+    // comment on the `test_format_content` cases above for why they aren't
+    // converted to indoc!.
+    #[rstest]
+    #[case::clean("first line\nsecond line\n", vec![])] // ast-grep-ignore: prefer-indoc
+    #[case::leading_blank_lines(
+        "\n\n\nfirst line\n", // ast-grep-ignore: prefer-indoc
+        vec![Violation::LeadingBlankLines { count: 3 }]
+    )]
+    #[case::trailing_whitespace(
+        "first line\nsecond line  \nthird\t\n", // ast-grep-ignore: prefer-indoc
+        vec![
+            Violation::TrailingWhitespace { line: 2, column: 12 },
+            Violation::TrailingWhitespace { line: 3, column: 6 },
+        ]
+    )]
+    #[case::trailing_whitespace_column_counts_chars(
+        "héllo \n",
+        vec![Violation::TrailingWhitespace { line: 1, column: 6 }]
+    )]
+    #[case::missing_final_newline("first line", vec![Violation::MissingFinalNewline])]
+    #[case::trailing_blank_lines(
+        "first line\n\n\n", // ast-grep-ignore: prefer-indoc
+        vec![Violation::TrailingBlankLines { line: 2, count: 2 }]
+    )]
+    #[case::inconsistent_line_ending(
+        "first line\r\nsecond line\nthird line\r\n", // ast-grep-ignore: prefer-indoc
+        vec![Violation::InconsistentLineEnding { line: 2 }]
+    )]
+    #[case::only_newlines(
+        "\n\n\n", // ast-grep-ignore: prefer-indoc
+        vec![Violation::LeadingBlankLines { count: 3 }]
+    )]
+    fn test_format_content_violations(#[case] input: &str, #[case] expected: Vec<Violation>) {
+        assert_eq!(
+            format_content(input, &FormatRules::default()).violations,
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::leading_newlines_only(
+        FormatRules {
+            ensure_final_newline: Some(false),
+            remove_trailing_spaces: Some(false),
+            remove_leading_newlines: Some(true),
+            preserve_markdown_hard_breaks: Some(false),
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
+        },
+        "first line  \n",
+        FormattedContent {
+            content: "first line  ".to_string(),
+            violations: vec![Violation::UnexpectedFinalNewline],
+        }
+    )]
+    #[case::trailing_spaces_disabled(
+        FormatRules {
+            ensure_final_newline: Some(true),
+            remove_trailing_spaces: Some(false),
+            remove_leading_newlines: Some(true),
+            preserve_markdown_hard_breaks: Some(false),
+            max_blank_lines: None,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line: None,
+            preserve_yaml_block_scalars: None,
+            normalize_encoding: None,
+        },
+        "first line  ",
+        FormattedContent {
+            content: "first line  \n".to_string(),
+            violations: vec![Violation::MissingFinalNewline],
+        }
+    )]
+    #[case::untrimmed_whitespace_after_last_line(
+        FormatRules {
+            remove_trailing_spaces: Some(false),
+            max_blank_lines: Some(0),
+            ..FormatRules::default()
+        },
+        "a\n\n  ",
+        FormattedContent {
+            content: "a\n\n  \n".to_string(),
+            violations: vec![Violation::MissingFinalNewline],
+        }
+    )]
+    fn test_format_content_violations_follow_rules(
+        #[case] rules: FormatRules,
+        #[case] input: &str,
+        #[case] expected: FormattedContent,
+    ) {
+        assert_eq!(format_content(input, &rules), expected);
+    }
+
+    #[rstest]
+    #[case::collapses_run(
+        1,
+        "a\n\n\n\nb\n", // ast-grep-ignore: prefer-indoc
+        "a\n\nb\n", // ast-grep-ignore: prefer-indoc
+        vec![Violation::ExcessBlankLines { line: 3, count: 2 }]
+    )]
+    #[case::zero_removes_every_blank_line(
+        0,
+        "a\n\nb\n\nc\n", // ast-grep-ignore: prefer-indoc
+        "a\nb\nc\n", // ast-grep-ignore: prefer-indoc
+        vec![
+            Violation::ExcessBlankLines { line: 2, count: 1 },
+            Violation::ExcessBlankLines { line: 4, count: 1 },
+        ]
+    )]
+    #[case::whitespace_only_lines_are_blank(
+        1,
+        "a\n  \n\t\nb\n",
+        "a\n\nb\n", // ast-grep-ignore: prefer-indoc
+        vec![
+            Violation::TrailingWhitespace { line: 2, column: 1 },
+            Violation::ExcessBlankLines { line: 3, count: 1 },
+        ]
+    )]
+    #[case::run_within_limit(2, "a\n\n\nb\n", "a\n\n\nb\n", vec![])] // ast-grep-ignore: prefer-indoc
+    fn test_format_content_max_blank_lines(
+        #[case] max: usize,
+        #[case] input: &str,
+        #[case] expected: &str,
+        #[case] violations: Vec<Violation>,
+    ) {
+        let rules = FormatRules {
+            max_blank_lines: Some(max),
+            ..FormatRules::default()
+        };
+        assert_eq!(
+            format_content(input, &rules),
+            FormattedContent {
+                content: expected.to_string(),
+                violations,
+            }
+        );
+    }
+
+    #[rstest]
+    #[case::body_kept(
+        "run: |  \n  echo a  \n\n\n  echo b\nname: x  \n",
+        "run: |\n  echo a  \n\n\n  echo b\nname: x\n",
+        vec![
+            Violation::TrailingWhitespace { line: 1, column: 7 },
+            Violation::TrailingWhitespace { line: 6, column: 8 },
+        ]
+    )]
+    #[case::outside_body_trimmed(
+        "a:  \n\n\nb: 1\n", // ast-grep-ignore: prefer-indoc
+        "a:\n\nb: 1\n", // ast-grep-ignore: prefer-indoc
+        vec![
+            Violation::TrailingWhitespace { line: 1, column: 3 },
+            Violation::ExcessBlankLines { line: 3, count: 1 },
+        ]
+    )]
+    fn test_format_content_preserves_yaml_block_scalars(
+        #[case] input: &str,
+        #[case] expected: &str,
+        #[case] violations: Vec<Violation>,
+    ) {
+        let rules = FormatRules {
+            max_blank_lines: Some(1),
+            preserve_yaml_block_scalars: Some(true),
+            ..FormatRules::default()
+        };
+        assert_eq!(
+            format_content(input, &rules),
+            FormattedContent {
+                content: expected.to_string(),
+                violations,
+            }
+        );
+    }
+
+    #[rstest]
+    #[case::inside_line("\"a\u{2028}b\u{2029}c\"\n", "\"a\u{2028}b\u{2029}c\"\n", vec![])]
+    #[case::before_trailing_spaces(
+        "a\u{2028}  \nb\u{2029}\n", // ast-grep-ignore: prefer-indoc
+        "a\u{2028}\nb\u{2029}\n", // ast-grep-ignore: prefer-indoc
+        vec![Violation::TrailingWhitespace { line: 1, column: 3 }]
+    )]
+    #[case::separator_only_line_is_not_blank(
+        "a\n\u{2028}\n\n", // ast-grep-ignore: prefer-indoc
+        "a\n\u{2028}\n", // ast-grep-ignore: prefer-indoc
+        vec![Violation::TrailingBlankLines { line: 3, count: 1 }]
+    )]
+    fn test_format_content_preserves_unicode_separators(
+        #[case] input: &str,
+        #[case] expected: &str,
+        #[case] violations: Vec<Violation>,
+    ) {
+        assert_eq!(
+            format_content(input, &FormatRules::default()),
+            FormattedContent {
+                content: expected.to_string(),
+                violations,
+            }
+        );
+    }
+
+    #[rstest]
+    #[case::middle_line(
+        "4:4",
+        "\n\na  \nb  \nc  \n\n", // ast-grep-ignore: prefer-indoc
+        "\n\na  \nb\nc  \n\n", // ast-grep-ignore: prefer-indoc
+        vec![Violation::TrailingWhitespace { line: 4, column: 2 }]
+    )]
+    #[case::leading_lines(
+        "1:3",
+        "\n\na  \nb  \nc  \n\n", // ast-grep-ignore: prefer-indoc
+        "a\nb  \nc  \n\n", // ast-grep-ignore: prefer-indoc
+        vec![
+            Violation::LeadingBlankLines { count: 2 },
+            Violation::TrailingWhitespace { line: 3, column: 2 },
+        ]
+    )]
+    #[case::part_of_leading_lines(
+        "2:2",
+        "\n\na  \nb  \nc  \n\n", // ast-grep-ignore: prefer-indoc
+        "\na  \nb  \nc  \n\n", // ast-grep-ignore: prefer-indoc
+        vec![Violation::LeadingBlankLines { count: 1 }]
+    )]
+    #[case::trailing_lines(
+        "5:6",
+        "\n\na  \nb  \nc  \n\n", // ast-grep-ignore: prefer-indoc
+        "\n\na  \nb  \nc\n", // ast-grep-ignore: prefer-indoc
+        vec![
+            Violation::TrailingWhitespace { line: 5, column: 2 },
+            Violation::TrailingBlankLines { line: 6, count: 1 },
+        ]
+    )]
+    #[case::final_newline_outside_range("1:1", "a\nb", "a\nb", vec![])]
+    #[case::line_ending_outside_range("2:3", "a\r\nb\nc\n", "a\r\nb\nc\n", vec![])] // ast-grep-ignore: prefer-indoc
+    #[case::line_ending_inside_range(
+        "1:1",
+        "a\r\nb\nc\n", // ast-grep-ignore: prefer-indoc
+        "a\nb\nc\n", // ast-grep-ignore: prefer-indoc
+        vec![Violation::InconsistentLineEnding { line: 1 }]
+    )]
+    fn test_format_content_in_ranges(
+        #[case] range: &str,
+        #[case] input: &str,
+        #[case] expected: &str,
+        #[case] violations: Vec<Violation>,
+    ) {
+        let ranges = [range.parse::<LineRange>().unwrap()];
+        assert_eq!(
+            format_content_in(input, &FormatRules::default(), &ranges),
+            FormattedContent {
+                content: expected.to_string(),
+                violations,
+            }
+        );
+    }
+}
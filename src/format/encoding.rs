@@ -0,0 +1,192 @@
+/// How the text of a file is encoded, so that formatted text is written back
+/// the way it was read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Encoding {
+    /// UTF-8, with or without a byte order mark
+    Utf8,
+    /// Little-endian UTF-16 starting with the byte order mark `FF FE`
+    Utf16Le,
+    /// Big-endian UTF-16 starting with the byte order mark `FE FF`
+    Utf16Be,
+    /// Windows-1252, a superset of the printable characters of Latin-1 that
+    /// legacy Windows and Western European text is usually in
+    Windows1252,
+}
+
+/// The characters of the bytes `0x80` to `0x9F` in Windows-1252, which
+/// leaves five of them undefined; every other byte is the code point of the
+/// same value.
+const WINDOWS_1252_HIGH: [Option<char>; 32] = [
+    Some('€'),
+    None,
+    Some('‚'),
+    Some('ƒ'),
+    Some('„'),
+    Some('…'),
+    Some('†'),
+    Some('‡'),
+    Some('ˆ'),
+    Some('‰'),
+    Some('Š'),
+    Some('‹'),
+    Some('Œ'),
+    None,
+    Some('Ž'),
+    None,
+    None,
+    Some('‘'),
+    Some('’'),
+    Some('“'),
+    Some('”'),
+    Some('•'),
+    Some('–'),
+    Some('—'),
+    Some('˜'),
+    Some('™'),
+    Some('š'),
+    Some('›'),
+    Some('œ'),
+    None,
+    Some('ž'),
+    Some('Ÿ'),
+];
+
+impl Encoding {
+    /// Detects UTF-16 by its byte order mark; anything else is read as UTF-8.
+    ///
+    /// `FF FE 00 00` is the byte order mark of little-endian UTF-32, which
+    /// would decode as UTF-16 full of NUL characters, so it isn't UTF-16.
+    pub(super) fn detect(content: &[u8]) -> Self {
+        match content {
+            [0xFF, 0xFE, 0, 0, ..] => Encoding::Utf8,
+            [0xFF, 0xFE, ..] => Encoding::Utf16Le,
+            [0xFE, 0xFF, ..] => Encoding::Utf16Be,
+            _ => Encoding::Utf8,
+        }
+    }
+
+    /// Returns the name of the encoding, e.g. `UTF-16LE`.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Windows1252 => "Windows-1252",
+        }
+    }
+
+    /// Writes `text` in this encoding.
+    ///
+    /// Characters that Windows-1252 has no byte for are written as `?`;
+    /// text decoded from it has none, as formatting only adds line endings.
+    pub(super) fn encode(self, text: String) -> Vec<u8> {
+        let units = text.encode_utf16();
+        match self {
+            Encoding::Utf8 => text.into_bytes(),
+            Encoding::Utf16Le => units.flat_map(u16::to_le_bytes).collect(),
+            Encoding::Utf16Be => units.flat_map(u16::to_be_bytes).collect(),
+            Encoding::Windows1252 => text.chars().map(windows_1252_byte).collect(),
+        }
+    }
+}
+
+/// Returns the Windows-1252 byte for `c`, or `?` if it has none.
+fn windows_1252_byte(c: char) -> u8 {
+    let high = || {
+        let index = WINDOWS_1252_HIGH.iter().position(|&high| high == Some(c))?;
+        u8::try_from(index).ok().map(|index| 0x80 + index)
+    };
+    match u8::try_from(c) {
+        Ok(byte) if !(0x80..0xA0).contains(&byte) => byte,
+        _ => high().unwrap_or(b'?'),
+    }
+}
+
+/// Decodes `content` as Windows-1252 text.
+///
+/// Returns `None` if `content` looks binary rather than like text: it has a
+/// control character other than a tab, a line ending, or a form feed, or a
+/// byte that Windows-1252 leaves undefined.
+pub(super) fn from_windows_1252(content: &[u8]) -> Option<String> {
+    content
+        .iter()
+        .map(|&byte| match byte {
+            0x00..=0x08 | 0x0E..=0x1F | 0x7F => None,
+            0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(byte - 0x80)],
+            _ => Some(char::from(byte)),
+        })
+        .collect()
+}
+
+/// Detects the encoding of `content` and returns it as UTF-8 bytes, with the
+/// byte order mark of UTF-16 kept as U+FEFF so that it is written back.
+///
+/// UTF-8 content is returned as it is, valid or not. Returns `None` if
+/// `content` is UTF-16 that doesn't decode.
+pub(super) fn to_utf8(content: Vec<u8>) -> Option<(Vec<u8>, Encoding)> {
+    let encoding = Encoding::detect(&content);
+    let from_bytes = match encoding {
+        Encoding::Utf8 | Encoding::Windows1252 => return Some((content, encoding)),
+        Encoding::Utf16Le => u16::from_le_bytes,
+        Encoding::Utf16Be => u16::from_be_bytes,
+    };
+    let chunks = content.chunks_exact(2);
+    if !chunks.remainder().is_empty() {
+        return None;
+    }
+    let units = chunks.map(|pair| from_bytes([pair[0], pair[1]]));
+    let text: String = char::decode_utf16(units).collect::<Result<_, _>>().ok()?;
+    Some((text.into_bytes(), encoding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::utf8(b"a\n".to_vec(), Some((b"a\n".to_vec(), Encoding::Utf8)))]
+    #[case::invalid_utf8(b"\xFF\n".to_vec(), Some((b"\xFF\n".to_vec(), Encoding::Utf8)))]
+    #[case::utf16le(
+        b"\xFF\xFEa\0\n\0".to_vec(),
+        Some(("\u{feff}a\n".as_bytes().to_vec(), Encoding::Utf16Le))
+    )]
+    #[case::utf16be(
+        b"\xFE\xFF\0a\0\n".to_vec(),
+        Some(("\u{feff}a\n".as_bytes().to_vec(), Encoding::Utf16Be))
+    )]
+    #[case::utf32le(
+        b"\xFF\xFE\0\0a\0\0\0".to_vec(),
+        Some((b"\xFF\xFE\0\0a\0\0\0".to_vec(), Encoding::Utf8))
+    )]
+    #[case::odd_length(b"\xFF\xFEa".to_vec(), None)]
+    #[case::unpaired_surrogate(b"\xFF\xFE\x00\xD8".to_vec(), None)]
+    fn test_to_utf8(#[case] content: Vec<u8>, #[case] expected: Option<(Vec<u8>, Encoding)>) {
+        assert_eq!(to_utf8(content), expected);
+    }
+
+    #[rstest]
+    #[case::latin1(b"caf\xE9\n", Some("café\n"))]
+    #[case::windows_1252_punctuation(b"\x93quoted\x94 \x80", Some("“quoted” €"))]
+    #[case::undefined_byte(b"a\x81", None)]
+    #[case::nul(b"a\0b", None)]
+    fn test_from_windows_1252(#[case] content: &[u8], #[case] expected: Option<&str>) {
+        assert_eq!(from_windows_1252(content).as_deref(), expected);
+    }
+
+    #[rstest]
+    #[case::utf8(Encoding::Utf8, "\u{feff}a\n".as_bytes().to_vec())]
+    #[case::utf16le(Encoding::Utf16Le, b"\xFF\xFEa\0\n\0".to_vec())]
+    #[case::utf16be(Encoding::Utf16Be, b"\xFE\xFF\0a\0\n".to_vec())]
+    fn test_encode(#[case] encoding: Encoding, #[case] expected: Vec<u8>) {
+        assert_eq!(encoding.encode("\u{feff}a\n".to_string()), expected);
+    }
+
+    #[test]
+    fn test_encode_windows_1252() {
+        assert_eq!(
+            Encoding::Windows1252.encode("“café” €\u{feff}".to_string()),
+            b"\x93caf\xE9\x94 \x80?".to_vec()
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use std::fs::{File, TryLockError};
+use std::io;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait between attempts to take a lock another process holds.
+const RETRY_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Takes an advisory lock (`flock` on Unix, `LockFileEx` on Windows) on the
+/// file at `path`, which is held until the returned file is dropped, so
+/// that editors and other formatters that lock files too don't write it at
+/// the same time.
+///
+/// Waits up to `timeout` for a process that holds the lock already, and
+/// returns an error of kind [`io::ErrorKind::TimedOut`] if it still does by
+/// then. On file systems without locks, the file is returned unlocked.
+pub(super) fn lock(path: &Path, timeout: Duration) -> io::Result<File> {
+    let file = File::open(path)?;
+    let started = Instant::now();
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(file),
+            Err(TryLockError::WouldBlock) if started.elapsed() < timeout => {
+                thread::sleep(RETRY_INTERVAL);
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("file is locked by another process (gave up after {timeout:?})"),
+                ));
+            }
+            // File systems that can't lock files are written without
+            Err(TryLockError::Error(err)) if err.kind() == io::ErrorKind::Unsupported => {
+                return Ok(file);
+            }
+            Err(TryLockError::Error(err)) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lock() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("a.txt");
+        fs::write(&path, "a\n").unwrap();
+        let holder = File::open(&path).unwrap();
+        holder.lock().unwrap();
+
+        let contended = lock(&path, Duration::from_millis(50))
+            .map(drop)
+            .map_err(|err| (err.kind(), err.to_string()));
+        drop(holder);
+        let released = lock(&path, Duration::ZERO).is_ok();
+
+        assert_eq!(
+            (contended, released),
+            (
+                Err((
+                    io::ErrorKind::TimedOut,
+                    "file is locked by another process (gave up after 50ms)".to_string()
+                )),
+                true
+            )
+        );
+    }
+}
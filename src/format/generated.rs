@@ -0,0 +1,71 @@
+/// Markers that code generators put near the top of their output, e.g.
+/// `// Code generated by protoc-gen-go. DO NOT EDIT.` or `# @generated`.
+const MARKERS: &[&[u8]] = &[b"@generated", b"DO NOT EDIT"];
+
+/// Whether any of the first `lines` lines of `content` has a generated-code
+/// marker.
+pub(super) fn has_generated_marker(content: &[u8], lines: usize) -> bool {
+    // Lines end with `\n`, `\r\n`, or a lone `\r`
+    let mut rest = content;
+    std::iter::from_fn(|| {
+        if rest.is_empty() {
+            return None;
+        }
+        let end = rest
+            .iter()
+            .position(|&byte| byte == b'\n' || byte == b'\r')
+            .unwrap_or(rest.len());
+        let (line, tail) = rest.split_at(end);
+        rest = tail
+            .strip_prefix(b"\r\n")
+            .or_else(|| tail.get(1..))
+            .unwrap_or_default();
+        Some(line)
+    })
+    .take(lines)
+    .any(|line| {
+        MARKERS
+            .iter()
+            .any(|marker| line.windows(marker.len()).any(|window| window == *marker))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::generated_tag("// @generated\n", 1, true)]
+    #[case::do_not_edit("// Code generated by stringer. DO NOT EDIT.\n", 1, true)]
+    #[case::marker_within_lines(
+        indoc! {"
+            #!/bin/sh
+            # This file is @generated by a script.
+        "},
+        2,
+        true
+    )]
+    #[case::marker_after_lines(
+        indoc! {"
+            #!/bin/sh
+            # This file is @generated by a script.
+        "},
+        1,
+        false
+    )]
+    #[case::cr_lines("#!/bin/sh\r# @generated\r", 2, true)]
+    #[case::cr_marker_after_lines("#!/bin/sh\r# @generated\r", 1, false)]
+    #[case::crlf_lines("#!/bin/sh\r\n# @generated\r\n", 2, true)] // ast-grep-ignore: prefer-indoc
+    #[case::no_lines("// @generated\n", 0, false)]
+    #[case::no_marker("fn main() {}\n", 5, false)]
+    #[case::lowercase("// do not edit\n", 5, false)]
+    fn test_has_generated_marker(
+        #[case] content: &str,
+        #[case] lines: usize,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(has_generated_marker(content.as_bytes(), lines), expected);
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+/// An inclusive, 1-based range of lines, written `START:END` (e.g. `10:40`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineRange {
+    start: usize,
+    end: usize,
+}
+
+impl LineRange {
+    /// Creates the range `start..=end`, or returns `None` if `start` is 0 or
+    /// greater than `end`.
+    pub fn new(start: usize, end: usize) -> Option<Self> {
+        (start >= 1 && start <= end).then_some(LineRange { start, end })
+    }
+
+    /// Returns whether `line` lies within the range.
+    pub fn contains(&self, line: usize) -> bool {
+        (self.start..=self.end).contains(&line)
+    }
+}
+
+impl FromStr for LineRange {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid line range '{s}': expected START:END with 1 <= START <= END"),
+            )
+        };
+        let (start, end) = s.split_once(':').ok_or_else(invalid)?;
+        let start = start.parse().map_err(|_| invalid())?;
+        let end = end.parse().map_err(|_| invalid())?;
+        LineRange::new(start, end).ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for LineRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.start, self.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::range("10:40", LineRange::new(10, 40))]
+    #[case::single_line("3:3", LineRange::new(3, 3))]
+    #[case::zero_start("0:4", None)]
+    #[case::reversed("40:10", None)]
+    #[case::missing_end("10:", None)]
+    #[case::no_separator("10", None)]
+    fn test_from_str(#[case] input: &str, #[case] expected: Option<LineRange>) {
+        assert_eq!(input.parse::<LineRange>().ok(), expected);
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        let range = LineRange::new(10, 40).unwrap();
+        assert_eq!(range.to_string().parse::<LineRange>().unwrap(), range);
+    }
+}
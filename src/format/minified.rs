@@ -0,0 +1,45 @@
+/// Files smaller than this are never considered minified; formatting them
+/// is cheap whatever they contain.
+pub(super) const MIN_SIZE: usize = 100 * 1024;
+
+/// A file whose first [`MIN_SIZE`] bytes have fewer line breaks than this
+/// has lines over 5 KiB long on average; source written by hand doesn't.
+const MAX_LINE_BREAKS: usize = MIN_SIZE / (5 * 1024);
+
+/// Whether `content`, the start of a file or all of it, looks like a
+/// minified asset such as a bundled script or stylesheet: at least
+/// [`MIN_SIZE`] bytes with hardly any line breaks.
+///
+/// Only the first [`MIN_SIZE`] bytes are looked at, so callers can decide
+/// without reading the rest of the file.
+pub(super) fn looks_minified(content: &[u8]) -> bool {
+    let Some(head) = content.get(..MIN_SIZE) else {
+        return false;
+    };
+    // `\n` and a lone `\r` each end a line
+    let line_breaks = head
+        .iter()
+        .enumerate()
+        .filter(|&(index, &byte)| {
+            byte == b'\n' || (byte == b'\r' && head.get(index + 1) != Some(&b'\n'))
+        })
+        .count();
+    line_breaks < MAX_LINE_BREAKS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::single_long_line("a".repeat(MIN_SIZE * 3), true)]
+    #[case::few_line_breaks(format!("{}\n", "a".repeat(MIN_SIZE / 10)).repeat(12), true)]
+    #[case::short_lines("line of source code\n".repeat(MIN_SIZE / 10), false)]
+    #[case::cr_lines("line of source code\r".repeat(MIN_SIZE / 10), false)]
+    #[case::crlf_lines("line of source code\r\n".repeat(MIN_SIZE / 10), false)]
+    #[case::small_file("a".repeat(MIN_SIZE - 1), false)]
+    fn test_looks_minified(#[case] content: String, #[case] expected: bool) {
+        assert_eq!(looks_minified(content.as_bytes()), expected);
+    }
+}
@@ -0,0 +1,14 @@
+use std::path::{Component, Path, PathBuf};
+
+/// Returns where `path` lands when mirrored into `dir`.
+///
+/// Root, prefix, `.` and `..` components are dropped, so `./docs/a.md` and
+/// `/work/docs/a.md` are mirrored to `<dir>/docs/a.md` and
+/// `<dir>/work/docs/a.md` respectively.
+pub(crate) fn mirror_path(dir: &Path, path: &Path) -> PathBuf {
+    let relative: PathBuf = path
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect();
+    dir.join(relative)
+}
@@ -0,0 +1,30 @@
+use super::replace::sync_dir;
+use super::{mirror, temp};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Writes `content` to the mirror of `path` inside `dir`, atomically
+/// replacing any copy left by a previous run.
+pub(crate) fn write(
+    dir: &Path,
+    path: &Path,
+    content: &[u8],
+    permissions: fs::Permissions,
+    durable: bool,
+) -> io::Result<()> {
+    let target = mirror::mirror_path(dir, path);
+    let parent_dir = target.parent().unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(parent_dir)?;
+
+    let mut temp_file = temp::create_in(parent_dir)?;
+    temp_file.write_all(content)?;
+    temp_file.as_file().sync_all()?;
+    temp_file.as_file().set_permissions(permissions)?;
+    temp_file.persist(&target).map_err(io::Error::from)?;
+
+    if durable {
+        sync_dir(parent_dir)?;
+    }
+    Ok(())
+}
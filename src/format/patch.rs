@@ -0,0 +1,84 @@
+use similar::TextDiff;
+use std::env;
+use std::path::{Component, Path};
+
+/// Returns the changes from `original` to `formatted` in the file at `path`
+/// as a patch in the format of `git diff`, which `git apply` and `patch -p1`
+/// accept.
+///
+/// The file is named by its path relative to the current directory, so the
+/// patch applies from there. Returns an empty string if nothing changed.
+pub fn unified_diff(path: &Path, original: &str, formatted: &str) -> String {
+    if original == formatted {
+        return String::new();
+    }
+    let name = patch_path(path);
+    let diff = TextDiff::from_lines(original, formatted);
+    format!(
+        "diff --git a/{name} b/{name}\n{}",
+        diff.unified_diff()
+            .header(&format!("a/{name}"), &format!("b/{name}"))
+    )
+}
+
+/// Spells `path` the way patches name files: relative to the current
+/// directory if it is inside it, without `.` components, and with `/` as the
+/// separator on every platform.
+fn patch_path(path: &Path) -> String {
+    let relative = env::current_dir()
+        .ok()
+        .and_then(|dir| path.strip_prefix(dir).ok())
+        .unwrap_or(path);
+    let parts: Vec<_> = relative
+        .components()
+        .filter(|component| *component != Component::CurDir)
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect();
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn test_unified_diff() {
+        let original = "\n\na\nb\nc\nd\ne\nf\ng"; // ast-grep-ignore: prefer-indoc
+        let formatted = indoc! {"
+            a
+            b
+            c
+            d
+            e
+            f
+            g
+        "};
+
+        assert_eq!(
+            unified_diff(Path::new("./docs/a.txt"), original, formatted),
+            indoc! {r"
+                diff --git a/docs/a.txt b/docs/a.txt
+                --- a/docs/a.txt
+                +++ b/docs/a.txt
+                @@ -1,9 +1,7 @@
+                -
+                -
+                 a
+                 b
+                 c
+                 d
+                 e
+                 f
+                -g
+                \ No newline at end of file
+                +g
+            "}
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_unchanged() {
+        assert_eq!(unified_diff(Path::new("a.txt"), "a\n", "a\n"), "");
+    }
+}
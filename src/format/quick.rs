@@ -0,0 +1,493 @@
+use super::content::{Line, is_blank, split_lines, trim_line_end};
+use super::encoding::Encoding;
+use super::read::text_to_format;
+use super::{CheckResult, SkipReason, Violation, check_content, generated, minified};
+use crate::editorconfig::FormatRules;
+use std::io::{self, Read};
+
+/// How many bytes to read at a time.
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Checks the content read from `reader` like [`check_content`], but stops
+/// checking once a violation shows that it needs formatting, returning only
+/// that violation. The rest is still read to make sure that it is UTF-8, as
+/// a file that isn't is skipped as binary, but isn't kept or checked.
+///
+/// Only violations that formatting fixes wherever they are in the file are
+/// looked for on the way, such as trailing whitespace after text; the rest,
+/// such as a missing final newline, are checked once the whole file is read,
+/// and only the first of them is returned. So are YAML block scalars and
+/// files in another encoding, whose lines can't be judged one at a time.
+pub(super) fn check_until_violation(
+    mut reader: impl Read,
+    rules: &FormatRules,
+) -> io::Result<CheckResult> {
+    let whole_file = rules.preserve_yaml_block_scalars() || rules.normalize_encoding.is_some();
+    let mut scan = (!whole_file).then(|| Scan::new(rules));
+    let mut content = Vec::new();
+    let mut head = Head::default();
+    loop {
+        if reader.by_ref().take(CHUNK_SIZE).read_to_end(&mut content)? == 0 {
+            break;
+        }
+        let Some(active) = &mut scan else {
+            continue;
+        };
+        // Whether the file is skipped can only be told from its start
+        if !head.complete {
+            if !head.read(&content, rules) {
+                continue;
+            }
+            if Encoding::detect(&content) != Encoding::Utf8 {
+                scan = None;
+                continue;
+            }
+            if generated::has_generated_marker(&content, rules.generated_marker_lines()) {
+                return Ok(CheckResult::Skipped(SkipReason::Generated));
+            }
+            if rules.skip_minified() && minified::looks_minified(&content) {
+                return Ok(CheckResult::Skipped(SkipReason::Minified));
+            }
+        }
+        match active.feed(&content) {
+            Some(Ok(violation)) => {
+                let unchecked = content.split_off(active.offset);
+                return Ok(if rest_is_utf8(unchecked, reader)? {
+                    CheckResult::NeedsFormatting(vec![violation])
+                } else {
+                    CheckResult::Skipped(SkipReason::Binary)
+                });
+            }
+            Some(Err(NotText)) => scan = None,
+            None => {}
+        }
+    }
+    Ok(match check_content(content, rules) {
+        CheckResult::NeedsFormatting(mut violations) => {
+            violations.truncate(1);
+            CheckResult::NeedsFormatting(violations)
+        }
+        result => result,
+    })
+}
+
+/// Returns why the content read from `reader` is skipped, as by
+/// [`check_content`], without checking it: only as much of its start is kept
+/// as tells whether it is generated or minified, and the rest is read only
+/// until it turns out not to be UTF-8.
+pub(super) fn skip_reason(
+    mut reader: impl Read,
+    rules: &FormatRules,
+) -> io::Result<Option<SkipReason>> {
+    let mut content = Vec::new();
+    let mut head = Head::default();
+    while !head.read(&content, rules) {
+        if reader.by_ref().take(CHUNK_SIZE).read_to_end(&mut content)? == 0 {
+            break;
+        }
+    }
+    // Other encodings are decoded as a whole
+    if Encoding::detect(&content) != Encoding::Utf8 {
+        reader.read_to_end(&mut content)?;
+        return Ok(text_to_format(content, rules).err());
+    }
+    if generated::has_generated_marker(&content, rules.generated_marker_lines()) {
+        return Ok(Some(SkipReason::Generated));
+    }
+    if rules.skip_minified() && minified::looks_minified(&content) {
+        return Ok(Some(SkipReason::Minified));
+    }
+    Ok((!rest_is_utf8(content, reader)?).then_some(SkipReason::Binary))
+}
+
+/// Returns whether `pending`, the part of a file read but not yet found to be
+/// UTF-8, and the rest of it read from `reader` are UTF-8, keeping no more
+/// of them in memory than a chunk.
+fn rest_is_utf8(mut pending: Vec<u8>, mut reader: impl Read) -> io::Result<bool> {
+    loop {
+        match std::str::from_utf8(&pending) {
+            Ok(_) => pending.clear(),
+            // The last character may continue in the next chunk
+            Err(err) if err.error_len().is_none() => {
+                pending.drain(..err.valid_up_to());
+            }
+            Err(_) => return Ok(false),
+        }
+        if reader.by_ref().take(CHUNK_SIZE).read_to_end(&mut pending)? == 0 {
+            return Ok(pending.is_empty());
+        }
+    }
+}
+
+/// How much of the start of a file has been read, until there is enough to
+/// tell whether it is skipped.
+#[derive(Default)]
+pub(super) struct Head {
+    /// How many bytes of the file were counted
+    counted: usize,
+    line_breaks: usize,
+    complete: bool,
+}
+
+impl Head {
+    /// Counts the lines of `content`, the file read so far, and returns
+    /// whether it has as many bytes as [`minified::looks_minified`] looks at
+    /// and as many lines as are searched for a generated-code marker.
+    pub(super) fn read(&mut self, content: &[u8], rules: &FormatRules) -> bool {
+        // Each `\r` ends a line, and so does each `\n` that isn't after one
+        let mut previous = self
+            .counted
+            .checked_sub(1)
+            .and_then(|index| content.get(index).copied());
+        for &byte in content.get(self.counted..).unwrap_or_default() {
+            if byte == b'\r' || (byte == b'\n' && previous != Some(b'\r')) {
+                self.line_breaks += 1;
+            }
+            previous = Some(byte);
+        }
+        self.counted = content.len();
+        self.complete = content.len() >= minified::MIN_SIZE
+            && self.line_breaks >= rules.generated_marker_lines();
+        self.complete
+    }
+}
+
+/// Content that isn't valid UTF-8, which the whole file is needed to judge.
+struct NotText;
+
+/// The state of checking a file line by line.
+struct Scan<'a> {
+    rules: &'a FormatRules,
+    /// How many bytes of the file were checked
+    offset: usize,
+    /// How many lines were checked
+    lines: usize,
+    /// How many blank lines the file starts with, while only blank lines
+    /// were found and leading blank lines are removed
+    leading: Option<usize>,
+    blank_run: usize,
+    /// The first blank line beyond `max_blank_lines` in the current run of
+    /// blank lines, and how many there are
+    excess: Option<(usize, usize)>,
+    /// The first violation on a blank line kept in the current run of blank
+    /// lines, which is one unless the run turns out to end the file
+    kept_blank: Option<Violation>,
+    /// A line with text that ends with another terminator than
+    /// `end_of_line`, which is a violation unless it turns out to be the
+    /// last such line
+    wrong_ending: Option<usize>,
+}
+
+impl<'a> Scan<'a> {
+    fn new(rules: &'a FormatRules) -> Self {
+        Self {
+            rules,
+            offset: 0,
+            lines: 0,
+            leading: rules.remove_leading_newlines().then_some(0),
+            blank_run: 0,
+            excess: None,
+            kept_blank: None,
+            wrong_ending: None,
+        }
+    }
+
+    /// Checks the complete lines of `content`, the file read so far, that
+    /// weren't checked yet, and returns the first violation found.
+    fn feed(&mut self, content: &[u8]) -> Option<Result<Violation, NotText>> {
+        let rest = content.get(self.offset..).unwrap_or_default();
+        // A `\r` at the end may be followed by a `\n`
+        let end = rest
+            .iter()
+            .enumerate()
+            .rposition(|(index, &byte)| byte == b'\n' || (byte == b'\r' && index + 1 < rest.len()))
+            .map_or(0, |index| index + 1);
+        let text = match std::str::from_utf8(rest.get(..end).unwrap_or_default()) {
+            Ok(text) => text,
+            Err(_) => return Some(Err(NotText)),
+        };
+        self.offset += end;
+        let lines = split_lines(text);
+        let count = lines.len();
+        for line in lines {
+            let line = Line {
+                number: self.lines + line.number,
+                ..line
+            };
+            if let Some(violation) = self.line(&line) {
+                return Some(Ok(violation));
+            }
+        }
+        self.lines += count;
+        None
+    }
+
+    /// Checks `line`, which is followed by more of the file, returning a
+    /// violation once it is certain that formatting fixes it.
+    fn line(&mut self, line: &Line) -> Option<Violation> {
+        let rules = self.rules;
+        let trim = rules.remove_trailing_spaces();
+        let blank = is_blank(line.text);
+        if let Some(count) = self.leading {
+            if line.text.is_empty() || (trim && blank) {
+                self.leading = Some(count + 1);
+                return None;
+            }
+            self.leading = None;
+            // A line that isn't dropped follows, so these are removed
+            if count > 0 {
+                return Some(Violation::LeadingBlankLines { count });
+            }
+        }
+        if blank {
+            if let Some(max) = rules.max_blank_lines() {
+                self.blank_run += 1;
+                if self.blank_run > max {
+                    let (_, count) = self.excess.get_or_insert((line.number, 0));
+                    *count += 1;
+                    return None;
+                }
+            }
+            if self.kept_blank.is_none() {
+                self.kept_blank = self.trailing_whitespace(line).or_else(|| {
+                    self.has_wrong_ending(line)
+                        .then_some(Violation::InconsistentLineEnding { line: line.number })
+                });
+            }
+            return None;
+        }
+
+        // A line with text follows the previous ones, so they are kept and
+        // aren't the last
+        self.blank_run = 0;
+        if let Some(line) = self.wrong_ending.take() {
+            return Some(Violation::InconsistentLineEnding { line });
+        }
+        if let Some(violation) = self.kept_blank.take() {
+            return Some(violation);
+        }
+        if let Some((line, count)) = self.excess.take() {
+            return Some(Violation::ExcessBlankLines { line, count });
+        }
+        if let Some(violation) = self.trailing_whitespace(line) {
+            return Some(violation);
+        }
+        if self.has_wrong_ending(line) {
+            self.wrong_ending = Some(line.number);
+        }
+        None
+    }
+
+    /// Returns the trailing whitespace of `line` that formatting removes.
+    fn trailing_whitespace(&self, line: &Line) -> Option<Violation> {
+        if !self.rules.remove_trailing_spaces() {
+            return None;
+        }
+        let text = trim_line_end(line.text, self.rules.preserve_markdown_hard_breaks());
+        (text.len() < line.text.len()).then(|| Violation::TrailingWhitespace {
+            line: line.number,
+            column: text.chars().count() + 1,
+        })
+    }
+
+    /// Returns whether `line` ends with another terminator than `end_of_line`.
+    fn has_wrong_ending(&self, line: &Line) -> bool {
+        self.rules.end_of_line.is_some_and(|end_of_line| {
+            !line.ending.is_empty() && line.ending != end_of_line.as_str()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editorconfig::LineEnding;
+    use rstest::rstest;
+
+    /// Lines enough for the start of a file to tell whether it is skipped.
+    fn filler() -> String {
+        "line\n".repeat(minified::MIN_SIZE / 5)
+    }
+
+    /// A reader that fails, standing in for the part of a file that must not
+    /// be read.
+    struct Unreadable;
+
+    impl Read for Unreadable {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("read past the first violation"))
+        }
+    }
+
+    #[rstest]
+    #[case::formatted("", b"", FormatRules::default())]
+    #[case::trailing_whitespace("a \n", b"", FormatRules::default())]
+    #[case::leading_blank_lines("\n \n", b"", FormatRules::default())]
+    #[case::excess_blank_lines(
+        "a\n\n\n\nb\n", // ast-grep-ignore: prefer-indoc
+        b"",
+        FormatRules { max_blank_lines: Some(1), ..FormatRules::default() }
+    )]
+    #[case::inconsistent_line_ending(
+        "a\r\nb\n", // ast-grep-ignore: prefer-indoc
+        b"",
+        FormatRules { end_of_line: Some(LineEnding::Lf), ..FormatRules::default() }
+    )]
+    #[case::interior_whitespace_only_line("a\n  \n", b"", FormatRules::default())]
+    #[case::leading_whitespace_only_lines(" \n\t\na \n", b"", FormatRules::default())]
+    #[case::trailing_whitespace_only_lines("", b"a\n  \n\t\n", FormatRules::default())]
+    #[case::excess_whitespace_only_line(
+        "a\n\n  \nb \n",
+        b"",
+        FormatRules { max_blank_lines: Some(1), ..FormatRules::default() }
+    )]
+    #[case::blank_line_ending(
+        "a\n\r\nb\r\n", // ast-grep-ignore: prefer-indoc
+        b"",
+        FormatRules { end_of_line: Some(LineEnding::Lf), ..FormatRules::default() }
+    )]
+    #[case::line_ending_before_excess_blank_lines(
+        "a\r\n\n\n\nb\n", // ast-grep-ignore: prefer-indoc
+        b"",
+        FormatRules {
+            end_of_line: Some(LineEnding::Lf),
+            max_blank_lines: Some(1),
+            ..FormatRules::default()
+        }
+    )]
+    #[case::violation_at_end("", b"end", FormatRules::default())]
+    #[case::generated(
+        "// @generated\n",
+        b"a \n",
+        FormatRules { generated_marker_lines: Some(5), ..FormatRules::default() }
+    )]
+    #[case::yaml_block_scalar(
+        "a: |\n  b \n",
+        b"",
+        FormatRules { preserve_yaml_block_scalars: Some(true), ..FormatRules::default() }
+    )]
+    #[case::invalid_utf8_after_violation("a \n", b"\xff\xfe\n", FormatRules::default())]
+    #[case::invalid_utf8_in_next_chunk(
+        "a \n",
+        &[filler().repeat(2).as_bytes(), b"\xff\xfe\n"].concat(),
+        FormatRules::default()
+    )]
+    #[case::multibyte_across_chunks(
+        "a \n",
+        &"\u{e9}".repeat(CHUNK_SIZE as usize).into_bytes(),
+        FormatRules::default()
+    )]
+    #[case::truncated_multibyte_at_end("a \n", b"\xc3", FormatRules::default())]
+    fn test_check_until_violation_finds_first_violation(
+        #[case] head: &str,
+        #[case] tail: &[u8],
+        #[case] rules: FormatRules,
+    ) {
+        let mut content = format!("{head}{}", filler()).into_bytes();
+        content.extend_from_slice(tail);
+
+        let result = check_until_violation(content.as_slice(), &rules).unwrap();
+
+        let expected = match check_content(content, &rules) {
+            CheckResult::NeedsFormatting(violations) => {
+                CheckResult::NeedsFormatting(violations.into_iter().take(1).collect())
+            }
+            result => result,
+        };
+        assert_eq!(result, expected);
+    }
+
+    #[rstest]
+    #[case::small_file(
+        b"a\n  \nb \nc\t\n".to_vec(),
+        CheckResult::NeedsFormatting(vec![Violation::TrailingWhitespace { line: 2, column: 1 }])
+    )]
+    #[case::small_file_violation_at_end(
+        b"a\n\n".to_vec(), // ast-grep-ignore: prefer-indoc
+        CheckResult::NeedsFormatting(vec![Violation::TrailingBlankLines { line: 2, count: 1 }])
+    )]
+    #[case::interior_whitespace_only_line(
+        [b"a\n  \n".as_slice(), filler().as_bytes(), b"tail \n"].concat(),
+        CheckResult::NeedsFormatting(vec![Violation::TrailingWhitespace { line: 2, column: 1 }])
+    )]
+    #[case::dropped_whitespace_only_lines(
+        [b" \n\t\n".as_slice(), filler().as_bytes(), b"tail \n  \n"].concat(),
+        CheckResult::NeedsFormatting(vec![Violation::LeadingBlankLines { count: 2 }])
+    )]
+    fn test_check_until_violation_returns_first_violation(
+        #[case] content: Vec<u8>,
+        #[case] expected: CheckResult,
+    ) {
+        let result = check_until_violation(content.as_slice(), &FormatRules::default()).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[rstest]
+    #[case::text(b"a \n".as_slice(), FormatRules::default(), None)]
+    #[case::empty(b"".as_slice(), FormatRules::default(), None)]
+    #[case::binary(b"a\n\xff".as_slice(), FormatRules::default(), Some(SkipReason::Binary))]
+    #[case::binary_after_start(
+        &[filler().repeat(3).as_bytes(), b"\xff"].concat(),
+        FormatRules::default(),
+        Some(SkipReason::Binary)
+    )]
+    #[case::truncated_multibyte(
+        b"a\xc3".as_slice(),
+        FormatRules::default(),
+        Some(SkipReason::Binary)
+    )]
+    #[case::utf16(b"\xff\xfea\0".as_slice(), FormatRules::default(), None)]
+    #[case::invalid_utf16(
+        b"\xff\xfea".as_slice(),
+        FormatRules::default(),
+        Some(SkipReason::Binary)
+    )]
+    #[case::generated(
+        b"// @generated\n\xff".as_slice(),
+        FormatRules { generated_marker_lines: Some(1), ..FormatRules::default() },
+        Some(SkipReason::Generated)
+    )]
+    #[case::minified(
+        &"a".repeat(minified::MIN_SIZE).into_bytes(),
+        FormatRules::default(),
+        Some(SkipReason::Minified)
+    )]
+    fn test_skip_reason(
+        #[case] content: &[u8],
+        #[case] rules: FormatRules,
+        #[case] expected: Option<SkipReason>,
+    ) {
+        // Skipped just as when the file is checked
+        let checked = match check_content(content.to_vec(), &rules) {
+            CheckResult::Skipped(reason) => Some(reason),
+            _ => None,
+        };
+
+        assert_eq!(
+            (skip_reason(content, &rules).unwrap(), checked),
+            (expected, expected)
+        );
+    }
+
+    #[rstest]
+    #[case::generated(
+        "// @generated\n",
+        FormatRules { generated_marker_lines: Some(1), ..FormatRules::default() },
+        CheckResult::Skipped(SkipReason::Generated)
+    )]
+    fn test_check_until_violation_stops_reading(
+        #[case] head: &str,
+        #[case] rules: FormatRules,
+        #[case] expected: CheckResult,
+    ) {
+        // Each read fills a whole chunk, so the file must be longer than the
+        // chunks read until its start is checked
+        let content = format!("{head}{}", filler().repeat(2));
+        let reader = content.as_bytes().chain(Unreadable);
+
+        let result = check_until_violation(reader, &rules).unwrap();
+
+        assert_eq!(result, expected);
+    }
+}
@@ -0,0 +1,123 @@
+use super::content::{FormattedContent, format_content_in};
+use super::encoding::{self, Encoding};
+use super::{LineRange, SkipReason, Violation, generated, minified};
+use crate::editorconfig::{self, Charset};
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// The content of a file decoded as text to format.
+pub(super) struct Text {
+    pub(super) content: String,
+    /// The encoding the file is in
+    pub(super) encoding: Encoding,
+    /// Whether to write the formatted content as UTF-8 rather than in
+    /// `encoding`
+    pub(super) to_utf8: bool,
+}
+
+impl Text {
+    /// Returns the encoding to write the formatted content in.
+    pub(super) fn target(&self) -> Encoding {
+        if self.to_utf8 {
+            Encoding::Utf8
+        } else {
+            self.encoding
+        }
+    }
+
+    /// Whether writing `formatted` would leave the file as it is.
+    pub(super) fn is_unchanged(&self, formatted: &str) -> bool {
+        !self.to_utf8 && self.content == formatted
+    }
+
+    /// Formats the text as `rules` say, only editing lines within `ranges`
+    /// (every line if empty); converting it to UTF-8 is a violation too.
+    pub(super) fn format(
+        &self,
+        rules: &editorconfig::FormatRules,
+        ranges: &[LineRange],
+    ) -> FormattedContent {
+        let mut formatted = format_content_in(&self.content, rules, ranges);
+        if self.to_utf8 {
+            formatted.violations.insert(
+                0,
+                Violation::NotUtf8 {
+                    encoding: self.encoding.name(),
+                },
+            );
+        }
+        formatted
+    }
+}
+
+/// A file read and formatted: its text, the formatted content, and its
+/// metadata.
+pub(super) type ReadAndFormatted = (Text, FormattedContent, fs::Metadata);
+
+pub(super) fn read_and_format_with_rules(
+    path: &Path,
+    rules: &editorconfig::FormatRules,
+    ranges: &[LineRange],
+) -> io::Result<Result<ReadAndFormatted, SkipReason>> {
+    let file = fs::File::open(path)?;
+    let metadata = file.metadata()?;
+
+    let mut content = Vec::new();
+    io::BufReader::new(file).read_to_end(&mut content)?;
+    let text = match text_to_format(content, rules) {
+        Ok(text) => text,
+        Err(skip) => return Ok(Err(skip)),
+    };
+    let formatted = text.format(rules, ranges);
+    Ok(Ok((text, formatted, metadata)))
+}
+
+/// Returns the content of a file as text to format, or why the file is left
+/// alone: binary files silently, generated and minified files as `rules`
+/// say.
+pub(super) fn text_to_format(
+    content: Vec<u8>,
+    rules: &editorconfig::FormatRules,
+) -> Result<Text, SkipReason> {
+    let (content, encoding) = encoding::to_utf8(content).ok_or(SkipReason::Binary)?;
+    if generated::has_generated_marker(&content, rules.generated_marker_lines()) {
+        return Err(SkipReason::Generated);
+    }
+    if rules.skip_minified() && minified::looks_minified(&content) {
+        return Err(SkipReason::Minified);
+    }
+    decode(content, encoding, rules).ok_or(SkipReason::Binary)
+}
+
+/// Decodes `content`, which [`encoding::to_utf8`] returned for a file in
+/// `encoding`, as text, or returns `None` if it isn't text.
+///
+/// UTF-16 files with a byte order mark are already decoded; anything else
+/// must be UTF-8, unless `rules` say to convert files to UTF-8, in which
+/// case other text is read as Windows-1252.
+pub(super) fn decode(
+    content: Vec<u8>,
+    mut encoding: Encoding,
+    rules: &editorconfig::FormatRules,
+) -> Option<Text> {
+    let to_utf8 = rules.normalize_encoding == Some(Charset::Utf8);
+    let mut content = match String::from_utf8(content) {
+        Ok(content) => content,
+        Err(err) if to_utf8 => {
+            encoding = Encoding::Windows1252;
+            encoding::from_windows_1252(err.as_bytes())?
+        }
+        Err(_) => return None,
+    };
+    let to_utf8 = to_utf8 && encoding != Encoding::Utf8;
+    if to_utf8 && content.starts_with('\u{feff}') {
+        // UTF-8 files are written without a byte order mark
+        content.remove(0);
+    }
+    Some(Text {
+        content,
+        encoding,
+        to_utf8,
+    })
+}
@@ -0,0 +1,36 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// What to do with a read-only file that needs formatting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReadOnlyPolicy {
+    /// Leave the file untouched and report it as read-only.
+    #[default]
+    Skip,
+    /// Temporarily make the file writable, format it, and restore its
+    /// original permissions.
+    Force,
+}
+
+/// Runs `replace` with the read-only bit of `path` lifted.
+///
+/// `replace` is expected to swap in a new file carrying `original`
+/// permissions, so they are only restored by hand when it fails.
+pub(crate) fn with_write_access(
+    path: &Path,
+    original: &fs::Permissions,
+    replace: impl FnOnce() -> io::Result<()>,
+) -> io::Result<()> {
+    let mut writable = original.clone();
+    #[expect(
+        clippy::permissions_set_readonly_false,
+        reason = "the file is made writable only until it is replaced by one with the original permissions"
+    )]
+    writable.set_readonly(false);
+    fs::set_permissions(path, writable)?;
+
+    replace().inspect_err(|_| {
+        let _ = fs::set_permissions(path, original.clone());
+    })
+}
@@ -0,0 +1,183 @@
+use super::{WriteOptions, file_lock, read_only, temp, xattrs};
+use crate::canonical;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Returns the file that formatting `path` in place writes, as
+/// [`SymlinkMode::write_target`](super::SymlinkMode) says, or an error of
+/// kind [`io::ErrorKind::PermissionDenied`] if, with its directory's
+/// symbolic links resolved, it is outside [`WriteOptions::restrict_to`].
+pub(super) fn write_target(path: &Path, options: &WriteOptions) -> io::Result<PathBuf> {
+    let target = options.symlinks.write_target(path)?;
+    if let Some(root) = &options.restrict_to {
+        let root = canonical::canonicalize(root)?;
+        let dir = match target.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let resolved = canonical::canonicalize(dir)?.join(target.file_name().unwrap_or_default());
+        if !resolved.starts_with(&root) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "refusing to write {} outside {}",
+                    resolved.display(),
+                    root.display()
+                ),
+            ));
+        }
+    }
+    Ok(target)
+}
+
+/// Writes `formatted` to a temporary file next to the file `path` is
+/// written through and replaces it with that (see [`replace_file`]).
+pub(super) fn write_formatted_output(
+    path: &Path,
+    formatted: &[u8],
+    metadata: fs::Metadata,
+    options: &WriteOptions,
+) -> io::Result<()> {
+    let target = write_target(path, options)?;
+
+    // Write to a temporary file first, then rename to preserve metadata
+    let parent_dir = target.parent().unwrap_or_else(|| Path::new("."));
+    let mut temp_file = temp::create_in(parent_dir)?;
+    temp_file.write_all(formatted)?;
+    replace_file(path, &target, temp_file, metadata, options)
+}
+
+/// Replaces `target`, the file `path` is written through, with `temp_file`,
+/// which holds its formatted content and is in the same directory, saving a
+/// backup of `path` first if `options` say so.
+pub(super) fn replace_file(
+    path: &Path,
+    target: &Path,
+    temp_file: tempfile::NamedTempFile,
+    metadata: fs::Metadata,
+    options: &WriteOptions,
+) -> io::Result<()> {
+    if let Some(backup) = &options.backup {
+        backup.write(path)?;
+    }
+    // Locked only now, since on Windows the lock keeps other handles, such
+    // as the backup's, from reading the file
+    let _lock = options
+        .lock_timeout
+        .map(|timeout| file_lock::lock(target, timeout))
+        .transpose()?;
+    ensure_unmodified(target, &metadata)?;
+    let parent_dir = target.parent().unwrap_or_else(|| Path::new("."));
+    temp_file.as_file().sync_all()?;
+
+    // Set permissions and extended attributes (ACLs, Finder metadata)
+    // before persisting
+    let permissions = metadata.permissions();
+    temp_file.as_file().set_permissions(permissions.clone())?;
+    xattrs::copy(target, temp_file.path())?;
+
+    // Atomically replace the original file
+    let persist = || temp_file.persist(target).map(drop).map_err(io::Error::from);
+    if permissions.readonly() {
+        read_only::with_write_access(target, &permissions, persist)?;
+    } else {
+        persist()?;
+    }
+
+    if options.durable {
+        sync_dir(parent_dir)?;
+    }
+    Ok(())
+}
+
+/// Returns an error if the file at `target` no longer has the size and
+/// modification time it had when it was read, as `metadata` says, so that
+/// changes another process made to it meanwhile, such as an editor saving
+/// it, aren't overwritten with the formatted old content.
+fn ensure_unmodified(target: &Path, metadata: &fs::Metadata) -> io::Result<()> {
+    let current = fs::metadata(target)?;
+    if current.len() != metadata.len() || current.modified().ok() != metadata.modified().ok() {
+        return Err(io::Error::other(
+            "file was modified while being formatted and was left as it is; run again to format it",
+        ));
+    }
+    Ok(())
+}
+
+/// Flushes a directory's entries to disk, making a completed rename durable.
+#[cfg(unix)]
+pub(super) fn sync_dir(dir: &Path) -> io::Result<()> {
+    fs::File::open(dir)?.sync_all()
+}
+
+/// Directories cannot be opened as files on Windows, so there is nothing to
+/// sync there.
+#[cfg(not(unix))]
+pub(super) fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use rstest::rstest;
+    use tempfile::TempDir;
+
+    #[cfg(unix)]
+    #[rstest]
+    #[case::inside("tree/a.txt", Ok(()))]
+    #[case::linked_file("tree/link.txt", Err(io::ErrorKind::PermissionDenied))]
+    #[case::linked_dir("tree/linked/b.txt", Err(io::ErrorKind::PermissionDenied))]
+    fn test_write_target_restricted(
+        #[case] path: &str,
+        #[case] expected: Result<(), io::ErrorKind>,
+    ) {
+        let temp_dir = TempDir::new().unwrap();
+        let tree = temp_dir.path().join("tree");
+        let outside = temp_dir.path().join("outside");
+        fs::create_dir_all(&tree).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(tree.join("a.txt"), "a").unwrap();
+        fs::write(outside.join("b.txt"), "b").unwrap();
+        std::os::unix::fs::symlink(outside.join("b.txt"), tree.join("link.txt")).unwrap();
+        std::os::unix::fs::symlink(&outside, tree.join("linked")).unwrap();
+        let options = WriteOptions {
+            restrict_to: Some(tree),
+            ..WriteOptions::default()
+        };
+
+        let result = write_target(&temp_dir.path().join(path), &options);
+
+        assert_eq!(result.map(drop).map_err(|err| err.kind()), expected);
+    }
+
+    #[test]
+    fn test_replace_file_modified_meanwhile() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("test.txt");
+        fs::write(&file_path, "a  ").unwrap();
+        let metadata = fs::metadata(&file_path).unwrap();
+        fs::write(&file_path, "saved by an editor").unwrap();
+        let mut temp_file = temp::create_in(temp_dir.path()).unwrap();
+        temp_file.write_all(b"a\n").unwrap();
+
+        let result = replace_file(
+            &file_path,
+            &file_path,
+            temp_file,
+            metadata,
+            &WriteOptions::default(),
+        )
+        .map_err(|err| err.to_string());
+
+        assert_eq!(
+            (result, fs::read_to_string(&file_path).unwrap()),
+            (
+                Err("file was modified while being formatted and was left as it is; run again to format it".to_string()),
+                "saved by an editor".to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,463 @@
+use super::content::{is_blank, most_common_line_ending, trim_line_end};
+use super::encoding::Encoding;
+use super::quick::Head;
+use super::replace::{replace_file, write_target};
+use super::{FormatResult, SkipReason, Violation, WriteOptions, generated, minified};
+use super::{ReadOnlyPolicy, temp};
+use crate::editorconfig::FormatRules;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Write};
+use std::path::Path;
+
+/// How many bytes to read at a time.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Returns whether files formatted with `rules` can be formatted line by
+/// line: YAML block scalars can't be told apart one line at a time, and
+/// converting the encoding needs the content as a whole.
+pub(super) fn can_stream(rules: &FormatRules) -> bool {
+    !rules.preserve_yaml_block_scalars() && rules.normalize_encoding.is_none()
+}
+
+/// Formats the file at `path` in place like
+/// [`format_file_with_options`](super::format_file_with_options), but line
+/// by line from the file into the temporary file that replaces it, so that
+/// only its start, which tells whether it is skipped, and a few lines are
+/// held in memory.
+///
+/// Returns `None` without formatting the file if it isn't in UTF-8, which
+/// is only known once its start is read. Unless `rules` set `end_of_line`,
+/// the file is read twice, first to find its dominant line ending.
+pub(super) fn format_file(
+    path: &Path,
+    rules: &FormatRules,
+    options: &WriteOptions,
+) -> io::Result<Option<FormatResult>> {
+    let file = fs::File::open(path)?;
+    let metadata = file.metadata()?;
+    let mut reader = BufReader::with_capacity(BUFFER_SIZE, file);
+
+    // Whether the file is skipped can only be told from its start
+    let mut head = Vec::new();
+    let mut head_state = Head::default();
+    while (&mut reader)
+        .take(BUFFER_SIZE as u64)
+        .read_to_end(&mut head)?
+        > 0
+        && !head_state.read(&head, rules)
+    {}
+    if Encoding::detect(&head) != Encoding::Utf8 {
+        return Ok(None);
+    }
+    if generated::has_generated_marker(&head, rules.generated_marker_lines()) {
+        return Ok(Some(FormatResult::Skipped(SkipReason::Generated)));
+    }
+    if rules.skip_minified() && minified::looks_minified(&head) {
+        return Ok(Some(FormatResult::Skipped(SkipReason::Minified)));
+    }
+
+    let line_ending = match rules.end_of_line {
+        Some(end_of_line) => end_of_line.as_str(),
+        None => dominant_line_ending(BufReader::new(fs::File::open(path)?))?,
+    };
+    let target = write_target(path, options)?;
+    let parent_dir = target.parent().unwrap_or_else(|| Path::new("."));
+    let mut temp_file = temp::create_in(parent_dir)?;
+    let mut out = io::BufWriter::new(temp_file.as_file_mut());
+    let violations = match format_lines(
+        Cursor::new(head).chain(reader),
+        rules,
+        line_ending,
+        &mut out,
+    )? {
+        Ok(violations) => violations,
+        Err(reason) => return Ok(Some(FormatResult::Skipped(reason))),
+    };
+    out.flush()?;
+    drop(out);
+
+    // Formatting changes nothing without fixing a violation
+    Ok(Some(if violations.is_empty() {
+        FormatResult::Unchanged
+    } else if metadata.permissions().readonly() && options.read_only == ReadOnlyPolicy::Skip {
+        FormatResult::ReadOnly(violations)
+    } else {
+        replace_file(path, &target, temp_file, metadata, options)?;
+        FormatResult::Changed(violations)
+    }))
+}
+
+/// Returns the line terminator used by most lines read from `reader`, as
+/// the whole-file formatter picks it.
+fn dominant_line_ending(mut reader: impl BufRead) -> io::Result<&'static str> {
+    let (mut crlf, mut lf, mut cr) = (0, 0, 0);
+    let mut previous = None;
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        for &byte in buf {
+            match (previous, byte) {
+                (Some(b'\r'), b'\n') => {
+                    cr -= 1;
+                    crlf += 1;
+                }
+                (_, b'\n') => lf += 1,
+                (_, b'\r') => cr += 1,
+                _ => {}
+            }
+            previous = Some(byte);
+        }
+        let len = buf.len();
+        reader.consume(len);
+    }
+    Ok(most_common_line_ending(crlf, lf, cr))
+}
+
+/// Formats the text read from `reader` as `rules` say, joining lines with
+/// `line_ending` unless `rules` leave line endings alone, and writes it to
+/// `out`, returning the violations fixed.
+///
+/// The output is the same as formatting the text as a whole (see
+/// [`format_content`](super::content::format_content)), but only the lines
+/// that may be dropped depending on what follows, a run of blank lines, are
+/// held back. Text that turns out not to be UTF-8 is binary, in which case
+/// `out` holds only part of it.
+fn format_lines(
+    mut reader: impl BufRead,
+    rules: &FormatRules,
+    line_ending: &'static str,
+    out: &mut impl Write,
+) -> io::Result<Result<Vec<Violation>, SkipReason>> {
+    let mut lines = Lines::new(rules, line_ending);
+    let mut line = Vec::new();
+    while read_line(&mut reader, &mut line)? {
+        let Ok(text) = std::str::from_utf8(&line) else {
+            return Ok(Err(SkipReason::Binary));
+        };
+        let (text, ending) = split_ending(text);
+        lines.push(text, ending, out)?;
+    }
+    lines.finish(out)?;
+    Ok(Ok(lines.violations))
+}
+
+/// Reads the next line of `reader` into `line`, with the `\n`, `\r\n`, or
+/// lone `\r` that ends it, and returns whether there was one.
+fn read_line(reader: &mut impl BufRead, line: &mut Vec<u8>) -> io::Result<bool> {
+    line.clear();
+    loop {
+        let buf = reader.fill_buf()?;
+        if line.last() == Some(&b'\r') {
+            if buf.first() == Some(&b'\n') {
+                line.push(b'\n');
+                reader.consume(1);
+            }
+            return Ok(true);
+        }
+        if buf.is_empty() {
+            return Ok(!line.is_empty());
+        }
+        let end = buf.iter().position(|&byte| byte == b'\n' || byte == b'\r');
+        let len = end.map_or(buf.len(), |end| end + 1);
+        line.extend_from_slice(&buf[..len]);
+        reader.consume(len);
+        if line.last() == Some(&b'\n') {
+            return Ok(true);
+        }
+    }
+}
+
+/// Splits the terminator off the end of `line`.
+fn split_ending(line: &str) -> (&str, &'static str) {
+    if let Some(text) = line.strip_suffix("\r\n") {
+        (text, "\r\n")
+    } else if let Some(text) = line.strip_suffix('\n') {
+        (text, "\n")
+    } else if let Some(text) = line.strip_suffix('\r') {
+        (text, "\r")
+    } else {
+        (line, "")
+    }
+}
+
+/// The state of formatting a file line by line.
+struct Lines<'a> {
+    rules: &'a FormatRules,
+    line_ending: &'static str,
+    trim: bool,
+    violations: Vec<Violation>,
+    /// How many lines were read
+    number: usize,
+    /// How many blank lines the file starts with, while only blank lines
+    /// were read and leading blank lines are removed
+    leading: Option<usize>,
+    /// The blank lines since the last line with text, which are dropped if
+    /// no text follows
+    pending: Vec<(usize, String, &'static str)>,
+    blank_run: usize,
+    /// The first blank line beyond `max_blank_lines` in the current run of
+    /// blank lines, and how many there are
+    excess: Option<(usize, usize)>,
+    /// The number and terminator of the last line written, whose terminator
+    /// is written once it is known not to be the last line
+    last: Option<(usize, &'static str)>,
+}
+
+impl<'a> Lines<'a> {
+    fn new(rules: &'a FormatRules, line_ending: &'static str) -> Self {
+        Self {
+            rules,
+            line_ending,
+            trim: rules.remove_trailing_spaces(),
+            violations: Vec::new(),
+            number: 0,
+            leading: rules.remove_leading_newlines().then_some(0),
+            pending: Vec::new(),
+            blank_run: 0,
+            excess: None,
+            last: None,
+        }
+    }
+
+    /// Whether no rule but `end_of_line` is enabled, which only replaces
+    /// line terminators.
+    fn only_line_endings(&self) -> bool {
+        !self.rules.remove_leading_newlines()
+            && !self.trim
+            && !self.rules.ensure_final_newline()
+            && self.rules.max_blank_lines().is_none()
+    }
+
+    /// Formats the line `text` ended by `ending`.
+    fn push(&mut self, text: &str, ending: &'static str, out: &mut impl Write) -> io::Result<()> {
+        self.number += 1;
+        if self.only_line_endings() {
+            out.write_all(text.as_bytes())?;
+            let Some(end_of_line) = self.rules.end_of_line.filter(|_| !ending.is_empty()) else {
+                return out.write_all(ending.as_bytes());
+            };
+            if ending != end_of_line.as_str() {
+                let line = self.number;
+                self.violations
+                    .push(Violation::InconsistentLineEnding { line });
+            }
+            return out.write_all(end_of_line.as_str().as_bytes());
+        }
+
+        if let Some(count) = self.leading {
+            if text.is_empty() || (self.trim && is_blank(text)) {
+                self.leading = Some(count + 1);
+                return Ok(());
+            }
+            self.leading = None;
+            if count > 0 {
+                self.violations.push(Violation::LeadingBlankLines { count });
+            }
+        }
+        if is_blank(text) {
+            self.pending.push((self.number, text.to_string(), ending));
+            return Ok(());
+        }
+
+        // Text follows the blank lines held back, so they are kept unless
+        // beyond `max_blank_lines`
+        for (number, text, ending) in std::mem::take(&mut self.pending) {
+            if let Some(max) = self.rules.max_blank_lines() {
+                self.blank_run += 1;
+                if self.blank_run > max {
+                    let (_, count) = self.excess.get_or_insert((number, 0));
+                    *count += 1;
+                    continue;
+                }
+            }
+            self.write(number, &text, ending, out)?;
+        }
+        self.blank_run = 0;
+        self.write(self.number, text, ending, out)
+    }
+
+    /// Writes the line `text` ended by `ending`, which is kept, and the
+    /// terminator of the line before it.
+    fn write(
+        &mut self,
+        number: usize,
+        text: &str,
+        ending: &'static str,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        if let Some((line, ending)) = self.last.take() {
+            out.write_all(self.line_ending.as_bytes())?;
+            if ending != self.line_ending {
+                self.violations
+                    .push(Violation::InconsistentLineEnding { line });
+            }
+        }
+        if let Some((line, count)) = self.excess.take() {
+            self.violations
+                .push(Violation::ExcessBlankLines { line, count });
+        }
+        let trimmed = if self.trim {
+            trim_line_end(text, self.rules.preserve_markdown_hard_breaks())
+        } else {
+            text
+        };
+        if trimmed.len() < text.len() {
+            self.violations.push(Violation::TrailingWhitespace {
+                line: number,
+                column: trimmed.chars().count() + 1,
+            });
+        }
+        out.write_all(trimmed.as_bytes())?;
+        self.last = Some((number, ending));
+        Ok(())
+    }
+
+    /// Finishes the file once every line is read: drops the blank lines
+    /// held back, but whitespace-only lines that aren't trimmed, and ends
+    /// the last line as `rules` say.
+    fn finish(&mut self, out: &mut impl Write) -> io::Result<()> {
+        if self.only_line_endings() {
+            return Ok(());
+        }
+        if let Some(count) = self.leading.filter(|count| *count > 0) {
+            self.violations.push(Violation::LeadingBlankLines { count });
+        }
+        let pending = std::mem::take(&mut self.pending);
+        let kept = if self.trim {
+            0
+        } else {
+            pending
+                .iter()
+                .rposition(|(_, text, _)| !text.is_empty())
+                .map_or(0, |index| index + 1)
+        };
+        for (number, text, ending) in &pending[..kept] {
+            self.write(*number, text, ending, out)?;
+        }
+        if let Some((line, _, _)) = pending.get(kept) {
+            let count = pending.len() - kept;
+            self.violations
+                .push(Violation::TrailingBlankLines { line: *line, count });
+        }
+
+        let Some((line, ending)) = self.last else {
+            return Ok(());
+        };
+        if self.rules.ensure_final_newline() {
+            out.write_all(self.line_ending.as_bytes())?;
+            if ending.is_empty() {
+                self.violations.push(Violation::MissingFinalNewline);
+            } else if ending != self.line_ending {
+                self.violations
+                    .push(Violation::InconsistentLineEnding { line });
+            }
+        } else if !ending.is_empty() {
+            self.violations.push(Violation::UnexpectedFinalNewline);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editorconfig::LineEnding;
+    use crate::format::content::{FormattedContent, format_content};
+    use rstest::rstest;
+    use tempfile::TempDir;
+
+    #[rstest]
+    #[case::formatted("a\nb\n", FormatRules::default())] // ast-grep-ignore: prefer-indoc
+    #[case::empty("", FormatRules::default())]
+    #[case::only_blank_lines("\n \n\n", FormatRules::default())]
+    #[case::leading_and_trailing_blank_lines("\n\na  \n\n \n", FormatRules::default())]
+    #[case::untrimmed_whitespace_lines(
+        "\n \na\n\n \n\n",
+        FormatRules { remove_trailing_spaces: Some(false), ..FormatRules::default() }
+    )]
+    #[case::excess_blank_lines(
+        "a\n\n \n\n\nb\n\n\n",
+        FormatRules { max_blank_lines: Some(1), ..FormatRules::default() }
+    )]
+    #[case::dominant_line_ending("a\r\nb\nc\r\nd", FormatRules::default())] // ast-grep-ignore: prefer-indoc
+    #[case::lone_cr("a\rb\r\rc", FormatRules::default())]
+    #[case::end_of_line(
+        "a\nb\r\nc\r\n", // ast-grep-ignore: prefer-indoc
+        FormatRules { end_of_line: Some(LineEnding::Crlf), ..FormatRules::default() }
+    )]
+    #[case::only_line_endings(
+        "\na \r\nb\n\n", // ast-grep-ignore: prefer-indoc
+        FormatRules {
+            ensure_final_newline: Some(false),
+            remove_trailing_spaces: Some(false),
+            remove_leading_newlines: Some(false),
+            end_of_line: Some(LineEnding::Lf),
+            ..FormatRules::default()
+        }
+    )]
+    #[case::unexpected_final_newline(
+        "a\n\n", // ast-grep-ignore: prefer-indoc
+        FormatRules { ensure_final_newline: Some(false), ..FormatRules::default() }
+    )]
+    #[case::markdown_hard_break(
+        "a  \nb \n", // ast-grep-ignore: prefer-indoc
+        FormatRules { preserve_markdown_hard_breaks: Some(true), ..FormatRules::default() }
+    )]
+    fn test_format_lines_matches_whole_file(#[case] input: &str, #[case] rules: FormatRules) {
+        let line_ending = match rules.end_of_line {
+            Some(end_of_line) => end_of_line.as_str(),
+            None => dominant_line_ending(input.as_bytes()).unwrap(),
+        };
+        // Reading a byte at a time splits every `\r\n` across reads
+        let reader = BufReader::with_capacity(1, input.as_bytes());
+        let mut out = Vec::new();
+
+        let violations = format_lines(reader, &rules, line_ending, &mut out)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            FormattedContent {
+                content: String::from_utf8(out).unwrap(),
+                violations,
+            },
+            format_content(input, &rules)
+        );
+    }
+
+    #[rstest]
+    #[case::formatted(b"a\n", Some(FormatResult::Unchanged), b"a\n")]
+    #[case::changed(
+        b"a \n",
+        Some(FormatResult::Changed(vec![Violation::TrailingWhitespace { line: 1, column: 2 }])),
+        b"a\n"
+    )]
+    #[case::binary(
+        b"a \n\xff\n", // ast-grep-ignore: prefer-indoc
+        Some(FormatResult::Skipped(SkipReason::Binary)),
+        b"a \n\xff\n" // ast-grep-ignore: prefer-indoc
+    )]
+    #[case::utf16(b"\xff\xfea\x00", None, b"\xff\xfea\x00")]
+    fn test_format_file(
+        #[case] content: &[u8],
+        #[case] expected: Option<FormatResult>,
+        #[case] expected_content: &[u8],
+    ) {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("a.txt");
+        fs::write(&path, content).unwrap();
+
+        let result = format_file(&path, &FormatRules::default(), &WriteOptions::default()).unwrap();
+
+        assert_eq!(
+            (
+                result,
+                fs::read(&path).unwrap(),
+                fs::read_dir(temp_dir.path()).unwrap().count()
+            ),
+            (expected, expected_content.to_vec(), 1)
+        );
+    }
+}
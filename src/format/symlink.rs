@@ -0,0 +1,26 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How to write a formatted file whose path is a symbolic link.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SymlinkMode {
+    /// Resolve the link and rewrite the file it points to, keeping the link
+    /// itself intact.
+    #[default]
+    Follow,
+    /// Replace the link with a regular file containing the formatted content.
+    Replace,
+}
+
+impl SymlinkMode {
+    /// Returns the path the formatted content of `path` should be written to.
+    pub(crate) fn write_target(self, path: &Path) -> io::Result<PathBuf> {
+        match self {
+            SymlinkMode::Follow if fs::symlink_metadata(path)?.file_type().is_symlink() => {
+                fs::canonicalize(path)
+            }
+            SymlinkMode::Follow | SymlinkMode::Replace => Ok(path.to_path_buf()),
+        }
+    }
+}
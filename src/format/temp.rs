@@ -0,0 +1,54 @@
+// temporary files module
+// This module names the temporary files formatted content is written to
+// before it replaces the original. The names are recognizable so that files
+// orphaned by a crashed or killed run can be found and removed later by
+// `basefmt clean`.
+
+use std::ffi::OsStr;
+use std::io;
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+const PREFIX: &str = ".basefmt-";
+const SUFFIX: &str = ".tmp";
+
+/// Creates a uniquely named temporary file in `dir`.
+pub(crate) fn create_in(dir: &Path) -> io::Result<NamedTempFile> {
+    tempfile::Builder::new()
+        .prefix(PREFIX)
+        .suffix(SUFFIX)
+        .tempfile_in(dir)
+}
+
+/// Returns whether `file_name` looks like a file created by [`create_in`].
+pub(crate) fn is_temp_file_name(file_name: &OsStr) -> bool {
+    file_name
+        .to_str()
+        .and_then(|name| name.strip_prefix(PREFIX))
+        .and_then(|rest| rest.strip_suffix(SUFFIX))
+        .is_some_and(|random| !random.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_created_files_are_recognized() {
+        let temp_dir = TempDir::new().unwrap();
+        let temp_file = create_in(temp_dir.path()).unwrap();
+
+        assert!(is_temp_file_name(temp_file.path().file_name().unwrap()));
+    }
+
+    #[rstest]
+    #[case::bare_prefix_and_suffix(".basefmt-.tmp")]
+    #[case::other_prefix(".tmpA1b2C3")]
+    #[case::other_suffix(".basefmt-A1b2C3.txt")]
+    #[case::regular_file("notes.tmp")]
+    fn test_other_names_are_not_recognized(#[case] name: &str) {
+        assert!(!is_temp_file_name(OsStr::new(name)));
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt;
+
+/// A single formatting problem found in a file's content.
+///
+/// Lines and columns are 1-based; columns count characters, not bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// Blank lines at the start of the file
+    LeadingBlankLines { count: usize },
+    /// Whitespace at the end of a line, starting at `column`
+    TrailingWhitespace { line: usize, column: usize },
+    /// A line terminator that differs from the configured line ending, or
+    /// from the file's dominant one if none is configured
+    InconsistentLineEnding { line: usize },
+    /// Blank lines beyond the allowed number of consecutive ones, starting
+    /// at `line`
+    ExcessBlankLines { line: usize, count: usize },
+    /// Blank lines at the end of the file, starting at `line`
+    TrailingBlankLines { line: usize, count: usize },
+    /// The file does not end with a newline
+    MissingFinalNewline,
+    /// The file ends with a newline although final newlines are disabled
+    UnexpectedFinalNewline,
+    /// The file is in `encoding` rather than UTF-8, which it is to be
+    /// converted to
+    NotUtf8 { encoding: &'static str },
+}
+
+impl Violation {
+    /// Returns a stable `snake_case` identifier for the kind of violation,
+    /// suitable for machine-readable output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Violation::LeadingBlankLines { .. } => "leading_blank_lines",
+            Violation::TrailingWhitespace { .. } => "trailing_whitespace",
+            Violation::InconsistentLineEnding { .. } => "inconsistent_line_ending",
+            Violation::ExcessBlankLines { .. } => "excess_blank_lines",
+            Violation::TrailingBlankLines { .. } => "trailing_blank_lines",
+            Violation::MissingFinalNewline => "missing_final_newline",
+            Violation::UnexpectedFinalNewline => "unexpected_final_newline",
+            Violation::NotUtf8 { .. } => "not_utf8",
+        }
+    }
+
+    /// Returns the line the violation starts on, if it points at a specific
+    /// line rather than the whole file.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Violation::LeadingBlankLines { .. } => Some(1),
+            Violation::TrailingWhitespace { line, .. }
+            | Violation::InconsistentLineEnding { line }
+            | Violation::ExcessBlankLines { line, .. }
+            | Violation::TrailingBlankLines { line, .. } => Some(*line),
+            Violation::MissingFinalNewline
+            | Violation::UnexpectedFinalNewline
+            | Violation::NotUtf8 { .. } => None,
+        }
+    }
+
+    /// Returns the column the violation starts at, if it points at a
+    /// specific character.
+    pub fn column(&self) -> Option<usize> {
+        match self {
+            Violation::TrailingWhitespace { column, .. } => Some(*column),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = |count: usize| if count == 1 { "" } else { "s" };
+        match self {
+            Violation::LeadingBlankLines { count } => {
+                write!(f, "{count} leading blank line{}", plural(*count))
+            }
+            Violation::TrailingWhitespace { .. } => f.write_str("trailing whitespace"),
+            Violation::InconsistentLineEnding { .. } => f.write_str("inconsistent line ending"),
+            Violation::ExcessBlankLines { count, .. } => {
+                write!(f, "{count} excess blank line{}", plural(*count))
+            }
+            Violation::TrailingBlankLines { count, .. } => {
+                write!(f, "{count} trailing blank line{}", plural(*count))
+            }
+            Violation::MissingFinalNewline => f.write_str("missing final newline"),
+            Violation::UnexpectedFinalNewline => f.write_str("unexpected final newline"),
+            Violation::NotUtf8 { encoding } => write!(f, "encoded in {encoding}, not UTF-8"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::leading_single(Violation::LeadingBlankLines { count: 1 }, "1 leading blank line")]
+    #[case::leading_plural(Violation::LeadingBlankLines { count: 3 }, "3 leading blank lines")]
+    #[case::trailing_whitespace(
+        Violation::TrailingWhitespace { line: 12, column: 5 },
+        "trailing whitespace"
+    )]
+    #[case::trailing_blank_lines(
+        Violation::TrailingBlankLines { line: 4, count: 2 },
+        "2 trailing blank lines"
+    )]
+    #[case::excess_blank_lines(
+        Violation::ExcessBlankLines { line: 7, count: 1 },
+        "1 excess blank line"
+    )]
+    #[case::missing_final_newline(Violation::MissingFinalNewline, "missing final newline")]
+    #[case::not_utf8(
+        Violation::NotUtf8 { encoding: "Windows-1252" },
+        "encoded in Windows-1252, not UTF-8"
+    )]
+    fn test_display(#[case] violation: Violation, #[case] expected: &str) {
+        assert_eq!(violation.to_string(), expected);
+    }
+}
@@ -0,0 +1,45 @@
+// extended attributes module
+// This module copies extended attributes between files. Replacing a file
+// through a temporary file and rename creates a brand-new inode, so anything
+// stored as an extended attribute on the original (POSIX ACLs, macOS Finder
+// metadata, user annotations) has to be copied over explicitly.
+
+use std::io;
+use std::path::Path;
+
+/// Copies every extended attribute of `src` onto `dst`.
+///
+/// Filesystems without extended attribute support are treated as having no
+/// attributes. Attributes in privileged namespaces (`trusted.*`, and
+/// `security.*` without the matching capability) cannot be written by
+/// unprivileged users and are skipped; the new file already receives the
+/// directory's default security label.
+#[cfg(unix)]
+pub(crate) fn copy(src: &Path, dst: &Path) -> io::Result<()> {
+    let names = match xattr::list(src) {
+        Ok(names) => names,
+        Err(err) if err.kind() == io::ErrorKind::Unsupported => return Ok(()),
+        Err(err) => return Err(err),
+    };
+
+    for name in names {
+        let Some(value) = xattr::get(src, &name)? else {
+            continue;
+        };
+        match xattr::set(dst, &name, &value) {
+            Ok(()) => {}
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::Unsupported | io::ErrorKind::PermissionDenied
+                ) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn copy(_src: &Path, _dst: &Path) -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,135 @@
+/// Returns, for each of `lines`, whether it is in the body of a YAML block
+/// scalar (a `|` or `>` value), whose trailing whitespace and blank lines
+/// are part of the value.
+///
+/// Only as much YAML is parsed as finding block scalars takes: a line whose
+/// value is a block scalar indicator, such as `key: |` or `- >-`, starts
+/// one, and its body is every following line that is blank or indented more
+/// than that line.
+pub(super) fn block_scalar_body_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<bool> {
+    let mut body = Vec::new();
+    // Indentation of the line that started the block scalar we're in
+    let mut parent_indent = None;
+    for line in lines {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        if parent_indent.is_some_and(|parent| line.trim().is_empty() || indent > parent) {
+            body.push(true);
+            continue;
+        }
+        body.push(false);
+        parent_indent = starts_block_scalar(line).then_some(indent);
+    }
+    body
+}
+
+/// Whether the value on `line` is a block scalar indicator, optionally
+/// preceded by a tag or anchor and followed by a comment.
+fn starts_block_scalar(line: &str) -> bool {
+    let mut tokens: Vec<&str> = strip_comment(line).split_whitespace().collect();
+    if !tokens.pop().is_some_and(is_indicator) {
+        return false;
+    }
+    while tokens
+        .last()
+        .is_some_and(|token| token.starts_with(['!', '&']))
+    {
+        tokens.pop();
+    }
+    tokens
+        .last()
+        .is_some_and(|token| token.ends_with(':') || matches!(*token, "-" | "?" | "---"))
+}
+
+/// Returns `line` without a `#` comment, which starts at a `#` at the start
+/// of the line or after whitespace.
+fn strip_comment(line: &str) -> &str {
+    let mut previous = ' ';
+    for (index, c) in line.char_indices() {
+        if c == '#' && previous.is_whitespace() {
+            return &line[..index];
+        }
+        previous = c;
+    }
+    line
+}
+
+/// Whether `token` is `|` or `>` followed by an optional chomping indicator
+/// (`+` or `-`) and indentation indicator (`1` to `9`) in either order.
+fn is_indicator(token: &str) -> bool {
+    let Some(rest) = token.strip_prefix(['|', '>']) else {
+        return false;
+    };
+    let chomping = rest.chars().filter(|c| matches!(c, '+' | '-')).count();
+    let indentation = rest.chars().filter(|c| matches!(c, '1'..='9')).count();
+    chomping <= 1 && indentation <= 1 && chomping + indentation == rest.chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::literal(
+        indoc! {"
+            script: |
+              echo a
+
+              echo b
+            name: x
+        "},
+        vec![false, true, true, true, false]
+    )]
+    #[case::folded_with_indicators_and_comment(
+        indoc! {"
+            - run: >-2 # folded
+                text
+            - run: done
+        "},
+        vec![false, true, false]
+    )]
+    #[case::sequence_entry(
+        indoc! {"
+            items:
+              - |
+                body
+              - plain
+        "},
+        vec![false, false, true, false]
+    )]
+    #[case::tag_and_anchor(
+        indoc! {"
+            key: !!str &anchor |
+              body
+        "},
+        vec![false, true]
+    )]
+    #[case::nested_indicator_is_body(
+        indoc! {"
+            outer: |
+              inner: |
+              text
+            next: 1
+        "},
+        vec![false, true, true, false]
+    )]
+    #[case::plain_value_with_pipe(
+        indoc! {"
+            key: a |
+              b
+        "},
+        vec![false, false]
+    )]
+    #[case::commented_out(
+        indoc! {"
+            # key: |
+              b
+        "},
+        vec![false, false]
+    )]
+    #[case::quoted_pipe("key: \"|\"\n  b\n", vec![false, false])]
+    fn test_block_scalar_body_lines(#[case] content: &str, #[case] expected: Vec<bool>) {
+        assert_eq!(block_scalar_body_lines(content.lines()), expected);
+    }
+}
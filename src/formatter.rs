@@ -0,0 +1,262 @@
+use crate::canonical;
+use crate::config::Config;
+use crate::editorconfig::{EditorConfigCache, FormatRules};
+use crate::format::{
+    self, CheckResult, FormatResult, Formatted, SkipReason, WriteOptions, check_file_with_rules,
+    format_file_with_options,
+};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// A reusable formatter configured in code rather than through
+/// `.basefmt.toml`, for tools that embed basefmt instead of running the
+/// binary.
+///
+/// Each `.editorconfig` is read once and cached for the formatter's
+/// lifetime, as within a single run of the binary; build a new formatter to
+/// pick up changes to them.
+///
+/// # Examples
+///
+/// ```no_run
+/// use basefmt::editorconfig::FormatRules;
+/// use basefmt::formatter::Formatter;
+/// use std::path::Path;
+///
+/// let formatter = Formatter::builder()
+///     .rules(FormatRules {
+///         remove_leading_newlines: Some(false),
+///         ..FormatRules::default()
+///     })
+///     .exclude("vendor/**")
+///     .build()
+///     .unwrap();
+/// formatter.format_path(Path::new("README.md")).unwrap();
+/// ```
+pub struct Formatter {
+    rules: FormatRules,
+    config: Config,
+    write: WriteOptions,
+    rule_cache: EditorConfigCache,
+}
+
+/// Builder for [`Formatter`].
+#[derive(Debug, Default)]
+pub struct FormatterBuilder {
+    rules: FormatRules,
+    exclude: Vec<String>,
+    write: WriteOptions,
+}
+
+impl FormatterBuilder {
+    /// Applies every rule set in `rules` to every file, overriding what
+    /// EditorConfig says about it. Rules left unset are still looked up in
+    /// EditorConfig.
+    pub fn rules(mut self, rules: FormatRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Skips files whose path matches the glob `pattern`.
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Sets how formatted files are written back to disk.
+    pub fn write_options(mut self, write: WriteOptions) -> Self {
+        self.write = write;
+        self
+    }
+
+    /// Builds the formatter.
+    ///
+    /// Returns an error if any exclude pattern is not a valid glob.
+    pub fn build(self) -> io::Result<Formatter> {
+        Ok(Formatter {
+            rules: self.rules,
+            config: Config::with_exclude(self.exclude)?,
+            write: self.write,
+            rule_cache: EditorConfigCache::new(),
+        })
+    }
+}
+
+impl Formatter {
+    /// Returns a builder with no exclusions, no rule overrides, and the
+    /// default write options.
+    pub fn builder() -> FormatterBuilder {
+        FormatterBuilder::default()
+    }
+
+    /// Formats the file at `path` in place.
+    ///
+    /// Exclude patterns are matched against `path` as given; excluded files
+    /// are reported as skipped with [`SkipReason::Excluded`] without being
+    /// read.
+    pub fn format_path(&self, path: &Path) -> io::Result<FormatResult> {
+        if self.config.is_excluded(path) {
+            return Ok(FormatResult::Skipped(SkipReason::Excluded));
+        }
+        format_file_with_options(path, &self.rules_for(path), &self.write)
+    }
+
+    /// Checks the file at `path` without modifying it.
+    ///
+    /// Excluded files are reported as [`CheckResult::Skipped`], as in
+    /// [`Formatter::format_path`].
+    pub fn check_path(&self, path: &Path) -> io::Result<CheckResult> {
+        if self.config.is_excluded(path) {
+            return Ok(CheckResult::Skipped(SkipReason::Excluded));
+        }
+        check_file_with_rules(path, &self.rules_for(path))
+    }
+
+    /// Formats an in-memory string with the configured rules, using the
+    /// defaults for rules left unset, since there is no path to look up in
+    /// EditorConfig.
+    pub fn format_str(&self, content: &str) -> Formatted<String> {
+        format::format_str(content, &self.rules)
+    }
+
+    fn rules_for(&self, path: &Path) -> FormatRules {
+        canonical::canonicalize(path)
+            .map(|resolved| self.rule_cache.rules_for(&resolved))
+            .unwrap_or_default()
+            .with_overrides(&self.rules)
+    }
+}
+
+impl fmt::Debug for Formatter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Formatter")
+            .field("rules", &self.rules)
+            .field("config", &self.config)
+            .field("write", &self.write)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn rules_without_final_newline() -> FormatRules {
+        FormatRules {
+            ensure_final_newline: Some(false),
+            ..FormatRules::default()
+        }
+    }
+
+    #[test]
+    fn test_format_path_layers_explicit_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        // The explicit rule takes precedence over insert_final_newline, while
+        // trim_trailing_whitespace still applies
+        fs::write(
+            temp_dir.path().join(".editorconfig"),
+            indoc! {"
+                root = true
+
+                [*]
+                insert_final_newline = true
+                trim_trailing_whitespace = false
+            "},
+        )
+        .unwrap();
+        let file_path = temp_dir.path().join("test.txt");
+        fs::write(&file_path, "content  \n").unwrap();
+        let formatter = Formatter::builder()
+            .rules(rules_without_final_newline())
+            .build()
+            .unwrap();
+
+        let result = formatter.format_path(&file_path).unwrap();
+
+        assert_eq!(
+            (result, fs::read_to_string(&file_path).unwrap()),
+            (
+                FormatResult::Changed(vec![format::Violation::UnexpectedFinalNewline]),
+                "content  ".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_format_path_reads_each_editorconfig_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let editorconfig = temp_dir.path().join(".editorconfig");
+        fs::write(
+            &editorconfig,
+            indoc! {"
+                root = true
+
+                [*]
+                trim_trailing_whitespace = false
+            "},
+        )
+        .unwrap();
+        let first = temp_dir.path().join("a.txt");
+        let second = temp_dir.path().join("b.txt");
+        fs::write(&first, "content  \n").unwrap();
+        fs::write(&second, "content  \n").unwrap();
+        let formatter = Formatter::builder().build().unwrap();
+
+        formatter.format_path(&first).unwrap();
+        // Without the cache, the second file would get the default rules
+        fs::remove_file(&editorconfig).unwrap();
+        let result = formatter.format_path(&second).unwrap();
+
+        assert_eq!(
+            (result, fs::read_to_string(&second).unwrap()),
+            (FormatResult::Unchanged, "content  \n".to_string())
+        );
+    }
+
+    #[test]
+    fn test_excluded_paths_are_skipped() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("test.min.js");
+        fs::write(&file_path, "content  ").unwrap();
+        let formatter = Formatter::builder().exclude("**/*.min.*").build().unwrap();
+
+        assert_eq!(
+            (
+                formatter.check_path(&file_path).unwrap(),
+                formatter.format_path(&file_path).unwrap(),
+                fs::read_to_string(&file_path).unwrap(),
+            ),
+            (
+                CheckResult::Skipped(SkipReason::Excluded),
+                FormatResult::Skipped(SkipReason::Excluded),
+                "content  ".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_build_rejects_invalid_exclude_pattern() {
+        let err = Formatter::builder().exclude("[").build().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_format_str_uses_explicit_rules() {
+        let formatter = Formatter::builder()
+            .rules(rules_without_final_newline())
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            formatter.format_str("content  \n"),
+            Formatted {
+                content: "content".to_string(),
+                changed: true,
+            }
+        );
+    }
+}
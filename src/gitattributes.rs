@@ -0,0 +1,331 @@
+// gitattributes integration module
+// This module reads `.gitattributes` files to find out how git converts the
+// line endings of a file, so that basefmt doesn't undo what git does on
+// checkout.
+
+use crate::editorconfig::LineEnding;
+use crate::filesystem::{self, FileSystem, RealFileSystem};
+use globset::{GlobBuilder, GlobMatcher};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// What `.gitattributes` says about the line endings of a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GitLineEndings {
+    /// Nothing, or that the file isn't text (`-text`, `binary`), so git
+    /// leaves its line endings alone
+    Unspecified,
+    /// `eol=lf` or `eol=crlf`: git checks the file out with this line ending
+    Eol(LineEnding),
+    /// `text` or `text=auto` without `eol`: git converts line endings as
+    /// `core.eol` and `core.autocrlf` say, so the file's own are right
+    Native,
+}
+
+/// Looks up the `.gitattributes` files that apply to each file, caching
+/// them per directory.
+///
+/// Every directory from the file's up to the repository root (the first one
+/// with a `.git` entry) is searched; deeper files take precedence, as do
+/// later lines within a file.
+pub struct GitAttributesCache {
+    fs: Arc<dyn FileSystem>,
+    dir_stacks: RwLock<HashMap<PathBuf, AttributesStack>>,
+}
+
+/// The parsed `.gitattributes` files that apply to a directory, outermost
+/// first.
+type AttributesStack = Arc<Vec<Arc<AttributesFile>>>;
+
+impl Default for GitAttributesCache {
+    fn default() -> Self {
+        Self::with_file_system(Arc::new(RealFileSystem))
+    }
+}
+
+impl GitAttributesCache {
+    /// Creates an empty cache that reads `.gitattributes` files from disk.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty cache that reads `.gitattributes` files through
+    /// `fs`.
+    pub fn with_file_system(fs: Arc<dyn FileSystem>) -> Self {
+        Self {
+            fs,
+            dir_stacks: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Returns how git treats the line endings of the file at the given
+    /// canonical path.
+    pub fn line_endings_for(&self, canonical_path: &Path) -> GitLineEndings {
+        let mut attributes = Attributes::default();
+        if let Some(parent) = canonical_path.parent() {
+            for file in self.stack_for_dir(parent).iter() {
+                file.apply_to(&mut attributes, canonical_path);
+            }
+        }
+        match attributes {
+            Attributes {
+                text: Some(Text::Unset),
+                ..
+            } => GitLineEndings::Unspecified,
+            Attributes { eol: Some(eol), .. } => GitLineEndings::Eol(eol),
+            Attributes { text: Some(_), .. } => GitLineEndings::Native,
+            Attributes { text: None, .. } => GitLineEndings::Unspecified,
+        }
+    }
+
+    fn stack_for_dir(&self, dir: &Path) -> AttributesStack {
+        let cached = self
+            .dir_stacks
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(dir)
+            .cloned();
+        if let Some(stack) = cached {
+            return stack;
+        }
+
+        let parent = dir
+            .parent()
+            .filter(|_| !filesystem::has_git_entry(self.fs.as_ref(), dir));
+        let mut combined = if let Some(parent) = parent {
+            self.stack_for_dir(parent).as_ref().clone()
+        } else {
+            Vec::new()
+        };
+        if let Some(file) = self.load_attributes_for_dir(dir) {
+            combined.push(file);
+        }
+
+        Arc::clone(
+            self.dir_stacks
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| Arc::new(combined)),
+        )
+    }
+
+    fn load_attributes_for_dir(&self, dir: &Path) -> Option<Arc<AttributesFile>> {
+        let path = dir.join(".gitattributes");
+        match self.fs.read(&path) {
+            Ok(content) => Some(Arc::new(AttributesFile::parse(
+                dir,
+                &String::from_utf8_lossy(&content),
+            ))),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => {
+                eprintln!("{}: failed to read .gitattributes: {}", path.display(), err);
+                None
+            }
+        }
+    }
+}
+
+/// The `text` attribute of a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Text {
+    /// `text`
+    Set,
+    /// `-text`, or the `binary` macro
+    Unset,
+    /// `text=auto`
+    Auto,
+}
+
+/// The attributes basefmt reads, as far as the lines matching a file set
+/// them; `None` if unspecified.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Attributes {
+    text: Option<Text>,
+    eol: Option<LineEnding>,
+}
+
+/// A single attribute set by a line, or reset to unspecified if `None`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Assignment {
+    Text(Option<Text>),
+    Eol(Option<LineEnding>),
+}
+
+impl Assignment {
+    /// Parses an attribute such as `text`, `-text`, `!eol`, or `eol=crlf`,
+    /// returning `None` for attributes basefmt doesn't read.
+    fn parse(attribute: &str) -> Option<Self> {
+        let assignment = match attribute {
+            "text" => Assignment::Text(Some(Text::Set)),
+            "-text" | "binary" => Assignment::Text(Some(Text::Unset)),
+            "text=auto" => Assignment::Text(Some(Text::Auto)),
+            "eol=lf" => Assignment::Eol(Some(LineEnding::Lf)),
+            "eol=crlf" => Assignment::Eol(Some(LineEnding::Crlf)),
+            _ if attribute.trim_start_matches(['-', '!']).starts_with("text") => {
+                Assignment::Text(None)
+            }
+            _ if attribute.trim_start_matches(['-', '!']).starts_with("eol") => {
+                Assignment::Eol(None)
+            }
+            _ => return None,
+        };
+        Some(assignment)
+    }
+
+    fn apply_to(self, attributes: &mut Attributes) {
+        match self {
+            Assignment::Text(text) => attributes.text = text,
+            Assignment::Eol(eol) => attributes.eol = eol,
+        }
+    }
+}
+
+/// A parsed `.gitattributes` file.
+#[derive(Debug)]
+struct AttributesFile {
+    /// The directory containing the file, which patterns are relative to
+    dir: PathBuf,
+    lines: Vec<(GlobMatcher, Vec<Assignment>)>,
+}
+
+impl AttributesFile {
+    /// Parses the lines of a `.gitattributes` file in `dir`.
+    ///
+    /// Lines basefmt can't use are ignored, as git ignores lines it can't
+    /// parse: comments, macro definitions, quoted and negative patterns,
+    /// patterns that only match directories, and lines without any attribute
+    /// basefmt reads.
+    fn parse(dir: &Path, content: &str) -> Self {
+        let lines = content
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let pattern = fields.next()?;
+                if pattern.starts_with(['#', '"', '!']) || pattern.starts_with("[attr]") {
+                    return None;
+                }
+                let assignments: Vec<_> = fields.filter_map(Assignment::parse).collect();
+                if assignments.is_empty() {
+                    return None;
+                }
+                Some((matcher(pattern)?, assignments))
+            })
+            .collect();
+        Self {
+            dir: dir.to_path_buf(),
+            lines,
+        }
+    }
+
+    fn apply_to(&self, attributes: &mut Attributes, file_path: &Path) {
+        let rel_path = file_path.strip_prefix(&self.dir).unwrap_or(file_path);
+        for (matcher, assignments) in &self.lines {
+            if matcher.is_match(rel_path) {
+                for assignment in assignments {
+                    assignment.apply_to(attributes);
+                }
+            }
+        }
+    }
+}
+
+/// Builds a matcher for a pattern, which is matched against the path
+/// relative to the `.gitattributes` file if it contains a slash and against
+/// the file name in any directory below otherwise.
+fn matcher(pattern: &str) -> Option<GlobMatcher> {
+    if pattern.ends_with('/') {
+        return None;
+    }
+    let glob = if pattern.contains('/') {
+        pattern.trim_start_matches('/').to_string()
+    } else {
+        format!("**/{pattern}")
+    };
+    GlobBuilder::new(&glob)
+        .literal_separator(true)
+        .build()
+        .ok()
+        .map(|glob| glob.compile_matcher())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::MemoryFileSystem;
+    use indoc::indoc;
+    use rstest::rstest;
+
+    fn line_endings(cache: &GitAttributesCache, path: &str) -> GitLineEndings {
+        cache.line_endings_for(Path::new(path))
+    }
+
+    fn cache(fs: MemoryFileSystem) -> GitAttributesCache {
+        GitAttributesCache::with_file_system(Arc::new(fs))
+    }
+
+    #[rstest]
+    #[case::eol_crlf("* text=auto eol=crlf", GitLineEndings::Eol(LineEnding::Crlf))]
+    #[case::eol_lf("*.txt eol=lf", GitLineEndings::Eol(LineEnding::Lf))]
+    #[case::text_auto("* text=auto", GitLineEndings::Native)]
+    #[case::text("*.txt text", GitLineEndings::Native)]
+    #[case::unset_text("* -text eol=crlf", GitLineEndings::Unspecified)]
+    #[case::binary("*.txt binary", GitLineEndings::Unspecified)]
+    #[case::other_extension("*.md eol=crlf", GitLineEndings::Unspecified)]
+    #[case::later_line_wins(
+        indoc! {"
+            * eol=lf
+            *.txt eol=crlf
+        "},
+        GitLineEndings::Eol(LineEnding::Crlf)
+    )]
+    #[case::reset_to_unspecified(
+        indoc! {"
+            * text eol=crlf
+            *.txt !text !eol
+        "},
+        GitLineEndings::Unspecified
+    )]
+    #[case::anchored_pattern("/docs/*.txt eol=crlf", GitLineEndings::Eol(LineEnding::Crlf))]
+    #[case::anchored_pattern_elsewhere("/*.txt eol=crlf", GitLineEndings::Unspecified)]
+    #[case::directory_pattern("docs/ eol=crlf", GitLineEndings::Unspecified)]
+    #[case::comment("# *.txt eol=crlf", GitLineEndings::Unspecified)]
+    fn test_line_endings_for(#[case] gitattributes: &str, #[case] expected: GitLineEndings) {
+        let fs = MemoryFileSystem::new().with_file("/repo/.gitattributes", gitattributes);
+
+        assert_eq!(line_endings(&cache(fs), "/repo/docs/guide.txt"), expected);
+    }
+
+    #[test]
+    fn test_deeper_file_takes_precedence() {
+        let fs = MemoryFileSystem::new()
+            .with_file("/repo/.gitattributes", "* eol=lf\n")
+            .with_file("/repo/win/.gitattributes", "*.bat eol=crlf\n");
+
+        let cache = cache(fs);
+        assert_eq!(
+            (
+                line_endings(&cache, "/repo/win/run.bat"),
+                line_endings(&cache, "/repo/win/run.sh"),
+            ),
+            (
+                GitLineEndings::Eol(LineEnding::Crlf),
+                GitLineEndings::Eol(LineEnding::Lf),
+            )
+        );
+    }
+
+    #[test]
+    fn test_stops_at_repository_root() {
+        let fs = MemoryFileSystem::new()
+            .with_file("/.gitattributes", "* eol=crlf\n")
+            .with_file("/repo/.git", "gitdir: /elsewhere\n");
+
+        assert_eq!(
+            line_endings(&cache(fs), "/repo/file.txt"),
+            GitLineEndings::Unspecified
+        );
+    }
+}
@@ -1,5 +1,16 @@
+mod canonical;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod clean;
 pub mod config;
+#[cfg(feature = "dprint")]
+pub mod dprint;
 pub mod editorconfig;
+pub mod error;
+pub mod filesystem;
 pub mod find;
 pub mod format;
+pub mod formatter;
+pub mod gitattributes;
+pub mod rules;
 pub mod runner;
@@ -1,31 +1,20 @@
-use basefmt::runner::{run_check, run_format};
 use clap::Parser;
-use std::path::PathBuf;
+use cli::{Args, CacheCommand, Command};
 use std::process::ExitCode;
 
-#[derive(Parser)]
-struct Args {
-    #[clap(default_value = ".", help = "List of files/directories to format")]
-    paths: Vec<PathBuf>,
-
-    #[clap(short, long, help = "Check mode (don't write changes)")]
-    check: bool,
-}
+mod cli;
 
 fn main() -> ExitCode {
     let args = Args::parse();
 
-    let result = if args.check {
-        run_check(&args.paths)
-    } else {
-        run_format(&args.paths)
-    };
-
-    match result {
-        Ok(result) => ExitCode::from(result.exit_code()),
-        Err(err) => {
-            eprintln!("Error: {err}");
-            ExitCode::from(2)
-        }
+    match &args.command {
+        Some(Command::Clean(clean_args)) => cli::clean(clean_args),
+        Some(Command::Cache(CacheCommand::Clear(clear_args))) => cli::clear_cache(clear_args),
+        Some(Command::CheckConfig(check_config_args)) => cli::check_config(check_config_args),
+        Some(Command::Doctor(doctor_args)) => cli::doctor(doctor_args),
+        Some(Command::Explain(explain_args)) => cli::explain(explain_args),
+        Some(Command::ListFiles(list_files_args)) => cli::list_files(list_files_args),
+        Some(Command::Why(why_args)) => cli::why(why_args),
+        None => cli::format(&args),
     }
 }
@@ -0,0 +1,720 @@
+// Rule resolution module
+// This module combines every source of formatting rules into the rules a
+// file is formatted with.
+
+use crate::config::NestedConfigs;
+use crate::editorconfig::{Charset, EditorConfigCache, FormatRules, LineEnding, PropertyWarning};
+use crate::gitattributes::{GitAttributesCache, GitLineEndings};
+use serde::Deserialize;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+mod conflicts;
+mod explain;
+mod language;
+
+pub use conflicts::LineEndingConflict;
+pub use explain::{RuleExplanation, RuleSource};
+
+/// Resolves the formatting rules for each file from, in order of precedence:
+///
+/// 1. Rules given on the command line (or in code)
+/// 2. The `[rules]` tables of the `.basefmt.toml` files in subdirectories
+///    that apply to the file, nearest first, if enabled with
+///    [`RuleResolver::with_nested_configs`]
+/// 3. The `[rules]` table of `.basefmt.toml`
+/// 4. The `.gitattributes` files that apply to the file, for `end_of_line`
+///    only, if enabled with [`RuleResolver::with_gitattributes`]
+/// 5. The `.editorconfig` files that apply to the file
+/// 6. Built-in rules for the file's extension, e.g. keeping trailing
+///    whitespace in Markdown, if enabled with
+///    [`RuleResolver::with_language_defaults`]
+/// 7. The `[defaults]` table of `.basefmt.toml`
+/// 8. The rules of the [`RuleProfile`] in use: the one given on the command
+///    line, or else the one of the nearest `.basefmt.toml` that sets one
+/// 9. The defaults of each rule (see the accessor methods of
+///    [`FormatRules`])
+///
+/// A rule set by a source replaces whatever lower sources say about it, so
+/// teams can enforce a policy in `.basefmt.toml` even when contributors'
+/// `.editorconfig` files disagree. Rules a source leaves unset are looked up
+/// in the next one.
+pub struct RuleResolver {
+    editorconfig: EditorConfigCache,
+    gitattributes: Option<GitAttributesCache>,
+    language_defaults: bool,
+    profile: RuleProfile,
+    cli_profile: Option<RuleProfile>,
+    config: FormatRules,
+    config_defaults: FormatRules,
+    nested: Option<NestedConfigs>,
+    cli: FormatRules,
+}
+
+impl RuleResolver {
+    /// Creates a resolver that only looks up rules in `editorconfig`, using
+    /// the [`RuleProfile::Standard`] defaults.
+    pub fn new(editorconfig: EditorConfigCache) -> Self {
+        Self {
+            editorconfig,
+            gitattributes: None,
+            language_defaults: false,
+            profile: RuleProfile::Standard,
+            cli_profile: None,
+            config: FormatRules::default(),
+            config_defaults: FormatRules::default(),
+            nested: None,
+            cli: FormatRules::default(),
+        }
+    }
+
+    /// Falls back to the rules of `profile` where EditorConfig sets nothing.
+    pub fn with_profile(mut self, profile: RuleProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Falls back to the rules of `profile` where EditorConfig sets nothing,
+    /// whatever profile the `.basefmt.toml` files in subdirectories choose.
+    pub fn with_cli_profile(mut self, profile: RuleProfile) -> Self {
+        self.cli_profile = Some(profile);
+        self
+    }
+
+    /// Takes line endings from `gitattributes` over EditorConfig, so that
+    /// files keep the line endings git checks them out with: `eol` sets
+    /// `end_of_line`, and `text` without it unsets `end_of_line`, leaving each
+    /// file's own.
+    pub fn with_gitattributes(mut self, gitattributes: GitAttributesCache) -> Self {
+        self.gitattributes = Some(gitattributes);
+        self
+    }
+
+    /// Falls back to built-in rules for some file extensions, such as
+    /// Markdown, patches, and SVG, where EditorConfig sets nothing, before
+    /// falling back to the profile.
+    pub fn with_language_defaults(mut self) -> Self {
+        self.language_defaults = true;
+        self
+    }
+
+    /// Layers rules from `.basefmt.toml` over EditorConfig.
+    pub fn with_config(mut self, rules: FormatRules) -> Self {
+        self.config = rules;
+        self
+    }
+
+    /// Falls back to `rules` from `.basefmt.toml` where neither EditorConfig
+    /// nor the built-in rules for the file's extension set anything, before
+    /// falling back to the profile.
+    pub fn with_config_defaults(mut self, rules: FormatRules) -> Self {
+        self.config_defaults = rules;
+        self
+    }
+
+    /// Layers rules and profiles from the `.basefmt.toml` files in the
+    /// subdirectories of the project over those of its own.
+    pub fn with_nested_configs(mut self, nested: NestedConfigs) -> Self {
+        self.nested = Some(nested);
+        self
+    }
+
+    /// Returns the `.basefmt.toml` files in subdirectories, if enabled with
+    /// [`RuleResolver::with_nested_configs`].
+    pub fn nested_configs(&self) -> Option<&NestedConfigs> {
+        self.nested.as_ref()
+    }
+
+    /// Layers rules from the command line over every other source.
+    pub fn with_cli(mut self, rules: FormatRules) -> Self {
+        self.cli = rules;
+        self
+    }
+
+    /// Returns the rules for the file at the given canonical path.
+    pub fn rules_for(&self, canonical_path: &Path) -> FormatRules {
+        self.complete(canonical_path, &self.explicit_rules_for(canonical_path))
+    }
+
+    /// Returns the rules that EditorConfig, `.basefmt.toml`, or the command
+    /// line set for the file at the given canonical path, leaving the rest
+    /// unset rather than taking them from the profile.
+    pub fn explicit_rules_for(&self, canonical_path: &Path) -> FormatRules {
+        let mut rules = self.editorconfig.rules_for(canonical_path);
+        if let Some(gitattributes) = &self.gitattributes {
+            match gitattributes.line_endings_for(canonical_path) {
+                GitLineEndings::Eol(eol) => rules.end_of_line = Some(eol),
+                GitLineEndings::Native => rules.end_of_line = None,
+                GitLineEndings::Unspecified => {}
+            }
+        }
+        rules = rules.with_overrides(&self.config);
+        if let Some(nested) = &self.nested {
+            rules = rules.with_overrides(&nested.rules_for(canonical_path));
+        }
+        rules.with_overrides(&self.cli)
+    }
+
+    /// Returns the paths of the EditorConfig files that apply to the files in
+    /// the given canonical directory (see [`EditorConfigCache::files_for`]).
+    pub fn editorconfig_files_for(&self, canonical_dir: &Path) -> Vec<PathBuf> {
+        self.editorconfig.files_for(canonical_dir)
+    }
+
+    /// Returns the paths an EditorConfig file was looked up at so far (see
+    /// [`EditorConfigCache::lookups`]).
+    pub fn editorconfig_lookups(&self) -> Vec<PathBuf> {
+        self.editorconfig.lookups()
+    }
+
+    /// Returns whether any EditorConfig file applies to the file at the given
+    /// canonical path (see [`EditorConfigCache::has_config_for`]).
+    pub fn has_editorconfig_for(&self, canonical_path: &Path) -> bool {
+        self.editorconfig.has_config_for(canonical_path)
+    }
+
+    /// Fills in the rules `explicit` leaves unset for the file at the given
+    /// canonical path from the built-in rules for its extension, if enabled,
+    /// the `[defaults]` of `.basefmt.toml`, and the profile.
+    pub fn complete(&self, canonical_path: &Path, explicit: &FormatRules) -> FormatRules {
+        let mut defaults = self
+            .profile_for(canonical_path)
+            .rules()
+            .with_overrides(&self.config_defaults);
+        if self.language_defaults {
+            defaults = defaults.with_overrides(&language::defaults_for(canonical_path));
+        }
+        defaults.with_overrides(explicit)
+    }
+
+    /// Returns the profile in use for the file at the given canonical path.
+    fn profile_for(&self, canonical_path: &Path) -> RuleProfile {
+        self.cli_profile
+            .or_else(|| self.nested.as_ref()?.profile_for(canonical_path))
+            .unwrap_or(self.profile)
+    }
+
+    /// Returns the lint warnings for the `.editorconfig` files read so far
+    /// (see [`EditorConfigCache::warnings`]).
+    pub fn warnings(&self) -> Vec<PropertyWarning> {
+        self.editorconfig.warnings()
+    }
+}
+
+/// The rules a file gets where neither EditorConfig nor any other source
+/// sets them, e.g. in a repository without an `.editorconfig`.
+///
+/// Also called a preset, since it gives a small project sensible rules
+/// without any configuration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleProfile {
+    /// Every rule's own default: leading blank lines and trailing whitespace
+    /// are removed and a final newline is ensured
+    #[default]
+    Standard,
+    /// The standard rules, and runs of blank lines are shortened to one
+    Strict,
+    /// The standard rules, but Markdown hard line breaks (two trailing
+    /// spaces) are kept
+    MarkdownSafe,
+    /// Only trailing whitespace is removed and a final newline ensured
+    Minimal,
+    /// Nothing is changed unless a rule is enabled explicitly
+    None,
+}
+
+impl RuleProfile {
+    /// Returns the rules of this profile.
+    pub fn rules(self) -> FormatRules {
+        match self {
+            RuleProfile::Standard => FormatRules::default(),
+            RuleProfile::Strict => FormatRules {
+                max_blank_lines: Some(1),
+                ..FormatRules::default()
+            },
+            RuleProfile::MarkdownSafe => FormatRules {
+                preserve_markdown_hard_breaks: Some(true),
+                ..FormatRules::default()
+            },
+            RuleProfile::Minimal => FormatRules {
+                remove_leading_newlines: Some(false),
+                ..FormatRules::default()
+            },
+            RuleProfile::None => FormatRules {
+                ensure_final_newline: Some(false),
+                remove_trailing_spaces: Some(false),
+                remove_leading_newlines: Some(false),
+                ..FormatRules::default()
+            },
+        }
+    }
+}
+
+/// Rules as written in the `[rules]` table of `.basefmt.toml` and given to
+/// `--rule`, keyed by the names of the EditorConfig properties they
+/// correspond to.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RuleTable {
+    insert_final_newline: Option<bool>,
+    trim_trailing_whitespace: Option<bool>,
+    trim_leading_newlines: Option<bool>,
+    preserve_markdown_hard_breaks: Option<bool>,
+    max_blank_lines: Option<usize>,
+    skip_minified: Option<bool>,
+    generated_marker_lines: Option<usize>,
+    end_of_line: Option<LineEnding>,
+    preserve_yaml_block_scalars: Option<bool>,
+    normalize_encoding: Option<Charset>,
+}
+
+impl From<RuleTable> for FormatRules {
+    fn from(table: RuleTable) -> Self {
+        FormatRules {
+            ensure_final_newline: table.insert_final_newline,
+            remove_trailing_spaces: table.trim_trailing_whitespace,
+            remove_leading_newlines: table.trim_leading_newlines,
+            preserve_markdown_hard_breaks: table.preserve_markdown_hard_breaks,
+            max_blank_lines: table.max_blank_lines,
+            skip_minified: table.skip_minified,
+            generated_marker_lines: table.generated_marker_lines,
+            end_of_line: table.end_of_line,
+            preserve_yaml_block_scalars: table.preserve_yaml_block_scalars,
+            normalize_encoding: table.normalize_encoding,
+        }
+    }
+}
+
+/// Parses a single `KEY=VALUE` setting such as `insert_final_newline=false`
+/// into rules with only that rule set, using the keys of the `[rules]` table
+/// of `.basefmt.toml`.
+impl FromStr for FormatRules {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid rule '{s}': {reason}"),
+            )
+        };
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| invalid("expected KEY=VALUE".to_string()))?;
+        let value = value.trim();
+        let value = if let Ok(flag) = value.parse() {
+            toml::Value::Boolean(flag)
+        } else if let Ok(number) = value.parse() {
+            toml::Value::Integer(number)
+        } else {
+            toml::Value::String(value.to_string())
+        };
+        let table = toml::Table::from_iter([(key.trim().to_string(), value)]);
+        let table =
+            RuleTable::deserialize(table).map_err(|err| invalid(err.message().to_string()))?;
+        Ok(table.into())
+    }
+}
+
+impl FormatRules {
+    /// Returns rules with only the rule named by its key in the `[rules]`
+    /// table of `.basefmt.toml` turned off, such as
+    /// `trim_trailing_whitespace`.
+    ///
+    /// Turning off `max_blank_lines` allows any number of consecutive blank
+    /// lines.
+    pub fn disabling(name: &str) -> io::Result<Self> {
+        let rules = match name.trim() {
+            "insert_final_newline" => FormatRules {
+                ensure_final_newline: Some(false),
+                ..FormatRules::default()
+            },
+            "trim_trailing_whitespace" => FormatRules {
+                remove_trailing_spaces: Some(false),
+                ..FormatRules::default()
+            },
+            "trim_leading_newlines" => FormatRules {
+                remove_leading_newlines: Some(false),
+                ..FormatRules::default()
+            },
+            "max_blank_lines" => FormatRules {
+                max_blank_lines: Some(usize::MAX),
+                ..FormatRules::default()
+            },
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "unknown rule '{name}', expected one of `insert_final_newline`, `trim_trailing_whitespace`, `trim_leading_newlines`, `max_blank_lines`"
+                    ),
+                ));
+            }
+        };
+        Ok(rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::MemoryFileSystem;
+    use indoc::indoc;
+    use rstest::rstest;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_precedence() {
+        let fs = MemoryFileSystem::new().with_file(
+            "/p/.editorconfig",
+            indoc! {"
+                [*]
+                insert_final_newline = false
+                trim_trailing_whitespace = false
+                trim_leading_newlines = false
+            "},
+        );
+        let resolver = RuleResolver::new(EditorConfigCache::with_file_system(Arc::new(fs)))
+            .with_config(FormatRules {
+                remove_trailing_spaces: Some(true),
+                remove_leading_newlines: Some(true),
+                ..FormatRules::default()
+            })
+            .with_cli(FormatRules {
+                remove_leading_newlines: Some(false),
+                ..FormatRules::default()
+            });
+
+        assert_eq!(
+            resolver.rules_for(Path::new("/p/a.txt")),
+            FormatRules {
+                ensure_final_newline: Some(false),
+                remove_trailing_spaces: Some(true),
+                remove_leading_newlines: Some(false),
+                ..FormatRules::default()
+            }
+        );
+    }
+
+    #[rstest]
+    #[case::eol_wins_over_editorconfig("a.bat", None, Some(LineEnding::Crlf))]
+    #[case::text_keeps_own_line_endings("b.txt", None, None)]
+    #[case::unspecified_keeps_editorconfig("c.md", None, Some(LineEnding::Lf))]
+    #[case::config_wins_over_gitattributes("a.bat", Some(LineEnding::Lf), Some(LineEnding::Lf))]
+    fn test_gitattributes_line_endings(
+        #[case] file: &str,
+        #[case] config: Option<LineEnding>,
+        #[case] expected: Option<LineEnding>,
+    ) {
+        let fs = Arc::new(
+            MemoryFileSystem::new()
+                .with_file(
+                    "/p/.editorconfig",
+                    indoc! {"
+                        [*]
+                        end_of_line = lf
+                    "},
+                )
+                .with_file(
+                    "/p/.gitattributes",
+                    indoc! {"
+                        *.txt text
+                        *.bat text eol=crlf
+                    "},
+                ),
+        );
+        let resolver = RuleResolver::new(EditorConfigCache::with_file_system(fs.clone()))
+            .with_gitattributes(GitAttributesCache::with_file_system(fs))
+            .with_config(FormatRules {
+                end_of_line: config,
+                ..FormatRules::default()
+            });
+
+        assert_eq!(
+            resolver.rules_for(&Path::new("/p").join(file)).end_of_line,
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::nested_profile(None, RuleProfile::Minimal)]
+    #[case::cli_profile(Some(RuleProfile::Strict), RuleProfile::Strict)]
+    fn test_nested_configs_over_config(
+        #[case] cli_profile: Option<RuleProfile>,
+        #[case] expected_profile: RuleProfile,
+    ) {
+        let fs = Arc::new(MemoryFileSystem::new().with_file(
+            "/p/sub/.basefmt.toml",
+            indoc! {r#"
+                profile = "minimal"
+
+                [rules]
+                max_blank_lines = 2
+            "#},
+        ));
+        let mut resolver = RuleResolver::new(EditorConfigCache::with_file_system(fs.clone()))
+            .with_profile(RuleProfile::None)
+            .with_config(FormatRules {
+                max_blank_lines: Some(1),
+                skip_minified: Some(true),
+                ..FormatRules::default()
+            })
+            .with_nested_configs(NestedConfigs::with_file_system(fs, "/p"));
+        if let Some(profile) = cli_profile {
+            resolver = resolver.with_cli_profile(profile);
+        }
+
+        assert_eq!(
+            resolver.rules_for(Path::new("/p/sub/a.txt")),
+            FormatRules {
+                max_blank_lines: Some(2),
+                skip_minified: Some(true),
+                ..expected_profile.rules()
+            }
+        );
+    }
+
+    #[test]
+    fn test_language_defaults_beneath_editorconfig() {
+        let fs = Arc::new(MemoryFileSystem::new().with_file(
+            "/p/.editorconfig",
+            indoc! {"
+                [docs/*.md]
+                trim_trailing_whitespace = true
+            "},
+        ));
+        let cache = || EditorConfigCache::with_file_system(fs.clone());
+        let resolver = RuleResolver::new(cache())
+            .with_profile(RuleProfile::Minimal)
+            .with_language_defaults();
+
+        assert_eq!(
+            (
+                resolver.rules_for(Path::new("/p/README.md")),
+                resolver.rules_for(Path::new("/p/docs/guide.md")),
+                RuleResolver::new(cache()).rules_for(Path::new("/p/README.md"))
+            ),
+            (
+                FormatRules {
+                    remove_trailing_spaces: Some(false),
+                    ..RuleProfile::Minimal.rules()
+                },
+                FormatRules {
+                    remove_trailing_spaces: Some(true),
+                    ..RuleProfile::Minimal.rules()
+                },
+                FormatRules::default()
+            )
+        );
+    }
+
+    #[test]
+    fn test_config_defaults_beneath_language_defaults() {
+        let fs = Arc::new(MemoryFileSystem::new().with_file(
+            "/p/.editorconfig",
+            indoc! {"
+                [*.txt]
+                insert_final_newline = false
+            "},
+        ));
+        let resolver = RuleResolver::new(EditorConfigCache::with_file_system(fs))
+            .with_profile(RuleProfile::None)
+            .with_language_defaults()
+            .with_config_defaults(FormatRules {
+                ensure_final_newline: Some(true),
+                remove_trailing_spaces: Some(true),
+                ..FormatRules::default()
+            });
+
+        assert_eq!(
+            (
+                resolver.rules_for(Path::new("/p/a.txt")),
+                resolver.rules_for(Path::new("/p/a.md"))
+            ),
+            (
+                FormatRules {
+                    ensure_final_newline: Some(false),
+                    remove_trailing_spaces: Some(true),
+                    ..RuleProfile::None.rules()
+                },
+                FormatRules {
+                    ensure_final_newline: Some(true),
+                    remove_trailing_spaces: Some(false),
+                    ..RuleProfile::None.rules()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_profile_fills_in_unset_rules() {
+        let fs = MemoryFileSystem::new().with_file(
+            "/p/.editorconfig",
+            indoc! {"
+                [*.md]
+                insert_final_newline = true
+            "},
+        );
+        let resolver = RuleResolver::new(EditorConfigCache::with_file_system(Arc::new(fs)))
+            .with_profile(RuleProfile::None);
+
+        assert_eq!(
+            (
+                resolver.rules_for(Path::new("/p/a.md")),
+                resolver.rules_for(Path::new("/p/b.txt"))
+            ),
+            (
+                FormatRules {
+                    ensure_final_newline: Some(true),
+                    ..RuleProfile::None.rules()
+                },
+                RuleProfile::None.rules()
+            )
+        );
+    }
+
+    #[rstest]
+    #[case::standard(RuleProfile::Standard, FormatRules::default())]
+    #[case::strict(
+        RuleProfile::Strict,
+        FormatRules {
+            max_blank_lines: Some(1),
+            ..FormatRules::default()
+        }
+    )]
+    #[case::markdown_safe(
+        RuleProfile::MarkdownSafe,
+        FormatRules {
+            preserve_markdown_hard_breaks: Some(true),
+            ..FormatRules::default()
+        }
+    )]
+    #[case::minimal(
+        RuleProfile::Minimal,
+        FormatRules {
+            remove_leading_newlines: Some(false),
+            ..FormatRules::default()
+        }
+    )]
+    #[case::none(
+        RuleProfile::None,
+        FormatRules {
+            ensure_final_newline: Some(false),
+            remove_trailing_spaces: Some(false),
+            remove_leading_newlines: Some(false),
+            ..FormatRules::default()
+        }
+    )]
+    fn test_profile_rules(#[case] profile: RuleProfile, #[case] expected: FormatRules) {
+        let resolver = RuleResolver::new(EditorConfigCache::with_file_system(Arc::new(
+            MemoryFileSystem::new(),
+        )))
+        .with_profile(profile);
+
+        assert_eq!(resolver.rules_for(Path::new("/p/a.txt")), expected);
+    }
+
+    #[rstest]
+    #[case::bool(
+        "insert_final_newline=false",
+        FormatRules {
+            ensure_final_newline: Some(false),
+            ..FormatRules::default()
+        }
+    )]
+    #[case::integer(
+        "max_blank_lines = 2",
+        FormatRules {
+            max_blank_lines: Some(2),
+            ..FormatRules::default()
+        }
+    )]
+    #[case::string(
+        "end_of_line=crlf",
+        FormatRules {
+            end_of_line: Some(LineEnding::Crlf),
+            ..FormatRules::default()
+        }
+    )]
+    #[case::encoding(
+        "normalize_encoding=utf-8",
+        FormatRules {
+            normalize_encoding: Some(Charset::Utf8),
+            ..FormatRules::default()
+        }
+    )]
+    fn test_from_str(#[case] input: &str, #[case] expected: FormatRules) {
+        assert_eq!(input.parse::<FormatRules>().unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::missing_value(
+        "insert_final_newline",
+        "invalid rule 'insert_final_newline': expected KEY=VALUE"
+    )]
+    #[case::not_a_value(
+        "insert_final_newline=yes",
+        "invalid rule 'insert_final_newline=yes': invalid type: string \"yes\", expected a boolean"
+    )]
+    #[case::wrong_type(
+        "max_blank_lines=true",
+        "invalid rule 'max_blank_lines=true': invalid type: boolean `true`, expected usize"
+    )]
+    #[case::unknown_key(
+        "trim_trailing_whitespaces=true",
+        "invalid rule 'trim_trailing_whitespaces=true': unknown field `trim_trailing_whitespaces`, expected one of `insert_final_newline`, `trim_trailing_whitespace`, `trim_leading_newlines`, `preserve_markdown_hard_breaks`, `max_blank_lines`, `skip_minified`, `generated_marker_lines`, `end_of_line`, `preserve_yaml_block_scalars`, `normalize_encoding`"
+    )]
+    fn test_from_str_invalid(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(
+            input.parse::<FormatRules>().unwrap_err().to_string(),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::bool(
+        "trim_trailing_whitespace",
+        Ok(FormatRules {
+            remove_trailing_spaces: Some(false),
+            ..FormatRules::default()
+        })
+    )]
+    #[case::unlimited(
+        "max_blank_lines",
+        Ok(FormatRules {
+            max_blank_lines: Some(usize::MAX),
+            ..FormatRules::default()
+        })
+    )]
+    #[case::unknown(
+        "end_of_line",
+        Err("unknown rule 'end_of_line', expected one of `insert_final_newline`, `trim_trailing_whitespace`, `trim_leading_newlines`, `max_blank_lines`".to_string())
+    )]
+    fn test_disabling(#[case] name: &str, #[case] expected: Result<FormatRules, String>) {
+        assert_eq!(
+            FormatRules::disabling(name).map_err(|err| err.to_string()),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::every_rule(
+        &["insert_final_newline", "trim_trailing_whitespace", "trim_leading_newlines", "max_blank_lines"],
+        true
+    )]
+    #[case::blank_line_limit_kept(
+        &["insert_final_newline", "trim_trailing_whitespace", "trim_leading_newlines"],
+        false
+    )]
+    fn test_disabling_changes_nothing(#[case] names: &[&str], #[case] expected: bool) {
+        let configured = FormatRules {
+            max_blank_lines: Some(1),
+            ..FormatRules::default()
+        };
+        let rules = names.iter().fold(configured, |rules, name| {
+            rules.with_overrides(&FormatRules::disabling(name).unwrap())
+        });
+        assert_eq!(rules.changes_nothing(), expected);
+    }
+}
@@ -0,0 +1,138 @@
+use super::RuleResolver;
+use crate::editorconfig::LineEnding;
+use crate::gitattributes::GitLineEndings;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// `.gitattributes` overriding the `end_of_line` that `.editorconfig` sets
+/// for a file (see [`RuleResolver::line_ending_conflict`]).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineEndingConflict {
+    /// Canonical path to the file
+    pub path: PathBuf,
+    /// What `.gitattributes` says, either [`GitLineEndings::Eol`] or
+    /// [`GitLineEndings::Native`]
+    pub gitattributes: GitLineEndings,
+    /// The `end_of_line` of `.editorconfig`
+    pub editorconfig: LineEnding,
+}
+
+impl fmt::Display for LineEndingConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let editorconfig = self.editorconfig.name();
+        match self.gitattributes {
+            GitLineEndings::Eol(eol) => write!(
+                f,
+                "{}: .gitattributes sets eol={}, overriding end_of_line = {editorconfig} in .editorconfig",
+                self.path.display(),
+                eol.name()
+            ),
+            _ => write!(
+                f,
+                "{}: .gitattributes sets text without eol, so end_of_line = {editorconfig} in .editorconfig is ignored",
+                self.path.display()
+            ),
+        }
+    }
+}
+
+impl RuleResolver {
+    /// Returns how `.gitattributes` overrides the `end_of_line` that
+    /// `.editorconfig` sets for the file at the given canonical path, if it
+    /// does and neither `.basefmt.toml` nor the command line settles it.
+    pub fn line_ending_conflict(&self, canonical_path: &Path) -> Option<LineEndingConflict> {
+        let mut explicit = self.config.with_overrides(&self.cli);
+        if let Some(nested) = &self.nested {
+            explicit = explicit.with_overrides(&nested.rules_for(canonical_path));
+        }
+        if explicit.end_of_line.is_some() {
+            return None;
+        }
+        let editorconfig = self.editorconfig.rules_for(canonical_path).end_of_line?;
+        let gitattributes = self
+            .gitattributes
+            .as_ref()?
+            .line_endings_for(canonical_path);
+        let conflicts = match gitattributes {
+            GitLineEndings::Eol(eol) => eol != editorconfig,
+            GitLineEndings::Native => true,
+            GitLineEndings::Unspecified => false,
+        };
+        conflicts.then(|| LineEndingConflict {
+            path: canonical_path.to_path_buf(),
+            gitattributes,
+            editorconfig,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editorconfig::{EditorConfigCache, FormatRules};
+    use crate::filesystem::MemoryFileSystem;
+    use crate::gitattributes::GitAttributesCache;
+    use indoc::indoc;
+    use rstest::rstest;
+    use std::sync::Arc;
+
+    #[rstest]
+    #[case::agree("a.sh", None, None)]
+    #[case::eol("a.bat", None, Some(GitLineEndings::Eol(LineEnding::Crlf)))]
+    #[case::native("a.txt", None, Some(GitLineEndings::Native))]
+    #[case::settled_by_config("a.bat", Some(LineEnding::Crlf), None)]
+    #[case::unspecified("a.md", None, None)]
+    fn test_line_ending_conflict(
+        #[case] file: &str,
+        #[case] config: Option<LineEnding>,
+        #[case] expected: Option<GitLineEndings>,
+    ) {
+        let fs = Arc::new(
+            MemoryFileSystem::new()
+                .with_file(
+                    "/p/.editorconfig",
+                    indoc! {"
+                        [*]
+                        end_of_line = lf
+                    "},
+                )
+                .with_file(
+                    "/p/.gitattributes",
+                    indoc! {"
+                        *.sh text eol=lf
+                        *.bat text eol=crlf
+                        *.txt text
+                    "},
+                ),
+        );
+        let resolver = RuleResolver::new(EditorConfigCache::with_file_system(fs.clone()))
+            .with_gitattributes(GitAttributesCache::with_file_system(fs))
+            .with_config(FormatRules {
+                end_of_line: config,
+                ..FormatRules::default()
+            });
+        let path = Path::new("/p").join(file);
+
+        assert_eq!(
+            resolver.line_ending_conflict(&path),
+            expected.map(|gitattributes| LineEndingConflict {
+                path: path.clone(),
+                gitattributes,
+                editorconfig: LineEnding::Lf,
+            })
+        );
+    }
+
+    #[test]
+    fn test_line_ending_conflict_display() {
+        let conflict = LineEndingConflict {
+            path: PathBuf::from("/p/a.bat"),
+            gitattributes: GitLineEndings::Eol(LineEnding::Crlf),
+            editorconfig: LineEnding::Lf,
+        };
+        assert_eq!(
+            conflict.to_string(),
+            "/p/a.bat: .gitattributes sets eol=crlf, overriding end_of_line = lf in .editorconfig"
+        );
+    }
+}
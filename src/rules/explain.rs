@@ -0,0 +1,310 @@
+use super::{RuleProfile, RuleResolver, language};
+use crate::editorconfig::{Charset, FormatRules, LineEnding};
+use crate::gitattributes::GitLineEndings;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The number of rules, i.e. of fields of [`FormatRules`].
+const RULE_COUNT: usize = 10;
+
+/// Where the value of a rule for a file comes from (see
+/// [`RuleResolver::explain`]).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RuleSource {
+    /// Rules given on the command line (or in code)
+    Cli,
+    /// The `[rules]` table of the `.basefmt.toml` in a subdirectory at `path`
+    NestedConfig { path: PathBuf },
+    /// The `[rules]` table of the project's `.basefmt.toml`
+    Config,
+    /// The `eol` or `text` attribute in `.gitattributes`
+    GitAttributes,
+    /// The section of the `.editorconfig` at `path` whose header is on
+    /// `line`
+    EditorConfig {
+        path: PathBuf,
+        line: usize,
+        pattern: String,
+    },
+    /// The built-in rules for the file's extension
+    LanguageDefaults,
+    /// The `[defaults]` table of the project's `.basefmt.toml`
+    ConfigDefaults,
+    /// The rules of the profile in use
+    Profile(RuleProfile),
+    /// The rule's own default, since no source sets it
+    Default,
+}
+
+impl fmt::Display for RuleSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleSource::Cli => write!(f, "command line"),
+            RuleSource::NestedConfig { path } => write!(f, "{} [rules]", path.display()),
+            RuleSource::Config => write!(f, ".basefmt.toml [rules]"),
+            RuleSource::GitAttributes => write!(f, ".gitattributes"),
+            RuleSource::EditorConfig {
+                path,
+                line,
+                pattern,
+            } => write!(f, "{}:{line} [{pattern}]", path.display()),
+            RuleSource::LanguageDefaults => write!(f, "built-in rules for the file's extension"),
+            RuleSource::ConfigDefaults => write!(f, ".basefmt.toml [defaults]"),
+            RuleSource::Profile(profile) => write!(f, "profile {}", profile_name(*profile)),
+            RuleSource::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// The value of a rule for a file, and where it comes from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleExplanation {
+    /// The key of the rule in the `[rules]` table of `.basefmt.toml`
+    pub name: &'static str,
+    /// The value the file is formatted with, as written in `[rules]`, or
+    /// `None` if the rule is unset, e.g. leaving line endings as they are
+    pub value: Option<String>,
+    /// The source with the highest precedence that sets the rule
+    pub source: RuleSource,
+}
+
+impl RuleResolver {
+    /// Returns every rule for the file at the given canonical path, in the
+    /// order of the `[rules]` table, with its value and the source it comes
+    /// from, following the precedence described on [`RuleResolver`].
+    pub fn explain(&self, canonical_path: &Path) -> Vec<RuleExplanation> {
+        let sets = |rules: &FormatRules| rule_values(rules).map(|(_, value)| value.is_some());
+        let mut layers = vec![(RuleSource::Cli, sets(&self.cli))];
+        if let Some(nested) = &self.nested {
+            for (path, rules) in nested.rules_by_file_for(canonical_path).iter().rev() {
+                let source = RuleSource::NestedConfig { path: path.clone() };
+                layers.push((source, sets(rules)));
+            }
+        }
+        layers.push((RuleSource::Config, sets(&self.config)));
+        let gitattributes = self
+            .gitattributes
+            .as_ref()
+            .map(|gitattributes| gitattributes.line_endings_for(canonical_path));
+        if let Some(GitLineEndings::Eol(_) | GitLineEndings::Native) = gitattributes {
+            // Either sets `end_of_line`, if only to unset it
+            let end_of_line = FormatRules {
+                end_of_line: Some(LineEnding::Lf),
+                ..FormatRules::default()
+            };
+            layers.push((RuleSource::GitAttributes, sets(&end_of_line)));
+        }
+        for section in self.editorconfig.sections_for(canonical_path).iter().rev() {
+            let source = RuleSource::EditorConfig {
+                path: section.path.clone(),
+                line: section.line,
+                pattern: section.pattern.clone(),
+            };
+            layers.push((source, sets(&section.rules)));
+        }
+        if self.language_defaults {
+            let rules = language::defaults_for(canonical_path);
+            layers.push((RuleSource::LanguageDefaults, sets(&rules)));
+        }
+        layers.push((RuleSource::ConfigDefaults, sets(&self.config_defaults)));
+        let profile = self.profile_for(canonical_path);
+        layers.push((RuleSource::Profile(profile), sets(&profile.rules())));
+
+        let rules = with_defaults(&self.rules_for(canonical_path));
+        rule_values(&rules)
+            .into_iter()
+            .enumerate()
+            .map(|(index, (name, value))| RuleExplanation {
+                name,
+                value,
+                source: layers
+                    .iter()
+                    .find(|(_, sets)| sets[index])
+                    .map_or(RuleSource::Default, |(source, _)| source.clone()),
+            })
+            .collect()
+    }
+}
+
+/// Returns the key of each rule in the `[rules]` table, in the order of the
+/// table, with its value in `rules` as written there, if set.
+fn rule_values(rules: &FormatRules) -> [(&'static str, Option<String>); RULE_COUNT] {
+    let flag = |value: Option<bool>| value.map(|value| value.to_string());
+    let number = |value: Option<usize>| value.map(|value| value.to_string());
+    [
+        ("insert_final_newline", flag(rules.ensure_final_newline)),
+        (
+            "trim_trailing_whitespace",
+            flag(rules.remove_trailing_spaces),
+        ),
+        ("trim_leading_newlines", flag(rules.remove_leading_newlines)),
+        (
+            "preserve_markdown_hard_breaks",
+            flag(rules.preserve_markdown_hard_breaks),
+        ),
+        ("max_blank_lines", number(rules.max_blank_lines)),
+        ("skip_minified", flag(rules.skip_minified)),
+        (
+            "generated_marker_lines",
+            number(rules.generated_marker_lines),
+        ),
+        (
+            "end_of_line",
+            rules
+                .end_of_line
+                .map(|end_of_line| end_of_line.name().to_string()),
+        ),
+        (
+            "preserve_yaml_block_scalars",
+            flag(rules.preserve_yaml_block_scalars),
+        ),
+        (
+            "normalize_encoding",
+            rules.normalize_encoding.map(|charset| match charset {
+                Charset::Utf8 => "utf-8".to_string(),
+            }),
+        ),
+    ]
+}
+
+/// Returns `rules` with every rule that has a default value when unset set
+/// to it, as the accessor methods of [`FormatRules`] read them.
+fn with_defaults(rules: &FormatRules) -> FormatRules {
+    let unset = FormatRules::default();
+    FormatRules {
+        ensure_final_newline: Some(unset.ensure_final_newline()),
+        remove_trailing_spaces: Some(unset.remove_trailing_spaces()),
+        remove_leading_newlines: Some(unset.remove_leading_newlines()),
+        preserve_markdown_hard_breaks: Some(unset.preserve_markdown_hard_breaks()),
+        skip_minified: Some(unset.skip_minified()),
+        generated_marker_lines: Some(unset.generated_marker_lines()),
+        preserve_yaml_block_scalars: Some(unset.preserve_yaml_block_scalars()),
+        ..FormatRules::default()
+    }
+    .with_overrides(rules)
+}
+
+/// Returns the name of `profile` as `.basefmt.toml` and `--profile` take it.
+fn profile_name(profile: RuleProfile) -> &'static str {
+    match profile {
+        RuleProfile::Standard => "standard",
+        RuleProfile::Strict => "strict",
+        RuleProfile::MarkdownSafe => "markdown-safe",
+        RuleProfile::Minimal => "minimal",
+        RuleProfile::None => "none",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NestedConfigs;
+    use crate::editorconfig::EditorConfigCache;
+    use crate::filesystem::MemoryFileSystem;
+    use indoc::indoc;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_explain() {
+        let fs = Arc::new(
+            MemoryFileSystem::new()
+                .with_file(
+                    "/p/.editorconfig",
+                    indoc! {"
+                        [*]
+                        insert_final_newline = true
+                        trim_trailing_whitespace = true
+                    "},
+                )
+                .with_file(
+                    "/p/sub/.basefmt.toml",
+                    indoc! {"
+                        [rules]
+                        max_blank_lines = 2
+                    "},
+                ),
+        );
+        let resolver = RuleResolver::new(EditorConfigCache::with_file_system(fs.clone()))
+            .with_profile(RuleProfile::MarkdownSafe)
+            .with_language_defaults()
+            .with_config(FormatRules {
+                skip_minified: Some(false),
+                ..FormatRules::default()
+            })
+            .with_config_defaults(FormatRules {
+                remove_leading_newlines: Some(false),
+                ..FormatRules::default()
+            })
+            .with_nested_configs(NestedConfigs::with_file_system(fs, "/p"))
+            .with_cli(FormatRules {
+                ensure_final_newline: Some(false),
+                ..FormatRules::default()
+            });
+        let explanation = |name, value: Option<&str>, source| RuleExplanation {
+            name,
+            value: value.map(str::to_string),
+            source,
+        };
+
+        assert_eq!(
+            resolver.explain(Path::new("/p/sub/a.md")),
+            vec![
+                explanation("insert_final_newline", Some("false"), RuleSource::Cli),
+                explanation(
+                    "trim_trailing_whitespace",
+                    Some("true"),
+                    RuleSource::EditorConfig {
+                        path: PathBuf::from("/p/.editorconfig"),
+                        line: 1,
+                        pattern: "*".to_string(),
+                    }
+                ),
+                explanation(
+                    "trim_leading_newlines",
+                    Some("false"),
+                    RuleSource::ConfigDefaults
+                ),
+                explanation(
+                    "preserve_markdown_hard_breaks",
+                    Some("true"),
+                    RuleSource::Profile(RuleProfile::MarkdownSafe)
+                ),
+                explanation(
+                    "max_blank_lines",
+                    Some("2"),
+                    RuleSource::NestedConfig {
+                        path: PathBuf::from("/p/sub/.basefmt.toml"),
+                    }
+                ),
+                explanation("skip_minified", Some("false"), RuleSource::Config),
+                explanation("generated_marker_lines", Some("0"), RuleSource::Default),
+                explanation("end_of_line", None, RuleSource::Default),
+                explanation(
+                    "preserve_yaml_block_scalars",
+                    Some("false"),
+                    RuleSource::Default
+                ),
+                explanation("normalize_encoding", None, RuleSource::Default),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rule_source_display() {
+        let source = RuleSource::EditorConfig {
+            path: PathBuf::from("/p/.editorconfig"),
+            line: 4,
+            pattern: "*.md".to_string(),
+        };
+        assert_eq!(
+            (
+                source.to_string(),
+                RuleSource::Profile(RuleProfile::MarkdownSafe).to_string()
+            ),
+            (
+                "/p/.editorconfig:4 [*.md]".to_string(),
+                "profile markdown-safe".to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use crate::editorconfig::FormatRules;
+use std::path::Path;
+
+/// Returns the built-in rules for files with the extension of `path`,
+/// which formatting would otherwise break: trailing whitespace is kept in
+/// Markdown, where two trailing spaces are a line break, and in patches,
+/// where a blank context line is a single space, and SVG files, which are
+/// usually exported by a drawing program, are left alone.
+pub(super) fn defaults_for(path: &Path) -> FormatRules {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("md" | "markdown" | "patch" | "diff") => FormatRules {
+            remove_trailing_spaces: Some(false),
+            ..FormatRules::default()
+        },
+        Some("svg") => FormatRules {
+            ensure_final_newline: Some(false),
+            remove_trailing_spaces: Some(false),
+            remove_leading_newlines: Some(false),
+            ..FormatRules::default()
+        },
+        _ => FormatRules::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::markdown(
+        "README.md",
+        FormatRules {
+            remove_trailing_spaces: Some(false),
+            ..FormatRules::default()
+        }
+    )]
+    #[case::uppercase_extension(
+        "fix.PATCH",
+        FormatRules {
+            remove_trailing_spaces: Some(false),
+            ..FormatRules::default()
+        }
+    )]
+    #[case::svg(
+        "logo.svg",
+        FormatRules {
+            ensure_final_newline: Some(false),
+            remove_trailing_spaces: Some(false),
+            remove_leading_newlines: Some(false),
+            ..FormatRules::default()
+        }
+    )]
+    #[case::other("main.rs", FormatRules::default())]
+    #[case::no_extension("Makefile", FormatRules::default())]
+    fn test_defaults_for(#[case] path: &str, #[case] expected: FormatRules) {
+        assert_eq!(defaults_for(Path::new(path)), expected);
+    }
+}
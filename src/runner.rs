@@ -1,50 +1,48 @@
-use crate::config::Config;
-use crate::editorconfig::{EditorConfigCache, FormatRules};
-use crate::find::find_files;
-use crate::format::{CheckResult, check_file_with_rules, format_file_with_rules};
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use crate::canonical;
+use crate::editorconfig::PropertyWarning;
+#[cfg(doc)]
+use crate::format::WriteOptions;
+use crate::format::{
+    CheckResult, FormatResult, SkipReason, check_file_until_violation, check_file_with_rules,
+    format_file_with_options, skip_reason,
+};
+use cache::Cache;
+use patch::PatchCollector;
 use std::io;
-use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicUsize, Ordering};
-
-/// Result of a formatting or checking operation on multiple files.
-pub struct RunnerResult {
-    /// Total number of files processed
-    pub total_files: usize,
-    /// Number of files that encountered errors
-    pub error_count: usize,
-    /// Number of files that were not properly formatted (check mode only)
-    pub unformatted_count: usize,
-}
-
-impl RunnerResult {
-    /// Returns the appropriate exit code based on the result.
-    ///
-    /// Exit codes:
-    /// - 0: Success (all files formatted/checked successfully)
-    /// - 1: Some files need formatting (check mode only)
-    /// - 2: Errors occurred during processing
-    pub fn exit_code(&self) -> u8 {
-        if self.error_count > 0 {
-            2
-        } else if self.unformatted_count > 0 {
-            1
-        } else {
-            0
-        }
-    }
-}
-
-/// A file that needs to be formatted along with its formatting rules.
-///
-/// This structure pre-computes and caches the formatting rules for each file
-/// to avoid redundant EditorConfig lookups during parallel processing.
-struct FileTask {
-    /// Original path to the file (may be relative or absolute)
-    path: PathBuf,
-    /// Cached formatting rules from EditorConfig
-    rules: FormatRules,
-}
+use std::path::Path;
+use tasks::{
+    collect_tasks, determine_config_dir, find_files_for, load_config, report_config_warnings,
+    rule_resolver_for, run_tasks,
+};
+
+mod builder;
+mod cache;
+mod doctor;
+mod explain;
+mod interrupt;
+mod ipc;
+mod list;
+mod lock;
+mod options;
+mod patch;
+mod report;
+mod result;
+mod stdin;
+mod tasks;
+mod why;
+
+pub use builder::Runner;
+pub use cache::{CacheStrategy, clear_cache};
+pub use doctor::{Diagnosis, diagnose};
+pub use explain::explain_rules;
+pub use interrupt::Interrupt;
+pub use ipc::serve_ipc;
+pub use list::list_files;
+pub use options::RunOptions;
+pub use report::{ErrorFormat, OutputFormat, display_path};
+pub use result::RunnerResult;
+pub use stdin::run_stdin;
+pub use why::{Exclusion, explain_exclusion};
 
 /// Formats files in the specified paths in parallel.
 ///
@@ -69,43 +67,128 @@ struct FileTask {
 /// let result = run_format(&[Path::new("src")]).unwrap();
 /// println!("Formatted {} files", result.total_files);
 /// ```
+#[deprecated(since = "0.1.1", note = "use `Runner` instead")]
 pub fn run_format(paths: &[impl AsRef<Path>]) -> io::Result<RunnerResult> {
-    let config_dir = determine_config_dir(paths);
-    let config = Config::load(config_dir).unwrap_or_default();
-    let files = find_files(paths)?;
-    let config_dir_abs = config_dir
-        .canonicalize()
-        .unwrap_or_else(|_| config_dir.to_path_buf());
-
-    let mut rule_cache = EditorConfigCache::new();
-    let filtered_files = collect_tasks(files, &config, &config_dir_abs, &mut rule_cache);
-
-    let error_count = AtomicUsize::new(0);
-
-    // Use parallel processing only for larger file counts to avoid overhead
-    const PARALLEL_THRESHOLD: usize = 10;
-
-    if filtered_files.len() < PARALLEL_THRESHOLD {
-        for task in &filtered_files {
-            if let Err(err) = format_file_with_rules(&task.path, &task.rules) {
-                eprintln!("{}: {}", task.path.display(), err);
-                error_count.fetch_add(1, Ordering::Relaxed);
-            }
-        }
-    } else {
-        filtered_files.par_iter().for_each(|task| {
-            if let Err(err) = format_file_with_rules(&task.path, &task.rules) {
-                eprintln!("{}: {}", task.path.display(), err);
-                error_count.fetch_add(1, Ordering::Relaxed);
-            }
-        });
-    }
+    format_paths(paths, &RunOptions::default(), |_, _| {})
+}
 
-    Ok(RunnerResult {
-        total_files: filtered_files.len(),
-        error_count: error_count.load(Ordering::Relaxed),
-        unformatted_count: 0,
-    })
+/// Formats files in the specified paths in parallel using the given options.
+///
+/// Behaves like [`run_format`], but lets callers control how files are
+/// written, e.g. to keep a backup of every changed file.
+///
+/// # Examples
+///
+/// ```no_run
+/// use basefmt::format::{Backup, WriteOptions};
+/// use basefmt::runner::{RunOptions, run_format_with_options};
+/// use std::path::Path;
+///
+/// let options = RunOptions {
+///     write: WriteOptions {
+///         backup: Some(Backup::Suffix(".orig".to_string())),
+///         ..WriteOptions::default()
+///     },
+///     ..RunOptions::default()
+/// };
+/// let result = run_format_with_options(&[Path::new("src")], &options).unwrap();
+/// println!("Formatted {} files", result.total_files);
+/// ```
+#[deprecated(since = "0.1.1", note = "use `Runner` instead")]
+pub fn run_format_with_options(
+    paths: &[impl AsRef<Path>],
+    options: &RunOptions,
+) -> io::Result<RunnerResult> {
+    format_paths(paths, options, |_, _| {})
+}
+
+/// Formats files like [`run_format_with_options`], calling `on_file` with
+/// the outcome of each file as soon as it is done.
+///
+/// `on_file` may be called from several threads at once, so it must be
+/// `Sync`; it runs in addition to the reporting chosen by
+/// [`RunOptions::output`].
+///
+/// Returns an error before processing any file if `.basefmt.toml` is
+/// invalid, including if it has a key basefmt doesn't know unless
+/// [`RunOptions::allow_unknown_config_keys`] is set.
+///
+/// A file whose rules can't change anything, e.g. with the `none` profile
+/// and no rule set, is reported unchanged without being formatted, unless it
+/// is copied to [`WriteOptions::output_dir`]; it is only read as far as
+/// tells whether it is skipped, e.g. for being binary (see
+/// [`skip_reason`]).
+///
+/// # Examples
+///
+/// ```no_run
+/// use basefmt::format::FormatResult;
+/// use basefmt::runner::{RunOptions, run_format_with};
+/// use std::path::Path;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// let changed = AtomicUsize::new(0);
+/// run_format_with(&[Path::new("src")], &RunOptions::default(), |_path, result| {
+///     if let Ok(FormatResult::Changed(_)) = result {
+///         changed.fetch_add(1, Ordering::Relaxed);
+///     }
+/// })
+/// .unwrap();
+/// println!("Changed {} files", changed.load(Ordering::Relaxed));
+/// ```
+#[deprecated(since = "0.1.1", note = "use `Runner::format_with` instead")]
+pub fn run_format_with<F>(
+    paths: &[impl AsRef<Path>],
+    options: &RunOptions,
+    on_file: F,
+) -> io::Result<RunnerResult>
+where
+    F: Fn(&Path, &io::Result<FormatResult>) + Sync,
+{
+    format_paths(paths, options, on_file)
+}
+
+/// Formats files in `paths` as [`run_format_with`] says, which
+/// [`Runner`] and the deprecated free functions share.
+fn format_paths<F>(
+    paths: &[impl AsRef<Path>],
+    options: &RunOptions,
+    on_file: F,
+) -> io::Result<RunnerResult>
+where
+    F: Fn(&Path, &io::Result<FormatResult>) + Sync,
+{
+    let config_dir = determine_config_dir(paths, options);
+    let config = load_config(config_dir, options)?.with_additional_exclude(&options.exclude)?;
+    let files = find_files_for(paths, options, &config)?;
+    let config_dir_abs =
+        canonical::canonicalize(config_dir).unwrap_or_else(|_| config_dir.to_path_buf());
+
+    let resolver = rule_resolver_for(options, &config, &config_dir_abs, options.verbose)?;
+    let filtered_files = collect_tasks(files, paths, &config, &config_dir_abs, options, &resolver);
+    report_config_warnings(&resolver);
+
+    let _lock = lock::acquire(options, &config_dir_abs);
+    let cache = Cache::open(options, &config, &config_dir_abs, false);
+    let result = run_tasks(
+        filtered_files,
+        options,
+        options.jobs.or(config.jobs),
+        |task| {
+            // Every text file is copied to the output directory
+            if task.rules.changes_nothing() && options.write.output_dir.is_none() {
+                return Ok(FormatResult::Skipped(
+                    skip_reason(&task.path, &task.rules)?.unwrap_or(SkipReason::NoRules),
+                ));
+            }
+            cache.run(task, |task| {
+                format_file_with_options(&task.path, &task.rules, &options.write)
+            })
+        },
+        on_file,
+    );
+    cache.save();
+    result
 }
 
 /// Checks if files in the specified paths are properly formatted, in parallel.
@@ -133,108 +216,180 @@ pub fn run_format(paths: &[impl AsRef<Path>]) -> io::Result<RunnerResult> {
 ///     println!("{} files need formatting", result.unformatted_count);
 /// }
 /// ```
+#[deprecated(since = "0.1.1", note = "use `Runner` instead")]
 pub fn run_check(paths: &[impl AsRef<Path>]) -> io::Result<RunnerResult> {
-    let config_dir = determine_config_dir(paths);
-    let config = Config::load(config_dir).unwrap_or_default();
-    let files = find_files(paths)?;
-    let config_dir_abs = config_dir
-        .canonicalize()
-        .unwrap_or_else(|_| config_dir.to_path_buf());
-
-    let mut rule_cache = EditorConfigCache::new();
-    let filtered_files = collect_tasks(files, &config, &config_dir_abs, &mut rule_cache);
-
-    let error_count = AtomicUsize::new(0);
-    let unformatted_count = AtomicUsize::new(0);
-
-    // Use parallel processing only for larger file counts to avoid overhead
-    const PARALLEL_THRESHOLD: usize = 10;
-
-    if filtered_files.len() < PARALLEL_THRESHOLD {
-        for task in &filtered_files {
-            match check_file_with_rules(&task.path, &task.rules) {
-                Ok(CheckResult::Formatted | CheckResult::Skipped) => {}
-                Ok(CheckResult::NeedsFormatting) => {
-                    eprintln!("{}: not formatted", task.path.display());
-                    unformatted_count.fetch_add(1, Ordering::Relaxed);
-                }
-                Err(err) => {
-                    eprintln!("{}: {}", task.path.display(), err);
-                    error_count.fetch_add(1, Ordering::Relaxed);
-                }
-            }
-        }
-    } else {
-        filtered_files.par_iter().for_each(|task| {
-            match check_file_with_rules(&task.path, &task.rules) {
-                Ok(CheckResult::Formatted | CheckResult::Skipped) => {}
-                Ok(CheckResult::NeedsFormatting) => {
-                    eprintln!("{}: not formatted", task.path.display());
-                    unformatted_count.fetch_add(1, Ordering::Relaxed);
-                }
-                Err(err) => {
-                    eprintln!("{}: {}", task.path.display(), err);
-                    error_count.fetch_add(1, Ordering::Relaxed);
-                }
-            }
-        });
-    }
+    check_paths(paths, &RunOptions::default(), |_, _| {})
+}
 
-    Ok(RunnerResult {
-        total_files: filtered_files.len(),
-        error_count: error_count.load(Ordering::Relaxed),
-        unformatted_count: unformatted_count.load(Ordering::Relaxed),
-    })
+/// Checks files in the specified paths in parallel using the given options.
+///
+/// Behaves like [`run_check`], but lets callers choose how the outcome of
+/// each file is reported. Write options are ignored since nothing is written.
+///
+/// # Examples
+///
+/// ```no_run
+/// use basefmt::runner::{OutputFormat, RunOptions, run_check_with_options};
+/// use std::path::Path;
+///
+/// let options = RunOptions {
+///     output: OutputFormat::Jsonl,
+///     ..RunOptions::default()
+/// };
+/// let result = run_check_with_options(&[Path::new("src")], &options).unwrap();
+/// println!("{} files need formatting", result.unformatted_count);
+/// ```
+#[deprecated(since = "0.1.1", note = "use `Runner` instead")]
+pub fn run_check_with_options(
+    paths: &[impl AsRef<Path>],
+    options: &RunOptions,
+) -> io::Result<RunnerResult> {
+    check_paths(paths, options, |_, _| {})
 }
 
-fn determine_config_dir(paths: &[impl AsRef<Path>]) -> &Path {
-    if let Some(first_path) = paths.first() {
-        let path = first_path.as_ref();
-        if path.is_dir() {
-            path
-        } else {
-            path.parent().unwrap_or_else(|| Path::new("."))
-        }
-    } else {
-        Path::new(".")
-    }
+/// Checks files like [`run_check_with_options`], calling `on_file` with the
+/// outcome of each file as soon as it is done.
+///
+/// As with [`run_format_with`], `on_file` may be called from several threads
+/// at once, and an invalid `.basefmt.toml` is an error.
+#[deprecated(since = "0.1.1", note = "use `Runner::check_with` instead")]
+pub fn run_check_with<F>(
+    paths: &[impl AsRef<Path>],
+    options: &RunOptions,
+    on_file: F,
+) -> io::Result<RunnerResult>
+where
+    F: Fn(&Path, &io::Result<CheckResult>) + Sync,
+{
+    check_paths(paths, options, on_file)
 }
 
-fn collect_tasks(
-    files: Vec<PathBuf>,
-    config: &Config,
-    config_dir_abs: &Path,
-    rule_cache: &mut EditorConfigCache,
-) -> Vec<FileTask> {
-    let mut tasks = Vec::with_capacity(files.len());
-    for path in files {
-        let canonical = match path.canonicalize() {
-            Ok(abs) => abs,
-            Err(err) => {
-                eprintln!("{}: failed to canonicalize: {}", path.display(), err);
-                continue;
+/// Checks files in `paths` as [`run_check_with`] says, which [`Runner`]
+/// and the deprecated free functions share.
+fn check_paths<F>(
+    paths: &[impl AsRef<Path>],
+    options: &RunOptions,
+    on_file: F,
+) -> io::Result<RunnerResult>
+where
+    F: Fn(&Path, &io::Result<CheckResult>) + Sync,
+{
+    let config_dir = determine_config_dir(paths, options);
+    let config = load_config(config_dir, options)?.with_additional_exclude(&options.exclude)?;
+    let files = find_files_for(paths, options, &config)?;
+    let config_dir_abs =
+        canonical::canonicalize(config_dir).unwrap_or_else(|_| config_dir.to_path_buf());
+
+    let resolver = rule_resolver_for(options, &config, &config_dir_abs, options.verbose)?;
+    let filtered_files = collect_tasks(files, paths, &config, &config_dir_abs, options, &resolver);
+    report_config_warnings(&resolver);
+
+    let cache = Cache::open(options, &config, &config_dir_abs, true);
+    let Some(patch_path) = &options.write_patch else {
+        let result = run_tasks(
+            filtered_files,
+            options,
+            options.jobs.or(config.jobs),
+            |task| {
+                if task.rules.changes_nothing() {
+                    return Ok(CheckResult::Skipped(
+                        skip_reason(&task.path, &task.rules)?.unwrap_or(SkipReason::NoRules),
+                    ));
+                }
+                cache.run(task, |task| {
+                    if options.first_violation {
+                        check_file_until_violation(&task.path, &task.rules)
+                    } else {
+                        check_file_with_rules(&task.path, &task.rules)
+                    }
+                })
+            },
+            on_file,
+        );
+        cache.save();
+        return result;
+    };
+    let patches = PatchCollector::default();
+    let result = run_tasks(
+        filtered_files,
+        options,
+        options.jobs.or(config.jobs),
+        |task| {
+            if task.rules.changes_nothing() {
+                return Ok(CheckResult::Skipped(
+                    skip_reason(&task.path, &task.rules)?.unwrap_or(SkipReason::NoRules),
+                ));
             }
-        };
-
-        let rel_path = canonical
-            .strip_prefix(config_dir_abs)
-            .unwrap_or(canonical.as_path());
+            cache.run(task, |task| patches.check(task))
+        },
+        on_file,
+    );
+    cache.save();
+    patches.write(patch_path)?;
+    result
+}
 
-        if config.is_excluded(rel_path) {
-            continue;
-        }
+/// Validates the configuration that applies to files in the specified paths.
+///
+/// Returns an error if `.basefmt.toml` is invalid, or one in a subdirectory
+/// that applies to a file, which a run would ignore, and otherwise the warnings from linting every `.editorconfig` that applies
+/// to a file that isn't excluded (see
+/// [`lint_editorconfig`](crate::editorconfig::lint_editorconfig)).
+///
+/// # Examples
+///
+/// ```no_run
+/// use basefmt::runner::check_config;
+/// use std::path::Path;
+///
+/// for warning in check_config(&[Path::new(".")]).unwrap() {
+///     eprintln!("{warning}");
+/// }
+/// ```
+pub fn check_config(paths: &[impl AsRef<Path>]) -> io::Result<Vec<PropertyWarning>> {
+    check_config_with_options(paths, &RunOptions::default())
+}
 
-        let rules = rule_cache.rules_for(&canonical);
-        tasks.push(FileTask { path, rules });
+/// Validates the configuration like [`check_config`], looking up the
+/// EditorConfig files a run with `options` would read.
+pub fn check_config_with_options(
+    paths: &[impl AsRef<Path>],
+    options: &RunOptions,
+) -> io::Result<Vec<PropertyWarning>> {
+    let config_dir = determine_config_dir(paths, options);
+    let config = load_config(config_dir, options)?.with_additional_exclude(&options.exclude)?;
+    let files = find_files_for(paths, options, &config)?;
+    let config_dir_abs =
+        canonical::canonicalize(config_dir).unwrap_or_else(|_| config_dir.to_path_buf());
+
+    let resolver = rule_resolver_for(options, &config, &config_dir_abs, true)?;
+    collect_tasks(files, paths, &config, &config_dir_abs, options, &resolver);
+    if let Some(err) = resolver
+        .nested_configs()
+        .and_then(|nested| nested.take_errors().into_iter().next())
+    {
+        return Err(err.into());
     }
-    tasks
+    Ok(resolver.warnings())
 }
 
 #[cfg(test)]
+#[expect(
+    deprecated,
+    reason = "the free functions are tested until they are removed"
+)]
 mod tests {
+    use super::tasks::PARALLEL_THRESHOLD;
     use super::*;
+    use crate::format::{SkipReason, Violation, WriteOptions};
+    use crate::rules::RuleProfile;
     use indoc::indoc;
+    use rayon::ThreadPoolBuilder;
+    use rstest::rstest;
     use std::fs;
+    use std::num::NonZeroUsize;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
     use tempfile::TempDir;
 
     // Helper function to create a .editorconfig file with all rules enabled
@@ -255,44 +410,27 @@ mod tests {
     }
 
     #[test]
-    fn test_runner_result_exit_code_success() {
-        let result = RunnerResult {
-            total_files: 5,
-            error_count: 0,
-            unformatted_count: 0,
-        };
-        assert_eq!(result.exit_code(), 0);
-    }
-
-    #[test]
-    fn test_runner_result_exit_code_unformatted() {
-        let result = RunnerResult {
-            total_files: 5,
-            error_count: 0,
-            unformatted_count: 2,
-        };
-        assert_eq!(result.exit_code(), 1);
-    }
-
-    #[test]
-    fn test_runner_result_exit_code_error() {
-        let result = RunnerResult {
-            total_files: 5,
-            error_count: 1,
-            unformatted_count: 0,
-        };
-        assert_eq!(result.exit_code(), 2);
-    }
-
-    #[test]
-    fn test_runner_result_exit_code_error_priority() {
-        let result = RunnerResult {
-            total_files: 5,
-            error_count: 1,
-            unformatted_count: 2,
+    fn test_run_format_interrupted_before_start() {
+        let temp_dir = TempDir::new().unwrap();
+        create_default_editorconfig(&temp_dir);
+        let file = temp_dir.path().join("dirty.txt");
+        fs::write(&file, "dirty").unwrap();
+        let options = RunOptions {
+            interrupt: Interrupt::new(),
+            ..RunOptions::default()
         };
-        // Errors have higher priority than unformatted
-        assert_eq!(result.exit_code(), 2);
+        options.interrupt.trigger();
+
+        let result = run_format_with_options(&[&file], &options).unwrap();
+
+        assert_eq!(
+            (
+                result.total_files,
+                result.interrupted_count,
+                fs::read_to_string(&file).unwrap()
+            ),
+            (1, 1, "dirty".to_string())
+        );
     }
 
     #[test]
@@ -435,6 +573,348 @@ mod tests {
         assert_eq!(result.exit_code(), 1);
     }
 
+    #[rstest]
+    #[case::format(
+        false,
+        RunnerResult {
+            total_files: 3,
+            changed_count: 1,
+            unchanged_count: 1,
+            skipped_count: 1,
+            binary_count: 1,
+            changed_files: vec![PathBuf::from("dirty.txt")],
+            ..RunnerResult::default()
+        }
+    )]
+    #[case::check(
+        true,
+        RunnerResult {
+            total_files: 3,
+            unformatted_count: 1,
+            unchanged_count: 1,
+            skipped_count: 1,
+            binary_count: 1,
+            ..RunnerResult::default()
+        }
+    )]
+    fn test_run_counts_outcomes(#[case] check: bool, #[case] expected: RunnerResult) {
+        let temp_dir = TempDir::new().unwrap();
+        create_default_editorconfig(&temp_dir);
+        fs::write(temp_dir.path().join("clean.txt"), "clean\n").unwrap();
+        fs::write(temp_dir.path().join("dirty.txt"), "dirty").unwrap();
+        fs::write(temp_dir.path().join("binary.bin"), [0xff, 0xfe, 0x00]).unwrap();
+
+        let result = if check {
+            run_check(&[temp_dir.path()])
+        } else {
+            run_format(&[temp_dir.path()])
+        };
+
+        let mut result = result.unwrap();
+        // The expected paths are relative to the temporary directory
+        for path in &mut result.changed_files {
+            *path = path.strip_prefix(temp_dir.path()).unwrap().to_path_buf();
+        }
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_run_format_with_reports_each_file() {
+        let temp_dir = TempDir::new().unwrap();
+        create_default_editorconfig(&temp_dir);
+        let clean = temp_dir.path().join("clean.txt");
+        let dirty = temp_dir.path().join("dirty.txt");
+        fs::write(&clean, "clean\n").unwrap();
+        fs::write(&dirty, "dirty").unwrap();
+        let seen = Mutex::new(Vec::new());
+
+        run_format_with(
+            &[temp_dir.path()],
+            &RunOptions::default(),
+            |path, result| {
+                let result = result.as_ref().map_err(io::Error::kind).cloned();
+                seen.lock().unwrap().push((path.to_path_buf(), result));
+            },
+        )
+        .unwrap();
+
+        let mut seen = seen.into_inner().unwrap();
+        seen.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            seen,
+            vec![
+                (clean, Ok(FormatResult::Unchanged)),
+                (
+                    dirty,
+                    Ok(FormatResult::Changed(vec![Violation::MissingFinalNewline]))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_check_with_reports_each_file() {
+        let temp_dir = TempDir::new().unwrap();
+        create_default_editorconfig(&temp_dir);
+        let file = temp_dir.path().join("dirty.txt");
+        fs::write(&file, "dirty").unwrap();
+        let seen = Mutex::new(Vec::new());
+
+        run_check_with(&[&file], &RunOptions::default(), |path, result| {
+            let result = result.as_ref().map_err(io::Error::kind).cloned();
+            seen.lock().unwrap().push((path.to_path_buf(), result));
+        })
+        .unwrap();
+
+        assert_eq!(
+            seen.into_inner().unwrap(),
+            vec![(
+                file,
+                Ok(CheckResult::NeedsFormatting(vec![
+                    Violation::MissingFinalNewline
+                ]))
+            )]
+        );
+    }
+
+    #[rstest]
+    #[case::excluded(
+        RunOptions { exclude: vec!["*.log".to_string()], ..RunOptions::default() },
+        (SkipReason::Excluded, 1, 1, 0)
+    )]
+    #[case::too_large(
+        RunOptions { max_file_size: Some(1), ..RunOptions::default() },
+        (SkipReason::TooLarge, 1, 0, 1)
+    )]
+    fn test_run_reports_files_skipped_unread(
+        #[case] options: RunOptions,
+        #[case] expected: (SkipReason, usize, usize, usize),
+    ) {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("a.log"), "a \n").unwrap();
+        let checked = Mutex::new(Vec::new());
+
+        let result = run_check_with(&[temp_dir.path()], &options, |_, result| {
+            checked.lock().unwrap().push(result.as_ref().ok().cloned());
+        })
+        .unwrap();
+
+        let (reason, skipped, excluded, too_large) = expected;
+        assert_eq!(
+            (
+                checked.into_inner().unwrap(),
+                result.skipped_count,
+                result.excluded_count,
+                result.too_large_count
+            ),
+            (
+                vec![Some(CheckResult::Skipped(reason))],
+                skipped,
+                excluded,
+                too_large
+            )
+        );
+    }
+
+    #[test]
+    fn test_run_skips_formatting_files_whose_rules_change_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        let binary = temp_dir.path().join("a.bin");
+        let text = temp_dir.path().join("b.txt");
+        fs::write(&binary, b"\x00\x01\xff").unwrap();
+        fs::write(&text, "\n\ntext  ").unwrap(); // ast-grep-ignore: prefer-indoc
+        let options = RunOptions {
+            profile: Some(RuleProfile::None),
+            ..RunOptions::default()
+        };
+        let checked = Mutex::new(Vec::new());
+        let formatted = Mutex::new(Vec::new());
+
+        run_check_with(&[&binary], &options, |_, result| {
+            checked.lock().unwrap().push(result.as_ref().ok().cloned());
+        })
+        .unwrap();
+        run_format_with(&[&text], &options, |_, result| {
+            formatted
+                .lock()
+                .unwrap()
+                .push(result.as_ref().ok().cloned());
+        })
+        .unwrap();
+
+        assert_eq!(
+            (
+                checked.into_inner().unwrap(),
+                formatted.into_inner().unwrap(),
+                fs::read_to_string(&text).unwrap()
+            ),
+            (
+                vec![Some(CheckResult::Skipped(SkipReason::Binary))],
+                vec![Some(FormatResult::Skipped(SkipReason::NoRules))],
+                "\n\ntext  ".to_string() // ast-grep-ignore: prefer-indoc
+            )
+        );
+    }
+
+    #[test]
+    fn test_run_format_with_jobs_uses_pool_of_that_size() {
+        let temp_dir = TempDir::new().unwrap();
+        create_default_editorconfig(&temp_dir);
+        // Enough files to be processed in parallel
+        for i in 0..PARALLEL_THRESHOLD {
+            fs::write(temp_dir.path().join(format!("file{i}.txt")), "dirty").unwrap();
+        }
+        let options = RunOptions {
+            jobs: NonZeroUsize::new(3),
+            ..RunOptions::default()
+        };
+        let pool_sizes = Mutex::new(Vec::new());
+
+        let result = run_format_with(&[temp_dir.path()], &options, |_, _| {
+            pool_sizes
+                .lock()
+                .unwrap()
+                .push(rayon::current_num_threads());
+        })
+        .unwrap();
+
+        assert_eq!(
+            (result.total_files, pool_sizes.into_inner().unwrap()),
+            (PARALLEL_THRESHOLD, vec![3; PARALLEL_THRESHOLD])
+        );
+    }
+
+    #[test]
+    fn test_run_format_uses_dedicated_pool() {
+        let temp_dir = TempDir::new().unwrap();
+        create_default_editorconfig(&temp_dir);
+        for i in 0..PARALLEL_THRESHOLD {
+            fs::write(temp_dir.path().join(format!("file{i}.txt")), "dirty").unwrap();
+        }
+        let thread_names = Mutex::new(Vec::new());
+        // A single job, so that every file runs on the same named thread
+        let options = RunOptions {
+            jobs: NonZeroUsize::new(1),
+            ..RunOptions::default()
+        };
+
+        // A pool the caller is already running on must not be reused
+        let caller_pool = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        caller_pool.install(|| {
+            run_format_with(&[temp_dir.path()], &options, |_, _| {
+                let name = std::thread::current().name().map(str::to_string);
+                thread_names.lock().unwrap().push(name);
+            })
+            .unwrap();
+        });
+
+        assert_eq!(
+            thread_names.into_inner().unwrap(),
+            vec![Some("basefmt-0".to_string()); PARALLEL_THRESHOLD]
+        );
+    }
+
+    #[test]
+    fn test_run_format_sequential_keeps_discovery_order() {
+        let temp_dir = TempDir::new().unwrap();
+        create_default_editorconfig(&temp_dir);
+        let files: Vec<PathBuf> = (0..PARALLEL_THRESHOLD)
+            .rev()
+            .map(|i| temp_dir.path().join(format!("file{i}.txt")))
+            .collect();
+        for file in &files {
+            fs::write(file, "dirty").unwrap();
+        }
+        let options = RunOptions {
+            sequential: true,
+            ..RunOptions::default()
+        };
+        let caller = std::thread::current().id();
+        let seen = Mutex::new(Vec::new());
+
+        run_format_with(&files, &options, |path, _| {
+            let on_caller = std::thread::current().id() == caller;
+            seen.lock().unwrap().push((path.to_path_buf(), on_caller));
+        })
+        .unwrap();
+
+        let expected: Vec<_> = files.into_iter().map(|file| (file, true)).collect();
+        assert_eq!(seen.into_inner().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_run_check_fail_fast() {
+        let temp_dir = TempDir::new().unwrap();
+        create_default_editorconfig(&temp_dir);
+        let files: Vec<PathBuf> = (0..3)
+            .map(|i| temp_dir.path().join(format!("file{i}.txt")))
+            .collect();
+        fs::write(&files[0], "clean\n").unwrap();
+        fs::write(&files[1], "dirty").unwrap();
+        fs::write(&files[2], "dirty").unwrap();
+        let options = RunOptions {
+            fail_fast: true,
+            sequential: true,
+            ..RunOptions::default()
+        };
+
+        let result = run_check_with(&files, &options, |_, _| {}).unwrap();
+
+        assert_eq!(
+            (result.exit_code(), result),
+            (
+                1,
+                RunnerResult {
+                    total_files: 3,
+                    unchanged_count: 1,
+                    unformatted_count: 1,
+                    cancelled_count: 1,
+                    ..RunnerResult::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_run_format_max_errors() {
+        let temp_dir = TempDir::new().unwrap();
+        create_default_editorconfig(&temp_dir);
+        let files: Vec<PathBuf> = (0..3)
+            .map(|i| temp_dir.path().join(format!("file{i}.txt")))
+            .collect();
+        for file in &files {
+            fs::write(file, "dirty").unwrap();
+        }
+        // Writing into a file instead of a directory fails for every file
+        let output_dir = temp_dir.path().join("out");
+        fs::write(&output_dir, "").unwrap();
+        let options = RunOptions {
+            write: WriteOptions {
+                output_dir: Some(output_dir),
+                ..WriteOptions::default()
+            },
+            max_errors: NonZeroUsize::new(2),
+            sequential: true,
+            ..RunOptions::default()
+        };
+
+        let result = run_format_with(&files, &options, |_, _| {}).unwrap();
+
+        assert_eq!(
+            (result.exit_code(), result),
+            (
+                4,
+                RunnerResult {
+                    total_files: 3,
+                    error_count: 2,
+                    cancelled_count: 1,
+                    aborted: true,
+                    ..RunnerResult::default()
+                }
+            )
+        );
+    }
+
     #[test]
     fn test_run_check_nonexistent_path() {
         let result = run_check(&["/nonexistent/path"]);
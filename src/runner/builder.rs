@@ -0,0 +1,359 @@
+use super::{
+    CacheStrategy, ErrorFormat, Interrupt, OutputFormat, RunOptions, RunnerResult, check_paths,
+    format_paths,
+};
+use crate::editorconfig::FormatRules;
+use crate::format::{CheckResult, FormatResult, WriteOptions};
+use crate::rules::RuleProfile;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+/// A formatting or checking run over a set of paths, configured step by
+/// step.
+///
+/// Every setting defaults to the value of [`RunOptions::default`], so only
+/// the ones that differ need to be given; settings added later won't break
+/// existing callers.
+///
+/// # Examples
+///
+/// ```no_run
+/// use basefmt::runner::Runner;
+///
+/// let result = Runner::new()
+///     .paths(["src", "docs"])
+///     .check(true)
+///     .jobs(8)
+///     .exclude(["docs/generated/**"])
+///     .run()
+///     .unwrap();
+/// println!("{} files need formatting", result.unformatted_count);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Runner {
+    paths: Vec<PathBuf>,
+    check: bool,
+    options: RunOptions,
+}
+
+impl Runner {
+    /// Creates a runner that formats the current directory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds files or directories to process. Without any, the current
+    /// directory is processed.
+    pub fn paths(mut self, paths: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        self.paths.extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    /// Only checks whether files are formatted instead of rewriting them.
+    pub fn check(mut self, check: bool) -> Self {
+        self.check = check;
+        self
+    }
+
+    /// In check mode, stops at the first file that needs formatting (see
+    /// [`RunOptions::fail_fast`]).
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.options.fail_fast = fail_fast;
+        self
+    }
+
+    /// In check mode, stops reading each file at its first violation (see
+    /// [`RunOptions::first_violation`]).
+    pub fn first_violation(mut self, first_violation: bool) -> Self {
+        self.options.first_violation = first_violation;
+        self
+    }
+
+    /// Shows the paths of files in reports as absolute paths (see
+    /// [`RunOptions::absolute_paths`]).
+    pub fn absolute_paths(mut self, absolute_paths: bool) -> Self {
+        self.options.absolute_paths = absolute_paths;
+        self
+    }
+
+    /// Stops starting new files once `max_errors` files failed; 0 means no
+    /// limit (see [`RunOptions::max_errors`]).
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.options.max_errors = NonZeroUsize::new(max_errors);
+        self
+    }
+
+    /// Processes files with `jobs` threads; 0 means one per CPU core (see
+    /// [`RunOptions::jobs`]).
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.options.jobs = NonZeroUsize::new(jobs);
+        self
+    }
+
+    /// Processes files one at a time in discovery order (see
+    /// [`RunOptions::sequential`]).
+    pub fn sequential(mut self, sequential: bool) -> Self {
+        self.options.sequential = sequential;
+        self
+    }
+
+    /// Adds glob patterns of files to leave alone (see
+    /// [`RunOptions::exclude`]).
+    pub fn exclude(mut self, patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.options
+            .exclude
+            .extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Also processes files in nested git repositories, such as submodules
+    /// (see [`RunOptions::include_submodules`]).
+    pub fn include_submodules(mut self, include_submodules: bool) -> Self {
+        self.options.include_submodules = include_submodules;
+        self
+    }
+
+    /// Applies `.gitignore` files outside git repositories too (see
+    /// [`RunOptions::no_require_git`]).
+    pub fn no_require_git(mut self, no_require_git: bool) -> Self {
+        self.options.no_require_git = no_require_git;
+        self
+    }
+
+    /// Ignores the keys of `.basefmt.toml` files that basefmt doesn't know
+    /// instead of failing on them (see
+    /// [`RunOptions::allow_unknown_config_keys`]).
+    pub fn allow_unknown_config_keys(mut self, allow_unknown_config_keys: bool) -> Self {
+        self.options.allow_unknown_config_keys = allow_unknown_config_keys;
+        self
+    }
+
+    /// Sets how formatted files are written back to disk.
+    pub fn write(mut self, write: WriteOptions) -> Self {
+        self.options.write = write;
+        self
+    }
+
+    /// Sets how the outcome of each file is reported.
+    pub fn output(mut self, output: OutputFormat) -> Self {
+        self.options.output = output;
+        self
+    }
+
+    /// Sets how text output renders the files that could not be processed.
+    pub fn error_format(mut self, error_format: ErrorFormat) -> Self {
+        self.options.error_format = error_format;
+        self
+    }
+
+    /// Skips files a previous run found formatted, keeping the cache in
+    /// `dir` if set (see [`RunOptions::cache`]).
+    pub fn cache(mut self, cache: bool, dir: Option<PathBuf>) -> Self {
+        self.options.cache = cache;
+        self.options.cache_dir = dir;
+        self
+    }
+
+    /// Sets how the cache tells whether a file has changed.
+    pub fn cache_strategy(mut self, strategy: CacheStrategy) -> Self {
+        self.options.cache_strategy = Some(strategy);
+        self
+    }
+
+    /// Holds a lock on the project while formatting (see
+    /// [`RunOptions::lock`]).
+    pub fn lock(mut self, lock: bool) -> Self {
+        self.options.lock = lock;
+        self
+    }
+
+    /// Stops the run from starting new files once `interrupt` is triggered.
+    pub fn interrupt(mut self, interrupt: Interrupt) -> Self {
+        self.options.interrupt = interrupt;
+        self
+    }
+
+    /// Lists rewritten files and warns about misspelled `.editorconfig`
+    /// properties (see [`RunOptions::verbose`]).
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.options.verbose = verbose;
+        self
+    }
+
+    /// Applies `rules` to every file, overriding every other source of rules
+    /// (see [`RunOptions::rules`]).
+    pub fn rules(mut self, rules: FormatRules) -> Self {
+        self.options.rules = rules;
+        self
+    }
+
+    /// Sets the rules for files EditorConfig says nothing about.
+    pub fn profile(mut self, profile: RuleProfile) -> Self {
+        self.options.profile = Some(profile);
+        self
+    }
+
+    /// Replaces every setting other than the paths and check mode with
+    /// `options`, for settings that have no method of their own.
+    pub fn options(mut self, options: RunOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Runs, formatting or checking every file under the paths.
+    ///
+    /// Returns an error if file discovery fails or the configuration is
+    /// invalid.
+    pub fn run(&self) -> io::Result<RunnerResult> {
+        if self.check {
+            self.check_with(|_, _| {})
+        } else {
+            self.format_with(|_, _| {})
+        }
+    }
+
+    /// Formats every file under the paths whatever [`check`](Self::check)
+    /// says, calling `on_file` with the outcome of each file as soon as it is
+    /// done.
+    ///
+    /// `on_file` may be called from several threads at once, so it must be
+    /// `Sync`; it runs in addition to the reporting chosen by
+    /// [`output`](Self::output).
+    ///
+    /// Returns an error before processing any file if file discovery fails
+    /// or `.basefmt.toml` is invalid, including if it has a key basefmt
+    /// doesn't know unless [`RunOptions::allow_unknown_config_keys`] is set.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use basefmt::format::FormatResult;
+    /// use basefmt::runner::Runner;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// let changed = AtomicUsize::new(0);
+    /// Runner::new()
+    ///     .paths(["src"])
+    ///     .format_with(|_path, result| {
+    ///         if let Ok(FormatResult::Changed(_)) = result {
+    ///             changed.fetch_add(1, Ordering::Relaxed);
+    ///         }
+    ///     })
+    ///     .unwrap();
+    /// println!("Changed {} files", changed.load(Ordering::Relaxed));
+    /// ```
+    pub fn format_with<F>(&self, on_file: F) -> io::Result<RunnerResult>
+    where
+        F: Fn(&Path, &io::Result<FormatResult>) + Sync,
+    {
+        format_paths(&self.paths_or_default(), &self.options, on_file)
+    }
+
+    /// Checks every file under the paths whatever [`check`](Self::check)
+    /// says, calling `on_file` with the outcome of each file as soon as it is
+    /// done, like [`format_with`](Self::format_with).
+    pub fn check_with<F>(&self, on_file: F) -> io::Result<RunnerResult>
+    where
+        F: Fn(&Path, &io::Result<CheckResult>) + Sync,
+    {
+        check_paths(&self.paths_or_default(), &self.options, on_file)
+    }
+
+    fn paths_or_default(&self) -> Vec<PathBuf> {
+        if self.paths.is_empty() {
+            vec![PathBuf::from(".")]
+        } else {
+            self.paths.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::Violation;
+    use std::fs;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_builder_collects_settings() {
+        let runner = Runner::new()
+            .paths(["a", "b"])
+            .paths(["c"])
+            .check(true)
+            .jobs(8)
+            .exclude(["vendor/**"])
+            .include_submodules(true);
+
+        assert_eq!(
+            (runner.paths.clone(), runner.check, runner.options.clone()),
+            (
+                vec![PathBuf::from("a"), PathBuf::from("b"), PathBuf::from("c")],
+                true,
+                RunOptions {
+                    jobs: NonZeroUsize::new(8),
+                    exclude: vec!["vendor/**".to_string()],
+                    include_submodules: true,
+                    // Interrupts compare by identity
+                    interrupt: runner.options.interrupt.clone(),
+                    ..RunOptions::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_run() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("a.txt"), "a").unwrap();
+        fs::create_dir(temp_dir.path().join("vendor")).unwrap();
+        fs::write(temp_dir.path().join("vendor/b.txt"), "b").unwrap();
+        let runner = Runner::new()
+            .paths([temp_dir.path()])
+            .exclude(["vendor/**"]);
+
+        let checked = runner.clone().check(true).run().unwrap();
+        let formatted = runner.run().unwrap();
+
+        assert_eq!(
+            (
+                checked.unformatted_count,
+                formatted.changed_count,
+                fs::read_to_string(temp_dir.path().join("a.txt")).unwrap(),
+                fs::read_to_string(temp_dir.path().join("vendor/b.txt")).unwrap()
+            ),
+            (1, 1, "a\n".to_string(), "b".to_string())
+        );
+    }
+
+    #[test]
+    fn test_check_with() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("a.txt");
+        fs::write(&file, "a").unwrap();
+        let outcomes = Mutex::new(Vec::new());
+
+        // The check mode of the runner doesn't matter
+        Runner::new()
+            .paths([&file])
+            .check_with(|path, result| {
+                outcomes.lock().unwrap().push((
+                    path.to_path_buf(),
+                    result.as_ref().map_err(io::Error::kind).cloned(),
+                ));
+            })
+            .unwrap();
+
+        assert_eq!(
+            outcomes.into_inner().unwrap(),
+            vec![(
+                file,
+                Ok(CheckResult::NeedsFormatting(vec![
+                    Violation::MissingFinalNewline
+                ]))
+            )]
+        );
+    }
+}
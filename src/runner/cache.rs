@@ -0,0 +1,532 @@
+use super::RunOptions;
+use super::tasks::{FileTask, determine_config_dir};
+use crate::canonical;
+use crate::config::{self, Config};
+use crate::format::{CheckResult, FormatResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::SystemTime;
+use xxhash_rust::xxh3::Xxh3Default;
+
+/// Name of the cache directory created in the project root unless another
+/// one is configured
+const DEFAULT_DIR: &str = ".basefmt-cache";
+
+/// Version of the layout of cache entries and of the hashes in them, part of
+/// every fingerprint so that bumping it when either changes discards the
+/// entries saved before
+const FORMAT_VERSION: u32 = 2;
+
+/// How the cache tells whether a file has changed since it was found
+/// formatted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CacheStrategy {
+    /// By size and modification time, so that unchanged files aren't read
+    #[default]
+    Metadata,
+    /// By size and a hash of the content, for checkouts whose modification
+    /// times change without the content changing or the other way round,
+    /// e.g. restored CI caches and copied trees. Files are read, but not
+    /// formatted, to hash them.
+    Content,
+}
+
+/// Remembers the files a previous run found formatted, so that a file that
+/// hasn't changed since (see [`CacheStrategy`]) and whose configuration is
+/// the same is not processed again.
+///
+/// Each entry holds a fingerprint of the configuration the file was
+/// formatted by: the versions of basefmt and of the cache format,
+/// `.basefmt.toml` along with the
+/// files it extends, and the rules of the file, which are what its
+/// `.editorconfig` and `.gitattributes` files come down to. Changing any of
+/// them makes the file be processed again, while editing an `.editorconfig`
+/// section that doesn't apply to the file keeps its entry.
+#[derive(Default)]
+pub(super) struct Cache {
+    /// File the entries are saved to; nothing is cached if unset
+    file: Option<PathBuf>,
+    /// Hash of the versions of basefmt and of the cache format,
+    /// `.basefmt.toml`, and the files it extends
+    config: u64,
+    strategy: CacheStrategy,
+    /// Formatted files by canonical path
+    entries: Mutex<HashMap<String, Entry>>,
+    /// Whether the entries differ from the saved ones
+    changed: AtomicBool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Entry {
+    len: u64,
+    stamp: Stamp,
+    /// Hash of the configuration the file is formatted by
+    fingerprint: u64,
+}
+
+/// What a file was like when it was found formatted, besides its size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Stamp {
+    Modified(SystemTime),
+    Content(u64),
+}
+
+/// The result of processing a file, which the cache can stand in for.
+pub(super) trait Cacheable {
+    /// The result for a file the cache knows to be formatted
+    const FORMATTED: Self;
+
+    /// Whether the file is formatted after processing.
+    fn is_formatted(&self) -> bool;
+
+    /// Whether the file was rewritten, so that it has changed since it was
+    /// processed.
+    fn is_rewritten(&self) -> bool {
+        false
+    }
+}
+
+impl Cacheable for FormatResult {
+    const FORMATTED: Self = FormatResult::Unchanged;
+
+    fn is_formatted(&self) -> bool {
+        matches!(self, FormatResult::Changed(_) | FormatResult::Unchanged)
+    }
+
+    fn is_rewritten(&self) -> bool {
+        matches!(self, FormatResult::Changed(_))
+    }
+}
+
+impl Cacheable for CheckResult {
+    const FORMATTED: Self = CheckResult::Formatted;
+
+    fn is_formatted(&self) -> bool {
+        matches!(self, CheckResult::Formatted)
+    }
+}
+
+impl Cache {
+    /// Opens the cache of the project in `config_dir_abs` for a run with
+    /// `options`, or one that remembers nothing if caching is off or files
+    /// aren't formatted in place as a whole.
+    ///
+    /// A `check` run, which leaves the tree alone, only uses a cache
+    /// directory that exists or is configured (see [`RunOptions::cache_dir`]).
+    /// A cache file that can't be read is ignored, so that every file is
+    /// processed.
+    pub(super) fn open(
+        options: &RunOptions,
+        config: &Config,
+        config_dir_abs: &Path,
+        check: bool,
+    ) -> Self {
+        if !options.cache || options.write.output_dir.is_some() || !options.write.lines.is_empty() {
+            return Cache::default();
+        }
+        if check
+            && configured_dir(options, config, config_dir_abs).is_none()
+            && !dir_for(options, config, config_dir_abs).is_dir()
+        {
+            return Cache::default();
+        }
+        let file = project_file(options, config, config_dir_abs, "json");
+        let entries = fs::read(&file)
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default();
+        let config_files: Vec<Option<Vec<u8>>> = config
+            .path
+            .iter()
+            .chain(&config.extends)
+            .map(|path| fs::read(path).ok())
+            .collect();
+        Cache {
+            file: Some(file),
+            config: hash((FORMAT_VERSION, env!("CARGO_PKG_VERSION"), config_files)),
+            strategy: options
+                .cache_strategy
+                .or(config.cache_strategy)
+                .unwrap_or_default(),
+            entries: Mutex::new(entries),
+            changed: AtomicBool::new(false),
+        }
+    }
+
+    /// Runs `op` on `task` unless the cache knows its file to be formatted,
+    /// and remembers whether it is afterwards.
+    pub(super) fn run<R: Cacheable>(
+        &self,
+        task: &FileTask,
+        op: impl FnOnce(&FileTask) -> io::Result<R>,
+    ) -> io::Result<R> {
+        let Some(key) = self.file.as_ref().and(task.canonical.to_str()) else {
+            return op(task);
+        };
+        let before = self.entry_for(task);
+        if before.is_some() && self.entries().get(key) == before.as_ref() {
+            return Ok(R::FORMATTED);
+        }
+        let result = op(task);
+        let after = match &result {
+            Ok(result) if result.is_rewritten() => self.entry_for(task),
+            // The file may have changed while it was read
+            Ok(result) if result.is_formatted() => {
+                before.filter(|_| self.entry_for(task) == before)
+            }
+            _ => None,
+        };
+        let mut entries = self.entries();
+        let previous = match after {
+            Some(entry) => entries.insert(key.to_string(), entry),
+            None => entries.remove(key),
+        };
+        if previous != after {
+            self.changed.store(true, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// Saves the entries if they changed, only warning if that fails since
+    /// the next run merely processes every file again.
+    pub(super) fn save(self) {
+        let Some(file) = self.file else {
+            return;
+        };
+        if !self.changed.into_inner() {
+            return;
+        }
+        let entries = self
+            .entries
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Err(err) = write(&file, &entries) {
+            eprintln!("{}: failed to write cache: {err}", file.display());
+        }
+    }
+
+    /// The entry saying that the file of `task` is formatted as it is now,
+    /// if its metadata can be read.
+    fn entry_for(&self, task: &FileTask) -> Option<Entry> {
+        let metadata = fs::metadata(&task.path).ok()?;
+        let stamp = match self.strategy {
+            CacheStrategy::Metadata => Stamp::Modified(metadata.modified().ok()?),
+            CacheStrategy::Content => Stamp::Content(hash(fs::read(&task.path).ok()?)),
+        };
+        Some(Entry {
+            len: metadata.len(),
+            stamp,
+            fingerprint: hash((self.config, &task.rules)),
+        })
+    }
+
+    fn entries(&self) -> MutexGuard<'_, HashMap<String, Entry>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Removes the cache directory a run over `paths` with `options` would use,
+/// returning it if there was one.
+///
+/// # Examples
+///
+/// ```no_run
+/// use basefmt::runner::{RunOptions, clear_cache};
+/// use std::path::Path;
+///
+/// if let Some(dir) = clear_cache(&[Path::new(".")], &RunOptions::default()).unwrap() {
+///     println!("Removed {}", dir.display());
+/// }
+/// ```
+pub fn clear_cache(
+    paths: &[impl AsRef<Path>],
+    options: &RunOptions,
+) -> io::Result<Option<PathBuf>> {
+    let config_dir = determine_config_dir(paths, options);
+    let config = Config::load(config_dir).unwrap_or_default();
+    let config_dir_abs =
+        canonical::canonicalize(config_dir).unwrap_or_else(|_| config_dir.to_path_buf());
+    let dir = dir_for(options, &config, &config_dir_abs);
+    match fs::remove_dir_all(&dir) {
+        Ok(()) => Ok(Some(dir)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(io::Error::new(
+            err.kind(),
+            format!("{}: failed to remove cache: {err}", dir.display()),
+        )),
+    }
+}
+
+/// The cache directory of a run with `options`: the configured one (see
+/// [`configured_dir`]), else `.basefmt-cache` in the project root (see
+/// [`project_root`]).
+pub(super) fn dir_for(options: &RunOptions, config: &Config, config_dir_abs: &Path) -> PathBuf {
+    configured_dir(options, config, config_dir_abs)
+        .unwrap_or_else(|| project_root(config_dir_abs).join(DEFAULT_DIR))
+}
+
+/// [`RunOptions::cache_dir`], else the `cache_dir` key of `.basefmt.toml`
+/// relative to its directory, if either is set.
+fn configured_dir(options: &RunOptions, config: &Config, config_dir_abs: &Path) -> Option<PathBuf> {
+    options.cache_dir.clone().or_else(|| {
+        config
+            .cache_dir
+            .as_ref()
+            .map(|dir| config_dir_abs.join(dir))
+    })
+}
+
+/// The root of the project in `config_dir_abs`: the closest directory at or
+/// above it that has a configuration file or is a git repository, so that a
+/// run on a file deep in the tree doesn't keep a cache next to it, or else
+/// `config_dir_abs` itself.
+fn project_root(config_dir_abs: &Path) -> &Path {
+    config_dir_abs
+        .ancestors()
+        .find(|dir| {
+            config::file_names().any(|name| dir.join(name).is_file()) || dir.join(".git").exists()
+        })
+        .unwrap_or(config_dir_abs)
+}
+
+/// The file with `extension` in the cache directory that belongs to the
+/// project in `config_dir_abs`, named after the project directory so that
+/// projects can share a cache directory, e.g. one under `$XDG_CACHE_HOME`.
+pub(super) fn project_file(
+    options: &RunOptions,
+    config: &Config,
+    config_dir_abs: &Path,
+    extension: &str,
+) -> PathBuf {
+    dir_for(options, config, config_dir_abs)
+        .join(format!("{:016x}.{extension}", hash(config_dir_abs)))
+}
+
+/// Hashes `value` with XXH3, which unlike
+/// [`DefaultHasher`](std::hash::DefaultHasher) is specified and may not
+/// change between Rust releases, so that the hashes saved in the cache and
+/// the name of the cache file stay the same across builds of basefmt.
+pub(super) fn hash(value: impl Hash) -> u64 {
+    let mut hasher = Xxh3Default::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Creates the cache directory that `file` is in, keeping it out of git.
+fn create_dir_of(file: &Path) -> io::Result<&Path> {
+    let dir = file.parent().unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(dir)?;
+    let gitignore = dir.join(".gitignore");
+    if !gitignore.exists() {
+        fs::write(gitignore, "*\n")?;
+    }
+    Ok(dir)
+}
+
+/// Writes `entries` to `file` through a temporary file, so that concurrent
+/// runs never read half a cache.
+fn write(file: &Path, entries: &HashMap<String, Entry>) -> io::Result<()> {
+    let dir = create_dir_of(file)?;
+    let mut temp = tempfile::NamedTempFile::new_in(dir)?;
+    temp.write_all(&serde_json::to_vec(entries)?)?;
+    temp.persist(file).map_err(|err| err.error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editorconfig::FormatRules;
+    use rstest::rstest;
+    use std::cell::Cell;
+    use tempfile::TempDir;
+
+    fn task(path: &Path) -> FileTask {
+        FileTask {
+            path: path.to_path_buf(),
+            canonical: path.to_path_buf(),
+            rules: FormatRules::default(),
+            unconfigured: false,
+            no_editorconfig: false,
+            explicit: false,
+            skip: None,
+        }
+    }
+
+    fn open(dir: &Path) -> Cache {
+        let options = RunOptions {
+            cache: true,
+            ..RunOptions::default()
+        };
+        Cache::open(&options, &Config::load(dir).unwrap(), dir, false)
+    }
+
+    #[test]
+    fn test_skips_formatted_file_until_it_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let file = root.join("a.txt");
+        fs::write(&file, "a\n").unwrap();
+        let calls = Cell::new(0);
+        let run = |cache: &Cache| {
+            cache
+                .run(&task(&file), |_| {
+                    calls.set(calls.get() + 1);
+                    Ok(CheckResult::Formatted)
+                })
+                .unwrap()
+        };
+
+        let cache = open(&root);
+        run(&cache);
+        cache.save();
+        let cache = open(&root);
+        run(&cache);
+        fs::write(&file, "ab\n").unwrap();
+        run(&cache);
+
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[rstest]
+    #[case::same(false, FormatRules::default(), 1)]
+    #[case::basefmt_toml_changed(true, FormatRules::default(), 2)]
+    #[case::rules_changed(
+        false,
+        FormatRules {
+            max_blank_lines: Some(1),
+            ..FormatRules::default()
+        },
+        2
+    )]
+    fn test_processes_file_again_when_configuration_changes(
+        #[case] write_config: bool,
+        #[case] rules: FormatRules,
+        #[case] expected_calls: usize,
+    ) {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let file = root.join("a.txt");
+        fs::write(&file, "a\n").unwrap();
+        let calls = Cell::new(0);
+        let run = |cache: &Cache, task: &FileTask| {
+            cache
+                .run(task, |_| {
+                    calls.set(calls.get() + 1);
+                    Ok(CheckResult::Formatted)
+                })
+                .unwrap()
+        };
+
+        let cache = open(&root);
+        run(&cache, &task(&file));
+        cache.save();
+        if write_config {
+            fs::write(root.join(".basefmt.toml"), "jobs = 2\n").unwrap();
+        }
+        run(
+            &open(&root),
+            &FileTask {
+                rules,
+                ..task(&file)
+            },
+        );
+
+        assert_eq!(calls.get(), expected_calls);
+    }
+
+    #[rstest]
+    #[case::metadata(CacheStrategy::Metadata, 2)]
+    #[case::content(CacheStrategy::Content, 1)]
+    fn test_touched_file(#[case] strategy: CacheStrategy, #[case] expected_calls: usize) {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let file = root.join("a.txt");
+        fs::write(&file, "a\n").unwrap();
+        let options = RunOptions {
+            cache: true,
+            cache_strategy: Some(strategy),
+            ..RunOptions::default()
+        };
+        let calls = Cell::new(0);
+        let run = |cache: &Cache| {
+            cache
+                .run(&task(&file), |_| {
+                    calls.set(calls.get() + 1);
+                    Ok(CheckResult::Formatted)
+                })
+                .unwrap()
+        };
+
+        let cache = Cache::open(&options, &Config::default(), &root, false);
+        run(&cache);
+        fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH)
+            .unwrap();
+        run(&cache);
+
+        assert_eq!(calls.get(), expected_calls);
+    }
+
+    #[test]
+    fn test_forgets_file_that_needs_formatting() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("a.txt");
+        fs::write(&file, "a").unwrap();
+        let cache = open(temp_dir.path());
+        cache
+            .run(&task(&file), |_| Ok(CheckResult::Formatted))
+            .unwrap();
+        fs::write(&file, "a  ").unwrap();
+        cache
+            .run(&task(&file), |_| {
+                Ok(CheckResult::NeedsFormatting(Vec::new()))
+            })
+            .unwrap();
+
+        assert_eq!(cache.entries().len(), 0);
+    }
+
+    #[test]
+    fn test_clear_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let file = root.join("a.txt");
+        fs::write(&file, "a\n").unwrap();
+        let cache = open(&root);
+        cache
+            .run(&task(&file), |_| Ok(CheckResult::Formatted))
+            .unwrap();
+        cache.save();
+
+        let options = RunOptions::default();
+        assert_eq!(
+            (
+                clear_cache(&[&root], &options).unwrap(),
+                clear_cache(&[&root], &options).unwrap(),
+            ),
+            (Some(root.join(DEFAULT_DIR)), None)
+        );
+    }
+
+    #[rstest]
+    #[case::empty(b"", 0x2d06_8005_38d3_94c2)]
+    #[case::one_byte(b"a", 0xe6c6_32b6_1e96_4e1f)]
+    #[case::word(b"foobar", 0xd78f_da63_144c_5c84)]
+    fn test_xxh3(#[case] bytes: &[u8], #[case] expected: u64) {
+        let mut hasher = Xxh3Default::new();
+        hasher.write(bytes);
+        assert_eq!(hasher.finish(), expected);
+    }
+}
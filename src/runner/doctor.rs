@@ -0,0 +1,164 @@
+use super::RunOptions;
+use super::tasks::{collect_tasks, find_files_for, load_config, rule_resolver_for};
+use crate::canonical;
+use crate::config::Config;
+use crate::editorconfig::PropertyWarning;
+use crate::error::BasefmtError;
+use crate::format::SkipReason;
+use crate::rules::{LineEndingConflict, RuleExplanation};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// What [`diagnose`] found out about the configuration of a directory.
+#[derive(Debug)]
+pub struct Diagnosis {
+    /// The `.basefmt.toml` (or YAML or JSON) file of the directory followed
+    /// by the files it extends, or nothing if there is none or it couldn't
+    /// be loaded
+    pub config_files: Vec<PathBuf>,
+    /// The EditorConfig files that apply to the files in the directory,
+    /// outermost first
+    pub editorconfig_files: Vec<PathBuf>,
+    /// Why `.basefmt.toml`, or one in a subdirectory, couldn't be loaded
+    pub config_errors: Vec<BasefmtError>,
+    /// Misspelled properties in the EditorConfig files read
+    pub warnings: Vec<PropertyWarning>,
+    /// Files whose line endings `.gitattributes` and `.editorconfig`
+    /// disagree about
+    pub conflicts: Vec<LineEndingConflict>,
+    /// The rules for a file without an extension in the directory, and
+    /// where each comes from
+    pub rules: Vec<RuleExplanation>,
+}
+
+impl Diagnosis {
+    /// Whether anything is wrong with the configuration.
+    pub fn has_problems(&self) -> bool {
+        !self.config_errors.is_empty() || !self.warnings.is_empty() || !self.conflicts.is_empty()
+    }
+}
+
+/// Checks the configuration that a run with `options` over `dir` would use:
+/// which files it comes from, what is wrong with them, and the rules they
+/// come down to.
+///
+/// Unlike a run, an invalid `.basefmt.toml` is reported in the diagnosis
+/// rather than returned as an error, and the rest is checked as if it didn't
+/// exist. Returns an error if `dir` cannot be resolved or searched.
+///
+/// # Examples
+///
+/// ```no_run
+/// use basefmt::runner::{RunOptions, diagnose};
+/// use std::path::Path;
+///
+/// let diagnosis = diagnose(Path::new("."), &RunOptions::default()).unwrap();
+/// for conflict in &diagnosis.conflicts {
+///     eprintln!("{conflict}");
+/// }
+/// ```
+pub fn diagnose(dir: &Path, options: &RunOptions) -> io::Result<Diagnosis> {
+    let dir_abs = canonical::canonicalize(dir).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("{}: failed to canonicalize: {err}", dir.display()),
+        )
+    })?;
+    let mut config_errors = Vec::new();
+    let config = load_config(&dir_abs, options).unwrap_or_else(|err| {
+        config_errors.push(err);
+        Config::default()
+    });
+    let config = config.with_additional_exclude(&options.exclude)?;
+    let config_files = config.path.iter().chain(&config.extends).cloned().collect();
+
+    let paths = [dir];
+    let files = find_files_for(&paths, options, &config)?;
+    let resolver = rule_resolver_for(options, &config, &dir_abs, true)?;
+    let tasks = collect_tasks(files, &paths, &config, &dir_abs, options, &resolver);
+    let conflicts = tasks
+        .iter()
+        .filter(|task| task.skip != Some(SkipReason::Excluded))
+        .filter_map(|task| resolver.line_ending_conflict(&task.canonical))
+        .collect();
+    let rules = resolver.explain(&dir_abs.join("file"));
+    if let Some(nested) = resolver.nested_configs() {
+        config_errors.extend(nested.take_errors());
+    }
+
+    Ok(Diagnosis {
+        config_files,
+        editorconfig_files: resolver.editorconfig_files_for(&dir_abs),
+        config_errors,
+        warnings: resolver.warnings(),
+        conflicts,
+        rules,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editorconfig::LineEnding;
+    use crate::gitattributes::GitLineEndings;
+    use indoc::indoc;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_diagnose() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().canonicalize().unwrap();
+        fs::write(
+            dir.join(".editorconfig"),
+            indoc! {"
+                root = true
+
+                [*]
+                end_of_line = lf
+                insert_final_newlines = true
+            "},
+        )
+        .unwrap();
+        fs::write(dir.join(".gitattributes"), "*.bat text eol=crlf\n").unwrap();
+        fs::write(dir.join(".basefmt.toml"), "exlcude = []\n").unwrap();
+        fs::write(dir.join("a.bat"), "a\n").unwrap();
+        fs::write(dir.join("b.txt"), "b\n").unwrap();
+
+        let diagnosis = diagnose(&dir, &RunOptions::default()).unwrap();
+
+        assert_eq!(
+            (
+                diagnosis.config_files,
+                diagnosis.editorconfig_files,
+                diagnosis
+                    .config_errors
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>(),
+                diagnosis.warnings.len(),
+                diagnosis.conflicts,
+                diagnosis
+                    .rules
+                    .iter()
+                    .find(|rule| rule.name == "end_of_line")
+                    .and_then(|rule| rule.value.clone()),
+            ),
+            (
+                Vec::new(),
+                vec![dir.join(".editorconfig")],
+                vec![format!(
+                    "{}: unknown key 'exlcude' (did you mean 'exclude'?)",
+                    dir.join(".basefmt.toml").display()
+                )],
+                1,
+                vec![LineEndingConflict {
+                    path: dir.join("a.bat"),
+                    gitattributes: GitLineEndings::Eol(LineEnding::Crlf),
+                    editorconfig: LineEnding::Lf,
+                }],
+                Some("lf".to_string()),
+            )
+        );
+    }
+}
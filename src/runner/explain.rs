@@ -0,0 +1,56 @@
+use super::RunOptions;
+use super::tasks::{load_config, rule_resolver_for};
+use crate::canonical;
+use crate::rules::RuleExplanation;
+use std::io;
+use std::path::Path;
+
+/// Returns every rule a run with `options` would format the file at `path`
+/// with, and where each comes from (see
+/// [`RuleResolver::explain`](crate::rules::RuleResolver::explain)).
+///
+/// `.basefmt.toml` is read from `config_dir`, as for a run given that
+/// directory. Returns an error if it, or one in a subdirectory that applies
+/// to the file, is invalid, or if `path` cannot be resolved.
+///
+/// # Examples
+///
+/// ```no_run
+/// use basefmt::runner::{RunOptions, explain_rules};
+/// use std::path::Path;
+///
+/// let rules = explain_rules(
+///     Path::new("."),
+///     Path::new("README.md"),
+///     &RunOptions::default(),
+/// )
+/// .unwrap();
+/// for rule in rules {
+///     println!("{} comes from {}", rule.name, rule.source);
+/// }
+/// ```
+pub fn explain_rules(
+    config_dir: &Path,
+    path: &Path,
+    options: &RunOptions,
+) -> io::Result<Vec<RuleExplanation>> {
+    let config = load_config(config_dir, options)?;
+    let config_dir_abs =
+        canonical::canonicalize(config_dir).unwrap_or_else(|_| config_dir.to_path_buf());
+    let canonical = canonical::canonicalize(path).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("{}: failed to canonicalize: {err}", path.display()),
+        )
+    })?;
+
+    let resolver = rule_resolver_for(options, &config, &config_dir_abs, false)?;
+    let explanation = resolver.explain(&canonical);
+    if let Some(err) = resolver
+        .nested_configs()
+        .and_then(|nested| nested.take_errors().into_iter().next())
+    {
+        return Err(err.into());
+    }
+    Ok(explanation)
+}
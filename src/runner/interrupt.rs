@@ -0,0 +1,61 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A shared flag that asks a run to stop starting new files.
+///
+/// Files that are already being processed are finished, so every rewrite
+/// still completes its atomic rename and never leaves a temporary file
+/// behind. Clones share the same flag, so one can be handed to a signal
+/// handler while another is passed in [`RunOptions`](super::RunOptions).
+#[derive(Clone, Debug, Default)]
+pub struct Interrupt {
+    flag: Arc<AtomicBool>,
+}
+
+impl Interrupt {
+    /// Creates a flag that has not been triggered.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks every run holding a clone of this flag to stop.
+    pub fn trigger(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether [`Interrupt::trigger`] has been called.
+    pub fn is_triggered(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+}
+
+/// Two interrupts are equal when they share the same flag.
+impl PartialEq for Interrupt {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.flag, &other.flag)
+    }
+}
+
+impl Eq for Interrupt {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_the_flag() {
+        let interrupt = Interrupt::new();
+        let clone = interrupt.clone();
+
+        clone.trigger();
+
+        assert_eq!(
+            (
+                interrupt.is_triggered(),
+                interrupt == clone,
+                interrupt == Interrupt::new()
+            ),
+            (true, true, false)
+        );
+    }
+}
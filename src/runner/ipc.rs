@@ -0,0 +1,313 @@
+use super::RunOptions;
+use super::tasks::{load_config, rule_resolver_for};
+use crate::canonical;
+use crate::config;
+use crate::format::format_str;
+use crate::rules::RuleResolver;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Error codes defined by JSON-RPC 2.0
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+#[derive(Deserialize)]
+struct Request {
+    /// Absent for notifications, which get no response
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct FormatParams {
+    path: PathBuf,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(flatten)]
+    body: Body,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Body {
+    Result(FormatResponse),
+    Error(ErrorObject),
+}
+
+#[derive(Serialize)]
+struct FormatResponse {
+    content: String,
+    changed: bool,
+}
+
+#[derive(Serialize)]
+struct ErrorObject {
+    code: i32,
+    message: String,
+}
+
+/// Serves formatting requests read from `input` as newline-delimited
+/// JSON-RPC 2.0, writing one response line to `output` for each, until
+/// `input` ends.
+///
+/// The only method is `format`, which takes the `path` of a file and the
+/// `content` to format as if it were that file's, and returns the formatted
+/// `content` and whether it `changed`. Rules are resolved for `path` as a
+/// run with `options` would, with `.basefmt.toml` read from `config_dir`;
+/// the file itself is never read or written, so it needn't exist.
+///
+/// Every `.editorconfig` is read once and cached, until it or a
+/// configuration file changes: before each request, the server looks at the
+/// files it has read or looked for so far, and resolves every rule anew if
+/// one was changed, created, or removed. A configuration that turns out to
+/// be invalid on such a reload is reported on stderr, and the previous one
+/// is kept until it is fixed.
+///
+/// Returns an error if reading `input` or writing `output` fails, or if the
+/// configuration is invalid to begin with as for
+/// [`Runner::format_with`](super::Runner::format_with).
+///
+/// # Examples
+///
+/// ```no_run
+/// use basefmt::runner::{RunOptions, serve_ipc};
+/// use std::io;
+/// use std::path::Path;
+///
+/// serve_ipc(
+///     Path::new("."),
+///     io::stdin().lock(),
+///     io::stdout().lock(),
+///     &RunOptions::default(),
+/// )
+/// .unwrap();
+/// ```
+pub fn serve_ipc(
+    config_dir: &Path,
+    input: impl BufRead,
+    mut output: impl Write,
+    options: &RunOptions,
+) -> io::Result<()> {
+    let mut rules = WatchedRules::load(config_dir, options)?;
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if rules.is_stale() {
+            match WatchedRules::load(config_dir, options) {
+                Ok(reloaded) => rules = reloaded,
+                Err(err) => {
+                    eprintln!("Error: failed to reload configuration: {err}");
+                    rules.restamp();
+                }
+            }
+        }
+        let handled = handle(&line, |params| format_request(&rules.resolver, params));
+        rules.watch_lookups();
+        let Some((id, body)) = handled else {
+            continue;
+        };
+        let response = Response {
+            jsonrpc: "2.0",
+            id,
+            body,
+        };
+        writeln!(output, "{}", serde_json::to_string(&response)?)?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// The last modification time and size of a file, or `None` if it doesn't
+/// exist.
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// The rule resolver of a server along with the files it has read or looked
+/// for, so that a change to any of them can be noticed.
+struct WatchedRules {
+    resolver: RuleResolver,
+    /// The stamp of each watched file when it was first watched
+    stamps: HashMap<PathBuf, Stamp>,
+}
+
+impl WatchedRules {
+    /// Loads the configuration as [`serve_ipc`] does, watching the
+    /// configuration files of `config_dir` and those they extend.
+    fn load(config_dir: &Path, options: &RunOptions) -> io::Result<Self> {
+        let config = load_config(config_dir, options)?;
+        let config_dir_abs =
+            canonical::canonicalize(config_dir).unwrap_or_else(|_| config_dir.to_path_buf());
+        let resolver = rule_resolver_for(options, &config, &config_dir_abs, false)?;
+        let mut rules = Self {
+            resolver,
+            stamps: HashMap::new(),
+        };
+        rules.watch(config::file_names().map(|name| config_dir.join(name)));
+        rules.watch(config.extends);
+        Ok(rules)
+    }
+
+    fn watch(&mut self, paths: impl IntoIterator<Item = PathBuf>) {
+        for path in paths {
+            self.stamps
+                .entry(path)
+                .or_insert_with_key(|path| stamp(path));
+        }
+    }
+
+    /// Watches the `.editorconfig` files looked up since the last call, and
+    /// the nested configuration files next to them.
+    fn watch_lookups(&mut self) {
+        for lookup in self.resolver.editorconfig_lookups() {
+            if self.stamps.contains_key(&lookup) {
+                continue;
+            }
+            if let Some(dir) = lookup.parent() {
+                let dir = dir.to_path_buf();
+                self.watch(config::file_names().map(|name| dir.join(name)));
+            }
+            self.watch([lookup]);
+        }
+    }
+
+    /// Returns whether any watched file changed since it was first watched.
+    fn is_stale(&self) -> bool {
+        self.stamps.iter().any(|(path, last)| stamp(path) != *last)
+    }
+
+    /// Takes the current state of every watched file as unchanged.
+    fn restamp(&mut self) {
+        for (path, last) in &mut self.stamps {
+            *last = stamp(path);
+        }
+    }
+}
+
+fn format_request(resolver: &RuleResolver, params: FormatParams) -> FormatResponse {
+    let canonical = canonical::canonicalize(&params.path)
+        .or_else(|_| std::path::absolute(&params.path))
+        .unwrap_or(params.path);
+    let formatted = format_str(&params.content, &resolver.rules_for(&canonical));
+    FormatResponse {
+        content: formatted.content,
+        changed: formatted.changed,
+    }
+}
+
+/// Handles one request line, returning the ID and body of its response, or
+/// `None` for a notification.
+fn handle(line: &str, format: impl Fn(FormatParams) -> FormatResponse) -> Option<(Value, Body)> {
+    let error = |code, message: String| Body::Error(ErrorObject { code, message });
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(err) => return Some((Value::Null, error(PARSE_ERROR, err.to_string()))),
+    };
+    let request: Request = match serde_json::from_value(request) {
+        Ok(request) => request,
+        Err(err) => return Some((Value::Null, error(INVALID_REQUEST, err.to_string()))),
+    };
+    let id = request.id?;
+    let body = match request.method.as_str() {
+        "format" => match serde_json::from_value(request.params) {
+            Ok(params) => Body::Result(format(params)),
+            Err(err) => error(INVALID_PARAMS, err.to_string()),
+        },
+        method => error(METHOD_NOT_FOUND, format!("unknown method '{method}'")),
+    };
+    Some((id, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::{formatdoc, indoc};
+    use rstest::rstest;
+    use tempfile::TempDir;
+
+    #[rstest]
+    #[case::editorconfig(".editorconfig")]
+    #[case::nested_editorconfig("sub/.editorconfig")]
+    #[case::config(".basefmt.toml")]
+    #[case::nested_config("sub/.basefmt.yaml")]
+    fn test_watched_rules(#[case] changed: &str) {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join(".editorconfig"), "root = true\n").unwrap();
+        fs::create_dir(temp_dir.path().join("sub")).unwrap();
+        let mut rules = WatchedRules::load(temp_dir.path(), &RunOptions::default()).unwrap();
+        let dir = canonical::canonicalize(temp_dir.path()).unwrap();
+        rules.resolver.rules_for(&dir.join("sub/a.txt"));
+        rules.watch_lookups();
+        let before = rules.is_stale();
+
+        fs::write(temp_dir.path().join(changed), "# changed\n").unwrap();
+
+        assert_eq!((before, rules.is_stale()), (false, true));
+    }
+
+    #[test]
+    fn test_serve_ipc() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join(".editorconfig"),
+            indoc! {"
+                root = true
+
+                [*.md]
+                trim_trailing_whitespace = false
+            "},
+        )
+        .unwrap();
+        let dir = temp_dir.path().display();
+        let input = formatdoc! {r#"
+            {{"jsonrpc":"2.0","id":1,"method":"format","params":{{"path":"{dir}/a.txt","content":"a  "}}}}
+            {{"jsonrpc":"2.0","id":"b","method":"format","params":{{"path":"{dir}/new/b.md","content":"b  \n"}}}}
+
+            {{"jsonrpc":"2.0","method":"format","params":{{"path":"{dir}/a.txt","content":"a"}}}}
+            {{"jsonrpc":"2.0","id":2,"method":"lint","params":{{}}}}
+            {{"jsonrpc":"2.0","id":3,"method":"format","params":{{"path":"{dir}/a.txt"}}}}
+            {{"jsonrpc":"2.0","id":4}}
+            not json
+        "#};
+        let mut output = Vec::new();
+
+        serve_ipc(
+            temp_dir.path(),
+            input.as_bytes(),
+            &mut output,
+            &RunOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            indoc! {r#"
+                {"jsonrpc":"2.0","id":1,"result":{"content":"a\n","changed":true}}
+                {"jsonrpc":"2.0","id":"b","result":{"content":"b  \n","changed":false}}
+                {"jsonrpc":"2.0","id":2,"error":{"code":-32601,"message":"unknown method 'lint'"}}
+                {"jsonrpc":"2.0","id":3,"error":{"code":-32602,"message":"missing field `content`"}}
+                {"jsonrpc":"2.0","id":null,"error":{"code":-32600,"message":"missing field `method`"}}
+                {"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"expected ident at line 1 column 2"}}
+            "#}
+        );
+    }
+}
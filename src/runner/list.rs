@@ -0,0 +1,69 @@
+use super::RunOptions;
+use super::tasks::{
+    collect_tasks, determine_config_dir, find_files_for, load_config, rule_resolver_for,
+};
+use crate::canonical;
+use crate::format::SkipReason;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Returns the files a run with `options` would process in the specified
+/// paths, in the order they are found, once `.gitignore` files and exclude
+/// patterns have been applied.
+///
+/// Files are not read, so those a run would skip for their content, such as
+/// binary files, are listed too. Returns an error if the configuration is
+/// invalid as for [`Runner::format_with`](super::Runner::format_with).
+///
+/// # Examples
+///
+/// ```no_run
+/// use basefmt::runner::{RunOptions, list_files};
+/// use std::path::Path;
+///
+/// for file in list_files(&[Path::new(".")], &RunOptions::default()).unwrap() {
+///     println!("{}", file.display());
+/// }
+/// ```
+pub fn list_files(paths: &[impl AsRef<Path>], options: &RunOptions) -> io::Result<Vec<PathBuf>> {
+    let config_dir = determine_config_dir(paths, options);
+    let config = load_config(config_dir, options)?.with_additional_exclude(&options.exclude)?;
+    let files = find_files_for(paths, options, &config)?;
+    let config_dir_abs =
+        canonical::canonicalize(config_dir).unwrap_or_else(|_| config_dir.to_path_buf());
+
+    let resolver = rule_resolver_for(options, &config, &config_dir_abs, false)?;
+    let tasks = collect_tasks(files, paths, &config, &config_dir_abs, options, &resolver);
+    Ok(tasks
+        .into_iter()
+        .filter(|task| task.skip != Some(SkipReason::Excluded))
+        .map(|task| task.path)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_list_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join(".basefmt.toml"), "exclude = [\"*.log\"]\n").unwrap();
+        fs::write(root.join("a.txt"), "a\n").unwrap();
+        fs::write(root.join("b.log"), "b\n").unwrap();
+        fs::write(root.join("c.bin"), [0, 1, 2]).unwrap();
+        fs::write(root.join("d.md"), "d\n").unwrap();
+        let options = RunOptions {
+            exclude: vec!["*.md".to_string()],
+            ..RunOptions::default()
+        };
+
+        let mut files = list_files(&[root], &options).unwrap();
+        files.sort();
+
+        assert_eq!(files, vec![root.join("a.txt"), root.join("c.bin")]);
+    }
+}
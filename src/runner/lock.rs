@@ -0,0 +1,94 @@
+use super::RunOptions;
+use super::cache::hash;
+use std::fs::{self, File, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Takes the lock of the project in `config_dir_abs`, waiting for any other
+/// run that holds it, so that two runs (e.g. an editor formatting on save
+/// and one in a terminal) don't rewrite the same files at once. The lock is
+/// released when the returned file is dropped, or when the process exits.
+///
+/// The lock file is kept in the temporary directory (see [`file_for`]), so
+/// that locking doesn't need the cache directory, which a run with
+/// [`RunOptions::cache`] off must not create. Returns `None` without locking
+/// if [`RunOptions::lock`] is off or the lock file can't be created, which
+/// is only warned about, since a lock is a courtesy to other runs.
+pub(super) fn acquire(options: &RunOptions, config_dir_abs: &Path) -> Option<File> {
+    if !options.lock {
+        return None;
+    }
+    let path = file_for(config_dir_abs);
+    let file = open(&path);
+    let locked = file.and_then(|file| match file.try_lock() {
+        Ok(()) => Ok(file),
+        Err(TryLockError::WouldBlock) => {
+            eprintln!(
+                "Waiting for another basefmt run to finish (lock file {})",
+                path.display()
+            );
+            file.lock().map(|()| file)
+        }
+        Err(TryLockError::Error(err)) => Err(err),
+    });
+    match locked {
+        Ok(file) => Some(file),
+        Err(err) => {
+            eprintln!("{}: failed to lock: {err}", path.display());
+            None
+        }
+    }
+}
+
+/// The lock file of the project in `config_dir_abs`, named after the
+/// project directory so that every run over the project takes the same one,
+/// whatever cache directory it uses.
+fn file_for(config_dir_abs: &Path) -> PathBuf {
+    std::env::temp_dir().join(format!("basefmt-{:016x}.lock", hash(config_dir_abs)))
+}
+
+fn open(path: &Path) -> io::Result<File> {
+    fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_acquire_holds_lock_until_dropped() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = RunOptions {
+            lock: true,
+            ..RunOptions::default()
+        };
+        let path = file_for(temp_dir.path());
+
+        let lock = acquire(&options, temp_dir.path());
+        let while_held = matches!(
+            open(&path).unwrap().try_lock(),
+            Err(TryLockError::WouldBlock)
+        );
+        drop(lock);
+        let after_release = open(&path).unwrap().try_lock().is_ok();
+
+        assert_eq!((while_held, after_release), (true, true));
+    }
+
+    #[test]
+    fn test_acquire_without_lock_option() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let lock = acquire(&RunOptions::default(), temp_dir.path());
+
+        assert_eq!(
+            (lock.is_none(), file_for(temp_dir.path()).exists()),
+            (true, false)
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use super::{CacheStrategy, ErrorFormat, Interrupt, OutputFormat};
+use crate::editorconfig::FormatRules;
+use crate::format::WriteOptions;
+#[cfg(doc)]
+use crate::format::check_file_until_violation;
+use crate::rules::RuleProfile;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+
+/// Options for a formatting run.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RunOptions {
+    /// How formatted files are written back to disk
+    pub write: WriteOptions,
+    /// How the outcome of each file is reported
+    pub output: OutputFormat,
+    /// How text output renders the files that could not be processed
+    pub error_format: ErrorFormat,
+    /// Stops the run from starting new files once triggered
+    pub interrupt: Interrupt,
+    /// Glob patterns of files to leave alone, in addition to the `exclude`
+    /// patterns of `.basefmt.toml` and matched the same way
+    pub exclude: Vec<String>,
+    /// Also process files in nested git repositories, such as submodules
+    /// (see [`FindOptions::include_submodules`](crate::find::FindOptions))
+    pub include_submodules: bool,
+    /// Apply `.gitignore` files outside git repositories too, as if
+    /// `.basefmt.toml` set `require_git = false` (see
+    /// [`FindOptions::gitignore_without_git`](crate::find::FindOptions))
+    pub no_require_git: bool,
+    /// Also search the directories symbolic links point to, each once (see
+    /// [`FindOptions::follow_links`](crate::find::FindOptions))
+    pub follow_links: bool,
+    /// Ignore the keys of `.basefmt.toml` files that basefmt doesn't know,
+    /// e.g. ones written for a newer version, instead of failing on them
+    /// (see [`Config::load_ignoring_unknown_keys`](crate::config::Config::load_ignoring_unknown_keys))
+    pub allow_unknown_config_keys: bool,
+    /// Number of threads to process files with, overriding the `jobs` key
+    /// in `.basefmt.toml`; one per CPU core if neither is set
+    pub jobs: Option<NonZeroUsize>,
+    /// In check mode, stop starting new files as soon as one needs
+    /// formatting, for a quick yes or no; files already being checked are
+    /// finished
+    pub fail_fast: bool,
+    /// In check mode, stop reading each file at the first violation that
+    /// shows it needs formatting, reporting only that one (see
+    /// [`check_file_until_violation`]); ignored with `write_patch`, which
+    /// needs the whole file
+    pub first_violation: bool,
+    /// Show the paths of files in reports as absolute paths rather than
+    /// relative to the working directory
+    pub absolute_paths: bool,
+    /// Stop starting new files once this many files failed, e.g. when a
+    /// mount goes away and every remaining file would fail the same way
+    pub max_errors: Option<NonZeroUsize>,
+    /// Count files named in the paths that are left alone, e.g. because
+    /// they are binary, as errors instead of only warning about them
+    pub error_on_skipped: bool,
+    /// Skip files larger than this many bytes without reading them
+    pub max_file_size: Option<u64>,
+    /// Process files one at a time in discovery order instead of in
+    /// parallel, reporting them in that order too
+    pub sequential: bool,
+    /// Also list each rewritten file along with what was changed in text
+    /// output, and warn about misspelled `.editorconfig` properties
+    pub verbose: bool,
+    /// Name of the EditorConfig file to look up in each directory, overriding
+    /// the `editorconfig_file` key in `.basefmt.toml`; `.editorconfig` if
+    /// neither is set
+    pub editorconfig_file: Option<String>,
+    /// Directory above which no EditorConfig file is read, overriding
+    /// `root = true` in `.basefmt.toml` (which makes its own directory the
+    /// root); without either, parent directories are searched up to the
+    /// file system root
+    pub root_dir: Option<PathBuf>,
+    /// The root of the project, which `.basefmt.toml` is read from, exclude
+    /// patterns are relative to, and reports show paths relative to, instead
+    /// of the first path and the working directory; for wrappers that run
+    /// basefmt from another directory
+    pub base_dir: Option<PathBuf>,
+    /// Rules applied to every file, overriding both the `[rules]` table of
+    /// `.basefmt.toml` and EditorConfig (see
+    /// [`RuleResolver`](crate::rules::RuleResolver))
+    pub rules: FormatRules,
+    /// Rules for files EditorConfig says nothing about, overriding the
+    /// `profile` key in `.basefmt.toml`; [`RuleProfile::Standard`] if
+    /// neither is set
+    pub profile: Option<RuleProfile>,
+    /// Report files that neither EditorConfig, `.basefmt.toml`, nor
+    /// [`RunOptions::rules`] set any rule for, and fail the run with exit
+    /// code 3 if there are any, so that misconfigured `.editorconfig` globs
+    /// don't pass vacuously. Such files are still processed with the rules
+    /// of the profile.
+    pub require_rules: bool,
+    /// Report files that no EditorConfig file applies to at all, and fail
+    /// the run with exit code 3 if there are any, so that running basefmt
+    /// outside the project by mistake doesn't pass without formatting
+    /// anything. Unlike [`RunOptions::require_rules`], a file counts as
+    /// configured once any EditorConfig file applies, even if none of its
+    /// sections match the file.
+    pub require_config: bool,
+    /// Also report how many files and lines violate each rule once every
+    /// file is done, e.g. to size the cleanup before adopting basefmt
+    pub stats: bool,
+    /// In check mode, also append a timestamped JSON record of how many
+    /// files and lines violate each rule, in total and by top-level
+    /// directory, to this file once every file is done, so that the
+    /// history of a project's formatting debt can be graphed
+    pub append_history: Option<PathBuf>,
+    /// In check mode, also write the changes formatting would make to this
+    /// file, as a single patch that `git apply` accepts (see
+    /// [`unified_diff`](crate::format::unified_diff))
+    pub write_patch: Option<PathBuf>,
+    /// Skip files that a previous run found formatted and that haven't
+    /// changed since, remembering them in a cache directory. Files formatted
+    /// into [`WriteOptions::output_dir`] or only in some lines are always
+    /// processed. Checking never creates the cache directory unless
+    /// [`cache_dir`](Self::cache_dir) or the `cache_dir` key is set.
+    pub cache: bool,
+    /// Directory to keep the cache in, overriding the `cache_dir` key in
+    /// `.basefmt.toml`; `.basefmt-cache` in the project root, the closest
+    /// directory with a configuration file or a `.git`, if neither is set
+    pub cache_dir: Option<PathBuf>,
+    /// How the cache tells whether a file has changed, overriding the
+    /// `cache_strategy` key in `.basefmt.toml`; [`CacheStrategy::Metadata`]
+    /// if neither is set
+    pub cache_strategy: Option<CacheStrategy>,
+    /// When formatting, hold a lock on the project (the directory of
+    /// `.basefmt.toml`) for the whole run, waiting for other runs holding it
+    /// to finish, so that concurrent runs don't rewrite the same files at
+    /// once
+    pub lock: bool,
+}
@@ -0,0 +1,44 @@
+use super::tasks::FileTask;
+use crate::format::{CheckResult, check_file_with_patch};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+/// Collects the patch of every file that needs formatting during a check,
+/// to be written as a single patch once every file is done.
+#[derive(Default)]
+pub(super) struct PatchCollector {
+    patches: Mutex<Vec<(PathBuf, String)>>,
+}
+
+impl PatchCollector {
+    /// Checks the file of `task`, keeping its patch if it needs formatting.
+    pub(super) fn check(&self, task: &FileTask) -> io::Result<CheckResult> {
+        let (result, patch) = check_file_with_patch(&task.path, &task.rules)?;
+        if let Some(patch) = patch {
+            self.patches
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push((task.path.clone(), patch));
+        }
+        Ok(result)
+    }
+
+    /// Writes the collected patches to `path` in path order, leaving the file
+    /// empty if no file needs formatting.
+    pub(super) fn write(self, path: &Path) -> io::Result<()> {
+        let mut patches = self
+            .patches
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        patches.sort();
+        let patch: String = patches.into_iter().map(|(_, patch)| patch).collect();
+        fs::write(path, patch).map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                format!("{}: failed to write patch: {err}", path.display()),
+            )
+        })
+    }
+}
@@ -0,0 +1,544 @@
+use super::RunnerResult;
+use crate::filesystem::AlreadyWalked;
+use crate::format::{Change, CheckResult, FormatResult, SkipReason, Violation};
+use serde::Serialize;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+mod badge;
+mod errors;
+mod github;
+mod grouped;
+mod history;
+mod path;
+mod stats;
+
+pub use errors::ErrorFormat;
+pub(super) use errors::ErrorSummary;
+pub(super) use grouped::GroupedReport;
+pub(super) use path::PathDisplay;
+pub use path::display_path;
+use stats::ViolationStats;
+
+/// How a run reports the outcome of each file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable diagnostics on stderr for files with problems
+    #[default]
+    Text,
+    /// The diagnostics of text output with their locations in bold, for an
+    /// interactive terminal
+    Color,
+    /// The diagnostics of text output on stderr, grouped by directory with
+    /// how many files in each have findings, once every file is done;
+    /// directories with many files only list the first few
+    Grouped,
+    /// One JSON object per processed file on stdout, written as soon as the
+    /// file is done
+    Jsonl,
+    /// GitHub Actions workflow commands on stdout, which the runner turns
+    /// into annotations on the offending lines
+    GithubActions,
+    /// A single JSON object for a shields.io endpoint badge on stdout once
+    /// every file is done, saying how many files need formatting; files
+    /// that could not be processed are reported on stderr as in text output
+    Badge,
+}
+
+impl OutputFormat {
+    /// Picks the format for the environment basefmt runs in:
+    /// [`OutputFormat::GithubActions`] inside GitHub Actions,
+    /// [`OutputFormat::Color`] in an interactive terminal, and
+    /// [`OutputFormat::Text`] everywhere else: in other CI systems (told by
+    /// the `CI` and `GITLAB_CI` variables), which have no annotation syntax
+    /// and display the plain, line-oriented text diagnostics as they are,
+    /// when stdout or stderr isn't a terminal, and when `NO_COLOR` is set.
+    pub fn detect() -> Self {
+        let terminal = io::stdout().is_terminal() && io::stderr().is_terminal();
+        Self::detect_from(|name| std::env::var(name).ok(), terminal)
+    }
+
+    fn detect_from(var: impl Fn(&str) -> Option<String>, terminal: bool) -> Self {
+        let set = |name| var(name).is_some_and(|value| !value.is_empty() && value != "false");
+        if var("GITHUB_ACTIONS").as_deref() == Some("true") {
+            OutputFormat::GithubActions
+        } else if !terminal || set("CI") || set("GITLAB_CI") || set("NO_COLOR") {
+            OutputFormat::Text
+        } else {
+            OutputFormat::Color
+        }
+    }
+}
+
+/// What happened to a single file, as reported in `status`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Status {
+    Changed,
+    Unchanged,
+    ReadOnly,
+    Formatted,
+    NeedsFormatting,
+    /// The file was left alone for the reason, which is reported in `reason`
+    Skipped(SkipReason),
+    Error,
+}
+
+impl Status {
+    /// Returns the `snake_case` identifier of the status in JSON output.
+    fn as_str(self) -> &'static str {
+        match self {
+            Status::Changed => "changed",
+            Status::Unchanged => "unchanged",
+            Status::ReadOnly => "read_only",
+            Status::Formatted => "formatted",
+            Status::NeedsFormatting => "needs_formatting",
+            Status::Skipped(_) => "skipped",
+            Status::Error => "error",
+        }
+    }
+}
+
+/// The outcome of formatting or checking a single file.
+pub(super) struct Outcome {
+    pub(super) status: Status,
+    violations: Vec<Violation>,
+    error: Option<io::Error>,
+}
+
+impl Outcome {
+    fn new(status: Status, violations: Vec<Violation>) -> Self {
+        Outcome {
+            status,
+            violations,
+            error: None,
+        }
+    }
+
+    fn error(err: io::Error) -> Self {
+        Outcome {
+            status: Status::Error,
+            violations: Vec::new(),
+            error: Some(err),
+        }
+    }
+}
+
+impl From<io::Result<FormatResult>> for Outcome {
+    fn from(result: io::Result<FormatResult>) -> Self {
+        match result {
+            Ok(FormatResult::Changed(violations)) => Outcome::new(Status::Changed, violations),
+            Ok(FormatResult::Unchanged) => Outcome::new(Status::Unchanged, Vec::new()),
+            Ok(FormatResult::Skipped(reason)) => Outcome::new(Status::Skipped(reason), Vec::new()),
+            Ok(FormatResult::ReadOnly(violations)) => Outcome::new(Status::ReadOnly, violations),
+            Err(err) => Outcome::error(err),
+        }
+    }
+}
+
+impl From<io::Result<CheckResult>> for Outcome {
+    fn from(result: io::Result<CheckResult>) -> Self {
+        match result {
+            Ok(CheckResult::Formatted) => Outcome::new(Status::Formatted, Vec::new()),
+            Ok(CheckResult::NeedsFormatting(violations)) => {
+                Outcome::new(Status::NeedsFormatting, violations)
+            }
+            Ok(CheckResult::Skipped(reason)) => Outcome::new(Status::Skipped(reason), Vec::new()),
+            Err(err) => Outcome::error(err),
+        }
+    }
+}
+
+/// Reports a file as soon as it is done, for formats that stream.
+pub(super) fn report_progress(
+    output: OutputFormat,
+    path: &Path,
+    outcome: &Outcome,
+    elapsed: Duration,
+) {
+    if output == OutputFormat::Jsonl {
+        let mut stdout = io::stdout().lock();
+        // Flush per record so consumers tailing the stream see each file
+        // as soon as it is done rather than when the buffer fills up.
+        let _ = writeln!(stdout, "{}", json_line(path, outcome, elapsed));
+        let _ = stdout.flush();
+    }
+}
+
+/// Reports a file once every file is done, for formats that are printed in
+/// path order.
+///
+/// With `verbose`, changed files are also listed along with what was changed,
+/// and so are skipped files along with why.
+/// Errors in text output are added to `errors` instead, to be printed with
+/// [`report_errors`] at the end, and so is every file in grouped output,
+/// to `grouped`.
+pub(super) fn report_summary(
+    output: OutputFormat,
+    verbose: bool,
+    path: &Path,
+    outcome: &Outcome,
+    errors: &mut ErrorSummary,
+    grouped: &mut GroupedReport,
+) {
+    match (output, &outcome.error) {
+        (
+            OutputFormat::Text | OutputFormat::Color | OutputFormat::Grouped | OutputFormat::Badge,
+            Some(err),
+        ) => {
+            errors.add(path, err);
+        }
+        (OutputFormat::Text, None) => {
+            let mut stderr = io::stderr().lock();
+            for line in text_lines(path, outcome, verbose) {
+                let _ = writeln!(stderr, "{line}");
+            }
+        }
+        (OutputFormat::Color, None) => {
+            let mut stderr = io::stderr().lock();
+            for line in color_lines(path, outcome, verbose) {
+                let _ = writeln!(stderr, "{line}");
+            }
+        }
+        (OutputFormat::Grouped, None) => {
+            grouped.add(path, |name| text_lines(name, outcome, verbose));
+        }
+        (OutputFormat::GithubActions, _) => github::report_github(path, outcome, verbose),
+        (OutputFormat::Jsonl | OutputFormat::Badge, _) => {}
+    }
+}
+
+/// Prints the files and errors collected by [`report_summary`], the errors
+/// in `format`.
+pub(super) fn report_errors(
+    output: OutputFormat,
+    format: ErrorFormat,
+    grouped: &GroupedReport,
+    errors: &ErrorSummary,
+) {
+    if matches!(
+        output,
+        OutputFormat::Text | OutputFormat::Color | OutputFormat::Grouped | OutputFormat::Badge
+    ) {
+        grouped.print();
+        errors.print(format);
+    }
+}
+
+/// Warns on stderr about the directories a search following symbolic links
+/// left out, since it had already walked them through another path.
+pub(super) fn report_revisited(revisited: &[AlreadyWalked]) {
+    for walked in revisited {
+        eprintln!("{}: {walked}", display_path(&walked.0));
+    }
+}
+
+/// Prints the badge for `result` on stdout, in badge output.
+pub(super) fn report_badge(output: OutputFormat, result: &RunnerResult) {
+    if output == OutputFormat::Badge
+        && let Ok(badge) = serde_json::to_string(&badge::Badge::new(result))
+    {
+        println!("{badge}");
+    }
+}
+
+/// Prints how many files and lines violate each rule over `outcomes`: as a
+/// final `{"stats":{...}}` record in JSONL output, and as a table on stderr
+/// otherwise.
+pub(super) fn report_stats<'a>(
+    output: OutputFormat,
+    outcomes: impl IntoIterator<Item = &'a Outcome>,
+) {
+    let mut stats = ViolationStats::default();
+    for outcome in outcomes {
+        stats.add(&outcome.violations);
+    }
+    if output == OutputFormat::Jsonl {
+        #[derive(Serialize)]
+        struct StatsRecord<'a> {
+            stats: &'a ViolationStats,
+        }
+        if let Ok(record) = serde_json::to_string(&StatsRecord { stats: &stats }) {
+            println!("{record}");
+        }
+    } else {
+        eprint!("{}", stats.render_table());
+    }
+}
+
+/// Appends a record of the findings over `outcomes`, each with the path
+/// reports show it at, to the history file at `file`.
+pub(super) fn append_history<'a>(
+    file: &Path,
+    outcomes: impl IntoIterator<Item = (PathBuf, &'a Outcome)>,
+) -> io::Result<()> {
+    let mut record = history::HistoryRecord::new(SystemTime::now());
+    for (path, outcome) in outcomes {
+        record.add(&path, outcome);
+    }
+    record.append(file)
+}
+
+/// Returns the lines that text output reports the file at `path` with: one
+/// `path:line:column: message` diagnostic per violation of a file that
+/// needs formatting, or none for a file without findings.
+fn text_lines(path: &Path, outcome: &Outcome, verbose: bool) -> Vec<String> {
+    diagnostics(path, outcome, verbose)
+        .into_iter()
+        .map(|(location, message)| format!("{location}: {message}"))
+        .collect()
+}
+
+/// Returns the lines of [`text_lines`] with their locations in bold.
+fn color_lines(path: &Path, outcome: &Outcome, verbose: bool) -> Vec<String> {
+    diagnostics(path, outcome, verbose)
+        .into_iter()
+        .map(|(location, message)| format!("\x1b[1m{location}:\x1b[0m {message}"))
+        .collect()
+}
+
+/// Returns the diagnostics of [`text_lines`], each split into its location
+/// and its message.
+fn diagnostics(path: &Path, outcome: &Outcome, verbose: bool) -> Vec<(String, String)> {
+    let line = |message: String| vec![(display_path(path).into_owned(), message)];
+    match (outcome.status, &outcome.error) {
+        (_, Some(err)) => line(err.to_string()),
+        (Status::NeedsFormatting, None) if outcome.violations.is_empty() => {
+            line("not formatted".to_string())
+        }
+        (Status::NeedsFormatting, None) => outcome
+            .violations
+            .iter()
+            .map(|violation| (location(path, violation), violation.to_string()))
+            .collect(),
+        (Status::Changed, None) if verbose => line(change_summary(outcome)),
+        (Status::ReadOnly, None) => {
+            line("skipped read-only file (use --force to format it)".to_string())
+        }
+        (Status::Skipped(reason), None) if verbose => line(skip_message(reason)),
+        _ => Vec::new(),
+    }
+}
+
+/// Describes what formatting changed, e.g. "removed trailing whitespace on
+/// 14 lines; added final newline".
+fn change_summary(outcome: &Outcome) -> String {
+    let changes = changes(outcome);
+    if changes.is_empty() {
+        return "formatted".to_string();
+    }
+    changes.join("; ")
+}
+
+/// Describes why a file was skipped, e.g. "skipped binary file".
+pub(super) fn skip_message(reason: SkipReason) -> String {
+    match reason {
+        SkipReason::NoRules => "skipped file no formatting rules apply to".to_string(),
+        SkipReason::TooLarge => "skipped file larger than --max-file-size".to_string(),
+        reason => format!("skipped {} file", reason.as_str()),
+    }
+}
+
+/// Returns what formatting changed in a rewritten file, or nothing if the
+/// file was not rewritten.
+fn changes(outcome: &Outcome) -> Vec<String> {
+    if outcome.status != Status::Changed {
+        return Vec::new();
+    }
+    Change::summarize(&outcome.violations)
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Returns where `violation` is in the file at `path`, e.g. `a.txt:3:13`.
+fn location(path: &Path, violation: &Violation) -> String {
+    match (violation.line(), violation.column()) {
+        (Some(line), Some(column)) => format!("{}:{line}:{column}", display_path(path)),
+        (Some(line), None) => format!("{}:{line}", display_path(path)),
+        (None, _) => display_path(path).into_owned(),
+    }
+}
+
+#[derive(Serialize)]
+struct Record {
+    path: String,
+    /// The bytes of a path that isn't valid UTF-8, which `path` escapes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path_bytes: Option<Vec<u8>>,
+    status: &'static str,
+    /// Why a skipped file was left alone
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<&'static str>,
+    violations: Vec<ViolationRecord>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    changes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    duration_ms: f64,
+}
+
+#[derive(Serialize)]
+struct ViolationRecord {
+    kind: &'static str,
+    line: Option<usize>,
+    column: Option<usize>,
+    message: String,
+}
+
+fn json_line(path: &Path, outcome: &Outcome, elapsed: Duration) -> String {
+    let record = Record {
+        path: display_path(path).into_owned(),
+        path_bytes: path::path_bytes(path),
+        status: outcome.status.as_str(),
+        reason: match outcome.status {
+            Status::Skipped(reason) => Some(reason.as_str()),
+            _ => None,
+        },
+        violations: outcome
+            .violations
+            .iter()
+            .map(|violation| ViolationRecord {
+                kind: violation.kind(),
+                line: violation.line(),
+                column: violation.column(),
+                message: violation.to_string(),
+            })
+            .collect(),
+        changes: changes(outcome),
+        error: outcome.error.as_ref().map(ToString::to_string),
+        duration_ms: elapsed.as_secs_f64() * 1000.0,
+    };
+    // Serializing plain strings and numbers cannot fail
+    serde_json::to_string(&record).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::github_actions(&[("GITHUB_ACTIONS", "true")], true, OutputFormat::GithubActions)]
+    #[case::github_actions_piped(&[("GITHUB_ACTIONS", "true")], false, OutputFormat::GithubActions)]
+    #[case::gitlab_ci(&[("GITLAB_CI", "true")], true, OutputFormat::Text)]
+    #[case::other_ci(&[("CI", "true")], true, OutputFormat::Text)]
+    #[case::ci_false(&[("CI", "false")], true, OutputFormat::Color)]
+    #[case::no_color(&[("NO_COLOR", "1")], true, OutputFormat::Text)]
+    #[case::terminal(&[], true, OutputFormat::Color)]
+    #[case::piped(&[], false, OutputFormat::Text)]
+    fn test_detect(
+        #[case] vars: &[(&str, &str)],
+        #[case] terminal: bool,
+        #[case] expected: OutputFormat,
+    ) {
+        let detected = OutputFormat::detect_from(
+            |name| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            },
+            terminal,
+        );
+        assert_eq!(detected, expected);
+    }
+
+    #[rstest]
+    #[case::line_and_column(
+        Violation::TrailingWhitespace { line: 3, column: 13 },
+        "a.txt:3:13: trailing whitespace"
+    )]
+    #[case::line_only(
+        Violation::TrailingBlankLines { line: 4, count: 2 },
+        "a.txt:4: 2 trailing blank lines"
+    )]
+    #[case::no_location(Violation::MissingFinalNewline, "a.txt: missing final newline")]
+    fn test_text_lines(#[case] violation: Violation, #[case] expected: &str) {
+        let outcome = Outcome::from(Ok(CheckResult::NeedsFormatting(vec![violation])));
+        assert_eq!(
+            text_lines(Path::new("a.txt"), &outcome, false),
+            vec![expected]
+        );
+    }
+
+    #[test]
+    fn test_color_lines() {
+        let outcome = Outcome::from(Ok(CheckResult::NeedsFormatting(vec![
+            Violation::TrailingWhitespace {
+                line: 3,
+                column: 13,
+            },
+            Violation::MissingFinalNewline,
+        ])));
+        assert_eq!(
+            color_lines(Path::new("a.txt"), &outcome, false),
+            vec![
+                "\x1b[1ma.txt:3:13:\x1b[0m trailing whitespace",
+                "\x1b[1ma.txt:\x1b[0m missing final newline",
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::changes(
+        Outcome::from(Ok(FormatResult::Changed(vec![
+            Violation::TrailingWhitespace { line: 3, column: 13 },
+            Violation::TrailingWhitespace { line: 5, column: 1 },
+            Violation::MissingFinalNewline,
+        ]))),
+        "removed trailing whitespace on 2 lines; added final newline"
+    )]
+    #[case::no_violations(Outcome::from(Ok(FormatResult::Changed(Vec::new()))), "formatted")]
+    fn test_change_summary(#[case] outcome: Outcome, #[case] expected: &str) {
+        assert_eq!(change_summary(&outcome), expected);
+    }
+
+    #[rstest]
+    #[case::changed(
+        Outcome::from(Ok(FormatResult::Changed(vec![
+            Violation::TrailingWhitespace { line: 3, column: 13 },
+            Violation::MissingFinalNewline,
+        ]))),
+        concat!(
+            r#"{"path":"a.txt","status":"changed","violations":["#,
+            r#"{"kind":"trailing_whitespace","line":3,"column":13,"message":"trailing whitespace"},"#,
+            r#"{"kind":"missing_final_newline","line":null,"column":null,"message":"missing final newline"}"#,
+            r#"],"changes":["removed trailing whitespace on 1 line","added final newline"],"#,
+            r#""duration_ms":1.5}"#,
+        )
+    )]
+    #[case::needs_formatting(
+        Outcome::from(Ok(CheckResult::NeedsFormatting(vec![Violation::MissingFinalNewline]))),
+        concat!(
+            r#"{"path":"a.txt","status":"needs_formatting","violations":["#,
+            r#"{"kind":"missing_final_newline","line":null,"column":null,"message":"missing final newline"}"#,
+            r#"],"duration_ms":1.5}"#,
+        )
+    )]
+    #[case::formatted(
+        Outcome::from(Ok(CheckResult::Formatted)),
+        r#"{"path":"a.txt","status":"formatted","violations":[],"duration_ms":1.5}"#
+    )]
+    #[case::skipped(
+        Outcome::from(Ok(FormatResult::Skipped(SkipReason::Binary))),
+        r#"{"path":"a.txt","status":"skipped","reason":"binary","violations":[],"duration_ms":1.5}"#
+    )]
+    #[case::minified(
+        Outcome::from(Ok(CheckResult::Skipped(SkipReason::Minified))),
+        r#"{"path":"a.txt","status":"skipped","reason":"minified","violations":[],"duration_ms":1.5}"#
+    )]
+    #[case::generated(
+        Outcome::from(Ok(FormatResult::Skipped(SkipReason::Generated))),
+        r#"{"path":"a.txt","status":"skipped","reason":"generated","violations":[],"duration_ms":1.5}"#
+    )]
+    #[case::error(
+        Outcome::from(Err::<CheckResult, _>(io::Error::other("boom"))),
+        r#"{"path":"a.txt","status":"error","violations":[],"error":"boom","duration_ms":1.5}"#
+    )]
+    fn test_json_line(#[case] outcome: Outcome, #[case] expected: &str) {
+        assert_eq!(
+            json_line(Path::new("a.txt"), &outcome, Duration::from_micros(1500)),
+            expected
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use super::RunnerResult;
+use super::errors::files;
+use serde::Serialize;
+
+/// The JSON that a [shields.io endpoint badge](https://shields.io/badges/endpoint-badge)
+/// reads, so that repositories can show whether they are formatted.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Badge {
+    schema_version: u8,
+    label: &'static str,
+    message: String,
+    color: &'static str,
+}
+
+impl Badge {
+    /// Describes the outcome of a run: red with how many files need
+    /// formatting if any do, orange with how many failed if any did, and
+    /// green otherwise.
+    pub(super) fn new(result: &RunnerResult) -> Self {
+        let (message, color) = match (result.unformatted_count, result.error_count) {
+            (0, 0) => ("clean".to_string(), "brightgreen"),
+            (0, errors) => {
+                let noun = if errors == 1 { "error" } else { "errors" };
+                (format!("{errors} {noun}"), "orange")
+            }
+            (unformatted, _) => (format!("{unformatted} {} dirty", files(unformatted)), "red"),
+        };
+        Badge {
+            schema_version: 1,
+            label: "basefmt",
+            message,
+            color,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::clean(
+        0,
+        0,
+        r#"{"schemaVersion":1,"label":"basefmt","message":"clean","color":"brightgreen"}"#
+    )]
+    #[case::dirty(
+        12,
+        1,
+        r#"{"schemaVersion":1,"label":"basefmt","message":"12 files dirty","color":"red"}"#
+    )]
+    #[case::one_dirty(
+        1,
+        0,
+        r#"{"schemaVersion":1,"label":"basefmt","message":"1 file dirty","color":"red"}"#
+    )]
+    #[case::errors(
+        0,
+        2,
+        r#"{"schemaVersion":1,"label":"basefmt","message":"2 errors","color":"orange"}"#
+    )]
+    fn test_badge(
+        #[case] unformatted_count: usize,
+        #[case] error_count: usize,
+        #[case] expected: &str,
+    ) {
+        let result = RunnerResult {
+            unformatted_count,
+            error_count,
+            ..RunnerResult::default()
+        };
+        assert_eq!(
+            serde_json::to_string(&Badge::new(&result)).unwrap(),
+            expected
+        );
+    }
+}
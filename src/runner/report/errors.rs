@@ -0,0 +1,193 @@
+use super::path::{display_path, path_bytes, split_dir};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How text output renders the files that could not be processed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// A report grouped by message and directory, e.g. `permission denied
+    /// (3 files)` followed by the files in each directory
+    #[default]
+    Human,
+    /// One `path:kind` line per file, e.g. `src/a.txt:permission_denied`,
+    /// for grepping
+    Short,
+    /// One JSON object per file with its `path`, `kind`, and `error`
+    /// message, for tools
+    Json,
+}
+
+/// Errors collected over a run, so that text output can list them grouped
+/// by message and directory once every file is done instead of one line per
+/// file. A run on a broken network mount can fail hundreds of files with
+/// the same error; grouped, that is a few lines.
+#[derive(Debug, Default)]
+pub(in crate::runner) struct ErrorSummary {
+    /// The files in the order they were added, with their errors
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    path: PathBuf,
+    kind: String,
+    error: String,
+}
+
+/// An entry as a JSON object, whose `path_bytes` holds the bytes of a path
+/// that isn't valid UTF-8
+#[derive(Serialize)]
+struct EntryRecord<'a> {
+    path: Cow<'a, str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path_bytes: Option<Vec<u8>>,
+    kind: &'a str,
+    error: &'a str,
+}
+
+impl ErrorSummary {
+    pub(super) fn add(&mut self, path: &Path, err: &io::Error) {
+        self.entries.push(Entry {
+            path: path.to_path_buf(),
+            kind: kind_name(err.kind()),
+            error: err.to_string(),
+        });
+    }
+
+    /// Prints the report on stderr, if there were any errors.
+    pub(super) fn print(&self, format: ErrorFormat) {
+        if !self.entries.is_empty() {
+            eprint!("{}", self.render(format));
+        }
+    }
+
+    fn render(&self, format: ErrorFormat) -> String {
+        match format {
+            ErrorFormat::Human => self.render_human(),
+            ErrorFormat::Short => self
+                .entries
+                .iter()
+                .map(|entry| format!("{}:{}\n", display_path(&entry.path), entry.kind))
+                .collect(),
+            ErrorFormat::Json => self
+                .entries
+                .iter()
+                .map(|entry| EntryRecord {
+                    path: display_path(&entry.path),
+                    path_bytes: path_bytes(&entry.path),
+                    kind: &entry.kind,
+                    error: &entry.error,
+                })
+                .filter_map(|record| serde_json::to_string(&record).ok())
+                .map(|line| line + "\n")
+                .collect(),
+        }
+    }
+
+    fn render_human(&self) -> String {
+        // File names by directory, by error message
+        let mut groups: BTreeMap<&str, BTreeMap<PathBuf, Vec<String>>> = BTreeMap::new();
+        for entry in &self.entries {
+            let (dir, name) = split_dir(&entry.path);
+            groups
+                .entry(&entry.error)
+                .or_default()
+                .entry(dir)
+                .or_default()
+                .push(name);
+        }
+
+        let count = self.entries.len();
+        let mut report = format!("{count} {} could not be processed:\n", files(count));
+        for (message, dirs) in &groups {
+            let count: usize = dirs.values().map(Vec::len).sum();
+            let _ = writeln!(report, "  {message} ({count} {})", files(count));
+            for (dir, names) in dirs {
+                let _ = writeln!(report, "    {}: {}", display_path(dir), names.join(", "));
+            }
+        }
+        report
+    }
+}
+
+/// Returns the noun for `count` files.
+pub(super) fn files(count: usize) -> &'static str {
+    if count == 1 { "file" } else { "files" }
+}
+
+/// Names an error kind in snake case, e.g. `permission_denied` for
+/// [`io::ErrorKind::PermissionDenied`].
+fn kind_name(kind: io::ErrorKind) -> String {
+    let mut name = String::new();
+    for (index, c) in format!("{kind:?}").chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if index > 0 {
+                name.push('_');
+            }
+            name.push(c.to_ascii_lowercase());
+        } else {
+            name.push(c);
+        }
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use rstest::rstest;
+
+    #[test]
+    fn test_render_human() {
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        let stale = io::Error::other("stale file handle");
+        let mut summary = ErrorSummary::default();
+        summary.add(Path::new("mnt/b/x.txt"), &denied);
+        summary.add(Path::new("mnt/a/y.txt"), &stale);
+        summary.add(Path::new("mnt/a/z.txt"), &stale);
+        summary.add(Path::new("top.txt"), &stale);
+
+        assert_eq!(
+            summary.render(ErrorFormat::Human),
+            indoc! {"
+                4 files could not be processed:
+                  permission denied (1 file)
+                    mnt/b: x.txt
+                  stale file handle (3 files)
+                    .: top.txt
+                    mnt/a: y.txt, z.txt
+            "}
+        );
+    }
+
+    #[rstest]
+    #[case::short(
+        ErrorFormat::Short,
+        indoc! {"
+            mnt/b/x.txt:permission_denied
+            top.txt:other
+        "}
+    )]
+    #[case::json(
+        ErrorFormat::Json,
+        indoc! {r#"
+            {"path":"mnt/b/x.txt","kind":"permission_denied","error":"permission denied"}
+            {"path":"top.txt","kind":"other","error":"stale file handle"}
+        "#}
+    )]
+    fn test_render_per_file(#[case] format: ErrorFormat, #[case] expected: &str) {
+        let mut summary = ErrorSummary::default();
+        summary.add(
+            Path::new("mnt/b/x.txt"),
+            &io::Error::from(io::ErrorKind::PermissionDenied),
+        );
+        summary.add(Path::new("top.txt"), &io::Error::other("stale file handle"));
+
+        assert_eq!(summary.render(format), expected);
+    }
+}
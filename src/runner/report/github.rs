@@ -0,0 +1,116 @@
+use super::{Outcome, Status, change_summary, display_path, skip_message};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Prints the outcome of a file as GitHub Actions workflow commands, which
+/// show up as annotations on the lines they point at.
+pub(super) fn report_github(path: &Path, outcome: &Outcome, verbose: bool) {
+    let file = display_path(path);
+    let mut stdout = io::stdout().lock();
+    let mut emit = |level: &str, line: Option<usize>, column: Option<usize>, message: &str| {
+        let _ = writeln!(stdout, "{}", command(level, &file, line, column, message));
+    };
+    match (outcome.status, &outcome.error) {
+        (_, Some(err)) => emit("error", None, None, &err.to_string()),
+        (Status::NeedsFormatting, None) => {
+            if outcome.violations.is_empty() {
+                emit("error", None, None, "not formatted");
+            }
+            for violation in &outcome.violations {
+                emit(
+                    "error",
+                    violation.line(),
+                    violation.column(),
+                    &violation.to_string(),
+                );
+            }
+        }
+        (Status::Changed, None) if verbose => {
+            emit("notice", None, None, &change_summary(outcome));
+        }
+        (Status::ReadOnly, None) => emit(
+            "warning",
+            None,
+            None,
+            "skipped read-only file (use --force to format it)",
+        ),
+        (Status::Skipped(reason), None) if verbose => {
+            emit("notice", None, None, &skip_message(reason));
+        }
+        _ => {}
+    }
+}
+
+/// Formats a workflow command such as
+/// `::error file=a.txt,line=3,col=13::trailing whitespace`.
+fn command(
+    level: &str,
+    file: &str,
+    line: Option<usize>,
+    column: Option<usize>,
+    message: &str,
+) -> String {
+    let mut properties = format!("file={}", escape_property(file));
+    if let Some(line) = line {
+        properties.push_str(&format!(",line={line}"));
+    }
+    if let Some(column) = column {
+        properties.push_str(&format!(",col={column}"));
+    }
+    format!("::{level} {properties}::{}", escape_data(message))
+}
+
+/// Escapes a command's message as the runner expects.
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a command property, which additionally must not contain the
+/// separators between properties.
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::line_and_column(
+        "error",
+        "a.txt",
+        Some(3),
+        Some(13),
+        "trailing whitespace",
+        "::error file=a.txt,line=3,col=13::trailing whitespace"
+    )]
+    #[case::whole_file(
+        "error",
+        "a.txt",
+        None,
+        None,
+        "missing final newline",
+        "::error file=a.txt::missing final newline"
+    )]
+    #[case::escaped(
+        "warning",
+        r"C:\a,b.txt",
+        None,
+        None,
+        "100% done\nreally",
+        r"::warning file=C%3A\a%2Cb.txt::100%25 done%0Areally"
+    )]
+    fn test_command(
+        #[case] level: &str,
+        #[case] file: &str,
+        #[case] line: Option<usize>,
+        #[case] column: Option<usize>,
+        #[case] message: &str,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(command(level, file, line, column, message), expected);
+    }
+}
@@ -0,0 +1,105 @@
+use super::errors::files;
+use super::path::{display_path, split_dir};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// How many files of a directory grouped output lists before collapsing
+/// the rest into a count.
+const MAX_FILES_PER_DIR: usize = 10;
+
+/// The findings of a run collected by directory, so that grouped output can
+/// list them once every file is done instead of as one flat list. A check
+/// that fails hundreds of files is easier to scan as a few directories with
+/// counts.
+#[derive(Debug, Default)]
+pub(in crate::runner) struct GroupedReport {
+    /// The diagnostics of each file with findings, by file name, by
+    /// directory
+    dirs: BTreeMap<PathBuf, BTreeMap<String, Vec<String>>>,
+}
+
+impl GroupedReport {
+    /// Adds the file at `path` with the diagnostics that `diagnostics`
+    /// returns for it named by its file name alone, if there are any.
+    pub(super) fn add(&mut self, path: &Path, diagnostics: impl FnOnce(&Path) -> Vec<String>) {
+        let (dir, name) = split_dir(path);
+        let lines = diagnostics(Path::new(&name));
+        if !lines.is_empty() {
+            self.dirs.entry(dir).or_default().insert(name, lines);
+        }
+    }
+
+    /// Prints the report on stderr, if any file had findings.
+    pub(super) fn print(&self) {
+        if !self.dirs.is_empty() {
+            eprint!("{}", self.render());
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut report = String::new();
+        for (dir, names) in &self.dirs {
+            let count = names.len();
+            let _ = writeln!(report, "{} ({count} {}):", display_path(dir), files(count));
+            for lines in names.values().take(MAX_FILES_PER_DIR) {
+                for line in lines {
+                    let _ = writeln!(report, "  {line}");
+                }
+            }
+            let rest = count.saturating_sub(MAX_FILES_PER_DIR);
+            if rest > 0 {
+                let _ = writeln!(report, "  ... and {rest} more {}", files(rest));
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn test_render() {
+        let mut report = GroupedReport::default();
+        let diagnostics = |path: &Path| vec![format!("{}: missing final newline", path.display())];
+        for index in 0..12 {
+            report.add(Path::new(&format!("docs/{index:02}.md")), diagnostics);
+        }
+        report.add(Path::new("src/b.rs"), diagnostics);
+        report.add(Path::new("src/a.rs"), |path: &Path| {
+            vec![
+                format!("{}:1:2: trailing whitespace", path.display()),
+                format!("{}: missing final newline", path.display()),
+            ]
+        });
+        report.add(Path::new("src/c.rs"), |_: &Path| Vec::new());
+        report.add(Path::new("top.txt"), diagnostics);
+
+        assert_eq!(
+            report.render(),
+            indoc! {"
+                . (1 file):
+                  top.txt: missing final newline
+                docs (12 files):
+                  00.md: missing final newline
+                  01.md: missing final newline
+                  02.md: missing final newline
+                  03.md: missing final newline
+                  04.md: missing final newline
+                  05.md: missing final newline
+                  06.md: missing final newline
+                  07.md: missing final newline
+                  08.md: missing final newline
+                  09.md: missing final newline
+                  ... and 2 more files
+                src (2 files):
+                  a.rs:1:2: trailing whitespace
+                  a.rs: missing final newline
+                  b.rs: missing final newline
+            "}
+        );
+    }
+}
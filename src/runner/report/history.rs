@@ -0,0 +1,144 @@
+use super::stats::ViolationStats;
+use super::{Outcome, Status, display_path};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Component, Path};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The findings of a run as one line of a history file, so that teams can
+/// graph how their whitespace debt goes down from run to run.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub(super) struct HistoryRecord {
+    /// When the run finished, in seconds since the Unix epoch
+    timestamp: u64,
+    /// How many files were checked
+    files: usize,
+    /// How many of them need formatting
+    unformatted_files: usize,
+    /// How many files and lines violate each rule
+    rules: ViolationStats,
+    /// The same counts for each top-level directory, `.` for files outside
+    /// any
+    directories: BTreeMap<String, ViolationStats>,
+}
+
+impl HistoryRecord {
+    pub(super) fn new(timestamp: SystemTime) -> Self {
+        HistoryRecord {
+            timestamp: timestamp
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            ..HistoryRecord::default()
+        }
+    }
+
+    /// Adds the file at `path`, as reports show it, with its `outcome`.
+    pub(super) fn add(&mut self, path: &Path, outcome: &Outcome) {
+        self.files += 1;
+        if outcome.status == Status::NeedsFormatting {
+            self.unformatted_files += 1;
+        }
+        if outcome.violations.is_empty() {
+            return;
+        }
+        self.rules.add(&outcome.violations);
+        self.directories
+            .entry(top_level_dir(path))
+            .or_default()
+            .add(&outcome.violations);
+    }
+
+    /// Appends the record to `file` as a line of JSON, creating the file if
+    /// it doesn't exist.
+    pub(super) fn append(&self, file: &Path) -> io::Result<()> {
+        let mut line = serde_json::to_string(self)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file)
+            .and_then(|mut history| history.write_all(line.as_bytes()))
+            .map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!("{}: failed to append history: {err}", file.display()),
+                )
+            })
+    }
+}
+
+/// Returns the first directory of `path`: `.` for a file directly in the
+/// current directory, and `/` for an absolute path outside it.
+fn top_level_dir(path: &Path) -> String {
+    let components: Vec<Component<'_>> = path
+        .components()
+        .filter(|component| component != &Component::CurDir)
+        .collect();
+    match components.as_slice() {
+        [] | [_] => ".".to_string(),
+        [Component::Normal(dir), ..] => display_path(Path::new(dir)).into_owned(),
+        _ => "/".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::Violation;
+    use rstest::rstest;
+    use std::time::Duration;
+
+    #[test]
+    fn test_history_record() {
+        let mut record = HistoryRecord::new(UNIX_EPOCH + Duration::from_secs(1_760_000_000));
+        let needs_formatting = |violations| Outcome::new(Status::NeedsFormatting, violations);
+        record.add(
+            Path::new("src/a.rs"),
+            &needs_formatting(vec![Violation::TrailingWhitespace { line: 1, column: 2 }]),
+        );
+        record.add(
+            Path::new("src/b/c.rs"),
+            &needs_formatting(vec![Violation::MissingFinalNewline]),
+        );
+        record.add(
+            Path::new("README.md"),
+            &needs_formatting(vec![Violation::MissingFinalNewline]),
+        );
+        record.add(
+            Path::new("docs/d.md"),
+            &Outcome::new(Status::Formatted, Vec::new()),
+        );
+
+        assert_eq!(
+            serde_json::to_value(&record).unwrap(),
+            serde_json::json!({
+                "timestamp": 1_760_000_000,
+                "files": 4,
+                "unformatted_files": 3,
+                "rules": {
+                    "missing_final_newline": {"files": 2, "lines": 2},
+                    "trailing_whitespace": {"files": 1, "lines": 1},
+                },
+                "directories": {
+                    ".": {"missing_final_newline": {"files": 1, "lines": 1}},
+                    "src": {
+                        "missing_final_newline": {"files": 1, "lines": 1},
+                        "trailing_whitespace": {"files": 1, "lines": 1},
+                    },
+                },
+            })
+        );
+    }
+
+    #[rstest]
+    #[case::file("a.txt", ".")]
+    #[case::nested("src/b/c.txt", "src")]
+    #[case::dot_relative("./src/c.txt", "src")]
+    #[case::dot_file("./a.txt", ".")]
+    #[case::absolute("/other/a.txt", "/")]
+    fn test_top_level_dir(#[case] path: &str, #[case] expected: &str) {
+        assert_eq!(top_level_dir(Path::new(path)), expected);
+    }
+}
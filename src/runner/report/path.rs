@@ -0,0 +1,176 @@
+use crate::canonical::{self, normalize};
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::path::{Component, Path, PathBuf};
+
+/// How reports show the paths of files: relative to the directory basefmt
+/// was invoked in, or the base directory of the run if one is set, so that
+/// output doesn't mix absolute and relative paths depending on how they were
+/// passed, or else all absolute.
+pub(in crate::runner) struct PathDisplay {
+    /// The working directory, if it could be read
+    cwd: Option<PathBuf>,
+    /// The absolute directory to show paths relative to instead of the
+    /// working directory
+    base_dir: Option<PathBuf>,
+    absolute: bool,
+}
+
+impl PathDisplay {
+    pub(in crate::runner) fn new(base_dir: Option<&Path>, absolute: bool) -> Self {
+        let cwd = std::env::current_dir().ok();
+        let base_dir = base_dir.map(|dir| {
+            canonical::canonicalize(dir).unwrap_or_else(|_| match &cwd {
+                Some(cwd) => normalize(&cwd.join(dir)),
+                None => dir.to_path_buf(),
+            })
+        });
+        Self {
+            cwd,
+            base_dir,
+            absolute,
+        }
+    }
+
+    /// Returns `path` as reports show it.
+    ///
+    /// Any path is resolved against the working directory and made relative
+    /// to it, going up with `..` to a shared parent directory, so that
+    /// `./a.txt` shows as `a.txt`; a path sharing nothing but the file
+    /// system root with it is left absolute. With a base directory, paths
+    /// are made relative to it instead. With `absolute` set, relative paths
+    /// are joined onto the working directory.
+    pub(in crate::runner) fn show(&self, path: &Path) -> PathBuf {
+        let Some(cwd) = &self.cwd else {
+            return path.to_path_buf();
+        };
+        if self.absolute {
+            return if path.is_relative() {
+                normalize(&cwd.join(path))
+            } else {
+                path.to_path_buf()
+            };
+        }
+        let path = normalize(&cwd.join(path));
+        relative_to(&path, self.base_dir.as_ref().unwrap_or(cwd)).unwrap_or(path)
+    }
+}
+
+/// Returns the absolute `path` relative to the absolute directory `root`,
+/// or `None` if they share no directory but the file system root.
+fn relative_to(path: &Path, root: &Path) -> Option<PathBuf> {
+    let shared = path
+        .components()
+        .zip(root.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if path
+        .components()
+        .take(shared)
+        .all(|component| matches!(component, Component::Prefix(_) | Component::RootDir))
+    {
+        return None;
+    }
+    let mut relative: PathBuf = root
+        .components()
+        .skip(shared)
+        .map(|_| Component::ParentDir)
+        .collect();
+    relative.extend(path.components().skip(shared));
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    Some(relative)
+}
+
+/// Renders `path` for a report, escaping each byte that isn't valid UTF-8
+/// as `\xNN` instead of replacing it with U+FFFD like [`Path::display`], so
+/// that the file can still be told apart from others and found.
+pub fn display_path(path: &Path) -> Cow<'_, str> {
+    if let Some(path) = path.to_str() {
+        return Cow::Borrowed(path);
+    }
+    let mut escaped = String::new();
+    for chunk in path.as_os_str().as_encoded_bytes().utf8_chunks() {
+        escaped.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            let _ = write!(escaped, "\\x{byte:02X}");
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Splits `path` into its directory, `.` for a bare file name, and its file
+/// name rendered with [`display_path`], for reports that group files by
+/// directory.
+pub(super) fn split_dir(path: &Path) -> (PathBuf, String) {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let name = path.file_name().map_or_else(
+        || display_path(path).into_owned(),
+        |name| display_path(Path::new(name)).into_owned(),
+    );
+    (dir, name)
+}
+
+/// Returns the bytes of `path` if it isn't valid UTF-8, so that JSON output
+/// can carry the exact path along with its [`display_path`] text.
+///
+/// On Unix these are the bytes the file system holds; elsewhere they are in
+/// the unspecified encoding of [`OsStr`](std::ffi::OsStr).
+pub(super) fn path_bytes(path: &Path) -> Option<Vec<u8>> {
+    match path.to_str() {
+        Some(_) => None,
+        None => Some(path.as_os_str().as_encoded_bytes().to_vec()),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    #[rstest]
+    #[case::utf8(b"src/caf\xC3\xA9.txt", ("src/café.txt", None))]
+    #[case::invalid(
+        b"src/caf\xE9.txt",
+        ("src/caf\\xE9.txt", Some(b"src/caf\xE9.txt".to_vec()))
+    )]
+    fn test_display_path(#[case] path: &[u8], #[case] expected: (&str, Option<Vec<u8>>)) {
+        let path = Path::new(OsStr::from_bytes(path));
+        assert_eq!((display_path(path).as_ref(), path_bytes(path)), expected);
+    }
+
+    #[rstest]
+    #[case::relative("./lib/a.txt", None, false, "lib/a.txt")]
+    #[case::relative_through_parent("../src/lib/a.txt", None, false, "lib/a.txt")]
+    #[case::absolute_below_cwd("/work/src/lib/a.txt", None, false, "lib/a.txt")]
+    #[case::absolute_above_cwd("/work/a.txt", None, false, "../a.txt")]
+    #[case::relative_above_cwd("../a.txt", None, false, "../a.txt")]
+    #[case::absolute_beside_cwd("/work/test/a.txt", None, false, "../test/a.txt")]
+    #[case::absolute_elsewhere("/other/a.txt", None, false, "/other/a.txt")]
+    #[case::cwd("/work/src", None, false, ".")]
+    #[case::relative_made_absolute("./a.txt", None, true, "/work/src/a.txt")]
+    #[case::absolute_kept("/work/src/a.txt", None, true, "/work/src/a.txt")]
+    #[case::relative_below_base_dir("../../p/src/a.txt", Some("/p"), false, "src/a.txt")]
+    #[case::absolute_below_base_dir("/p/src/a.txt", Some("/p"), false, "src/a.txt")]
+    #[case::above_base_dir("a.txt", Some("/work/src/sub"), false, "../a.txt")]
+    #[case::outside_base_dir("a.txt", Some("/p"), false, "/work/src/a.txt")]
+    fn test_path_display(
+        #[case] path: &str,
+        #[case] base_dir: Option<&str>,
+        #[case] absolute: bool,
+        #[case] expected: &str,
+    ) {
+        let display = PathDisplay {
+            cwd: Some(PathBuf::from("/work/src")),
+            base_dir: base_dir.map(PathBuf::from),
+            absolute,
+        };
+        assert_eq!(display.show(Path::new(path)), PathBuf::from(expected));
+    }
+}
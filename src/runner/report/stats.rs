@@ -0,0 +1,115 @@
+use crate::format::Violation;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+/// How many files and lines violate each rule over a run, to size the
+/// cleanup before adopting basefmt.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub(super) struct ViolationStats {
+    /// Counts by [`Violation::kind`]
+    kinds: BTreeMap<&'static str, KindStats>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+struct KindStats {
+    files: usize,
+    lines: usize,
+}
+
+impl ViolationStats {
+    /// Adds the violations of a single file.
+    pub(super) fn add(&mut self, violations: &[Violation]) {
+        let mut seen = Vec::new();
+        for violation in violations {
+            let stats = self.kinds.entry(violation.kind()).or_default();
+            stats.lines += lines(violation);
+            if !seen.contains(&violation.kind()) {
+                seen.push(violation.kind());
+                stats.files += 1;
+            }
+        }
+    }
+
+    /// Renders a table with a row per rule, e.g.
+    /// `trailing_whitespace  3  12`.
+    pub(super) fn render_table(&self) -> String {
+        let width = self
+            .kinds
+            .keys()
+            .map(|kind| kind.len())
+            .chain(["rule".len()])
+            .max()
+            .unwrap_or_default();
+        let mut table = format!("{:width$}  {:>5}  {:>5}\n", "rule", "files", "lines");
+        for (kind, stats) in &self.kinds {
+            let _ = writeln!(
+                table,
+                "{kind:width$}  {:>5}  {:>5}",
+                stats.files, stats.lines
+            );
+        }
+        table
+    }
+}
+
+/// The number of lines a violation covers; the last line for final newline
+/// problems, and none for the encoding of the whole file.
+fn lines(violation: &Violation) -> usize {
+    match violation {
+        Violation::LeadingBlankLines { count }
+        | Violation::ExcessBlankLines { count, .. }
+        | Violation::TrailingBlankLines { count, .. } => *count,
+        Violation::TrailingWhitespace { .. }
+        | Violation::InconsistentLineEnding { .. }
+        | Violation::MissingFinalNewline
+        | Violation::UnexpectedFinalNewline => 1,
+        Violation::NotUtf8 { .. } => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    fn stats() -> ViolationStats {
+        let mut stats = ViolationStats::default();
+        stats.add(&[
+            Violation::TrailingWhitespace { line: 1, column: 4 },
+            Violation::TrailingWhitespace { line: 3, column: 2 },
+            Violation::TrailingBlankLines { line: 5, count: 2 },
+        ]);
+        stats.add(&[
+            Violation::TrailingWhitespace { line: 2, column: 1 },
+            Violation::MissingFinalNewline,
+        ]);
+        stats
+    }
+
+    #[test]
+    fn test_render_table() {
+        assert_eq!(
+            stats().render_table(),
+            indoc! {"
+                rule                   files  lines
+                missing_final_newline      1      1
+                trailing_blank_lines       1      2
+                trailing_whitespace        2      3
+            "}
+        );
+    }
+
+    #[test]
+    fn test_serialize() {
+        assert_eq!(
+            serde_json::to_string(&stats()).unwrap(),
+            concat!(
+                r#"{"missing_final_newline":{"files":1,"lines":1},"#,
+                r#""trailing_blank_lines":{"files":1,"lines":2},"#,
+                r#""trailing_whitespace":{"files":2,"lines":3}}"#
+            )
+        );
+    }
+}
@@ -0,0 +1,179 @@
+#[cfg(doc)]
+use super::RunOptions;
+use std::path::PathBuf;
+
+/// Result of a formatting or checking operation on multiple files.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RunnerResult {
+    /// Total number of files processed
+    pub total_files: usize,
+    /// Number of files that encountered errors
+    pub error_count: usize,
+    /// Number of files that were not properly formatted (check mode only)
+    pub unformatted_count: usize,
+    /// Number of files that were rewritten (format mode only)
+    pub changed_count: usize,
+    /// Number of files that were already formatted
+    pub unchanged_count: usize,
+    /// Number of files left alone without being formatted, because they are
+    /// binary, generated, excluded, or, in format mode, read-only, for
+    /// example
+    pub skipped_count: usize,
+    /// Number of the skipped files that aren't text
+    pub binary_count: usize,
+    /// Number of the skipped files that look minified
+    pub minified_count: usize,
+    /// Number of the skipped files that have a generated-code marker
+    pub generated_count: usize,
+    /// Number of the skipped files that an exclude pattern matches
+    pub excluded_count: usize,
+    /// Number of the skipped files that every rule is off for
+    pub no_rules_count: usize,
+    /// Number of the skipped files larger than [`RunOptions::max_file_size`]
+    pub too_large_count: usize,
+    /// Paths of the files that were rewritten, in the order they were
+    /// reported (format mode only)
+    pub changed_files: Vec<PathBuf>,
+    /// Number of files left unprocessed because the run was interrupted
+    pub interrupted_count: usize,
+    /// Number of files left unprocessed because another file needed
+    /// formatting, if [`RunOptions::fail_fast`] is set, or because the run
+    /// was aborted
+    pub cancelled_count: usize,
+    /// Whether the run stopped starting new files because
+    /// [`RunOptions::max_errors`] files failed
+    pub aborted: bool,
+    /// Number of files no rule was set for, if [`RunOptions::require_rules`]
+    /// is set (0 otherwise)
+    pub unconfigured_count: usize,
+    /// Number of files no EditorConfig file applies to, if
+    /// [`RunOptions::require_config`] is set (0 otherwise)
+    pub no_editorconfig_count: usize,
+}
+
+impl RunnerResult {
+    /// Returns the appropriate exit code based on the result.
+    ///
+    /// Exit codes:
+    /// - 0: Success (all files formatted/checked successfully)
+    /// - 1: Some files need formatting (check mode only)
+    /// - 2: Errors occurred during processing
+    /// - 3: No rule was set for some files (with
+    ///   [`RunOptions::require_rules`]), or no EditorConfig file applies to
+    ///   some (with [`RunOptions::require_config`])
+    /// - 4: The run was aborted after [`RunOptions::max_errors`] files
+    ///   failed
+    /// - 130: The run was interrupted before every file was processed
+    pub fn exit_code(&self) -> u8 {
+        if self.interrupted_count > 0 {
+            130
+        } else if self.aborted {
+            4
+        } else if self.error_count > 0 {
+            2
+        } else if self.unconfigured_count > 0 || self.no_editorconfig_count > 0 {
+            3
+        } else if self.unformatted_count > 0 {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runner_result_exit_code_success() {
+        let result = RunnerResult {
+            total_files: 5,
+            error_count: 0,
+            unformatted_count: 0,
+            interrupted_count: 0,
+            unconfigured_count: 0,
+            ..RunnerResult::default()
+        };
+        assert_eq!(result.exit_code(), 0);
+    }
+
+    #[test]
+    fn test_runner_result_exit_code_unformatted() {
+        let result = RunnerResult {
+            total_files: 5,
+            error_count: 0,
+            unformatted_count: 2,
+            interrupted_count: 0,
+            unconfigured_count: 0,
+            ..RunnerResult::default()
+        };
+        assert_eq!(result.exit_code(), 1);
+    }
+
+    #[test]
+    fn test_runner_result_exit_code_error() {
+        let result = RunnerResult {
+            total_files: 5,
+            error_count: 1,
+            unformatted_count: 0,
+            interrupted_count: 0,
+            unconfigured_count: 0,
+            ..RunnerResult::default()
+        };
+        assert_eq!(result.exit_code(), 2);
+    }
+
+    #[test]
+    fn test_runner_result_exit_code_error_priority() {
+        let result = RunnerResult {
+            total_files: 5,
+            error_count: 1,
+            unformatted_count: 2,
+            interrupted_count: 0,
+            unconfigured_count: 0,
+            ..RunnerResult::default()
+        };
+        // Errors have higher priority than unformatted
+        assert_eq!(result.exit_code(), 2);
+    }
+
+    #[test]
+    fn test_runner_result_exit_code_unconfigured() {
+        let result = RunnerResult {
+            total_files: 5,
+            error_count: 0,
+            unformatted_count: 2,
+            interrupted_count: 0,
+            unconfigured_count: 1,
+            ..RunnerResult::default()
+        };
+        // Missing rules outrank files that merely need formatting
+        assert_eq!(result.exit_code(), 3);
+    }
+
+    #[test]
+    fn test_runner_result_exit_code_no_editorconfig() {
+        let result = RunnerResult {
+            total_files: 5,
+            unformatted_count: 2,
+            no_editorconfig_count: 1,
+            ..RunnerResult::default()
+        };
+        assert_eq!(result.exit_code(), 3);
+    }
+
+    #[test]
+    fn test_runner_result_exit_code_interrupted() {
+        let result = RunnerResult {
+            total_files: 5,
+            error_count: 1,
+            unformatted_count: 2,
+            interrupted_count: 3,
+            unconfigured_count: 0,
+            ..RunnerResult::default()
+        };
+        // An interrupted run is reported as such even if some files failed
+        assert_eq!(result.exit_code(), 130);
+    }
+}
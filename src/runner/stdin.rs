@@ -0,0 +1,138 @@
+use super::tasks::{FileTask, load_config, rule_resolver_for, run_tasks};
+use super::{RunOptions, RunnerResult};
+use crate::canonical;
+use crate::editorconfig::FormatRules;
+use crate::filesystem::{FileSystem, MemoryFileSystem};
+use crate::format::{check_file_in, format_file_in};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Formats the content read from `input` as if it were the file at `path`,
+/// writing the result to `output`, so that basefmt can run as a filter in a
+/// pipeline.
+///
+/// The content is written as it was read if it is skipped, e.g. as binary.
+/// With `check` set, nothing is written and the content is reported like a
+/// checked file instead, so the result says whether it needs formatting.
+/// Rules are resolved for `path` as a run with `options` would, with
+/// `.basefmt.toml` read from `config_dir`; the file itself is never read or
+/// written, so it needn't exist, and reports show `path` as it is given.
+///
+/// Returns an error if reading `input` or writing `output` fails, or if the
+/// configuration is invalid as for
+/// [`Runner::format_with`](super::Runner::format_with).
+///
+/// # Examples
+///
+/// ```no_run
+/// use basefmt::runner::{RunOptions, run_stdin};
+/// use std::io;
+/// use std::path::Path;
+///
+/// let result = run_stdin(
+///     Path::new("."),
+///     Path::new("README.md"),
+///     io::stdin().lock(),
+///     io::stdout().lock(),
+///     false,
+///     &RunOptions::default(),
+/// )
+/// .unwrap();
+/// ```
+pub fn run_stdin(
+    config_dir: &Path,
+    path: &Path,
+    mut input: impl Read,
+    mut output: impl Write,
+    check: bool,
+    options: &RunOptions,
+) -> io::Result<RunnerResult> {
+    let mut content = Vec::new();
+    input.read_to_end(&mut content)?;
+    let config = load_config(config_dir, options)?;
+    let config_dir_abs =
+        canonical::canonicalize(config_dir).unwrap_or_else(|_| config_dir.to_path_buf());
+    let resolver = rule_resolver_for(options, &config, &config_dir_abs, false)?;
+    let canonical = canonical::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf());
+
+    let rules = resolver.explicit_rules_for(&canonical);
+    let fs = MemoryFileSystem::new().with_file(&canonical, content);
+    let tasks = vec![FileTask {
+        path: path.to_path_buf(),
+        canonical: canonical.clone(),
+        rules: resolver.complete(&canonical, &rules),
+        unconfigured: rules == FormatRules::default(),
+        no_editorconfig: !resolver.has_editorconfig_for(&canonical),
+        explicit: true,
+        skip: None,
+    }];
+    if check {
+        return run_tasks(
+            tasks,
+            options,
+            None,
+            |task| check_file_in(&fs, &task.canonical, &task.rules),
+            |_, _| {},
+        );
+    }
+    let result = run_tasks(
+        tasks,
+        options,
+        None,
+        |task| format_file_in(&fs, &task.canonical, &task.rules),
+        |_, _| {},
+    )?;
+    output.write_all(&fs.read(&canonical)?)?;
+    output.flush()?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use rstest::rstest;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[rstest]
+    #[case::format(false, b"a  ", (b"a\n".to_vec(), 1, 0))]
+    #[case::check(true, b"a  ", (Vec::new(), 0, 1))]
+    #[case::binary(false, &[0xC3, 0x28], (vec![0xC3, 0x28], 0, 0))]
+    fn test_run_stdin(
+        #[case] check: bool,
+        #[case] input: &[u8],
+        #[case] expected: (Vec<u8>, usize, usize),
+    ) {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join(".editorconfig"),
+            indoc! {"
+                root = true
+
+                [*.txt]
+                insert_final_newline = true
+                trim_trailing_whitespace = true
+            "},
+        )
+        .unwrap();
+        let mut output = Vec::new();
+
+        let result = run_stdin(
+            temp_dir.path(),
+            &temp_dir.path().join("a.txt"),
+            input,
+            &mut output,
+            check,
+            &RunOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            (output, result.changed_count, result.unformatted_count),
+            expected
+        );
+    }
+}
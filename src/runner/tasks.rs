@@ -0,0 +1,485 @@
+use super::cache;
+use super::report::{self, Outcome, Status};
+use super::{Interrupt, RunOptions, RunnerResult};
+use crate::canonical;
+use crate::config::{Config, NestedConfigs};
+use crate::editorconfig::{EditorConfigCache, FormatRules};
+use crate::error::BasefmtError;
+use crate::filesystem::RealFileSystem;
+use crate::find::{FindOptions, find_files_and_revisits};
+use crate::format::{Backup, CheckResult, FormatResult, SkipReason};
+use crate::gitattributes::GitAttributesCache;
+use crate::rules::RuleResolver;
+use rayon::ThreadPoolBuilder;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// A file that needs to be formatted along with its formatting rules.
+///
+/// This structure pre-computes and caches the formatting rules for each file
+/// to avoid redundant EditorConfig lookups during parallel processing.
+pub(super) struct FileTask {
+    /// Original path to the file (may be relative or absolute)
+    pub(super) path: PathBuf,
+    /// Canonical path to the file
+    pub(super) canonical: PathBuf,
+    /// Cached formatting rules from EditorConfig
+    pub(super) rules: FormatRules,
+    /// Whether no source set any rule for the file, so that it only gets the
+    /// rules of the profile
+    pub(super) unconfigured: bool,
+    /// Whether no EditorConfig file applies to the file at all
+    pub(super) no_editorconfig: bool,
+    /// Whether the file was named in the paths rather than found in a
+    /// directory
+    pub(super) explicit: bool,
+    /// Why the file is left alone without being read, if it is: an exclude
+    /// pattern matches it, or it is larger than
+    /// [`RunOptions::max_file_size`]
+    pub(super) skip: Option<SkipReason>,
+}
+
+/// The result of processing a file, which [`run_tasks`] stands in for a file
+/// it leaves alone without running the operation (see [`FileTask::skip`]).
+pub(super) trait Skippable {
+    fn skipped(reason: SkipReason) -> Self;
+}
+
+impl Skippable for FormatResult {
+    fn skipped(reason: SkipReason) -> Self {
+        FormatResult::Skipped(reason)
+    }
+}
+
+impl Skippable for CheckResult {
+    fn skipped(reason: SkipReason) -> Self {
+        CheckResult::Skipped(reason)
+    }
+}
+
+/// Loads `.basefmt.toml` from `config_dir`, ignoring the keys basefmt
+/// doesn't know if `options` allows them.
+pub(super) fn load_config(config_dir: &Path, options: &RunOptions) -> Result<Config, BasefmtError> {
+    if options.allow_unknown_config_keys {
+        Config::load_ignoring_unknown_keys(config_dir)
+    } else {
+        Config::load(config_dir)
+    }
+}
+
+/// Creates the rule resolver for a run, whose EditorConfig cache lints every
+/// `.editorconfig` it reads if `lint` is set.
+///
+/// Returns an error if the configured EditorConfig file name is a path or
+/// the root directory cannot be resolved.
+pub(super) fn rule_resolver_for(
+    options: &RunOptions,
+    config: &Config,
+    config_dir_abs: &Path,
+    lint: bool,
+) -> io::Result<RuleResolver> {
+    let mut rule_cache = EditorConfigCache::new();
+    if let Some(root_dir) = &options.root_dir {
+        let resolved = canonical::canonicalize(root_dir).map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                format!(
+                    "{}: failed to resolve root directory: {err}",
+                    root_dir.display()
+                ),
+            )
+        })?;
+        rule_cache = rule_cache.with_root_dir(resolved);
+    } else if config.root {
+        rule_cache = rule_cache.with_root_dir(config_dir_abs.to_path_buf());
+    }
+    if let Some(file_name) = options
+        .editorconfig_file
+        .as_deref()
+        .or(config.editorconfig_file.as_deref())
+    {
+        if Path::new(file_name).file_name() != Some(OsStr::new(file_name)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("EditorConfig file name '{file_name}' must not contain a directory"),
+            ));
+        }
+        rule_cache = rule_cache.with_file_name(file_name);
+    }
+    if lint {
+        rule_cache = rule_cache.with_lint();
+    }
+    let mut nested = NestedConfigs::new(config_dir_abs);
+    if options.allow_unknown_config_keys {
+        nested = nested.ignoring_unknown_keys();
+    }
+    let mut resolver = RuleResolver::new(rule_cache)
+        .with_gitattributes(GitAttributesCache::new())
+        .with_profile(config.profile.unwrap_or_default())
+        .with_config(config.rules.clone())
+        .with_config_defaults(config.defaults.clone())
+        .with_nested_configs(nested)
+        .with_cli(options.rules.clone());
+    if let Some(profile) = options.profile {
+        resolver = resolver.with_cli_profile(profile);
+    }
+    if config.language_defaults {
+        resolver = resolver.with_language_defaults();
+    }
+    Ok(resolver)
+}
+
+/// Finds the files to process under `paths` as `options` and `config` say.
+pub(super) fn find_files_for(
+    paths: &[impl AsRef<Path>],
+    options: &RunOptions,
+    config: &Config,
+) -> Result<Vec<PathBuf>, BasefmtError> {
+    let (files, revisited) =
+        find_files_and_revisits(&RealFileSystem, paths, &find_options_for(options, config))?;
+    report::report_revisited(&revisited);
+    Ok(files)
+}
+
+/// Returns how to search for files as `options` and `config` say.
+pub(super) fn find_options_for(options: &RunOptions, config: &Config) -> FindOptions {
+    FindOptions {
+        include_submodules: options.include_submodules,
+        gitignore_without_git: options.no_require_git || !config.require_git,
+        follow_links: options.follow_links,
+    }
+}
+
+/// Warns that a file named in the paths was left alone, unless the report
+/// of its outcome already says so, and returns whether it was.
+///
+/// Files found in a directory are skipped quietly, since there are usually
+/// many binary files in a tree; a named one is more likely a mistake. So are
+/// files every rule is off for, as the configuration says to leave them
+/// alone.
+pub(super) fn warn_if_skipped(
+    task: &FileTask,
+    path: &Path,
+    outcome: &Outcome,
+    verbose: bool,
+) -> bool {
+    if !task.explicit {
+        return false;
+    }
+    match outcome.status {
+        Status::Skipped(SkipReason::NoRules) => return false,
+        Status::Skipped(reason) if !verbose => {
+            eprintln!(
+                "{}: {}",
+                report::display_path(path),
+                report::skip_message(reason)
+            );
+        }
+        Status::Skipped(_) | Status::ReadOnly => {}
+        _ => return false,
+    }
+    true
+}
+
+/// Prints the lint warnings of the resolver, and the errors of the invalid
+/// `.basefmt.toml` files in subdirectories, which are ignored.
+pub(super) fn report_config_warnings(resolver: &RuleResolver) {
+    for err in resolver
+        .nested_configs()
+        .map(NestedConfigs::take_errors)
+        .unwrap_or_default()
+    {
+        eprintln!("{err}");
+    }
+    for warning in resolver.warnings() {
+        eprintln!("{warning}");
+    }
+}
+
+/// Returns the directory to read `.basefmt.toml` from, which exclude
+/// patterns are relative to: `options.base_dir` if set, else the first path
+/// if it is a directory, and otherwise the directory it is in, which for a
+/// glob pattern is the deepest one before the pattern.
+pub(super) fn determine_config_dir<'a>(
+    paths: &'a [impl AsRef<Path>],
+    options: &'a RunOptions,
+) -> &'a Path {
+    if let Some(base_dir) = &options.base_dir {
+        base_dir
+    } else if let Some(first_path) = paths.first() {
+        let path = first_path.as_ref();
+        if path.is_dir() {
+            path
+        } else if path.exists() {
+            path.parent().unwrap_or_else(|| Path::new("."))
+        } else {
+            path.ancestors()
+                .skip(1)
+                .find(|dir| dir.is_dir())
+                .unwrap_or_else(|| Path::new("."))
+        }
+    } else {
+        Path::new(".")
+    }
+}
+
+/// Resolves the rules for every file that isn't excluded.
+///
+/// Like git, exclude patterns only apply to files found by searching a
+/// directory: a file named in `paths` itself is always processed. The
+/// excluded files are kept as tasks that are skipped (see
+/// [`FileTask::skip`]), so that runs report them, and so are files larger
+/// than [`RunOptions::max_file_size`].
+/// Files inside the output directory (see
+/// [`WriteOptions::output_dir`](crate::format::WriteOptions::output_dir))
+/// are skipped as well, so that formatted copies left there by a previous
+/// run are not formatted as sources, and so are backups (see
+/// [`WriteOptions::backup`](crate::format::WriteOptions::backup)) and the
+/// files in the cache directory (see [`RunOptions::cache_dir`]) unless
+/// named in `paths`.
+pub(super) fn collect_tasks(
+    files: Vec<PathBuf>,
+    paths: &[impl AsRef<Path>],
+    config: &Config,
+    config_dir_abs: &Path,
+    options: &RunOptions,
+    resolver: &RuleResolver,
+) -> Vec<FileTask> {
+    let explicit: HashSet<&Path> = paths.iter().map(AsRef::as_ref).collect();
+    let output_dir = options
+        .write
+        .output_dir
+        .as_deref()
+        .and_then(|dir| canonical::canonicalize(dir).ok());
+    let backup_dir = match &options.write.backup {
+        Some(Backup::Directory(dir)) => canonical::canonicalize(dir).ok(),
+        _ => None,
+    };
+    let backup_suffix = match &options.write.backup {
+        Some(Backup::Suffix(suffix)) if !suffix.is_empty() => Some(suffix.as_str()),
+        _ => None,
+    };
+    let cache_dir = canonical::canonicalize(&cache::dir_for(options, config, config_dir_abs)).ok();
+    let mut tasks = Vec::with_capacity(files.len());
+    for path in files {
+        let canonical = match canonical::canonicalize(&path) {
+            Ok(abs) => abs,
+            Err(err) => {
+                eprintln!(
+                    "{}: failed to canonicalize: {}",
+                    report::display_path(&path),
+                    err
+                );
+                continue;
+            }
+        };
+
+        let rel_path = canonical
+            .strip_prefix(config_dir_abs)
+            .unwrap_or(canonical.as_path());
+
+        let excluded = config.is_excluded(rel_path)
+            || resolver
+                .nested_configs()
+                .is_some_and(|nested| nested.is_excluded(&canonical));
+        let is_own_file = backup_suffix.is_some_and(|suffix| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().ends_with(suffix))
+        }) || [&backup_dir, &cache_dir]
+            .into_iter()
+            .flatten()
+            .any(|dir| canonical.starts_with(dir));
+        let explicit = explicit.contains(path.as_path());
+        if (!explicit && is_own_file)
+            || output_dir
+                .as_ref()
+                .is_some_and(|dir| canonical.starts_with(dir))
+        {
+            continue;
+        }
+        if !explicit && excluded {
+            tasks.push(FileTask {
+                path,
+                canonical,
+                rules: FormatRules::default(),
+                unconfigured: false,
+                no_editorconfig: false,
+                explicit,
+                skip: Some(SkipReason::Excluded),
+            });
+            continue;
+        }
+
+        let too_large = options
+            .max_file_size
+            .is_some_and(|max| std::fs::metadata(&path).is_ok_and(|metadata| metadata.len() > max));
+        let rules = resolver.explicit_rules_for(&canonical);
+        tasks.push(FileTask {
+            explicit,
+            path,
+            rules: resolver.complete(&canonical, &rules),
+            no_editorconfig: !resolver.has_editorconfig_for(&canonical),
+            canonical,
+            unconfigured: rules == FormatRules::default(),
+            skip: too_large.then_some(SkipReason::TooLarge),
+        });
+    }
+    tasks
+}
+
+/// Use parallel processing only for larger file counts to avoid overhead
+pub(super) const PARALLEL_THRESHOLD: usize = 10;
+
+/// Runs `op` on every task and tallies the outcomes.
+///
+/// Files are processed in parallel and passed to `on_file` and streaming
+/// reports as soon as they are done, but the text report is printed in path
+/// order once every file is, so that logs are comparable between runs. Once
+/// `options.interrupt` is triggered, the remaining tasks are not started.
+/// With `options.sequential`, files are instead processed and reported one
+/// at a time in the order they were discovered. With `options.fail_fast`,
+/// the remaining tasks are not started either once a file needs formatting,
+/// and with `options.max_errors`, once that many files failed.
+///
+/// Parallel work runs on a thread pool of its own rather than rayon's global
+/// pool, so it doesn't compete with callers that use rayon themselves. The
+/// pool has `jobs` threads, or rayon's default number if unset.
+pub(super) fn run_tasks<R>(
+    mut tasks: Vec<FileTask>,
+    options: &RunOptions,
+    jobs: Option<NonZeroUsize>,
+    op: impl Fn(&FileTask) -> io::Result<R> + Sync,
+    on_file: impl Fn(&Path, &io::Result<R>) + Sync,
+) -> io::Result<RunnerResult>
+where
+    R: Skippable,
+    Outcome: From<io::Result<R>>,
+{
+    let failed = Interrupt::new();
+    let aborted = Interrupt::new();
+    let errors = AtomicUsize::new(0);
+    let paths = report::PathDisplay::new(options.base_dir.as_deref(), options.absolute_paths);
+    let process = |task: &FileTask| {
+        if options.interrupt.is_triggered() || failed.is_triggered() || aborted.is_triggered() {
+            return None;
+        }
+        let started = Instant::now();
+        let result = match task.skip {
+            Some(reason) => Ok(R::skipped(reason)),
+            None => op(task),
+        };
+        let elapsed = started.elapsed();
+        on_file(&task.path, &result);
+        let outcome = Outcome::from(result);
+        if options.fail_fast && outcome.status == Status::NeedsFormatting {
+            failed.trigger();
+        }
+        if outcome.status == Status::Error
+            && options
+                .max_errors
+                .is_some_and(|max| errors.fetch_add(1, Ordering::Relaxed) + 1 >= max.get())
+        {
+            aborted.trigger();
+        }
+        report::report_progress(options.output, &paths.show(&task.path), &outcome, elapsed);
+        Some(outcome)
+    };
+
+    if !options.sequential {
+        tasks.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+
+    let outcomes: Vec<Option<Outcome>> = if options.sequential || tasks.len() < PARALLEL_THRESHOLD {
+        tasks.iter().map(process).collect()
+    } else {
+        ThreadPoolBuilder::new()
+            // Zero lets rayon pick its default
+            .num_threads(jobs.map_or(0, NonZeroUsize::get))
+            .thread_name(|index| format!("basefmt-{index}"))
+            .build()
+            .map_err(io::Error::other)?
+            .install(|| tasks.par_iter().map(process).collect())
+    };
+
+    let mut result = RunnerResult {
+        total_files: tasks.len(),
+        aborted: aborted.is_triggered(),
+        ..RunnerResult::default()
+    };
+    let mut errors = report::ErrorSummary::default();
+    let mut grouped = report::GroupedReport::default();
+    for (task, outcome) in tasks.iter().zip(&outcomes) {
+        let Some(outcome) = outcome else {
+            if options.interrupt.is_triggered() {
+                result.interrupted_count += 1;
+            } else {
+                result.cancelled_count += 1;
+            }
+            continue;
+        };
+        let path = paths.show(&task.path);
+        if options.require_rules && task.unconfigured {
+            eprintln!("{}: no formatting rules apply", report::display_path(&path));
+            result.unconfigured_count += 1;
+        }
+        if options.require_config && task.no_editorconfig {
+            eprintln!(
+                "{}: no EditorConfig file applies",
+                report::display_path(&path)
+            );
+            result.no_editorconfig_count += 1;
+        }
+        report::report_summary(
+            options.output,
+            options.verbose,
+            &path,
+            outcome,
+            &mut errors,
+            &mut grouped,
+        );
+        let skipped_explicitly = warn_if_skipped(task, &path, outcome, options.verbose);
+        match outcome.status {
+            _ if skipped_explicitly && options.error_on_skipped => result.error_count += 1,
+            Status::Changed => {
+                result.changed_count += 1;
+                result.changed_files.push(task.path.clone());
+            }
+            Status::Unchanged | Status::Formatted => result.unchanged_count += 1,
+            Status::ReadOnly => result.skipped_count += 1,
+            Status::Skipped(reason) => {
+                result.skipped_count += 1;
+                match reason {
+                    SkipReason::Binary => result.binary_count += 1,
+                    SkipReason::Minified => result.minified_count += 1,
+                    SkipReason::Generated => result.generated_count += 1,
+                    SkipReason::Excluded => result.excluded_count += 1,
+                    SkipReason::NoRules => result.no_rules_count += 1,
+                    SkipReason::TooLarge => result.too_large_count += 1,
+                }
+            }
+            Status::NeedsFormatting => result.unformatted_count += 1,
+            Status::Error => result.error_count += 1,
+        }
+    }
+    report::report_errors(options.output, options.error_format, &grouped, &errors);
+    report::report_badge(options.output, &result);
+    if options.stats {
+        report::report_stats(options.output, outcomes.iter().flatten());
+    }
+    if let Some(file) = &options.append_history {
+        report::append_history(
+            file,
+            tasks
+                .iter()
+                .zip(&outcomes)
+                .filter_map(|(task, outcome)| Some((paths.show(&task.path), outcome.as_ref()?))),
+        )?;
+    }
+    Ok(result)
+}
@@ -0,0 +1,281 @@
+use super::RunOptions;
+use super::report::PathDisplay;
+use super::tasks::{find_options_for, load_config, rule_resolver_for};
+use crate::canonical;
+use crate::config::Config;
+use crate::filesystem::RealFileSystem;
+use crate::find::{Omission, omission_for};
+use crate::format::{self, CheckResult, SkipReason};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Why a run over a directory would leave a file alone (see
+/// [`explain_exclusion`]).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Exclusion {
+    /// The file isn't in the directory at `dir`
+    OutsideDirectory { dir: PathBuf },
+    /// Searching the directory leaves the file out
+    Omitted(Omission),
+    /// An exclude pattern matches the file: one of the configuration file at
+    /// `config`, or of `--exclude` if `None`
+    Pattern {
+        config: Option<PathBuf>,
+        pattern: String,
+    },
+    /// The file is found, but skipped for its content
+    Skipped(SkipReason),
+}
+
+impl fmt::Display for Exclusion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Exclusion::OutsideDirectory { dir } => write!(f, "it is outside {}", dir.display()),
+            Exclusion::Omitted(omission) => write!(f, "{omission}"),
+            Exclusion::Pattern {
+                config: Some(config),
+                pattern,
+            } => write!(
+                f,
+                "exclude pattern '{pattern}' in {} matches it",
+                config.display()
+            ),
+            Exclusion::Pattern {
+                config: None,
+                pattern,
+            } => write!(f, "--exclude '{pattern}' matches it"),
+            Exclusion::Skipped(reason) => write!(f, "it is a {} file", reason.as_str()),
+        }
+    }
+}
+
+impl Exclusion {
+    fn map_paths(self, show: impl Fn(&Path) -> PathBuf) -> Self {
+        match self {
+            Exclusion::OutsideDirectory { dir } => Exclusion::OutsideDirectory { dir: show(&dir) },
+            Exclusion::Omitted(omission) => Exclusion::Omitted(omission.map_paths(show)),
+            Exclusion::Pattern { config, pattern } => Exclusion::Pattern {
+                config: config.map(|config| show(&config)),
+                pattern,
+            },
+            Exclusion::Skipped(reason) => Exclusion::Skipped(reason),
+        }
+    }
+}
+
+/// Returns why a run with `options` over the directory `dir` would leave the
+/// file at `path` alone, or `None` if it would process the file.
+///
+/// The checks follow the order of a run, and the first one that leaves the
+/// file out is returned: the `.gitignore` files and the other filters of
+/// the search (see [`omission_for`]), the exclude patterns, and then what
+/// the file contains, e.g. whether it is binary. Returns an error if `path`
+/// is not a file, or if the configuration is invalid as for
+/// [`Runner::format_with`](super::Runner::format_with).
+///
+/// The paths of the exclusion are shown as reports show them: relative to
+/// the working directory or [`RunOptions::base_dir`], unless
+/// [`RunOptions::absolute_paths`] is set.
+///
+/// # Examples
+///
+/// ```no_run
+/// use basefmt::runner::{RunOptions, explain_exclusion};
+/// use std::path::Path;
+///
+/// let path = Path::new("dist/app.js");
+/// match explain_exclusion(Path::new("."), path, &RunOptions::default()).unwrap() {
+///     Some(exclusion) => println!("{}: not processed: {exclusion}", path.display()),
+///     None => println!("{}: processed", path.display()),
+/// }
+/// ```
+pub fn explain_exclusion(
+    dir: &Path,
+    path: &Path,
+    options: &RunOptions,
+) -> io::Result<Option<Exclusion>> {
+    let paths = PathDisplay::new(options.base_dir.as_deref(), options.absolute_paths);
+    let exclusion = exclusion_for(dir, path, options)?;
+    Ok(exclusion.map(|exclusion| exclusion.map_paths(|path| paths.show(path))))
+}
+
+fn exclusion_for(dir: &Path, path: &Path, options: &RunOptions) -> io::Result<Option<Exclusion>> {
+    let with_path = |path: &Path, err: io::Error| {
+        io::Error::new(err.kind(), format!("{}: {err}", path.display()))
+    };
+    let dir_abs = canonical::canonicalize(dir).map_err(|err| with_path(dir, err))?;
+    // Resolve the links of the directories only, as the search sees the file
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(with_path(
+            path,
+            io::Error::from(io::ErrorKind::InvalidInput),
+        ));
+    };
+    let parent = if parent.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        parent
+    };
+    let abs = canonical::canonicalize(parent)
+        .map_err(|err| with_path(path, err))?
+        .join(name);
+    if fs::metadata(&abs)
+        .map_err(|err| with_path(path, err))?
+        .is_dir()
+    {
+        return Err(with_path(
+            path,
+            io::Error::new(io::ErrorKind::InvalidInput, "is a directory"),
+        ));
+    }
+    if !abs.starts_with(&dir_abs) {
+        return Ok(Some(Exclusion::OutsideDirectory { dir: dir_abs }));
+    }
+
+    let config = load_config(&dir_abs, options)?;
+    if let Some(omission) = omission_for(&dir_abs, &abs, &find_options_for(options, &config)) {
+        return Ok(Some(Exclusion::Omitted(omission)));
+    }
+
+    let canonical = canonical::canonicalize(&abs).map_err(|err| with_path(path, err))?;
+    let rel_path = canonical
+        .strip_prefix(&dir_abs)
+        .unwrap_or(canonical.as_path());
+    if let Some(pattern) = config.matching_exclude(rel_path) {
+        return Ok(Some(Exclusion::Pattern {
+            config: config.path.clone(),
+            pattern: pattern.to_string(),
+        }));
+    }
+    if let Some(pattern) = Config::with_exclude(options.exclude.clone())?.matching_exclude(rel_path)
+    {
+        return Ok(Some(Exclusion::Pattern {
+            config: None,
+            pattern: pattern.to_string(),
+        }));
+    }
+    let resolver = rule_resolver_for(options, &config, &dir_abs, false)?;
+    if let Some((config, pattern)) = resolver
+        .nested_configs()
+        .and_then(|nested| nested.exclusion_for(&canonical))
+    {
+        return Ok(Some(Exclusion::Pattern {
+            config: Some(config),
+            pattern,
+        }));
+    }
+
+    let rules = resolver.complete(&canonical, &resolver.explicit_rules_for(&canonical));
+    match format::check_file_in(&RealFileSystem, &canonical, &rules) {
+        Ok(CheckResult::Skipped(reason)) => Ok(Some(Exclusion::Skipped(reason))),
+        Ok(_) => Ok(None),
+        Err(err) => Err(with_path(path, err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use rstest::rstest;
+    use tempfile::TempDir;
+
+    #[rstest]
+    #[case::processed("src/a.txt", &[], None)]
+    #[case::hidden(
+        ".github/a.txt",
+        &[],
+        Some(Exclusion::Omitted(Omission::Hidden { path: PathBuf::from(".github") }))
+    )]
+    #[case::gitignore(
+        "build/a.txt",
+        &[],
+        Some(Exclusion::Omitted(Omission::Ignored {
+            path: PathBuf::from("build"),
+            file: PathBuf::from(".gitignore"),
+            line: Some(2),
+            pattern: "build/".to_string(),
+        }))
+    )]
+    #[case::config(
+        "src/a.log",
+        &[],
+        Some(Exclusion::Pattern {
+            config: Some(PathBuf::from(".basefmt.toml")),
+            pattern: "*.log".to_string(),
+        })
+    )]
+    #[case::cli(
+        "src/a.txt",
+        &["src/**"],
+        Some(Exclusion::Pattern { config: None, pattern: "src/**".to_string() })
+    )]
+    #[case::nested(
+        "pkg/a.md",
+        &[],
+        Some(Exclusion::Pattern {
+            config: Some(PathBuf::from("pkg/.basefmt.toml")),
+            pattern: "*.md".to_string(),
+        })
+    )]
+    #[case::binary("src/a.bin", &[], Some(Exclusion::Skipped(SkipReason::Binary)))]
+    fn test_explain_exclusion(
+        #[case] path: &str,
+        #[case] exclude: &[&str],
+        #[case] expected: Option<Exclusion>,
+    ) {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::write(
+            root.join(".gitignore"),
+            indoc! {"
+                *.tmp
+                build/
+            "},
+        )
+        .unwrap();
+        fs::write(root.join(".basefmt.toml"), "exclude = [\"*.log\"]\n").unwrap();
+        for dir in ["src", "build", ".github", "pkg"] {
+            fs::create_dir(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("pkg/.basefmt.toml"), "exclude = [\"*.md\"]\n").unwrap();
+        fs::write(root.join(path), "a\n").unwrap();
+        fs::write(root.join("src/a.bin"), b"\x00\x01\xff").unwrap();
+        let options = RunOptions {
+            exclude: exclude.iter().map(ToString::to_string).collect(),
+            absolute_paths: true,
+            ..RunOptions::default()
+        };
+
+        let exclusion = explain_exclusion(&root, &root.join(path), &options).unwrap();
+
+        let expected = expected.map(|exclusion| match exclusion {
+            Exclusion::Omitted(Omission::Hidden { path }) => Exclusion::Omitted(Omission::Hidden {
+                path: root.join(path),
+            }),
+            Exclusion::Omitted(Omission::Ignored {
+                path,
+                file,
+                line,
+                pattern,
+            }) => Exclusion::Omitted(Omission::Ignored {
+                path: root.join(path),
+                file: root.join(file),
+                line,
+                pattern,
+            }),
+            Exclusion::Pattern {
+                config: Some(config),
+                pattern,
+            } => Exclusion::Pattern {
+                config: Some(root.join(config)),
+                pattern,
+            },
+            exclusion => exclusion,
+        });
+        assert_eq!(exclusion, expected);
+    }
+}
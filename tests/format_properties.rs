@@ -0,0 +1,157 @@
+//! Property tests for the formatting core: for any rules and generated
+//! input, formatting must not panic, must be idempotent, and must leave the
+//! input alone when the rules change nothing. proptest shrinks a failing
+//! input to a minimal one and records it in
+//! `format_properties.proptest-regressions` so that it reproduces; `fuzz/`
+//! explores the same properties without the shape the strategies impose.
+
+use basefmt::editorconfig::{FormatRules, LineEnding};
+use basefmt::format::{Formatted, format_bytes, format_str};
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
+use proptest::sample::select;
+use proptest::test_runner::FileFailurePersistence;
+
+prop_compose! {
+    /// A run of the whitespace that trimming has to recognize.
+    fn whitespace_run()(pieces in vec(select(&[" ", "\t", "\u{a0}"][..]), 1..4)) -> String {
+        pieces.concat()
+    }
+}
+
+prop_compose! {
+    /// Every terminator line splitting has to handle, including a Unicode
+    /// separator that is not one.
+    fn line_ending()(ending in select(&["\n", "\r\n", "\r", "\u{2028}"][..])) -> &'static str {
+        ending
+    }
+}
+
+prop_compose! {
+    /// The text of a line: non-ASCII characters, a BOM, and the markers the
+    /// Markdown and YAML rules look for, with whitespace around and between
+    /// them.
+    fn line_content()(
+        pieces in vec(
+            prop_oneof![
+                select(&["a", "é", "\u{feff}", "#", ": |", "- "][..]).prop_map(str::to_owned),
+                whitespace_run(),
+            ],
+            0..6,
+        ),
+    ) -> String {
+        pieces.concat()
+    }
+}
+
+prop_compose! {
+    /// A whole input: lines, some of them blank or ending in whitespace, and
+    /// a last line that may lack a terminator.
+    fn text()(
+        lines in vec((line_content(), option::of(whitespace_run()), line_ending()), 0..8),
+        last in line_content(),
+    ) -> String {
+        let mut text: String = lines
+            .into_iter()
+            .map(|(content, trailing, ending)| content + &trailing.unwrap_or_default() + ending)
+            .collect();
+        text.push_str(&last);
+        text
+    }
+}
+
+prop_compose! {
+    /// Any combination of the rules, each set or unset.
+    fn format_rules()(
+        ensure_final_newline in option::of(any::<bool>()),
+        remove_trailing_spaces in option::of(any::<bool>()),
+        remove_leading_newlines in option::of(any::<bool>()),
+        preserve_markdown_hard_breaks in option::of(any::<bool>()),
+        max_blank_lines in option::of(0..3_usize),
+        end_of_line in option::of(select(&[LineEnding::Lf, LineEnding::Crlf, LineEnding::Cr][..])),
+        preserve_yaml_block_scalars in option::of(any::<bool>()),
+    ) -> FormatRules {
+        FormatRules {
+            ensure_final_newline,
+            remove_trailing_spaces,
+            remove_leading_newlines,
+            preserve_markdown_hard_breaks,
+            max_blank_lines,
+            skip_minified: None,
+            generated_marker_lines: None,
+            end_of_line,
+            preserve_yaml_block_scalars,
+            normalize_encoding: None,
+        }
+    }
+}
+
+prop_compose! {
+    /// Rules that change nothing: every rule turned off, except the ones
+    /// that only refine the others, which are left as they come.
+    fn inert_rules()(rules in format_rules()) -> FormatRules {
+        FormatRules {
+            ensure_final_newline: Some(false),
+            remove_trailing_spaces: Some(false),
+            remove_leading_newlines: Some(false),
+            max_blank_lines: None,
+            end_of_line: None,
+            ..rules
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 2000,
+        // Integration tests have no `lib.rs` beside them for the default
+        // location to be derived from
+        failure_persistence: Some(Box::new(FileFailurePersistence::WithSource(
+            "proptest-regressions"
+        ))),
+        ..ProptestConfig::default()
+    })]
+
+    #[test]
+    fn test_format_str_is_idempotent(input in text(), rules in format_rules()) {
+        let once = format_str(&input, &rules).content;
+        let twice = format_str(&once, &rules);
+        prop_assert_eq!(
+            twice,
+            Formatted {
+                content: once.clone(),
+                changed: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_rules_that_change_nothing_leave_input_alone(
+        input in text(),
+        rules in inert_rules(),
+    ) {
+        prop_assert_eq!(
+            format_str(&input, &rules),
+            Formatted {
+                content: input.clone(),
+                changed: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_format_bytes_matches_format_str(
+        input in prop_oneof![text().prop_map(String::into_bytes), vec(any::<u8>(), 0..24)],
+    ) {
+        let rules = FormatRules::default();
+        let expected = std::str::from_utf8(&input).ok().map(|text| {
+            let Formatted { content, changed } = format_str(text, &rules);
+            Formatted {
+                content: content.into_bytes(),
+                changed,
+            }
+        });
+        prop_assert_eq!(format_bytes(&input, &rules), expected);
+    }
+}
@@ -4,14 +4,22 @@
     blocks, not the helper functions in this integration test binary"
 )]
 
-use indoc::indoc;
+use indoc::{formatdoc, indoc};
+use rstest::rstest;
 use std::fs;
+use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 use tempfile::TempDir;
 
 fn basefmt() -> Command {
-    Command::new(env!("CARGO_BIN_EXE_basefmt"))
+    let mut command = Command::new(env!("CARGO_BIN_EXE_basefmt"));
+    // Keep the environment of whoever runs the tests out of the results
+    command
+        .env_remove("BASEFMT_EDITORCONFIG_FILE")
+        .env_remove("BASEFMT_CACHE_DIR")
+        .env_remove("GITHUB_ACTIONS");
+    command
 }
 
 /// Helper function to recursively copy a directory tree
@@ -32,6 +40,24 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
+/// Lists the paths of the files and directories under `dir`, relative to it
+/// and sorted, to tell whether a run left the tree alone.
+fn tree(dir: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                pending.push(path.clone());
+            }
+            paths.push(path.strip_prefix(dir).unwrap().to_path_buf());
+        }
+    }
+    paths.sort();
+    paths
+}
+
 fn setup_test_file(temp_dir: &TempDir, fixture_name: &str) -> PathBuf {
     let input_path = PathBuf::from("tests/fixtures/input").join(fixture_name);
     let temp_file = temp_dir.path().join(fixture_name);
@@ -87,6 +113,42 @@ fn test_format_single_files() {
     }
 }
 
+/// Every line keeps the file's dominant line ending, or takes the one
+/// `end_of_line` says, whatever else formatting changes about it.
+#[rstest]
+fn test_format_line_endings(
+    #[values("lf", "crlf", "mixed")] input: &str,
+    #[values(None, Some("lf"), Some("crlf"))] end_of_line: Option<&str>,
+) {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    if let Some(end_of_line) = end_of_line {
+        let editorconfig = temp_dir.path().join(".editorconfig");
+        let content = fs::read_to_string(&editorconfig).unwrap();
+        fs::write(
+            editorconfig,
+            format!("{content}end_of_line = {end_of_line}\n"),
+        )
+        .unwrap();
+    }
+    let test_file = setup_test_file(&temp_dir, &format!("line_endings_{input}.txt"));
+    let line_ending = match (end_of_line, input) {
+        (Some("crlf"), _) | (None, "crlf" | "mixed") => "\r\n",
+        _ => "\n",
+    };
+
+    basefmt().arg(&test_file).status().unwrap();
+    let check = basefmt().arg("--check").arg(&test_file).status().unwrap();
+
+    assert_eq!(
+        (fs::read_to_string(&test_file).unwrap(), check.code()),
+        (
+            read_expected("line_endings.txt").replace('\n', line_ending),
+            Some(0)
+        )
+    );
+}
+
 #[test]
 fn test_format_directory() {
     let temp_dir = TempDir::new().unwrap();
@@ -152,6 +214,169 @@ fn test_check_mode_dirty_file() {
     let after_check = fs::read_to_string(&test_file).unwrap();
     assert_eq!(original_content, after_check);
 }
+
+#[test]
+fn test_check_mode_reports_violations() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let test_file = temp_dir.path().join("dirty.txt");
+    fs::write(&test_file, "\n\ntest content  \nend").unwrap(); // ast-grep-ignore: prefer-indoc
+
+    let output = basefmt()
+        .arg("--check")
+        .arg(test_file.to_str().unwrap())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let path = test_file.display();
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        format!(
+            "{path}:1: 2 leading blank lines\n\
+             {path}:3:13: trailing whitespace\n\
+             {path}: missing final newline\n"
+        )
+    );
+}
+
+#[test]
+fn test_verbose_lists_changes() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let dirty_file = temp_dir.path().join("dirty.txt");
+    fs::write(&dirty_file, "a  \nb  \nc").unwrap(); // ast-grep-ignore: prefer-indoc
+    fs::write(temp_dir.path().join("clean.txt"), "clean\n").unwrap();
+
+    let output = basefmt()
+        .arg("--verbose")
+        .arg(temp_dir.path().to_str().unwrap())
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap()
+        ),
+        (
+            Some(0),
+            format!(
+                "{}: removed trailing whitespace on 2 lines; added final newline\n\
+                 2 files: 1 changed, 1 unchanged, 0 skipped\n",
+                dirty_file.display()
+            )
+        )
+    );
+}
+
+#[test]
+fn test_normalize_encoding() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(
+        temp_dir.path().join(".editorconfig"),
+        indoc! {"
+            root = true
+
+            [*]
+            basefmt_normalize_encoding = utf-8
+        "},
+    )
+    .unwrap();
+    let legacy_file = temp_dir.path().join("legacy.txt");
+    fs::write(&legacy_file, b"caf\xE9\n").unwrap();
+    fs::write(temp_dir.path().join("utf8.txt"), "café\n").unwrap();
+
+    let output = basefmt()
+        .arg("--verbose")
+        .arg(temp_dir.path().to_str().unwrap())
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap(),
+            fs::read_to_string(&legacy_file).unwrap()
+        ),
+        (
+            Some(0),
+            format!(
+                "{}: converted from Windows-1252 to UTF-8\n\
+                 2 files: 1 changed, 1 unchanged, 0 skipped\n",
+                legacy_file.display()
+            ),
+            "café\n".to_string()
+        )
+    );
+}
+
+#[rstest]
+#[case::check(&["--check"], 1, "needs_formatting", None, "\n\ntest content  \n\n")] // ast-grep-ignore: prefer-indoc
+#[case::format(
+    &[],
+    0,
+    "changed",
+    Some(serde_json::json!([
+        "removed 2 leading blank lines",
+        "removed trailing whitespace on 1 line",
+        "removed 1 trailing blank line",
+    ])),
+    "test content\n"
+)]
+fn test_jsonl_output(
+    #[case] args: &[&str],
+    #[case] expected_code: i32,
+    #[case] expected_status: &str,
+    #[case] expected_changes: Option<serde_json::Value>,
+    #[case] expected_content: &str,
+) {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let test_file = temp_dir.path().join("dirty.txt");
+    fs::write(&test_file, "\n\ntest content  \n\n").unwrap(); // ast-grep-ignore: prefer-indoc
+
+    let output = basefmt()
+        .args(args)
+        .args(["--output", "jsonl"])
+        .arg(test_file.to_str().unwrap())
+        .output()
+        .unwrap();
+
+    let mut record: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    // The duration varies between runs, so only its type is checked
+    let duration = record["duration_ms"].take();
+    // Only rewritten files list their changes
+    let changes = record.as_object_mut().unwrap().remove("changes");
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap(),
+            duration.is_f64(),
+            changes,
+            record,
+            fs::read_to_string(&test_file).unwrap(),
+        ),
+        (
+            Some(expected_code),
+            String::new(),
+            true,
+            expected_changes,
+            serde_json::json!({
+                "path": test_file.to_str().unwrap(),
+                "status": expected_status,
+                "violations": [
+                    {"kind": "leading_blank_lines", "line": 1, "column": null, "message": "2 leading blank lines"},
+                    {"kind": "trailing_whitespace", "line": 3, "column": 13, "message": "trailing whitespace"},
+                    {"kind": "trailing_blank_lines", "line": 4, "column": null, "message": "1 trailing blank line"},
+                ],
+                "duration_ms": null,
+            }),
+            expected_content.to_string(),
+        )
+    );
+}
+
 #[test]
 fn test_format_skips_binary_file() {
     let temp_dir = TempDir::new().unwrap();
@@ -172,6 +397,22 @@ fn test_format_skips_binary_file() {
     assert_eq!(content, vec![0xFF, 0xFE, 0xFD]);
 }
 
+#[test]
+fn test_format_utf16_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let file = temp_dir.path().join("app.reg");
+    let utf16le =
+        |text: &str| -> Vec<u8> { text.encode_utf16().flat_map(u16::to_le_bytes).collect() };
+    fs::write(&file, utf16le("\u{feff}key=1  \r\n\r\n")).unwrap(); // ast-grep-ignore: prefer-indoc
+
+    let status = basefmt().arg(&file).status().unwrap();
+
+    assert_eq!(
+        (status.code(), fs::read(&file).unwrap()),
+        (Some(0), utf16le("\u{feff}key=1\r\n"))
+    );
+}
+
 #[test]
 fn test_format_directory_with_binary_file() {
     let temp_dir = TempDir::new().unwrap();
@@ -226,6 +467,73 @@ fn test_check_skips_binary_file() {
     let content = fs::read(&binary_file).unwrap();
     assert_eq!(content, vec![0xFF, 0xFE, 0xFD]);
 }
+
+#[test]
+fn test_skips_generated_file() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::write(
+        temp_dir.path().join(".basefmt.toml"),
+        indoc! {"
+            [rules]
+            generated_marker_lines = 3
+        "},
+    )
+    .unwrap();
+    let generated_file = temp_dir.path().join("generated.rs");
+    fs::write(&generated_file, "// @generated by build.rs  \nfn f() {}").unwrap();
+
+    let output = basefmt()
+        .args(["--check", "--verbose"])
+        .arg(temp_dir.path().to_str().unwrap())
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap()
+        ),
+        (
+            Some(0),
+            format!(
+                "{}: skipped generated file\n\
+                 1 file: 0 need formatting, 0 unchanged, 1 skipped (1 generated)\n",
+                generated_file.display()
+            )
+        )
+    );
+}
+
+#[test]
+fn test_verbose_lists_binary_file() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let binary_file = temp_dir.path().join("image.bin");
+    fs::write(&binary_file, [0xFF, 0xFE, 0xFD]).unwrap();
+    fs::write(temp_dir.path().join("clean.txt"), "clean\n").unwrap();
+
+    let output = basefmt()
+        .arg("--verbose")
+        .arg(temp_dir.path().to_str().unwrap())
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap()
+        ),
+        (
+            Some(0),
+            format!(
+                "{}: skipped binary file\n\
+                 2 files: 0 changed, 1 unchanged, 1 skipped (1 binary)\n",
+                binary_file.display()
+            )
+        )
+    );
+}
 // ==============================================================================
 // EditorConfig + exclude configuration integration tests
 // ==============================================================================
@@ -511,3 +819,2039 @@ fn test_multiple_exclusion_patterns() {
     let normal = fs::read_to_string(temp_dir.path().join("normal.txt")).unwrap();
     assert_eq!(normal, "normal file with trailing spaces\n");
 }
+
+// ==============================================================================
+// Backup integration tests
+// ==============================================================================
+
+#[rstest]
+#[case::suffix(&["--backup"], "trailing_space.txt.orig")]
+#[case::custom_suffix(&["--backup", "--backup-suffix", ".bak"], "trailing_space.txt.bak")]
+fn test_format_with_backup(#[case] flags: &[&str], #[case] backup_name: &str) {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let test_file = setup_test_file(&temp_dir, "trailing_space.txt");
+    let original_content = fs::read_to_string(&test_file).unwrap();
+
+    let status = basefmt()
+        .args(flags)
+        .arg(test_file.to_str().unwrap())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    assert_eq!(
+        (
+            fs::read_to_string(&test_file).unwrap(),
+            fs::read_to_string(temp_dir.path().join(backup_name)).unwrap(),
+        ),
+        (read_expected("trailing_space.txt"), original_content)
+    );
+}
+
+#[rstest]
+#[case::suffix(&["--backup"], "n.txt.orig", "n.txt.orig.orig")]
+#[case::directory(&["--backup-dir", "backups"], "backups/n.txt", "backups/backups/n.txt")]
+fn test_format_with_backup_twice_leaves_backups_alone(
+    #[case] flags: &[&str],
+    #[case] backup_name: &str,
+    #[case] backup_of_backup: &str,
+) {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::write(temp_dir.path().join("n.txt"), "a  \n").unwrap();
+    let run = || {
+        basefmt()
+            .current_dir(temp_dir.path())
+            .args(flags)
+            .arg(".")
+            .status()
+            .unwrap()
+            .code()
+    };
+
+    let codes = (run(), run());
+
+    assert_eq!(
+        (
+            codes,
+            fs::read_to_string(temp_dir.path().join("n.txt")).unwrap(),
+            fs::read_to_string(temp_dir.path().join(backup_name)).unwrap(),
+            temp_dir.path().join(backup_of_backup).exists()
+        ),
+        (
+            (Some(0), Some(0)),
+            "a\n".to_string(),
+            "a  \n".to_string(),
+            false
+        )
+    );
+}
+
+#[test]
+fn test_format_with_backup_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    setup_test_file(&temp_dir, "trailing_space.txt");
+    let original_content = fs::read_to_string(temp_dir.path().join("trailing_space.txt")).unwrap();
+
+    let status = basefmt()
+        .current_dir(temp_dir.path())
+        .args(["--backup-dir", "backups", "trailing_space.txt"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("backups/trailing_space.txt")).unwrap(),
+        original_content
+    );
+}
+
+#[test]
+fn test_format_with_output_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    setup_test_file(&temp_dir, "trailing_space.txt");
+    fs::write(temp_dir.path().join("clean.txt"), "clean\n").unwrap();
+    let original_content = fs::read_to_string(temp_dir.path().join("trailing_space.txt")).unwrap();
+
+    // The second run must not pick up the copies written by the first one
+    for _ in 0..2 {
+        let status = basefmt()
+            .current_dir(temp_dir.path())
+            .args(["--output-dir", "out", "."])
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    assert_eq!(
+        (
+            fs::read_to_string(temp_dir.path().join("trailing_space.txt")).unwrap(),
+            fs::read_to_string(temp_dir.path().join("out/trailing_space.txt")).unwrap(),
+            fs::read_to_string(temp_dir.path().join("out/clean.txt")).unwrap(),
+            temp_dir.path().join("out/out").exists(),
+        ),
+        (
+            original_content,
+            read_expected("trailing_space.txt"),
+            "clean\n".to_string(),
+            false
+        )
+    );
+}
+
+#[test]
+fn test_backup_suffix_requires_backup() {
+    let temp_dir = TempDir::new().unwrap();
+
+    let status = basefmt()
+        .args(["--backup-suffix", ".bak"])
+        .arg(temp_dir.path().to_str().unwrap())
+        .status()
+        .unwrap();
+
+    assert_eq!(status.code(), Some(2));
+}
+
+// ==============================================================================
+// Symlink integration tests
+// ==============================================================================
+
+#[rstest]
+#[case::default(&[], true)]
+#[case::follow(&["--symlinks", "follow"], true)]
+#[case::replace(&["--symlinks", "replace"], false)]
+#[cfg(unix)]
+fn test_format_symlink_modes(#[case] flags: &[&str], #[case] keeps_link: bool) {
+    use std::os::unix::fs as unix_fs;
+
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let target = setup_test_file(&temp_dir, "trailing_space.txt");
+    let link = temp_dir.path().join("link.txt");
+    unix_fs::symlink(&target, &link).unwrap();
+
+    let status = basefmt()
+        .args(flags)
+        .arg(link.to_str().unwrap())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    assert_eq!(
+        (
+            fs::symlink_metadata(&link)
+                .unwrap()
+                .file_type()
+                .is_symlink(),
+            fs::read_to_string(&link).unwrap(),
+        ),
+        (keeps_link, read_expected("trailing_space.txt"))
+    );
+}
+
+// ==============================================================================
+// Read-only file integration tests
+// ==============================================================================
+
+#[rstest]
+#[case::skipped_by_default(&[], false)]
+#[case::formatted_with_force(&["--force"], true)]
+fn test_format_read_only_file(#[case] flags: &[&str], #[case] formatted: bool) {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let test_file = setup_test_file(&temp_dir, "trailing_space.txt");
+    let original_content = fs::read_to_string(&test_file).unwrap();
+    let mut permissions = fs::metadata(&test_file).unwrap().permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&test_file, permissions).unwrap();
+
+    let output = basefmt()
+        .args(flags)
+        .arg(test_file.to_str().unwrap())
+        .output()
+        .unwrap();
+
+    let expected_content = if formatted {
+        read_expected("trailing_space.txt")
+    } else {
+        original_content
+    };
+    let expected_stderr = if formatted {
+        String::new()
+    } else {
+        format!(
+            "{}: skipped read-only file (use --force to format it)\n",
+            test_file.display()
+        )
+    };
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap(),
+            fs::read_to_string(&test_file).unwrap(),
+            fs::metadata(&test_file).unwrap().permissions().readonly(),
+        ),
+        (Some(0), expected_stderr, expected_content, true)
+    );
+}
+
+#[rstest]
+#[case::remove(&[], false)]
+#[case::dry_run(&["--dry-run"], true)]
+fn test_clean_stale_temp_files(#[case] args: &[&str], #[case] expected_exists: bool) {
+    let temp_dir = TempDir::new().unwrap();
+    let stale = temp_dir.path().join(".basefmt-AbC123.tmp");
+    let file = fs::File::create(&stale).unwrap();
+    file.set_modified(std::time::SystemTime::now() - std::time::Duration::from_secs(3600))
+        .unwrap();
+    // Too recent to be left over from a crashed run
+    let recent = temp_dir.path().join(".basefmt-DeF456.tmp");
+    fs::write(&recent, "").unwrap();
+
+    let output = basefmt()
+        .arg("clean")
+        .args(args)
+        .arg(temp_dir.path().to_str().unwrap())
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stdout).unwrap(),
+            stale.exists(),
+            recent.exists(),
+        ),
+        (
+            Some(0),
+            format!("{}\n", stale.display()),
+            expected_exists,
+            true
+        )
+    );
+}
+
+#[rstest]
+#[case::lines(&[], "a.txt\nsub/c.txt\n")] // ast-grep-ignore: prefer-indoc
+#[case::null(&["-0"], "a.txt\0sub/c.txt\0")]
+fn test_list_files(#[case] flags: &[&str], #[case] expected: &str) {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(
+        temp_dir.path().join(".basefmt.toml"),
+        "exclude = [\"*.log\"]\n",
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join(".gitignore"), "b.txt\n").unwrap();
+    fs::create_dir(temp_dir.path().join(".git")).unwrap();
+    fs::create_dir(temp_dir.path().join("sub")).unwrap();
+    for file in ["a.txt", "b.txt", "sub/c.txt", "sub/d.log"] {
+        fs::write(temp_dir.path().join(file), "x").unwrap();
+    }
+
+    let output = basefmt()
+        .arg("list-files")
+        .args(flags)
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+
+    let mut files: Vec<String> = String::from_utf8(output.stdout)
+        .unwrap()
+        .split_inclusive(['\n', '\0'])
+        .map(|file| file.trim_start_matches("./").to_string())
+        .collect();
+    files.sort();
+    assert_eq!(
+        (output.status.code(), files.concat()),
+        (Some(0), expected.to_string())
+    );
+}
+
+#[rstest]
+#[case::processed("a.txt", 0, "a.txt: processed\n")]
+#[case::gitignore(
+    "build/a.txt",
+    1,
+    "build/a.txt: not processed: build is ignored by 'build/' in .gitignore:1\n"
+)]
+#[case::exclude(
+    "a.log",
+    1,
+    "a.log: not processed: exclude pattern '*.log' in .basefmt.toml matches it\n"
+)]
+#[case::binary("a.bin", 1, "a.bin: not processed: it is a binary file\n")]
+fn test_why(#[case] path: &str, #[case] code: i32, #[case] expected: &str) {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir(root.join(".git")).unwrap();
+    fs::write(root.join(".gitignore"), "build/\n").unwrap();
+    fs::write(root.join(".basefmt.toml"), "exclude = [\"*.log\"]\n").unwrap();
+    fs::create_dir(root.join("build")).unwrap();
+    for file in ["a.txt", "a.log", "build/a.txt"] {
+        fs::write(root.join(file), "a\n").unwrap();
+    }
+    fs::write(root.join("a.bin"), b"\x00\x01\xff").unwrap();
+
+    let output = basefmt()
+        .args(["why", path])
+        .current_dir(&root)
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stdout).unwrap()
+        ),
+        (Some(code), expected.to_string())
+    );
+}
+
+#[test]
+fn test_doctor() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(
+        temp_dir.path().join(".editorconfig"),
+        "root = true\n\n[*.bat]\nend_of_line = lf\n", // ast-grep-ignore: prefer-indoc
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join(".gitattributes"), "*.bat eol=crlf\n").unwrap();
+    fs::write(temp_dir.path().join("a.bat"), "a\n").unwrap();
+
+    let output = basefmt()
+        .arg("doctor")
+        .current_dir(temp_dir.path())
+        .output()
+        .unwrap();
+
+    let root = temp_dir.path().canonicalize().unwrap();
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stdout).unwrap()
+        ),
+        (
+            Some(1),
+            formatdoc! {"
+                Configuration files:
+                  none
+
+                EditorConfig files:
+                  {root}/.editorconfig
+
+                Problems:
+                  {root}/a.bat: .gitattributes sets eol=crlf, overriding end_of_line = lf in .editorconfig
+
+                Effective defaults:
+                  insert_final_newline = true            # default
+                  trim_trailing_whitespace = true        # default
+                  trim_leading_newlines = true           # default
+                  preserve_markdown_hard_breaks = false  # default
+                  max_blank_lines = unset                # default
+                  skip_minified = true                   # default
+                  generated_marker_lines = 0             # default
+                  end_of_line = unset                    # default
+                  preserve_yaml_block_scalars = false    # default
+                  normalize_encoding = unset             # default
+                ",
+                root = root.display()
+            }
+        )
+    );
+}
+
+#[rstest]
+#[case::clean("insert_final_newline = true", "", 0, "")]
+#[case::misspelled_property(
+    "insert_final_newlines = true",
+    "",
+    1,
+    "{editorconfig}:3: unknown property 'insert_final_newlines' (did you mean 'insert_final_newline'?)\n"
+)]
+#[case::invalid_basefmt_toml(
+    "insert_final_newline = true",
+    "exclude = 1\n",
+    2,
+    "Error: failed to parse .basefmt.toml: "
+)]
+fn test_check_config(
+    #[case] property: &str,
+    #[case] basefmt_toml: &str,
+    #[case] expected_code: i32,
+    #[case] expected_stderr: &str,
+) {
+    let temp_dir = TempDir::new().unwrap();
+    let editorconfig = temp_dir
+        .path()
+        .canonicalize()
+        .unwrap()
+        .join(".editorconfig");
+    fs::write(
+        &editorconfig,
+        formatdoc! {"
+            root = true
+            [*]
+            {property}
+        "},
+    )
+    .unwrap();
+    if !basefmt_toml.is_empty() {
+        fs::write(temp_dir.path().join(".basefmt.toml"), basefmt_toml).unwrap();
+    }
+    fs::write(temp_dir.path().join("a.txt"), "a\n").unwrap();
+
+    let output = basefmt()
+        .arg("check-config")
+        .arg(temp_dir.path().to_str().unwrap())
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    // The TOML parser's own message is not part of the spec
+    let stderr = match stderr.split_once(".basefmt.toml: ") {
+        Some((prefix, _)) => format!("{prefix}.basefmt.toml: "),
+        None => stderr,
+    };
+    assert_eq!(
+        (output.status.code(), stderr),
+        (
+            Some(expected_code),
+            expected_stderr.replace("{editorconfig}", &editorconfig.display().to_string())
+        )
+    );
+}
+
+#[test]
+fn test_explain() {
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path().canonicalize().unwrap();
+    fs::write(
+        dir.join(".editorconfig"),
+        indoc! {"
+            root = true
+
+            [*.md]
+            trim_trailing_whitespace = false
+        "},
+    )
+    .unwrap();
+    fs::write(dir.join(".basefmt.toml"), "profile = \"strict\"\n").unwrap();
+    fs::create_dir(dir.join("docs")).unwrap();
+    fs::write(
+        dir.join("docs/.basefmt.toml"),
+        indoc! {"
+            [rules]
+            max_blank_lines = 2
+        "},
+    )
+    .unwrap();
+    fs::write(dir.join("docs/a.md"), "a\n").unwrap();
+
+    let output = basefmt()
+        .args(["explain", "docs/a.md", "--rule", "end_of_line=lf"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+
+    let dir = dir.display();
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stdout).unwrap()
+        ),
+        (
+            Some(0),
+            formatdoc! {"
+                insert_final_newline = true            # default
+                trim_trailing_whitespace = false       # {dir}/.editorconfig:3 [*.md]
+                trim_leading_newlines = true           # default
+                preserve_markdown_hard_breaks = false  # default
+                max_blank_lines = 2                    # {dir}/docs/.basefmt.toml [rules]
+                skip_minified = true                   # default
+                generated_marker_lines = 0             # default
+                end_of_line = lf                       # command line
+                preserve_yaml_block_scalars = false    # default
+                normalize_encoding = unset             # default
+            "}
+        )
+    );
+}
+
+#[rstest]
+#[case::default(None, None, 1)]
+#[case::config_key(Some(".editorconfig-ci"), None, 0)]
+#[case::env(None, Some(".editorconfig-ci"), 0)]
+#[case::env_overrides_config_key(Some(".editorconfig"), Some(".editorconfig-ci"), 0)]
+fn test_alternate_editorconfig_file(
+    #[case] config_key: Option<&str>,
+    #[case] env: Option<&str>,
+    #[case] expected_code: i32,
+) {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(
+        temp_dir.path().join(".editorconfig"),
+        indoc! {"
+            [*]
+            trim_trailing_whitespace = true
+        "},
+    )
+    .unwrap();
+    fs::write(
+        temp_dir.path().join(".editorconfig-ci"),
+        indoc! {"
+            [*]
+            trim_trailing_whitespace = false
+        "},
+    )
+    .unwrap();
+    if let Some(file_name) = config_key {
+        fs::write(
+            temp_dir.path().join(".basefmt.toml"),
+            format!("editorconfig_file = \"{file_name}\"\n"),
+        )
+        .unwrap();
+    }
+    let file_path = temp_dir.path().join("a.txt");
+    fs::write(&file_path, "a  \n").unwrap();
+
+    let mut command = basefmt();
+    command.arg("--check").arg(&file_path);
+    if let Some(file_name) = env {
+        command.env("BASEFMT_EDITORCONFIG_FILE", file_name);
+    }
+
+    assert_eq!(command.status().unwrap().code(), Some(expected_code));
+}
+
+#[rstest]
+#[case::unbounded(&[], "", 0)]
+#[case::root_dir_flag(&["--root-dir", "{checkout}"], "", 1)]
+#[case::root_config_key(&[], "root = true\n", 1)]
+fn test_root_dir_bounds_editorconfig_search(
+    #[case] flags: &[&str],
+    #[case] basefmt_toml: &str,
+    #[case] expected_code: i32,
+) {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(
+        temp_dir.path().join(".editorconfig"),
+        indoc! {"
+            root = true
+            [*]
+            insert_final_newline = false
+        "},
+    )
+    .unwrap();
+    let checkout = temp_dir.path().join("checkout");
+    fs::create_dir(&checkout).unwrap();
+    if !basefmt_toml.is_empty() {
+        fs::write(checkout.join(".basefmt.toml"), basefmt_toml).unwrap();
+    }
+    fs::write(checkout.join("a.txt"), "a").unwrap();
+
+    let status = basefmt()
+        .arg("--check")
+        .args(
+            flags
+                .iter()
+                .map(|flag| flag.replace("{checkout}", checkout.to_str().unwrap())),
+        )
+        .arg(&checkout)
+        .status()
+        .unwrap();
+
+    assert_eq!(status.code(), Some(expected_code));
+}
+
+#[rstest]
+#[case::editorconfig(&[], "", 0)]
+#[case::basefmt_toml_overrides_editorconfig(&[], "[rules]\ntrim_trailing_whitespace = true\n", 1)] // ast-grep-ignore: prefer-indoc
+#[case::editorconfig_overrides_defaults(&[], "[defaults]\ntrim_trailing_whitespace = true\n", 0)] // ast-grep-ignore: prefer-indoc
+#[case::cli_overrides_basefmt_toml(
+    &["--rule", "trim_trailing_whitespace=false"],
+    "[rules]\ntrim_trailing_whitespace = true\n", // ast-grep-ignore: prefer-indoc
+    0
+)]
+#[case::cli_enables_rule_disabled_by_editorconfig(
+    &["--rule", "trim_trailing_whitespace=true"],
+    "",
+    1
+)]
+#[case::skip_rule_overrides_cli(
+    &["--rule", "trim_trailing_whitespace=true", "--skip-rule", "trim_trailing_whitespace"],
+    "[rules]\ntrim_trailing_whitespace = true\n", // ast-grep-ignore: prefer-indoc
+    0
+)]
+fn test_rule_precedence(
+    #[case] flags: &[&str],
+    #[case] basefmt_toml: &str,
+    #[case] expected_code: i32,
+) {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(
+        temp_dir.path().join(".editorconfig"),
+        indoc! {"
+            root = true
+            [*]
+            trim_trailing_whitespace = false
+        "},
+    )
+    .unwrap();
+    if !basefmt_toml.is_empty() {
+        fs::write(temp_dir.path().join(".basefmt.toml"), basefmt_toml).unwrap();
+    }
+    let file_path = temp_dir.path().join("a.txt");
+    fs::write(&file_path, "a  \n").unwrap();
+
+    let status = basefmt()
+        .arg("--check")
+        .args(flags)
+        .arg(&file_path)
+        .status()
+        .unwrap();
+
+    assert_eq!(status.code(), Some(expected_code));
+}
+
+#[rstest]
+#[case::standard_by_default(&[], "", 1)]
+#[case::minimal_keeps_leading_blank_lines(&["--profile", "minimal"], "", 0)]
+#[case::config_key(&[], "profile = \"minimal\"\n", 0)]
+#[case::flag_overrides_config_key(&["--profile", "standard"], "profile = \"minimal\"\n", 1)]
+#[case::preset_flag(&["--preset", "minimal"], "", 0)]
+#[case::preset_config_key(&[], "preset = \"minimal\"\n", 0)]
+#[case::markdown_safe(&["--preset", "markdown-safe"], "", 1)]
+fn test_rule_profile(
+    #[case] flags: &[&str],
+    #[case] basefmt_toml: &str,
+    #[case] expected_code: i32,
+) {
+    let temp_dir = TempDir::new().unwrap();
+    // No .editorconfig anywhere, as in a repository without one
+    fs::write(
+        temp_dir.path().join(".basefmt.toml"),
+        format!("root = true\n{basefmt_toml}"),
+    )
+    .unwrap();
+    let file_path = temp_dir.path().join("a.txt");
+    fs::write(&file_path, "\na\n").unwrap(); // ast-grep-ignore: prefer-indoc
+
+    let status = basefmt()
+        .arg("--check")
+        .args(flags)
+        .arg(&file_path)
+        .status()
+        .unwrap();
+
+    assert_eq!(status.code(), Some(expected_code));
+}
+
+#[rstest]
+#[case::eol_overrides_editorconfig("*.txt text eol=crlf\n", "a\r\nb\r\n")] // ast-grep-ignore: prefer-indoc
+#[case::text_keeps_own_line_endings("*.txt text\n", "a\r\nb\r\n")] // ast-grep-ignore: prefer-indoc
+#[case::binary_follows_editorconfig("*.txt binary\n", "a\nb\n")] // ast-grep-ignore: prefer-indoc
+fn test_gitattributes_line_endings(#[case] gitattributes: &str, #[case] expected: &str) {
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir(temp_dir.path().join(".git")).unwrap();
+    fs::write(
+        temp_dir.path().join(".editorconfig"),
+        indoc! {"
+            root = true
+
+            [*]
+            end_of_line = lf
+        "},
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join(".gitattributes"), gitattributes).unwrap();
+    let file_path = temp_dir.path().join("a.txt");
+    fs::write(&file_path, "a\r\nb").unwrap();
+
+    let status = basefmt().arg(&file_path).status().unwrap();
+
+    assert_eq!(
+        (status.code(), fs::read_to_string(&file_path).unwrap()),
+        (Some(0), expected.to_string())
+    );
+}
+
+#[rstest]
+#[case::skipped_by_default(&[], "b")]
+#[case::include_submodules(&["--include-submodules"], "b\n")]
+fn test_submodules(#[case] flags: &[&str], #[case] expected: &str) {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let submodule = temp_dir.path().join("vendor/lib");
+    fs::create_dir_all(&submodule).unwrap();
+    fs::write(submodule.join(".git"), "gitdir: ../../.git/modules/lib\n").unwrap();
+    fs::write(submodule.join("b.txt"), "b").unwrap();
+
+    let status = basefmt().args(flags).arg(temp_dir.path()).status().unwrap();
+
+    assert_eq!(
+        (
+            status.code(),
+            fs::read_to_string(submodule.join("b.txt")).unwrap()
+        ),
+        (Some(0), expected.to_string())
+    );
+}
+
+#[rstest]
+#[case::required_by_default(&[], "", "a\n")]
+#[case::flag(&["--no-require-git"], "", "a")]
+#[case::config(&[], "require_git = false\n", "a")]
+fn test_gitignore_outside_git(
+    #[case] flags: &[&str],
+    #[case] config: &str,
+    #[case] expected: &str,
+) {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::write(temp_dir.path().join(".basefmt.toml"), config).unwrap();
+    fs::write(temp_dir.path().join(".gitignore"), "a.txt\n").unwrap();
+    fs::write(temp_dir.path().join("a.txt"), "a").unwrap();
+    fs::write(temp_dir.path().join("b.txt"), "b").unwrap();
+
+    let status = basefmt().args(flags).arg(temp_dir.path()).status().unwrap();
+
+    assert_eq!(
+        (
+            status.code(),
+            fs::read_to_string(temp_dir.path().join("a.txt")).unwrap(),
+            fs::read_to_string(temp_dir.path().join("b.txt")).unwrap()
+        ),
+        (Some(0), expected.to_string(), "b\n".to_string())
+    );
+}
+
+#[rstest]
+#[case::rejected(
+    &[],
+    2,
+    "Error: {dir}/.basefmt.toml: unknown key 'exlcude' (did you mean 'exclude'?)\n",
+    "a"
+)]
+#[case::allowed(&["--allow-unknown-config-keys"], 0, "", "a\n")]
+fn test_unknown_config_key(
+    #[case] flags: &[&str],
+    #[case] expected_code: i32,
+    #[case] expected_stderr: &str,
+    #[case] expected: &str,
+) {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::write(
+        temp_dir.path().join(".basefmt.toml"),
+        "exlcude = [\"a.txt\"]\n",
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join("a.txt"), "a").unwrap();
+
+    let output = basefmt().args(flags).arg(temp_dir.path()).output().unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap(),
+            fs::read_to_string(temp_dir.path().join("a.txt")).unwrap()
+        ),
+        (
+            Some(expected_code),
+            expected_stderr.replace("{dir}", &temp_dir.path().display().to_string()),
+            expected.to_string()
+        )
+    );
+}
+
+#[rstest]
+#[case::yaml(".basefmt.yaml", "exclude:\n  - a.txt\n")]
+#[case::json(".basefmt.json", r#"{"exclude": ["a.txt"]}"#)]
+fn test_config_file_formats(#[case] name: &str, #[case] content: &str) {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::write(temp_dir.path().join(name), content).unwrap();
+    fs::write(temp_dir.path().join("a.txt"), "a").unwrap();
+    fs::write(temp_dir.path().join("b.txt"), "b").unwrap();
+
+    let status = basefmt().arg(temp_dir.path()).status().unwrap();
+
+    assert_eq!(
+        (
+            status.code(),
+            fs::read_to_string(temp_dir.path().join("a.txt")).unwrap(),
+            fs::read_to_string(temp_dir.path().join("b.txt")).unwrap()
+        ),
+        (Some(0), "a".to_string(), "b\n".to_string())
+    );
+}
+
+#[test]
+fn test_cache() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let cache_dir = temp_dir
+        .path()
+        .canonicalize()
+        .unwrap()
+        .join(".basefmt-cache");
+    fs::write(temp_dir.path().join("a.txt"), "a\n").unwrap();
+    let check = || {
+        basefmt()
+            .current_dir(temp_dir.path())
+            .args(["--check", "a.txt"])
+            .status()
+            .unwrap()
+            .code()
+    };
+
+    // Checking only uses a cache that formatting created
+    let first = basefmt()
+        .current_dir(temp_dir.path())
+        .arg("a.txt")
+        .status()
+        .unwrap()
+        .code();
+    let gitignore = fs::read_to_string(cache_dir.join(".gitignore")).unwrap();
+    let second = check();
+    fs::write(temp_dir.path().join("a.txt"), "a ").unwrap();
+    let after_edit = check();
+    let clear = basefmt()
+        .current_dir(temp_dir.path())
+        .args(["cache", "clear"])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            first,
+            gitignore,
+            second,
+            after_edit,
+            String::from_utf8(clear.stdout).unwrap(),
+            cache_dir.exists()
+        ),
+        (
+            Some(0),
+            "*\n".to_string(),
+            Some(0),
+            Some(1),
+            format!("{}\n", cache_dir.display()),
+            false
+        )
+    );
+}
+
+#[test]
+fn test_cache_invalidated_by_editorconfig_change() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("a.txt"), "a \n").unwrap();
+    let check = |trim_trailing_whitespace: bool| {
+        fs::write(
+            temp_dir.path().join(".editorconfig"),
+            formatdoc! {"
+                [*]
+                trim_trailing_whitespace = {trim_trailing_whitespace}
+            "},
+        )
+        .unwrap();
+        basefmt()
+            .current_dir(temp_dir.path())
+            .args(["--check", "a.txt"])
+            .status()
+            .unwrap()
+            .code()
+    };
+
+    assert_eq!((check(false), check(true)), (Some(0), Some(1)));
+}
+
+#[test]
+fn test_lock() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::write(temp_dir.path().join("a.txt"), "a").unwrap();
+    fs::write(temp_dir.path().join("b.txt"), "b").unwrap();
+    fs::write(temp_dir.path().join("c.txt"), "c\n").unwrap();
+    // The lock file is kept in the temporary directory
+    let lock_dir = temp_dir.path().join("tmp");
+    fs::create_dir(&lock_dir).unwrap();
+    let run = |args: &[&str]| {
+        basefmt()
+            .current_dir(temp_dir.path())
+            .env("TMPDIR", &lock_dir)
+            .env("TMP", &lock_dir)
+            .args(args)
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap()
+    };
+    // Creates the lock file
+    run(&["c.txt"]).wait().unwrap();
+    let lock_path = fs::read_dir(&lock_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension() == Some("lock".as_ref()))
+        .unwrap();
+    let lock = fs::File::open(&lock_path).unwrap();
+    lock.lock().unwrap();
+
+    let unlocked = run(&["--no-lock", "a.txt"]).wait().unwrap().success();
+    let mut waiting = run(&["b.txt"]);
+    // The message is printed right before blocking on the lock
+    let mut stderr = BufReader::new(waiting.stderr.take().unwrap());
+    let mut message = String::new();
+    stderr.read_line(&mut message).unwrap();
+    let waited = waiting.try_wait().unwrap().is_none();
+    drop(lock);
+    let success = waiting.wait().unwrap().success();
+    stderr.read_line(&mut message).unwrap();
+
+    assert_eq!(
+        (
+            unlocked,
+            fs::read_to_string(temp_dir.path().join("a.txt")).unwrap(),
+            waited,
+            success,
+            message,
+            fs::read_to_string(temp_dir.path().join("b.txt")).unwrap(),
+        ),
+        (
+            true,
+            "a\n".to_string(),
+            true,
+            true,
+            format!(
+                "Waiting for another basefmt run to finish (lock file {})\n",
+                lock_path.display()
+            ),
+            "b\n".to_string()
+        )
+    );
+}
+
+#[rstest]
+#[case::no_cache(&["--no-cache"], &[], 0)]
+#[case::flag(&["--cache-dir", "cache"], &[], 1)]
+#[case::env(&[], &[("BASEFMT_CACHE_DIR", "cache")], 1)]
+fn test_cache_dir(#[case] args: &[&str], #[case] envs: &[(&str, &str)], #[case] expected: usize) {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::write(temp_dir.path().join("a.txt"), "a").unwrap();
+
+    basefmt()
+        .current_dir(temp_dir.path())
+        .args(args)
+        .envs(envs.iter().copied())
+        .arg("a.txt")
+        .status()
+        .unwrap();
+
+    let cache_files = |dir: &str| {
+        fs::read_dir(temp_dir.path().join(dir)).map_or(0, |entries| {
+            entries
+                .filter(|entry| entry.as_ref().unwrap().path().extension() == Some("json".as_ref()))
+                .count()
+        })
+    };
+    assert_eq!(
+        (cache_files("cache"), cache_files(".basefmt-cache")),
+        (expected, 0)
+    );
+}
+
+#[test]
+fn test_no_cache_leaves_tree_alone() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::write(temp_dir.path().join("a.txt"), "a").unwrap();
+    let before = tree(temp_dir.path());
+
+    let status = basefmt()
+        .current_dir(temp_dir.path())
+        .args(["--no-cache", "a.txt"])
+        .status()
+        .unwrap();
+
+    assert_eq!(
+        (
+            status.code(),
+            tree(temp_dir.path()),
+            fs::read_to_string(temp_dir.path().join("a.txt")).unwrap()
+        ),
+        (Some(0), before, "a\n".to_string())
+    );
+}
+
+#[rstest]
+#[case::top_level("a.txt")]
+#[case::nested("sub/deep/a.txt")]
+fn test_check_creates_no_cache(#[case] path: &str) {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::create_dir(temp_dir.path().join(".git")).unwrap();
+    fs::create_dir_all(temp_dir.path().join("sub/deep")).unwrap();
+    fs::write(temp_dir.path().join(path), "a\n").unwrap();
+    let before = tree(temp_dir.path());
+
+    let status = basefmt()
+        .current_dir(temp_dir.path())
+        .args(["--check", path])
+        .status()
+        .unwrap();
+
+    assert_eq!((status.code(), tree(temp_dir.path())), (Some(0), before));
+}
+
+#[test]
+fn test_cache_in_project_root() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::create_dir(temp_dir.path().join(".git")).unwrap();
+    fs::create_dir_all(temp_dir.path().join("sub/deep")).unwrap();
+    fs::write(temp_dir.path().join("sub/deep/a.txt"), "a").unwrap();
+
+    let status = basefmt()
+        .current_dir(temp_dir.path())
+        .arg("sub/deep/a.txt")
+        .status()
+        .unwrap();
+
+    assert_eq!(
+        (
+            status.code(),
+            temp_dir.path().join(".basefmt-cache").is_dir(),
+            temp_dir.path().join("sub/deep/.basefmt-cache").exists()
+        ),
+        (Some(0), true, false)
+    );
+}
+
+#[test]
+fn test_cache_dir_in_tree_is_not_formatted() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::write(temp_dir.path().join("a.txt"), "a").unwrap();
+    let run = |args: &[&str]| {
+        let output = basefmt()
+            .current_dir(temp_dir.path())
+            .args(["--cache-dir", "cache", "."])
+            .args(args)
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    };
+
+    assert_eq!(
+        (run(&[]), run(&["--check"])),
+        ((Some(0), String::new()), (Some(0), String::new()))
+    );
+}
+
+#[test]
+fn test_stats() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::write(temp_dir.path().join("a.txt"), "a \nb \n").unwrap(); // ast-grep-ignore: prefer-indoc
+    fs::write(temp_dir.path().join("b.txt"), "c ").unwrap();
+
+    let text = basefmt()
+        .current_dir(temp_dir.path())
+        .args(["--check", "--stats", "a.txt", "b.txt"])
+        .output()
+        .unwrap();
+    let jsonl = basefmt()
+        .current_dir(temp_dir.path())
+        .args(["--check", "--stats", "--output", "jsonl", "a.txt", "b.txt"])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            String::from_utf8(text.stderr).unwrap(),
+            String::from_utf8(jsonl.stdout)
+                .unwrap()
+                .lines()
+                .last()
+                .map(str::to_string)
+        ),
+        (
+            indoc! {"
+                a.txt:1:2: trailing whitespace
+                a.txt:2:2: trailing whitespace
+                b.txt:1:2: trailing whitespace
+                b.txt: missing final newline
+                rule                   files  lines
+                missing_final_newline      1      1
+                trailing_whitespace        2      3
+            "}
+            .to_string(),
+            Some(
+                concat!(
+                    r#"{"stats":{"missing_final_newline":{"files":1,"lines":1},"#,
+                    r#""trailing_whitespace":{"files":2,"lines":3}}}"#
+                )
+                .to_string()
+            )
+        )
+    );
+}
+
+#[rstest]
+#[case::warns(&[], Some(0))]
+#[case::error_on_skipped(&["--error-on-skipped"], Some(2))]
+fn test_explicit_skipped_file(#[case] flags: &[&str], #[case] expected_code: Option<i32>) {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::create_dir_all(temp_dir.path().join("assets")).unwrap();
+    for file in ["logo.bin", "assets/icon.bin"] {
+        fs::write(temp_dir.path().join(file), b"\xc3\x28 binary").unwrap();
+    }
+
+    let output = basefmt()
+        .current_dir(temp_dir.path())
+        .args(flags)
+        .args(["logo.bin", "assets"])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap()
+        ),
+        (expected_code, "logo.bin: skipped binary file\n".to_string())
+    );
+}
+
+#[test]
+fn test_explicit_file_bypasses_exclude() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::create_dir_all(temp_dir.path().join("docs")).unwrap();
+    for file in ["a.txt", "docs/b.txt"] {
+        fs::write(temp_dir.path().join(file), "dirty").unwrap();
+    }
+
+    let status = basefmt()
+        .current_dir(temp_dir.path())
+        .args(["--exclude", "**/*.txt", "a.txt", "docs"])
+        .status()
+        .unwrap();
+
+    assert_eq!(
+        (
+            status.code(),
+            ["a.txt", "docs/b.txt"]
+                .map(|file| fs::read_to_string(temp_dir.path().join(file)).unwrap())
+        ),
+        (Some(0), ["dirty\n".to_string(), "dirty".to_string()])
+    );
+}
+
+#[test]
+fn test_nested_config() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let package = temp_dir.path().join("packages/app");
+    fs::create_dir_all(package.join("generated")).unwrap();
+    fs::write(
+        package.join(".basefmt.toml"),
+        indoc! {r#"
+            exclude = ["generated/**"]
+
+            [rules]
+            trim_trailing_whitespace = false
+        "#},
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join("a.txt"), "a  ").unwrap();
+    fs::write(package.join("b.txt"), "b  ").unwrap();
+    fs::write(package.join("generated/c.txt"), "c  ").unwrap();
+
+    let status = basefmt().arg(temp_dir.path()).status().unwrap();
+
+    assert_eq!(
+        (
+            status.code(),
+            fs::read_to_string(temp_dir.path().join("a.txt")).unwrap(),
+            fs::read_to_string(package.join("b.txt")).unwrap(),
+            fs::read_to_string(package.join("generated/c.txt")).unwrap()
+        ),
+        (
+            Some(0),
+            "a\n".to_string(),
+            "b  \n".to_string(),
+            "c  ".to_string()
+        )
+    );
+}
+
+#[test]
+fn test_exclude_flag() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::write(
+        temp_dir.path().join(".basefmt.toml"),
+        "exclude = [\"vendor/**\"]\n",
+    )
+    .unwrap();
+    fs::create_dir_all(temp_dir.path().join("vendor")).unwrap();
+    fs::create_dir_all(temp_dir.path().join("docs")).unwrap();
+    for file in ["a.txt", "vendor/b.txt", "docs/c.txt"] {
+        fs::write(temp_dir.path().join(file), "dirty").unwrap();
+    }
+
+    let status = basefmt()
+        .current_dir(temp_dir.path())
+        .args(["--exclude", "docs/**", "."])
+        .status()
+        .unwrap();
+
+    assert_eq!(
+        (
+            status.code(),
+            ["a.txt", "vendor/b.txt", "docs/c.txt"]
+                .map(|file| fs::read_to_string(temp_dir.path().join(file)).unwrap())
+        ),
+        (
+            Some(0),
+            [
+                "dirty\n".to_string(),
+                "dirty".to_string(),
+                "dirty".to_string()
+            ]
+        )
+    );
+}
+
+#[test]
+fn test_write_patch() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::create_dir(temp_dir.path().join("src")).unwrap();
+    fs::write(temp_dir.path().join("src/a.txt"), "a").unwrap();
+    fs::write(
+        temp_dir.path().join("b.txt"),
+        indoc! {"
+
+            b
+        "},
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join("c.txt"), "c\n").unwrap();
+    let patch_dir = TempDir::new().unwrap();
+    let patch_path = patch_dir.path().join("fix.patch");
+
+    let status = basefmt()
+        .current_dir(temp_dir.path())
+        .arg("--write-patch")
+        .arg(&patch_path)
+        .arg(".")
+        .status()
+        .unwrap();
+
+    assert_eq!(
+        (
+            status.code(),
+            fs::read_to_string(temp_dir.path().join("src/a.txt")).unwrap(),
+            fs::read_to_string(&patch_path).unwrap()
+        ),
+        (
+            Some(1),
+            "a".to_string(),
+            indoc! {r"
+                diff --git a/b.txt b/b.txt
+                --- a/b.txt
+                +++ b/b.txt
+                @@ -1,2 +1 @@
+                -
+                 b
+                diff --git a/src/a.txt b/src/a.txt
+                --- a/src/a.txt
+                +++ b/src/a.txt
+                @@ -1 +1 @@
+                -a
+                \ No newline at end of file
+                +a
+            "}
+            .to_string()
+        )
+    );
+}
+
+#[rstest]
+#[case::matching_glob("[*.txt]", 0, "")]
+#[case::no_matching_glob("[*.md]", 3, "{file}: no formatting rules apply\n")]
+fn test_require_rules(
+    #[case] section: &str,
+    #[case] expected_code: i32,
+    #[case] expected_stderr: &str,
+) {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(
+        temp_dir.path().join(".editorconfig"),
+        formatdoc! {"
+            root = true
+            {section}
+            insert_final_newline = true
+        "},
+    )
+    .unwrap();
+    let file_path = temp_dir.path().join("a.txt");
+    fs::write(&file_path, "a\n").unwrap();
+
+    let output = basefmt()
+        .arg("--check")
+        .arg("--require-rules")
+        .arg(&file_path)
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap()
+        ),
+        (
+            Some(expected_code),
+            expected_stderr.replace("{file}", &file_path.display().to_string())
+        )
+    );
+}
+
+#[rstest]
+#[case::editorconfig_without_matching_section(true, 0, "")]
+#[case::no_editorconfig(false, 3, "{file}: no EditorConfig file applies\n")]
+fn test_require_config(
+    #[case] has_editorconfig: bool,
+    #[case] expected_code: i32,
+    #[case] expected_stderr: &str,
+) {
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path().join("project");
+    fs::create_dir(&project).unwrap();
+    if has_editorconfig {
+        fs::write(project.join(".editorconfig"), "root = true\n[*.md]\n").unwrap(); // ast-grep-ignore: prefer-indoc
+    }
+    let file_path = project.join("a.txt");
+    fs::write(&file_path, "a\n").unwrap();
+
+    let output = basefmt()
+        .arg("--check")
+        .arg("--require-config")
+        .arg("--root-dir")
+        .arg(&project)
+        .arg(&file_path)
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap()
+        ),
+        (
+            Some(expected_code),
+            expected_stderr.replace("{file}", &file_path.display().to_string())
+        )
+    );
+}
+
+#[rstest]
+#[case::auto_in_github_actions(&[], &[("GITHUB_ACTIONS", "true")], "::error file={file},line=1,col=2::trailing whitespace\n")]
+#[case::explicit_github(&["--output", "github"], &[], "::error file={file},line=1,col=2::trailing whitespace\n")]
+#[case::text_overrides_detection(&["--output", "text"], &[("GITHUB_ACTIONS", "true")], "")]
+fn test_github_actions_output(
+    #[case] flags: &[&str],
+    #[case] env: &[(&str, &str)],
+    #[case] expected_stdout: &str,
+) {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let file_path = temp_dir.path().join("a.txt");
+    fs::write(&file_path, "a \n").unwrap();
+
+    let output = basefmt()
+        .arg("--check")
+        .args(flags)
+        .arg(&file_path)
+        .envs(env.iter().copied())
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stdout).unwrap()
+        ),
+        (
+            Some(1),
+            expected_stdout.replace("{file}", &file_path.display().to_string())
+        )
+    );
+}
+
+#[test]
+fn test_check_mode_reports_in_path_order() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    // Enough files to be checked in parallel
+    let files: Vec<PathBuf> = (0..20)
+        .map(|i| temp_dir.path().join(format!("file{i:02}.txt")))
+        .collect();
+    for file in files.iter().rev() {
+        fs::write(file, "dirty").unwrap();
+    }
+
+    let output = basefmt()
+        .arg("--check")
+        .arg(temp_dir.path().to_str().unwrap())
+        .output()
+        .unwrap();
+
+    let expected: String = files
+        .iter()
+        .map(|file| format!("{}: missing final newline\n", file.display()))
+        .collect();
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), expected);
+}
+
+#[test]
+fn test_format_with_lines() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let test_file = temp_dir.path().join("test.txt");
+    fs::write(&test_file, "a  \nb  \nc  \nd  ").unwrap(); // ast-grep-ignore: prefer-indoc
+
+    let status = basefmt()
+        .args(["--lines", "1:1", "--lines", "3:4"])
+        .arg(test_file.to_str().unwrap())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    assert_eq!(
+        fs::read_to_string(&test_file).unwrap(),
+        "a\nb  \nc\nd\n" // ast-grep-ignore: prefer-indoc
+    );
+}
+
+#[test]
+fn test_lines_requires_single_file() {
+    let temp_dir = TempDir::new().unwrap();
+
+    let status = basefmt()
+        .args(["--lines", "1:1"])
+        .arg(temp_dir.path().to_str().unwrap())
+        .status()
+        .unwrap();
+
+    assert_eq!(status.code(), Some(2));
+}
+
+#[test]
+fn test_ipc() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let mut child = basefmt()
+        .current_dir(temp_dir.path())
+        .arg("--ipc")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(
+            indoc! {r#"
+                {"jsonrpc":"2.0","id":1,"method":"format","params":{"path":"a.txt","content":"a  "}}
+                {"jsonrpc":"2.0","id":2,"method":"format","params":{"path":"b.txt","content":"b\n"}}
+            "#}
+            .as_bytes(),
+        )
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stdout).unwrap()
+        ),
+        (
+            Some(0),
+            indoc! {r#"
+                {"jsonrpc":"2.0","id":1,"result":{"content":"a\n","changed":true}}
+                {"jsonrpc":"2.0","id":2,"result":{"content":"b\n","changed":false}}
+            "#}
+            .to_string()
+        )
+    );
+}
+
+#[test]
+fn test_fail_fast() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        fs::write(temp_dir.path().join(name), "dirty").unwrap();
+    }
+
+    let output = basefmt()
+        .current_dir(temp_dir.path())
+        .args(["--check", "--fail-fast", "--sequential", "--verbose"])
+        .args(["a.txt", "b.txt", "c.txt"])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap()
+        ),
+        (
+            Some(1),
+            indoc! {"
+                a.txt: missing final newline
+                3 files: 1 need formatting, 0 unchanged, 0 skipped, 2 not checked
+            "}
+            .to_string()
+        )
+    );
+}
+
+#[test]
+fn test_max_errors() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        fs::write(temp_dir.path().join(name), "dirty").unwrap();
+    }
+    // Writing into a file instead of a directory fails for every file
+    fs::write(temp_dir.path().join("out"), "").unwrap();
+
+    let output = basefmt()
+        .current_dir(temp_dir.path())
+        .args(["--output-dir", "out", "--max-errors", "1", "--sequential"])
+        .args(["a.txt", "b.txt", "c.txt"])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap()
+        ),
+        (
+            Some(4),
+            indoc! {"
+                1 file could not be processed:
+                  File exists (os error 17) (1 file)
+                    .: a.txt
+                Aborted after 1 error, leaving 2 of 3 files unprocessed
+            "}
+            .to_string()
+        )
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_non_utf8_path_in_reports() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let name = OsStr::from_bytes(b"caf\xE9.txt");
+    fs::write(temp_dir.path().join(name), "a").unwrap();
+    let check = |args: &[&str]| {
+        basefmt()
+            .current_dir(temp_dir.path())
+            .arg("--check")
+            .args(args)
+            .arg(name)
+            .output()
+            .unwrap()
+    };
+
+    let text = check(&[]);
+    let jsonl = check(&["--output", "jsonl"]);
+    let mut record: serde_json::Value = serde_json::from_slice(&jsonl.stdout).unwrap();
+    record["duration_ms"].take();
+
+    assert_eq!(
+        (String::from_utf8(text.stderr).unwrap(), record),
+        (
+            "caf\\xE9.txt: missing final newline\n".to_string(),
+            serde_json::json!({
+                "path": "caf\\xE9.txt",
+                "path_bytes": b"caf\xE9.txt",
+                "status": "needs_formatting",
+                "violations": [{
+                    "kind": "missing_final_newline",
+                    "line": null,
+                    "column": null,
+                    "message": "missing final newline",
+                }],
+                "duration_ms": null,
+            })
+        )
+    );
+}
+
+#[test]
+fn test_glob_path_argument() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::write(
+        temp_dir.path().join(".basefmt.toml"),
+        "exclude = [\"vendor/**\"]\n",
+    )
+    .unwrap();
+    fs::create_dir_all(temp_dir.path().join("docs")).unwrap();
+    fs::create_dir_all(temp_dir.path().join("vendor")).unwrap();
+    for name in ["a.md", "docs/b.md", "vendor/c.md", "d.txt"] {
+        fs::write(temp_dir.path().join(name), "dirty").unwrap();
+    }
+
+    let output = basefmt()
+        .current_dir(temp_dir.path())
+        .args(["--check", "**/*.md"])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap()
+        ),
+        (
+            Some(1),
+            indoc! {"
+                a.md: missing final newline
+                docs/b.md: missing final newline
+            "}
+            .to_string()
+        )
+    );
+}
+
+#[rstest]
+#[case::from_repository_root("", "sub/dir", "sub/dir/kept.txt")]
+#[case::from_parent_of_path("sub", "dir", "dir/kept.txt")]
+#[case::from_path_itself("sub/dir", ".", "kept.txt")]
+fn test_nested_path_honors_ancestor_gitignore(
+    #[case] cwd: &str,
+    #[case] path: &str,
+    #[case] expected_file: &str,
+) {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::create_dir(temp_dir.path().join(".git")).unwrap();
+    fs::write(
+        temp_dir.path().join(".gitignore"),
+        indoc! {"
+            *.log
+            /sub/dir/anchored.txt
+        "},
+    )
+    .unwrap();
+    let dir = temp_dir.path().join("sub/dir");
+    fs::create_dir_all(&dir).unwrap();
+    for name in ["a.log", "anchored.txt", "kept.txt"] {
+        fs::write(dir.join(name), "dirty").unwrap();
+    }
+
+    let output = basefmt()
+        .current_dir(temp_dir.path().join(cwd))
+        .args(["--check", path])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        format!("{expected_file}: missing final newline\n")
+    );
+}
+
+#[test]
+fn test_first_violation() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let content = format!("a \n{}b \n", "line\n".repeat(50_000)); // ast-grep-ignore: prefer-indoc
+    fs::write(temp_dir.path().join("a.txt"), content).unwrap();
+
+    let output = basefmt()
+        .current_dir(temp_dir.path())
+        .args(["--check", "--first-violation", "a.txt"])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap()
+        ),
+        (Some(1), "a.txt:1:2: trailing whitespace\n".to_string())
+    );
+}
+
+#[rstest]
+#[case::whole_file(&["--check", "a.txt"])]
+#[case::first_violation(&["--check", "--first-violation", "a.txt"])]
+fn test_first_violation_binary(#[case] args: &[&str]) {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let mut content = format!("a \n{}", "line\n".repeat(40_000)).into_bytes();
+    content.extend_from_slice(b"\xff\xfe\n");
+    fs::write(temp_dir.path().join("a.txt"), content).unwrap();
+
+    let output = basefmt()
+        .current_dir(temp_dir.path())
+        .args(args)
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap()
+        ),
+        (Some(0), "a.txt: skipped binary file\n".to_string())
+    );
+}
+
+#[test]
+fn test_stream_above() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::write(temp_dir.path().join("small.txt"), "a  ").unwrap();
+    fs::write(
+        temp_dir.path().join("large.csv"),
+        "\n\na,b  \r\nc,d\r\n\r\n\r\n", // ast-grep-ignore: prefer-indoc
+    )
+    .unwrap();
+
+    let output = basefmt()
+        .current_dir(temp_dir.path())
+        .args(["--stream-above", "4", "small.txt", "large.csv"])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            fs::read_to_string(temp_dir.path().join("small.txt")).unwrap(),
+            fs::read_to_string(temp_dir.path().join("large.csv")).unwrap()
+        ),
+        (Some(0), "a\n".to_string(), "a,b\r\nc,d\r\n".to_string()) // ast-grep-ignore: prefer-indoc
+    );
+}
+
+#[test]
+fn test_output_grouped() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::create_dir(temp_dir.path().join("src")).unwrap();
+    fs::write(temp_dir.path().join("src/a.txt"), "a  \n").unwrap();
+    fs::write(temp_dir.path().join("src/b.txt"), "b").unwrap();
+    fs::write(temp_dir.path().join("src/c.txt"), "c\n").unwrap();
+    fs::write(temp_dir.path().join("top.txt"), "top").unwrap();
+
+    let output = basefmt()
+        .current_dir(temp_dir.path())
+        .args(["--check", "--output", "grouped", "src", "top.txt"])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap()
+        ),
+        (
+            Some(1),
+            indoc! {"
+                . (1 file):
+                  top.txt: missing final newline
+                src (2 files):
+                  a.txt:1:2: trailing whitespace
+                  b.txt: missing final newline
+            "}
+            .to_string()
+        )
+    );
+}
+
+#[rstest]
+#[case::relative(&[], "src/a.txt")]
+#[case::absolute(&["--absolute-paths"], "{dir}/src/a.txt")]
+fn test_path_display(#[case] args: &[&str], #[case] expected: &str) {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let dir = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir(dir.join("src")).unwrap();
+    fs::write(dir.join("src/a.txt"), "a").unwrap();
+
+    let output = basefmt()
+        .current_dir(&dir)
+        .args(["--check"])
+        .args(args)
+        .arg(dir.join("src/a.txt"))
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        format!(
+            "{}: missing final newline\n",
+            expected.replace("{dir}", &dir.display().to_string())
+        )
+    );
+}
+
+#[test]
+fn test_base_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let project = temp_dir.path().join("project");
+    fs::create_dir_all(project.join("src")).unwrap();
+    fs::create_dir_all(project.join("vendor")).unwrap();
+    fs::write(project.join(".basefmt.toml"), "exclude = [\"vendor/**\"]\n").unwrap();
+    fs::write(project.join("src/a.txt"), "a").unwrap();
+    fs::write(project.join("vendor/b.txt"), "b").unwrap();
+    let wrapper_dir = temp_dir.path().join("wrapper");
+    fs::create_dir(&wrapper_dir).unwrap();
+
+    let output = basefmt()
+        .current_dir(&wrapper_dir)
+        .args([
+            "--check",
+            "--base-dir",
+            "../project",
+            "../project/src",
+            "../project/vendor",
+        ])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap()
+        ),
+        (Some(1), "src/a.txt: missing final newline\n".to_string())
+    );
+}
+
+#[rstest]
+#[case::format(&["-"], (Some(0), "a\n", ""))]
+#[case::check(&["--check", "-"], (Some(1), "", "<stdin>: missing final newline\n"))]
+#[case::stdin_path(
+    &["--check", "--stdin-path", "src/a.txt", "-"],
+    (Some(1), "", "src/a.txt: missing final newline\n")
+)]
+fn test_stdin(#[case] args: &[&str], #[case] expected: (Option<i32>, &str, &str)) {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let mut child = basefmt()
+        .current_dir(temp_dir.path())
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"a").unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stdout).unwrap().as_str(),
+            String::from_utf8(output.stderr).unwrap().as_str()
+        ),
+        expected
+    );
+}
+
+#[test]
+fn test_file_lock_timeout() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::write(temp_dir.path().join("a.txt"), "a").unwrap();
+    let holder = fs::File::open(temp_dir.path().join("a.txt")).unwrap();
+    holder.lock().unwrap();
+
+    let output = basefmt()
+        .current_dir(temp_dir.path())
+        .args(["--file-lock-timeout", "0", "a.txt"])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap(),
+            fs::read_to_string(temp_dir.path().join("a.txt")).unwrap()
+        ),
+        (
+            Some(2),
+            indoc! {"
+                1 file could not be processed:
+                  file is locked by another process (gave up after 0ns) (1 file)
+                    .: a.txt
+            "}
+            .to_string(),
+            "a".to_string()
+        )
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_restrict_to() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    let tree = temp_dir.path().join("tree");
+    fs::create_dir(&tree).unwrap();
+    fs::write(temp_dir.path().join("secret.txt"), "secret").unwrap();
+    std::os::unix::fs::symlink(temp_dir.path().join("secret.txt"), tree.join("link.txt")).unwrap();
+
+    let output = basefmt()
+        .current_dir(&tree)
+        .args(["--restrict-to", ".", "link.txt"])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            fs::read_to_string(temp_dir.path().join("secret.txt")).unwrap()
+        ),
+        (Some(2), "secret".to_string())
+    );
+}
+
+#[test]
+fn test_append_history() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::create_dir(temp_dir.path().join("src")).unwrap();
+    fs::write(temp_dir.path().join("src/a.txt"), "a  \n").unwrap();
+    fs::write(temp_dir.path().join("b.txt"), "b").unwrap();
+
+    for _ in 0..2 {
+        basefmt()
+            .current_dir(temp_dir.path())
+            .args([
+                "--check",
+                "--append-history",
+                "history.jsonl",
+                "src",
+                "b.txt",
+            ])
+            .output()
+            .unwrap();
+    }
+
+    let history = fs::read_to_string(temp_dir.path().join("history.jsonl")).unwrap();
+    let records: Vec<serde_json::Value> = history
+        .lines()
+        .map(|line| {
+            let mut record: serde_json::Value = serde_json::from_str(line).unwrap();
+            record.as_object_mut().unwrap().remove("timestamp");
+            record
+        })
+        .collect();
+    let record = serde_json::json!({
+        "files": 2,
+        "unformatted_files": 2,
+        "rules": {
+            "missing_final_newline": {"files": 1, "lines": 1},
+            "trailing_whitespace": {"files": 1, "lines": 1},
+        },
+        "directories": {
+            ".": {"missing_final_newline": {"files": 1, "lines": 1}},
+            "src": {"trailing_whitespace": {"files": 1, "lines": 1}},
+        },
+    });
+    assert_eq!(records, vec![record.clone(), record]);
+}
+
+#[test]
+fn test_output_badge() {
+    let temp_dir = TempDir::new().unwrap();
+    create_default_editorconfig(&temp_dir);
+    fs::write(temp_dir.path().join("a.txt"), "a").unwrap();
+    fs::write(temp_dir.path().join("b.txt"), "b  \n").unwrap();
+    fs::write(temp_dir.path().join("c.txt"), "c\n").unwrap();
+
+    let output = basefmt()
+        .current_dir(temp_dir.path())
+        .args(["--check", "--output", "badge", "."])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        (
+            output.status.code(),
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(output.stderr).unwrap()
+        ),
+        (
+            Some(1),
+            "{\"schemaVersion\":1,\"label\":\"basefmt\",\"message\":\"2 files dirty\",\"color\":\"red\"}\n".to_string(),
+            String::new()
+        )
+    );
+}